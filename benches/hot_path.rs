@@ -0,0 +1,153 @@
+// Per-block work between a new head arriving and the update transaction being built;
+// every updater on the network races through it, so regressions here cost inclusions
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use serde_json::Value;
+use starknet::core::types::Felt;
+
+use pp_fee_updater::calldata::{decode_price, encode_u256, CalldataLayout, PriceEncoding};
+use pp_fee_updater::components::{
+    ComponentConfig, Denomination, FeeComponent, PriceSource, ThresholdStrategy,
+};
+use pp_fee_updater::queue::{QueuedUpdate, SendQueue};
+use pp_fee_updater::spec::{Notification, SpecVersion};
+use pp_fee_updater::updater::{check_component, BlockPrices, TransactionStatus};
+
+const NEW_HEADS: &str = include_str!("../tests/fixtures/ws/new_heads.json");
+const TRANSACTION_STATUS: &str = include_str!("../tests/fixtures/ws/transaction_status.json");
+
+const POOL: Felt = Felt::from_hex_unchecked("0x5f3a1c");
+
+fn components() -> Vec<FeeComponent> {
+    let strategy = ThresholdStrategy {
+        upward_threshold: 105,
+        downward_threshold: 85,
+        upward_buffer: 110,
+        downward_buffer: 110,
+        max_change_percent: Some(50),
+        min_price: None,
+        max_price: None,
+    };
+    [
+        ("l1_gas", PriceSource::L1Gas),
+        ("l1_data_gas", PriceSource::L1DataGas),
+        ("l2_gas", PriceSource::L2Gas),
+    ]
+    .into_iter()
+    .map(|(name, source)| {
+        ComponentConfig::builtin(
+            name,
+            source,
+            &format!("get_{name}_price"),
+            &format!("set_{name}_price"),
+        )
+        .resolve(strategy)
+        .expect("builtin component")
+    })
+    .collect()
+}
+
+fn ws_parsing(c: &mut Criterion) {
+    c.bench_function("ws/new_heads", |b| {
+        b.iter(|| {
+            let message: Value = serde_json::from_str(black_box(NEW_HEADS)).unwrap();
+            let Some(Notification::NewHeads { header, .. }) =
+                SpecVersion::V0_8.notification(&message)
+            else {
+                panic!("not a new heads notification");
+            };
+            BlockPrices::from_header(header).expect("header prices")
+        })
+    });
+    c.bench_function("ws/transaction_status", |b| {
+        b.iter(|| {
+            let message: Value = serde_json::from_str(black_box(TRANSACTION_STATUS)).unwrap();
+            let Some(Notification::TransactionStatus { result, .. }) =
+                SpecVersion::V0_8.notification(&message)
+            else {
+                panic!("not a transaction status notification");
+            };
+            TransactionStatus::from_notification(result).expect("transaction status")
+        })
+    });
+}
+
+fn decisions(c: &mut Criterion) {
+    let components = components();
+    // Network prices moved past the upward threshold of every component
+    let network = Felt::from(50_000_000u128);
+    let contract = Felt::from(40_000_000u128);
+
+    c.bench_function("decision/check_components", |b| {
+        b.iter(|| {
+            components
+                .iter()
+                .map(|component| {
+                    check_component(
+                        component,
+                        POOL,
+                        Denomination::Fri,
+                        black_box(network),
+                        black_box(contract),
+                        false,
+                        0,
+                    )
+                    .unwrap()
+                })
+                .collect::<Vec<_>>()
+        })
+    });
+
+    let decisions: Vec<_> = components
+        .iter()
+        .map(|component| {
+            check_component(
+                component,
+                POOL,
+                Denomination::Fri,
+                network,
+                contract,
+                false,
+                0,
+            )
+            .unwrap()
+        })
+        .collect();
+    c.bench_function("decision/send_queue", |b| {
+        b.iter(|| {
+            let items = black_box(&decisions)
+                .iter()
+                .cloned()
+                .filter_map(QueuedUpdate::from_decision)
+                .collect();
+            SendQueue::build(items, Some(2), None)
+        })
+    });
+}
+
+fn calldata(c: &mut Criterion) {
+    let price = Felt::from(55_000_000u128);
+    let layout = |encoding| CalldataLayout {
+        getter_args: Vec::new(),
+        setter_args: vec![POOL],
+        encoding,
+        binding: None,
+    };
+    let felt = layout(PriceEncoding::Felt);
+    let u256 = layout(PriceEncoding::U256);
+
+    c.bench_function("calldata/setter_felt", |b| {
+        b.iter(|| felt.setter_calldata(black_box(price)))
+    });
+    c.bench_function("calldata/setter_u256", |b| {
+        b.iter(|| u256.setter_calldata(black_box(price)))
+    });
+    let result = encode_u256(price);
+    c.bench_function("calldata/decode_u256", |b| {
+        b.iter(|| decode_price(black_box(&result)).unwrap())
+    });
+}
+
+criterion_group!(hot_path, ws_parsing, decisions, calldata);
+criterion_main!(hot_path);
@@ -0,0 +1,148 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{
+    extract::{Path, Request, State},
+    http::{header::AUTHORIZATION, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
+    Json, Router,
+};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::{error, info};
+
+use crate::components::StrategyPatch;
+use crate::control::{ControlCommand, ControlHandle};
+
+#[derive(Debug, Deserialize)]
+struct PriceChange {
+    price: u128,
+}
+
+// Authenticated HTTP control of the running updater; every request needs
+// `Authorization: Bearer <token>`
+pub async fn serve(addr: SocketAddr, token: String, control: ControlHandle) -> anyhow::Result<()> {
+    let token = Arc::new(token);
+    let app = Router::new()
+        .route("/admin/status", get(status))
+        .route("/admin/pause", post(pause))
+        .route("/admin/resume", post(resume))
+        .route("/admin/check", post(check))
+        .route("/admin/strategy", put(set_strategy))
+        .route(
+            "/admin/components/:component/strategy",
+            put(set_component_strategy),
+        )
+        .route("/admin/components/:component/price", post(set_price))
+        .route("/admin/components/:component/confirm", post(confirm_update))
+        .layer(middleware::from_fn_with_state(token, authorize))
+        .with_state(control);
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!("🔐 Serving the admin API on http://{}/admin", addr);
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, app).await {
+            error!("Admin API server stopped: {:?}", e);
+        }
+    });
+    Ok(())
+}
+
+async fn authorize(State(token): State<Arc<String>>, request: Request, next: Next) -> Response {
+    let authorized = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|presented| constant_time_eq(presented.as_bytes(), token.as_bytes()));
+    if !authorized {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({ "error": "missing or invalid bearer token" })),
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
+
+// Compares without an early exit, so response timing does not leak the token
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+async fn run(control: &ControlHandle, command: ControlCommand) -> Response {
+    match control.send(command).await {
+        Ok(body) => Json(body).into_response(),
+        Err(message) => {
+            (StatusCode::BAD_REQUEST, Json(json!({ "error": message }))).into_response()
+        }
+    }
+}
+
+async fn status(State(control): State<ControlHandle>) -> Response {
+    run(&control, ControlCommand::Status).await
+}
+
+async fn pause(State(control): State<ControlHandle>) -> Response {
+    run(&control, ControlCommand::Pause).await
+}
+
+async fn resume(State(control): State<ControlHandle>) -> Response {
+    run(&control, ControlCommand::Resume).await
+}
+
+async fn check(State(control): State<ControlHandle>) -> Response {
+    run(&control, ControlCommand::Check).await
+}
+
+async fn set_strategy(
+    State(control): State<ControlHandle>,
+    Json(strategy): Json<StrategyPatch>,
+) -> Response {
+    run(
+        &control,
+        ControlCommand::SetStrategy {
+            component: None,
+            strategy,
+        },
+    )
+    .await
+}
+
+async fn set_component_strategy(
+    State(control): State<ControlHandle>,
+    Path(component): Path<String>,
+    Json(strategy): Json<StrategyPatch>,
+) -> Response {
+    run(
+        &control,
+        ControlCommand::SetStrategy {
+            component: Some(component),
+            strategy,
+        },
+    )
+    .await
+}
+
+async fn set_price(
+    State(control): State<ControlHandle>,
+    Path(component): Path<String>,
+    Json(change): Json<PriceChange>,
+) -> Response {
+    run(
+        &control,
+        ControlCommand::SetPrice {
+            component,
+            price: change.price,
+        },
+    )
+    .await
+}
+
+async fn confirm_update(
+    State(control): State<ControlHandle>,
+    Path(component): Path<String>,
+) -> Response {
+    run(&control, ControlCommand::ConfirmUpdate { component }).await
+}
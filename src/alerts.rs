@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use serde::Serialize;
+use tokio::sync::{mpsc, Mutex};
+use tracing::{error, warn};
+use url::Url;
+
+use crate::history::unix_timestamp;
+use crate::retry::BackoffPolicy;
+use crate::supervisor;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertSeverity {
+    Warning,
+    Critical,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub timestamp: u64,
+    pub severity: AlertSeverity,
+    pub kind: &'static str,
+    pub message: String,
+}
+
+// Raises operator alerts: always logged, optionally POSTed as JSON to a webhook by a
+// delivery task, so a slow webhook never holds up a fee check
+#[derive(Debug, Clone)]
+pub struct Alerter {
+    deliveries: Option<mpsc::UnboundedSender<Alert>>,
+}
+
+impl Alerter {
+    pub fn new(webhook_url: Option<Url>, restarts: BackoffPolicy) -> Self {
+        let deliveries = webhook_url.map(|webhook_url| {
+            let (deliveries, pending) = mpsc::unbounded_channel();
+            // Alerts still queued when the delivery task fails wait for its restart
+            let pending = Arc::new(Mutex::new(pending));
+            let client = reqwest::Client::new();
+            supervisor::supervise("alert delivery", restarts, move || {
+                let pending = pending.clone();
+                let client = client.clone();
+                let webhook_url = webhook_url.clone();
+                async move {
+                    let mut pending = pending.lock().await;
+                    while let Some(alert) = pending.recv().await {
+                        deliver(&client, &webhook_url, &alert).await;
+                    }
+                    Ok(())
+                }
+            });
+            deliveries
+        });
+        Self { deliveries }
+    }
+
+    pub async fn raise(&self, severity: AlertSeverity, kind: &'static str, message: String) {
+        match severity {
+            AlertSeverity::Warning => warn!("🚨 [{}] {}", kind, message),
+            AlertSeverity::Critical => error!("🚨 [{}] {}", kind, message),
+        }
+        metrics::counter!("pp_fee_updater_alerts_total", "kind" => kind).increment(1);
+
+        if let Some(deliveries) = &self.deliveries {
+            let _ = deliveries.send(Alert {
+                timestamp: unix_timestamp(),
+                severity,
+                kind,
+                message,
+            });
+        }
+    }
+}
+
+async fn deliver(client: &reqwest::Client, webhook_url: &Url, alert: &Alert) {
+    let result = client
+        .post(webhook_url.clone())
+        .json(alert)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    if let Err(e) = result {
+        error!("Failed to deliver alert to webhook: {:?}", e);
+    }
+}
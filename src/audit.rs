@@ -0,0 +1,556 @@
+use std::{
+    collections::BTreeMap,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+use starknet::core::{
+    crypto::{pedersen_hash, Signature},
+    utils::starknet_keccak,
+};
+use starknet::signers::{SigningKey, VerifyingKey};
+use starknet_types_core::felt::Felt;
+use thiserror::Error;
+use tracing::info;
+
+use crate::components::ThresholdStrategy;
+use crate::history::unix_timestamp;
+use crate::updater::{FeeDecision, PendingUpdate, ResolvedUpdate, TxOutcome};
+
+#[derive(Error, Debug)]
+pub enum AuditError {
+    #[error("Audit file error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+    #[error("Failed to sign an audit entry: {0}")]
+    Signing(String),
+    #[error("Audit line {line} fails verification: {reason}")]
+    Tampered { line: usize, reason: String },
+}
+
+// Appended to every entry when signing is on, as the last field of the line:
+// `hash = pedersen(prev, starknet_keccak(<line without the seal>))`, signed with the audit
+// key. Chaining each hash to the one before makes a removed, reordered or edited line break
+// every seal after it.
+const SEAL_FIELD: &str = ",\"seal\":";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Seal {
+    prev: Felt,
+    hash: Felt,
+    signature: [Felt; 2],
+}
+
+// A line of the audit file. Decisions and transactions are separate entries, joined by
+// block number and component or by transaction hash.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "entry", rename_all = "snake_case")]
+pub enum AuditEntry {
+    Decision(AuditDecision),
+    TransactionSent(AuditTransaction),
+    TransactionResolved {
+        #[serde(flatten)]
+        transaction: AuditTransaction,
+        outcome: TxOutcome,
+    },
+    // Signed summary of the chain so far, also logged, so a copy of the head lives
+    // outside the file
+    Checkpoint {
+        timestamp: u64,
+        entries: u64,
+        head: Felt,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditDecision {
+    pub timestamp: u64,
+    pub block_number: Option<u64>,
+    pub component: String,
+    pub network_gas_price: u128,
+    pub contract_gas_price: u128,
+    // Missing only when the component is no longer configured
+    pub thresholds: Option<ThresholdStrategy>,
+    pub decision: Decision,
+    pub new_gas_price: Option<u128>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decision {
+    Update,
+    Hold,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditTransaction {
+    pub timestamp: u64,
+    pub block_number: Option<u64>,
+    pub tx_hash: String,
+    pub sender: String,
+    // New value per component written by the transaction
+    pub prices: BTreeMap<String, u128>,
+}
+
+impl AuditEntry {
+    pub fn decision(
+        block_number: Option<u64>,
+        decision: &FeeDecision,
+        thresholds: Option<ThresholdStrategy>,
+    ) -> Self {
+        AuditEntry::Decision(AuditDecision {
+            timestamp: unix_timestamp(),
+            block_number,
+            component: decision.component.clone(),
+            network_gas_price: decision.network_gas_price,
+            contract_gas_price: decision.contract_gas_price,
+            thresholds,
+            decision: if decision.should_update {
+                Decision::Update
+            } else {
+                Decision::Hold
+            },
+            new_gas_price: decision
+                .should_update
+                .then(|| decision.new_gas_price.to_biguint())
+                .and_then(|price| price.try_into().ok()),
+        })
+    }
+
+    pub fn sent(block_number: Option<u64>, pending: &PendingUpdate) -> Self {
+        AuditEntry::TransactionSent(AuditTransaction::new(block_number, pending))
+    }
+
+    pub fn resolved(resolved: &ResolvedUpdate) -> Self {
+        AuditEntry::TransactionResolved {
+            transaction: AuditTransaction::new(resolved.block_number, &resolved.pending),
+            outcome: resolved.outcome,
+        }
+    }
+}
+
+impl AuditTransaction {
+    fn new(block_number: Option<u64>, pending: &PendingUpdate) -> Self {
+        Self {
+            timestamp: unix_timestamp(),
+            block_number,
+            tx_hash: format!("{:#x}", pending.tx_hash),
+            sender: format!("{:#x}", pending.sender),
+            prices: pending
+                .updates
+                .iter()
+                .map(|update| {
+                    (
+                        update.component.clone(),
+                        update.gas_price.to_biguint().try_into().unwrap_or_default(),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+// Append-only JSONL record of every decision and transaction of the updater, kept apart
+// from the logs and the history file; each entry is flushed to disk before the next
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    path: PathBuf,
+    signer: Option<Arc<Signer>>,
+}
+
+#[derive(Debug)]
+struct Signer {
+    key: SigningKey,
+    // A checkpoint follows every this many entries, never when zero
+    checkpoint_every: u64,
+    chain: Mutex<Chain>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Chain {
+    head: Felt,
+    entries: u64,
+}
+
+// What verifying a signed audit file found
+#[derive(Debug)]
+pub struct Verification {
+    // Lines written before signing was turned on
+    pub unsigned: usize,
+    pub signed: usize,
+    pub checkpoints: usize,
+    pub head: Felt,
+}
+
+impl AuditLog {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            signer: None,
+        }
+    }
+
+    // Seal every entry with `key`; the chain carries on from the last sealed line of an
+    // existing file
+    pub fn with_signer(
+        mut self,
+        key: SigningKey,
+        checkpoint_every: u64,
+    ) -> Result<Self, AuditError> {
+        let head = match File::open(&self.path) {
+            Ok(file) => last_seal(file)?.map_or(Felt::ZERO, |seal| seal.hash),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Felt::ZERO,
+            Err(e) => return Err(e.into()),
+        };
+        info!(
+            "🔏 Signing audit entries with public key {:#x}",
+            key.verifying_key().scalar()
+        );
+        self.signer = Some(Arc::new(Signer {
+            key,
+            checkpoint_every,
+            chain: Mutex::new(Chain { head, entries: 0 }),
+        }));
+        Ok(self)
+    }
+
+    pub fn append(&self, entry: &AuditEntry) -> Result<(), AuditError> {
+        let line = serde_json::to_string(entry)?;
+        let Some(signer) = &self.signer else {
+            return self.write(&[line]);
+        };
+        let mut chain = signer.chain.lock().unwrap_or_else(|e| e.into_inner());
+        // Only a written line moves the chain on
+        let mut next = *chain;
+        let mut lines = vec![seal(&line, &signer.key, &mut next)?];
+        next.entries += 1;
+        if signer.checkpoint_every > 0 && next.entries % signer.checkpoint_every == 0 {
+            let checkpoint = serde_json::to_string(&AuditEntry::Checkpoint {
+                timestamp: unix_timestamp(),
+                entries: next.entries,
+                head: next.head,
+            })?;
+            lines.push(seal(&checkpoint, &signer.key, &mut next)?);
+        }
+        self.write(&lines)?;
+        if lines.len() > 1 {
+            info!(
+                "🔏 Audit checkpoint after {} entries, head {:#x}",
+                next.entries, next.head
+            );
+        }
+        *chain = next;
+        Ok(())
+    }
+
+    fn write(&self, lines: &[String]) -> Result<(), AuditError> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        for line in lines {
+            file.write_all(line.as_bytes())?;
+            file.write_all(b"\n")?;
+        }
+        file.sync_data()?;
+        Ok(())
+    }
+}
+
+fn seal(line: &str, key: &SigningKey, chain: &mut Chain) -> Result<String, AuditError> {
+    let hash = pedersen_hash(&chain.head, &starknet_keccak(line.as_bytes()));
+    let signature = key
+        .sign(&hash)
+        .map_err(|e| AuditError::Signing(e.to_string()))?;
+    let seal = Seal {
+        prev: chain.head,
+        hash,
+        signature: [signature.r, signature.s],
+    };
+    chain.head = hash;
+    // The entry is a JSON object, so the seal goes in before its closing brace
+    let body = line.strip_suffix('}').unwrap_or(line);
+    Ok(format!(
+        "{}{}{}}}",
+        body,
+        SEAL_FIELD,
+        serde_json::to_string(&seal)?
+    ))
+}
+
+// Splits a line into the entry as it was signed and its seal, if it has one
+fn unseal(line: &str) -> Result<Option<(String, Seal)>, serde_json::Error> {
+    let Some(at) = line.rfind(SEAL_FIELD) else {
+        return Ok(None);
+    };
+    let seal = line[at + SEAL_FIELD.len()..].trim_end();
+    let seal = serde_json::from_str(seal.strip_suffix('}').unwrap_or(seal))?;
+    Ok(Some((format!("{}}}", &line[..at]), seal)))
+}
+
+fn last_seal(file: File) -> Result<Option<Seal>, AuditError> {
+    let mut last = None;
+    for line in BufReader::new(file).lines() {
+        if let Some((_, seal)) = unseal(&line?)? {
+            last = Some(seal);
+        }
+    }
+    Ok(last)
+}
+
+// Checks that every sealed line is intact, signed by `public_key` and chained to the one
+// before; unsigned lines may only precede the first sealed one
+pub fn verify(path: &Path, public_key: Felt) -> Result<Verification, AuditError> {
+    let key = VerifyingKey::from_scalar(public_key);
+    let mut verification = Verification {
+        unsigned: 0,
+        signed: 0,
+        checkpoints: 0,
+        head: Felt::ZERO,
+    };
+    for (index, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        let tampered = |reason: &str| AuditError::Tampered {
+            line: index + 1,
+            reason: reason.to_string(),
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Some((entry, seal)) = unseal(&line).map_err(|e| tampered(&e.to_string()))? else {
+            if verification.signed > 0 {
+                return Err(tampered("unsigned line after signed ones"));
+            }
+            verification.unsigned += 1;
+            continue;
+        };
+        if seal.prev != verification.head {
+            return Err(tampered("not chained to the previous signed line"));
+        }
+        if seal.hash != pedersen_hash(&seal.prev, &starknet_keccak(entry.as_bytes())) {
+            return Err(tampered("content does not match its hash"));
+        }
+        let signature = Signature {
+            r: seal.signature[0],
+            s: seal.signature[1],
+        };
+        if !key.verify(&seal.hash, &signature).unwrap_or(false) {
+            return Err(tampered("signature does not match the public key"));
+        }
+        if entry.starts_with("{\"entry\":\"checkpoint\"") {
+            verification.checkpoints += 1;
+        }
+        verification.signed += 1;
+        verification.head = seal.hash;
+    }
+    Ok(verification)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn key() -> SigningKey {
+        SigningKey::from_secret_scalar(Felt::from(42u8))
+    }
+
+    // A fresh file under the temp dir, unique to the test
+    fn audit_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "pp-fee-updater-{}-audit-{name}.jsonl",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn decision(network_gas_price: u128) -> AuditEntry {
+        AuditEntry::Decision(AuditDecision {
+            timestamp: 1,
+            block_number: Some(100),
+            component: "l1_gas".to_string(),
+            network_gas_price,
+            contract_gas_price: 100,
+            thresholds: None,
+            decision: Decision::Hold,
+            new_gas_price: None,
+        })
+    }
+
+    fn append_all(log: &AuditLog, prices: &[u128]) {
+        for price in prices {
+            log.append(&decision(*price)).unwrap();
+        }
+    }
+
+    fn lines(path: &Path) -> Vec<String> {
+        fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn rewrite(path: &Path, lines: &[String]) {
+        fs::write(path, lines.join("\n") + "\n").unwrap();
+    }
+
+    fn tampered_line(result: Result<Verification, AuditError>) -> (usize, String) {
+        match result {
+            Err(AuditError::Tampered { line, reason }) => (line, reason),
+            other => panic!("expected tampering, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn sealed_line_unseals_to_the_signed_entry() {
+        let line = serde_json::to_string(&decision(100)).unwrap();
+        let mut chain = Chain {
+            head: Felt::from(7u8),
+            entries: 0,
+        };
+
+        let sealed = seal(&line, &key(), &mut chain).unwrap();
+        let (entry, seal) = unseal(&sealed).unwrap().unwrap();
+        assert_eq!(entry, line);
+        assert_eq!(seal.prev, Felt::from(7u8));
+        assert_eq!(seal.hash, chain.head);
+        assert_eq!(
+            seal.hash,
+            pedersen_hash(&seal.prev, &starknet_keccak(line.as_bytes()))
+        );
+        let signature = Signature {
+            r: seal.signature[0],
+            s: seal.signature[1],
+        };
+        assert!(key()
+            .verifying_key()
+            .verify(&seal.hash, &signature)
+            .unwrap());
+        // Still a JSON object
+        serde_json::from_str::<serde_json::Value>(&sealed).unwrap();
+    }
+
+    #[test]
+    fn unsigned_line_has_no_seal() {
+        let line = serde_json::to_string(&decision(100)).unwrap();
+        assert!(unseal(&line).unwrap().is_none());
+    }
+
+    #[test]
+    fn unsigned_log_writes_plain_entries() {
+        let path = audit_file("unsigned");
+        append_all(&AuditLog::new(&path), &[100, 110]);
+
+        let verification = verify(&path, key().verifying_key().scalar()).unwrap();
+        assert_eq!((verification.unsigned, verification.signed), (2, 0));
+        assert_eq!(verification.head, Felt::ZERO);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn empty_file_verifies_with_nothing_signed() {
+        let path = audit_file("empty");
+        fs::write(&path, "").unwrap();
+
+        let verification = verify(&path, key().verifying_key().scalar()).unwrap();
+        assert_eq!((verification.unsigned, verification.signed), (0, 0));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn signed_log_verifies_and_checkpoints_every_n_entries() {
+        let path = audit_file("signed");
+        let log = AuditLog::new(&path).with_signer(key(), 2).unwrap();
+        append_all(&log, &[100, 110, 120, 130, 140]);
+
+        let verification = verify(&path, key().verifying_key().scalar()).unwrap();
+        assert_eq!(verification.unsigned, 0);
+        assert_eq!(verification.signed, 7);
+        assert_eq!(verification.checkpoints, 2);
+        let (_, last) = unseal(lines(&path).last().unwrap()).unwrap().unwrap();
+        assert_eq!(verification.head, last.hash);
+        assert!(lines(&path)[2].starts_with("{\"entry\":\"checkpoint\",\"timestamp\""));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn reopened_log_carries_the_chain_on() {
+        let path = audit_file("reopened");
+        append_all(&AuditLog::new(&path), &[100]);
+        append_all(&AuditLog::new(&path).with_signer(key(), 0).unwrap(), &[110]);
+        append_all(&AuditLog::new(&path).with_signer(key(), 0).unwrap(), &[120]);
+
+        let verification = verify(&path, key().verifying_key().scalar()).unwrap();
+        assert_eq!((verification.unsigned, verification.signed), (1, 2));
+        assert_eq!(verification.checkpoints, 0);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn edited_line_fails_verification() {
+        let path = audit_file("edited");
+        append_all(
+            &AuditLog::new(&path).with_signer(key(), 0).unwrap(),
+            &[100, 110],
+        );
+        let mut edited = lines(&path);
+        edited[0] = edited[0].replace("\"network_gas_price\":100", "\"network_gas_price\":101");
+        rewrite(&path, &edited);
+
+        let (line, reason) = tampered_line(verify(&path, key().verifying_key().scalar()));
+        assert_eq!(line, 1);
+        assert_eq!(reason, "content does not match its hash");
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn removed_or_reordered_lines_break_the_chain() {
+        let path = audit_file("removed");
+        append_all(
+            &AuditLog::new(&path).with_signer(key(), 0).unwrap(),
+            &[100, 110, 120],
+        );
+        let written = lines(&path);
+
+        rewrite(&path, &[written[0].clone(), written[2].clone()]);
+        let (line, reason) = tampered_line(verify(&path, key().verifying_key().scalar()));
+        assert_eq!(line, 2);
+        assert_eq!(reason, "not chained to the previous signed line");
+
+        rewrite(&path, &[written[1].clone(), written[0].clone()]);
+        let (line, _) = tampered_line(verify(&path, key().verifying_key().scalar()));
+        assert_eq!(line, 1);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn unsigned_line_after_signed_ones_fails_verification() {
+        let path = audit_file("unsigned-after");
+        append_all(&AuditLog::new(&path).with_signer(key(), 0).unwrap(), &[100]);
+        append_all(&AuditLog::new(&path), &[110]);
+
+        let (line, reason) = tampered_line(verify(&path, key().verifying_key().scalar()));
+        assert_eq!(line, 2);
+        assert_eq!(reason, "unsigned line after signed ones");
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn other_public_key_fails_verification() {
+        let path = audit_file("other-key");
+        append_all(&AuditLog::new(&path).with_signer(key(), 0).unwrap(), &[100]);
+
+        let other = SigningKey::from_secret_scalar(Felt::from(43u8));
+        let (line, reason) = tampered_line(verify(&path, other.verifying_key().scalar()));
+        assert_eq!(line, 1);
+        assert_eq!(reason, "signature does not match the public key");
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,186 @@
+use std::{collections::BTreeSet, path::Path};
+
+use anyhow::Context;
+use futures_util::{stream, StreamExt, TryStreamExt};
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::components::{Denomination, FeeComponent};
+use crate::endpoints::RpcClient;
+use crate::sources::to_u128;
+use crate::updater::{block_prices_at, BlockPrices, UpdaterError};
+
+// Historical blocks fetched at once
+const FETCH_CONCURRENCY: usize = 16;
+
+// Simulated outcome of one component's policy over the range
+#[derive(Debug, Serialize)]
+pub struct ComponentBacktest {
+    pub component: String,
+    pub updates: u64,
+    // Blocks where users paid less than the network price
+    pub underpriced_blocks: u64,
+    // Contract price relative to the network price, averaged over the range
+    pub average_margin_bps: i128,
+    pub worst_margin_bps: i128,
+    pub final_price: u128,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BacktestReport {
+    pub from_block: u64,
+    pub to_block: u64,
+    pub blocks: u64,
+    // Components updated in the same block share one multicall transaction
+    pub transactions: u64,
+    pub total_tx_cost: Option<u128>,
+    pub components: Vec<ComponentBacktest>,
+}
+
+pub async fn fetch_blocks(
+    provider: &RpcClient,
+    from: u64,
+    to: u64,
+) -> Result<Vec<BlockPrices>, UpdaterError> {
+    stream::iter(from..=to)
+        .map(|block_number| block_prices_at(provider, block_number))
+        .buffered(FETCH_CONCURRENCY)
+        .try_collect()
+        .await
+}
+
+// Replays the block prices through each component's strategy, starting from
+// `initial_price` or, without one, from the first block's network price
+pub fn simulate(
+    components: &[FeeComponent],
+    denomination: Denomination,
+    from_block: u64,
+    blocks: &[BlockPrices],
+    initial_price: Option<u128>,
+    tx_cost: Option<u128>,
+) -> BacktestReport {
+    let mut update_blocks = BTreeSet::new();
+    let mut reports = Vec::with_capacity(components.len());
+
+    for component in components {
+        if component.token.is_some() {
+            warn!(
+                "⚠️ [{}] Per-token components need live exchange rates and are not backtested",
+                component.name
+            );
+            continue;
+        }
+
+        let network_prices: Vec<u128> = blocks
+            .iter()
+            .map(|prices| {
+                to_u128(component.network_price(
+                    denomination,
+                    &prices.l1_gas,
+                    &prices.l1_data_gas,
+                    &prices.l2_gas,
+                ))
+                .unwrap_or(u128::MAX)
+            })
+            .collect();
+        let Some(first_price) = network_prices.first() else {
+            continue;
+        };
+
+        let mut contract_price = initial_price.unwrap_or(*first_price);
+        let mut report = ComponentBacktest {
+            component: component.name.clone(),
+            updates: 0,
+            underpriced_blocks: 0,
+            average_margin_bps: 0,
+            worst_margin_bps: i128::MAX,
+            final_price: 0,
+        };
+        let mut margin_sum: i128 = 0;
+        let mut margin_blocks: i128 = 0;
+
+        for (offset, network_price) in network_prices.iter().enumerate() {
+            // Users of this block pay the price set before it
+            if *network_price > 0 {
+                let margin_bps = (contract_price as i128 - *network_price as i128)
+                    .saturating_mul(10_000)
+                    / *network_price as i128;
+                margin_sum = margin_sum.saturating_add(margin_bps);
+                margin_blocks += 1;
+                report.worst_margin_bps = report.worst_margin_bps.min(margin_bps);
+                report.underpriced_blocks += u64::from(margin_bps < 0);
+            }
+
+            let (_, new_price) = component.strategy.decide(*network_price, contract_price);
+            if let Some(new_price) = new_price {
+                report.updates += 1;
+                contract_price = new_price;
+                update_blocks.insert(offset);
+            }
+        }
+
+        if margin_blocks > 0 {
+            report.average_margin_bps = margin_sum / margin_blocks;
+        } else {
+            report.worst_margin_bps = 0;
+        }
+        report.final_price = contract_price;
+        reports.push(report);
+    }
+
+    let transactions = update_blocks.len() as u64;
+    BacktestReport {
+        from_block,
+        to_block: from_block + blocks.len().saturating_sub(1) as u64,
+        blocks: blocks.len() as u64,
+        transactions,
+        total_tx_cost: tx_cost.map(|tx_cost| tx_cost.saturating_mul(u128::from(transactions))),
+        components: reports,
+    }
+}
+
+pub fn log_report(report: &BacktestReport) {
+    info!(
+        "📊 Backtest over blocks {}..={}: {} transaction(s){}",
+        report.from_block,
+        report.to_block,
+        report.transactions,
+        report
+            .total_tx_cost
+            .map(|cost| format!(", total cost {cost}"))
+            .unwrap_or_default()
+    );
+    for component in &report.components {
+        info!(
+            "   [{}] {} update(s), average margin {} bps, worst {} bps, underpriced in {} block(s)",
+            component.component,
+            component.updates,
+            component.average_margin_bps,
+            component.worst_margin_bps,
+            component.underpriced_blocks
+        );
+    }
+}
+
+// Outcome of the run as gauges, for pushing to a Pushgateway
+pub fn record_metrics(report: &BacktestReport) {
+    metrics::gauge!("pp_fee_updater_backtest_blocks").set(report.blocks as f64);
+    metrics::gauge!("pp_fee_updater_backtest_transactions").set(report.transactions as f64);
+    for component in &report.components {
+        let label = component.component.clone();
+        metrics::gauge!("pp_fee_updater_backtest_updates", "component" => label.clone())
+            .set(component.updates as f64);
+        metrics::gauge!("pp_fee_updater_backtest_underpriced_blocks", "component" => label.clone())
+            .set(component.underpriced_blocks as f64);
+        metrics::gauge!("pp_fee_updater_backtest_average_margin_bps", "component" => label.clone())
+            .set(component.average_margin_bps as f64);
+        metrics::gauge!("pp_fee_updater_backtest_worst_margin_bps", "component" => label)
+            .set(component.worst_margin_bps as f64);
+    }
+}
+
+pub fn write_report(report: &BacktestReport, path: &Path) -> anyhow::Result<()> {
+    let contents = serde_json::to_string_pretty(report)?;
+    std::fs::write(path, contents)
+        .with_context(|| format!("Failed to write backtest report {}", path.display()))
+}
@@ -0,0 +1,146 @@
+use std::time::{Duration, Instant};
+
+use starknet::core::types::Felt;
+use tracing::{debug, info};
+
+use crate::alerts::{AlertSeverity, Alerter};
+use crate::contracts::erc20_balance;
+use crate::endpoints::{EndpointPool, RpcClient};
+use crate::updater::UpdaterError;
+
+// STRK fee token, deployed at the same address on mainnet and sepolia
+pub const STRK_TOKEN_ADDRESS: &str =
+    "0x04718f5a0fc34cc1af16a1cdee98ffb20c31f5cd61d6ab07201858f4287c938d";
+
+pub async fn fetch_token_balance(
+    provider: &RpcClient,
+    token_address: Felt,
+    account_address: Felt,
+) -> Result<u128, UpdaterError> {
+    let balance = erc20_balance(provider, token_address, account_address).await?;
+    // Balances beyond a u128 are more than any fee will ever need
+    Ok(if balance.high == 0 {
+        balance.low
+    } else {
+        u128::MAX
+    })
+}
+
+// Tracks the owner balance and alerts once per drop below the threshold
+#[derive(Debug)]
+pub struct BalanceMonitor {
+    token_address: Felt,
+    owner_address: Felt,
+    // Never checked when unset
+    min_balance: Option<u128>,
+    interval: Duration,
+    last_check: Option<Instant>,
+    below_threshold: bool,
+}
+
+impl BalanceMonitor {
+    pub fn new(
+        token_address: Felt,
+        owner_address: Felt,
+        min_balance: Option<u128>,
+        interval: Duration,
+    ) -> Self {
+        Self {
+            token_address,
+            owner_address,
+            min_balance,
+            interval,
+            last_check: None,
+            below_threshold: false,
+        }
+    }
+
+    // Reads the balance once the interval has passed and alerts on a drop below the
+    // minimum; `None` when nothing was read
+    pub async fn check(
+        &mut self,
+        rpc: &EndpointPool,
+        alerter: &Alerter,
+    ) -> Result<Option<u128>, UpdaterError> {
+        let Some(min_balance) = self.min_balance else {
+            return Ok(None);
+        };
+        if self
+            .last_check
+            .is_some_and(|last_check| last_check.elapsed() < self.interval)
+        {
+            return Ok(None);
+        }
+        self.last_check = Some(Instant::now());
+
+        let balance =
+            fetch_token_balance(&rpc.client(), self.token_address, self.owner_address).await?;
+        debug!("Owner STRK balance (in fri): {}", balance);
+        metrics::gauge!("pp_fee_updater_owner_balance_fri").set(balance as f64);
+
+        if balance < min_balance {
+            if !self.below_threshold {
+                alerter
+                    .raise(
+                        AlertSeverity::Critical,
+                        "low_owner_balance",
+                        format!(
+                            "Owner {:#x} STRK balance {} fri is below the minimum of {} fri; fee updates will fail once it is drained",
+                            self.owner_address, balance, min_balance
+                        ),
+                    )
+                    .await;
+            }
+            self.below_threshold = true;
+        } else if self.below_threshold {
+            info!("💰 Owner STRK balance recovered: {} fri", balance);
+            self.below_threshold = false;
+        }
+
+        Ok(Some(balance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::fixtures::{Reply, StubNode};
+    use crate::retry::BackoffPolicy;
+    use crate::transport::RetryPolicy;
+
+    fn token() -> StubNode {
+        StubNode::new(|method, _| match method {
+            "starknet_call" => Reply::Result(json!(["0x2a", "0x0"])),
+            _ => Reply::Error(-32601),
+        })
+    }
+
+    fn alerter() -> Alerter {
+        Alerter::new(None, BackoffPolicy::default())
+    }
+
+    #[tokio::test]
+    async fn balance_is_never_read_without_a_minimum() {
+        let node = token();
+        let pool = StubNode::pool(&[&node], RetryPolicy::default());
+        let mut monitor = BalanceMonitor::new(Felt::ONE, Felt::TWO, None, Duration::ZERO);
+
+        assert_eq!(monitor.check(&pool, &alerter()).await.unwrap(), None);
+        assert!(node.requests("starknet_call").is_empty());
+    }
+
+    #[tokio::test]
+    async fn balance_is_read_once_per_interval() {
+        let node = token();
+        let pool = StubNode::pool(&[&node], RetryPolicy::default());
+        let mut monitor =
+            BalanceMonitor::new(Felt::ONE, Felt::TWO, Some(100), Duration::from_secs(60));
+
+        assert_eq!(monitor.check(&pool, &alerter()).await.unwrap(), Some(42));
+        assert!(monitor.below_threshold);
+        assert_eq!(monitor.check(&pool, &alerter()).await.unwrap(), None);
+        assert_eq!(node.requests("starknet_call").len(), 1);
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use crate::components::Direction;
+
+// Consecutive checks a threshold has to stay breached before an update is sent, per
+// direction, so gas hovering at the boundary does not churn the fee. 0 and 1 both send on
+// the first breach.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BreachConfirmations {
+    pub upward: u64,
+    pub downward: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct BreachTracker {
+    policy: BreachConfirmations,
+    // Direction and length of the current breach per component
+    streaks: HashMap<String, (Direction, u64)>,
+}
+
+impl BreachTracker {
+    pub fn new(policy: BreachConfirmations) -> Self {
+        Self {
+            policy,
+            streaks: HashMap::new(),
+        }
+    }
+
+    // Counts a check breaching in `direction`, `Direction::None` ending the streak; returns
+    // the checks seen and needed while the breach is not yet confirmed
+    pub fn observe(&mut self, component: &str, direction: Direction) -> Option<(u64, u64)> {
+        let needed = match direction {
+            Direction::Upward => self.policy.upward,
+            Direction::Downward => self.policy.downward,
+            Direction::None => {
+                self.streaks.remove(component);
+                return None;
+            }
+        };
+        let streak = self
+            .streaks
+            .entry(component.to_string())
+            .or_insert((direction, 0));
+        // A breach the other way starts over
+        if streak.0 != direction {
+            *streak = (direction, 0);
+        }
+        streak.1 += 1;
+        (streak.1 < needed).then_some((streak.1, needed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker(upward: u64, downward: u64) -> BreachTracker {
+        BreachTracker::new(BreachConfirmations { upward, downward })
+    }
+
+    #[test]
+    fn zero_and_one_confirm_the_first_breach() {
+        let mut tracker = tracker(0, 1);
+        assert_eq!(tracker.observe("l1_gas", Direction::Upward), None);
+        assert_eq!(tracker.observe("l2_gas", Direction::Downward), None);
+    }
+
+    #[test]
+    fn breach_is_confirmed_at_exactly_the_needed_checks() {
+        let mut tracker = tracker(3, 0);
+        assert_eq!(tracker.observe("l1_gas", Direction::Upward), Some((1, 3)));
+        assert_eq!(tracker.observe("l1_gas", Direction::Upward), Some((2, 3)));
+        assert_eq!(tracker.observe("l1_gas", Direction::Upward), None);
+        // Stays confirmed while the breach lasts
+        assert_eq!(tracker.observe("l1_gas", Direction::Upward), None);
+    }
+
+    #[test]
+    fn check_within_the_thresholds_ends_the_streak() {
+        let mut tracker = tracker(2, 2);
+        assert_eq!(tracker.observe("l1_gas", Direction::Upward), Some((1, 2)));
+        assert_eq!(tracker.observe("l1_gas", Direction::None), None);
+        assert_eq!(tracker.observe("l1_gas", Direction::Upward), Some((1, 2)));
+    }
+
+    #[test]
+    fn breach_the_other_way_starts_over() {
+        let mut tracker = tracker(2, 3);
+        assert_eq!(tracker.observe("l1_gas", Direction::Upward), Some((1, 2)));
+        assert_eq!(tracker.observe("l1_gas", Direction::Downward), Some((1, 3)));
+        assert_eq!(tracker.observe("l1_gas", Direction::Upward), Some((1, 2)));
+        assert_eq!(tracker.observe("l1_gas", Direction::Upward), None);
+    }
+
+    #[test]
+    fn components_are_counted_separately() {
+        let mut tracker = tracker(2, 2);
+        assert_eq!(tracker.observe("l1_gas", Direction::Upward), Some((1, 2)));
+        assert_eq!(tracker.observe("l2_gas", Direction::Upward), Some((1, 2)));
+        assert_eq!(tracker.observe("l1_gas", Direction::Upward), None);
+    }
+
+    #[test]
+    fn ending_a_streak_never_started_is_harmless() {
+        let mut tracker = tracker(2, 2);
+        assert_eq!(tracker.observe("l1_gas", Direction::None), None);
+        assert_eq!(tracker.observe("l1_gas", Direction::Downward), Some((1, 2)));
+    }
+}
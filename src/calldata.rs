@@ -0,0 +1,209 @@
+use serde::{Deserialize, Serialize};
+use starknet::{
+    accounts::ConnectedAccount,
+    core::types::{Call, Felt},
+};
+
+use crate::contracts::PoolPrice;
+use crate::updater::UpdaterError;
+
+// How a price is written into the setter calldata
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, clap::ValueEnum,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceEncoding {
+    /// A single felt
+    Felt,
+    /// A u256 as low and high limbs
+    #[default]
+    U256,
+}
+
+// Calldata shape of a component's entrypoints: fixed leading arguments (e.g. a token
+// address) followed, for the setter, by the encoded price. The pool's own entry points
+// are called through its typed bindings instead.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CalldataLayout {
+    pub getter_args: Vec<Felt>,
+    pub setter_args: Vec<Felt>,
+    pub encoding: PriceEncoding,
+    pub binding: Option<PoolPrice>,
+}
+
+impl CalldataLayout {
+    pub fn setter_call<A: ConnectedAccount + Sync>(
+        &self,
+        account: &A,
+        to: Felt,
+        setter: Felt,
+        price: Felt,
+    ) -> Call {
+        match self.binding {
+            Some(binding) => binding.setter_call(account, to, price),
+            None => Call {
+                to,
+                selector: setter,
+                calldata: self.setter_calldata(price),
+            },
+        }
+    }
+
+    pub fn setter_calldata(&self, price: Felt) -> Vec<Felt> {
+        let mut calldata = self.setter_args.clone();
+        match self.encoding {
+            PriceEncoding::Felt => calldata.push(price),
+            PriceEncoding::U256 => calldata.extend(encode_u256(price)),
+        }
+        calldata
+    }
+}
+
+// Cairo serializes a u256 as two u128 limbs, low limb first
+pub fn encode_u256(value: Felt) -> [Felt; 2] {
+    let (low, high) = u256_limbs(value);
+    [Felt::from(low), Felt::from(high)]
+}
+
+// Low and high limbs of a felt read as a u256, which it always fits in
+pub fn u256_limbs(value: Felt) -> (u128, u128) {
+    let bytes = value.to_bytes_be();
+    let mut high = [0u8; 16];
+    let mut low = [0u8; 16];
+    high.copy_from_slice(&bytes[..16]);
+    low.copy_from_slice(&bytes[16..]);
+    (u128::from_be_bytes(low), u128::from_be_bytes(high))
+}
+
+// Getters may return a bare felt or a u256 split into low and high limbs
+pub fn decode_price(result: &[Felt]) -> Result<Felt, UpdaterError> {
+    match result {
+        [] => Err(UpdaterError::InvalidGasPrice(
+            "Getter returned no values".to_string(),
+        )),
+        [value] => Ok(*value),
+        [low, high] => decode_u256(*low, *high),
+        values => Err(UpdaterError::InvalidGasPrice(format!(
+            "Getter returned {} values, expected a felt or a u256",
+            values.len()
+        ))),
+    }
+}
+
+// A price read into a u128, as every price and rate is handled; a u256 with a non-zero
+// high limb is rejected
+pub fn decode_u128(result: &[Felt]) -> Result<u128, UpdaterError> {
+    let value = decode_price(result)?;
+    value
+        .to_biguint()
+        .try_into()
+        .map_err(|_| UpdaterError::Conversion(format!("value {value:#x} does not fit in 128 bits")))
+}
+
+fn decode_u256(low: Felt, high: Felt) -> Result<Felt, UpdaterError> {
+    let limb = |limb: Felt, name: &str| -> Result<u128, UpdaterError> {
+        limb.to_biguint().try_into().map_err(|_| {
+            UpdaterError::Conversion(format!("u256 {} limb does not fit in 128 bits", name))
+        })
+    };
+    u256_to_felt(limb(low, "low")?, limb(high, "high")?)
+}
+
+// The u256 with these limbs, unless it is beyond the field
+pub fn u256_to_felt(low: u128, high: u128) -> Result<Felt, UpdaterError> {
+    let value = Felt::from(high) * (Felt::from(u128::MAX) + Felt::ONE) + Felt::from(low);
+    // Values at or above the field prime wrap around, which the round trip exposes
+    if u256_limbs(value) != (low, high) {
+        return Err(UpdaterError::Conversion(
+            "u256 value does not fit in a felt".to_string(),
+        ));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn two_pow_128() -> Felt {
+        Felt::from(u128::MAX) + Felt::ONE
+    }
+
+    // The field prime split into u256 limbs: 2^251 + 17 * 2^192 + 1
+    const PRIME_HIGH: u128 = (1 << 123) + (17 << 64);
+    const PRIME_LOW: u128 = 1;
+
+    #[test]
+    fn u256_round_trips_around_the_limb_boundary() {
+        let cases = [
+            (Felt::from(u128::MAX), [u128::MAX, 0]),
+            (two_pow_128(), [0, 1]),
+            (two_pow_128() + Felt::ONE, [1, 1]),
+        ];
+        for (value, [low, high]) in cases {
+            let encoded = encode_u256(value);
+            assert_eq!(encoded, [Felt::from(low), Felt::from(high)]);
+            assert_eq!(decode_u256(encoded[0], encoded[1]).unwrap(), value);
+            assert_eq!(decode_price(&encoded).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn largest_felt_round_trips() {
+        let max = Felt::ZERO - Felt::ONE;
+        let encoded = encode_u256(max);
+        assert_eq!(encoded, [Felt::from(PRIME_LOW - 1), Felt::from(PRIME_HIGH)]);
+        assert_eq!(decode_u256(encoded[0], encoded[1]).unwrap(), max);
+    }
+
+    #[test]
+    fn values_from_the_field_prime_on_are_rejected() {
+        for low in [PRIME_LOW, PRIME_LOW + 1, u128::MAX] {
+            assert!(decode_u256(Felt::from(low), Felt::from(PRIME_HIGH)).is_err());
+        }
+        assert!(decode_u256(Felt::ZERO, Felt::from(u128::MAX)).is_err());
+    }
+
+    #[test]
+    fn limbs_wider_than_128_bits_are_rejected() {
+        assert!(decode_u256(two_pow_128(), Felt::ZERO).is_err());
+        assert!(decode_u256(Felt::ZERO, two_pow_128()).is_err());
+    }
+
+    #[test]
+    fn non_zero_high_limb_does_not_fit_u128() {
+        assert_eq!(
+            decode_u128(&[Felt::from(u128::MAX), Felt::ZERO]).unwrap(),
+            u128::MAX
+        );
+        assert!(decode_u128(&[Felt::ZERO, Felt::ONE]).is_err());
+        assert!(decode_u128(&[Felt::from(u128::MAX), Felt::ONE]).is_err());
+        assert!(decode_u128(&[two_pow_128()]).is_err());
+    }
+
+    #[test]
+    fn decode_price_accepts_a_felt_or_a_u256() {
+        assert_eq!(decode_price(&[Felt::from(42u8)]).unwrap(), Felt::from(42u8));
+        assert!(decode_price(&[]).is_err());
+        assert!(decode_price(&[Felt::ONE, Felt::ONE, Felt::ONE]).is_err());
+    }
+
+    #[test]
+    fn setter_calldata_follows_the_encoding() {
+        let price = two_pow_128() + Felt::from(7u8);
+        let felt = CalldataLayout {
+            setter_args: vec![Felt::from(5u8)],
+            encoding: PriceEncoding::Felt,
+            ..CalldataLayout::default()
+        };
+        assert_eq!(felt.setter_calldata(price), vec![Felt::from(5u8), price]);
+        let u256 = CalldataLayout {
+            encoding: PriceEncoding::U256,
+            ..felt
+        };
+        assert_eq!(
+            u256.setter_calldata(price),
+            vec![Felt::from(5u8), Felt::from(7u8), Felt::ONE]
+        );
+    }
+}
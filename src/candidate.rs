@@ -0,0 +1,150 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+use tracing::{error, info, warn};
+
+use crate::components::FeeComponent;
+use crate::history::unix_timestamp;
+use crate::updater::FeeDecision;
+
+// A block where the candidate config would have decided differently from the live one
+#[derive(Debug, Clone, Serialize)]
+pub struct Divergence {
+    pub timestamp: u64,
+    pub block_number: Option<u64>,
+    pub component: String,
+    pub network_gas_price: u128,
+    pub contract_gas_price: u128,
+    pub live_update: Option<u128>,
+    pub candidate_update: Option<u128>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct DivergenceReport {
+    pub blocks_observed: u64,
+    pub decisions_compared: u64,
+    pub live_updates: u64,
+    pub candidate_updates: u64,
+    pub divergences: Vec<Divergence>,
+}
+
+// Evaluates a candidate config on the live observations without sending anything
+#[derive(Debug)]
+pub struct CandidateRun {
+    components: Vec<FeeComponent>,
+    report_path: PathBuf,
+    trial_blocks: Option<u64>,
+    report: DivergenceReport,
+}
+
+impl CandidateRun {
+    pub fn new(
+        components: Vec<FeeComponent>,
+        report_path: PathBuf,
+        trial_blocks: Option<u64>,
+    ) -> Self {
+        Self {
+            components,
+            report_path,
+            trial_blocks,
+            report: DivergenceReport::default(),
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.trial_blocks
+            .is_some_and(|trial_blocks| self.report.blocks_observed >= trial_blocks)
+    }
+
+    // Replays the live decisions of one block through the candidate strategies
+    pub fn observe(&mut self, block_number: Option<u64>, decisions: &[FeeDecision]) {
+        if self.is_finished() {
+            return;
+        }
+        self.report.blocks_observed += 1;
+
+        let mut diverged = false;
+        for decision in decisions {
+            let Some(candidate) = self
+                .components
+                .iter()
+                .find(|component| component.name == decision.component)
+            else {
+                continue;
+            };
+
+            let (_, candidate_update) = candidate
+                .strategy
+                .decide(decision.network_gas_price, decision.contract_gas_price);
+            let live_update = decision
+                .should_update
+                .then(|| decision.new_gas_price.to_biguint().try_into().ok())
+                .flatten();
+
+            self.report.decisions_compared += 1;
+            self.report.live_updates += u64::from(live_update.is_some());
+            self.report.candidate_updates += u64::from(candidate_update.is_some());
+
+            if live_update != candidate_update {
+                info!(
+                    "🔀 [{}] Candidate config diverges: live {:?} vs candidate {:?}",
+                    decision.component, live_update, candidate_update
+                );
+                self.report.divergences.push(Divergence {
+                    timestamp: unix_timestamp(),
+                    block_number,
+                    component: decision.component.clone(),
+                    network_gas_price: decision.network_gas_price,
+                    contract_gas_price: decision.contract_gas_price,
+                    live_update,
+                    candidate_update,
+                });
+                diverged = true;
+            }
+        }
+
+        let finished = self.is_finished();
+        if diverged || finished {
+            self.write_report();
+        }
+        if finished {
+            info!(
+                "🏁 Candidate trial finished after {} blocks: {} divergence(s), {} live vs {} candidate update(s); report written to {}",
+                self.report.blocks_observed,
+                self.report.divergences.len(),
+                self.report.live_updates,
+                self.report.candidate_updates,
+                self.report_path.display()
+            );
+        }
+    }
+
+    fn write_report(&self) {
+        let result = serde_json::to_string_pretty(&self.report)
+            .map_err(std::io::Error::other)
+            .and_then(|contents| std::fs::write(&self.report_path, contents));
+        if let Err(e) = result {
+            error!("Failed to write divergence report: {:?}", e);
+        }
+    }
+}
+
+// Only components known to the live config can be compared
+pub fn matching_components(
+    live: &[FeeComponent],
+    candidate: Vec<FeeComponent>,
+) -> Vec<FeeComponent> {
+    candidate
+        .into_iter()
+        .filter(|component| {
+            let known = live.iter().any(|live| live.name == component.name);
+            if !known {
+                warn!(
+                    "⚠️ Candidate component `{}` is not tracked by the live config and will be ignored",
+                    component.name
+                );
+            }
+            known
+        })
+        .collect()
+}
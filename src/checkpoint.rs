@@ -0,0 +1,146 @@
+use std::{fs, io::ErrorKind, path::PathBuf};
+
+use anyhow::Context;
+use tracing::{error, warn};
+
+// Last block the fee was checked at, kept on disk when a file is configured so a restart
+// or a reconnect can tell which blocks went by unchecked
+#[derive(Debug, Default)]
+pub struct BlockCheckpoint {
+    path: Option<PathBuf>,
+    last: Option<u64>,
+}
+
+impl BlockCheckpoint {
+    pub fn load(path: Option<PathBuf>) -> anyhow::Result<Self> {
+        let Some(file) = &path else {
+            return Ok(Self::default());
+        };
+        let last = match fs::read_to_string(file) {
+            Ok(contents) => Some(
+                contents
+                    .trim()
+                    .parse::<u64>()
+                    .with_context(|| format!("Invalid block number in {}", file.display()))?,
+            ),
+            Err(e) if e.kind() == ErrorKind::NotFound => None,
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", file.display())),
+        };
+        Ok(Self { path, last })
+    }
+
+    pub fn last(&self) -> Option<u64> {
+        self.last
+    }
+
+    // Records a checked block; a gap to the one before is logged and counted. Older blocks,
+    // as a replay checks them, leave the checkpoint where it is.
+    pub fn advance(&mut self, block_number: u64) {
+        if let Some(last) = self.last {
+            if block_number <= last {
+                return;
+            }
+            let missed = block_number - last - 1;
+            if missed > 0 {
+                warn!(
+                    "🕳️ {} block(s) after block {} went by unchecked, reconciling at {}",
+                    missed, last, block_number
+                );
+                metrics::counter!("pp_fee_updater_missed_blocks_total").increment(missed);
+            }
+        }
+        self.last = Some(block_number);
+        if let Some(path) = &self.path {
+            // Written aside and renamed, so a crash never leaves a torn file
+            let staged = path.with_extension("tmp");
+            let written = fs::write(&staged, block_number.to_string())
+                .and_then(|()| fs::rename(&staged, path));
+            if let Err(e) = written {
+                error!(
+                    "Failed to save the last block to {}: {:?}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh file under the temp dir, unique to the test
+    fn last_block_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "pp-fee-updater-{}-last-block-{name}",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn without_a_file_the_checkpoint_lives_in_memory() {
+        let mut checkpoint = BlockCheckpoint::load(None).unwrap();
+        assert_eq!(checkpoint.last(), None);
+        checkpoint.advance(100);
+        assert_eq!(checkpoint.last(), Some(100));
+    }
+
+    #[test]
+    fn missing_file_starts_without_a_last_block() {
+        let path = last_block_file("missing");
+        let checkpoint = BlockCheckpoint::load(Some(path)).unwrap();
+        assert_eq!(checkpoint.last(), None);
+    }
+
+    #[test]
+    fn saved_block_survives_a_restart() {
+        let path = last_block_file("restart");
+        let mut checkpoint = BlockCheckpoint::load(Some(path.clone())).unwrap();
+        checkpoint.advance(100);
+        checkpoint.advance(105);
+
+        let reloaded = BlockCheckpoint::load(Some(path.clone())).unwrap();
+        assert_eq!(reloaded.last(), Some(105));
+        // Nothing is left staged
+        assert!(!path.with_extension("tmp").exists());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn surrounding_whitespace_is_ignored() {
+        let path = last_block_file("whitespace");
+        fs::write(&path, " 42\n").unwrap();
+        let checkpoint = BlockCheckpoint::load(Some(path.clone())).unwrap();
+        assert_eq!(checkpoint.last(), Some(42));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn invalid_contents_are_an_error() {
+        for contents in ["", "block 42", "-1"] {
+            let path = last_block_file("invalid");
+            fs::write(&path, contents).unwrap();
+            let error = BlockCheckpoint::load(Some(path.clone())).unwrap_err();
+            assert!(
+                error.to_string().starts_with("Invalid block number"),
+                "{error}"
+            );
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn replayed_blocks_leave_the_checkpoint_where_it_is() {
+        let path = last_block_file("replay");
+        let mut checkpoint = BlockCheckpoint::load(Some(path.clone())).unwrap();
+        checkpoint.advance(100);
+        checkpoint.advance(100);
+        checkpoint.advance(90);
+        assert_eq!(checkpoint.last(), Some(100));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "100");
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,638 @@
+use serde::{Deserialize, Serialize};
+use starknet::core::{
+    types::{Felt, ResourcePrice},
+    utils::get_selector_from_name,
+};
+
+use crate::calldata::{CalldataLayout, PriceEncoding};
+use crate::contracts::PoolPrice;
+use crate::tokens::FeeToken;
+use crate::updater::UpdaterError;
+
+// Which value a fee component tracks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceSource {
+    L1Gas,
+    L1DataGas,
+    L2Gas,
+    // A fixed target value from the configuration
+    Flat,
+}
+
+// Unit the network prices are read in and written to the contract
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, clap::ValueEnum)]
+pub enum Denomination {
+    /// STRK-denominated prices (`price_in_fri`)
+    Fri,
+    /// ETH-denominated prices (`price_in_wei`)
+    Wei,
+}
+
+impl Denomination {
+    pub fn price(self, resource_price: &ResourcePrice) -> Felt {
+        match self {
+            Denomination::Fri => resource_price.price_in_fri,
+            Denomination::Wei => resource_price.price_in_wei,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Denomination::Fri => "fri",
+            Denomination::Wei => "wei",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Upward,
+    Downward,
+    None,
+}
+
+impl Direction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Direction::Upward => "upward",
+            Direction::Downward => "downward",
+            Direction::None => "none",
+        }
+    }
+}
+
+// Asymmetric dead-band strategy, all values in percent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThresholdStrategy {
+    pub upward_threshold: u128,
+    pub downward_threshold: u128,
+    pub upward_buffer: u128,
+    pub downward_buffer: u128,
+    // Largest change of the contract price in a single update
+    #[serde(default)]
+    pub max_change_percent: Option<u128>,
+    // Absolute floor and ceiling of the written price
+    #[serde(default)]
+    pub min_price: Option<u128>,
+    #[serde(default)]
+    pub max_price: Option<u128>,
+}
+
+impl ThresholdStrategy {
+    // Returns the move direction and the buffered, change-limited target when an update is due
+    pub fn decide(&self, network_price: u128, contract_price: u128) -> (Direction, Option<u128>) {
+        let (direction, target) = self.target(network_price, contract_price);
+        let target = target
+            .map(|target| self.bound(self.limit_change(contract_price, target)))
+            .filter(|target| *target != contract_price);
+        (direction, target)
+    }
+
+    // Never below the floor nor above the ceiling
+    pub fn bound(&self, target: u128) -> u128 {
+        let target = self.min_price.map_or(target, |min| target.max(min));
+        self.max_price.map_or(target, |max| target.min(max))
+    }
+
+    // Keep the target within `max_change_percent` of the contract price; an unset contract
+    // price has nothing to be relative to
+    pub fn limit_change(&self, contract_price: u128, target: u128) -> u128 {
+        match self.max_change_percent {
+            Some(max_change) if contract_price > 0 => {
+                let step = percent_of(contract_price, max_change);
+                target.clamp(
+                    contract_price.saturating_sub(step),
+                    contract_price.saturating_add(step),
+                )
+            }
+            _ => target,
+        }
+    }
+
+    // Buffered target regardless of the dead-band, for refreshing a stale value
+    pub fn refresh_target(&self, network_price: u128, contract_price: u128) -> (Direction, u128) {
+        if network_price >= contract_price {
+            (
+                Direction::Upward,
+                percent_of(network_price, self.upward_buffer),
+            )
+        } else {
+            (
+                Direction::Downward,
+                percent_of(network_price, self.downward_buffer),
+            )
+        }
+    }
+
+    // Move direction and buffered target before the change limit
+    pub fn target(&self, network_price: u128, contract_price: u128) -> (Direction, Option<u128>) {
+        // Asymmetric paymaster thresholds for profit optimization
+        let upward_threshold = percent_of(contract_price, self.upward_threshold);
+        let downward_threshold = percent_of(contract_price, self.downward_threshold);
+
+        if network_price > upward_threshold {
+            // Gas price rising - quick reaction for profits
+            (
+                Direction::Upward,
+                Some(percent_of(network_price, self.upward_buffer)),
+            )
+        } else if network_price < downward_threshold {
+            // Gas price falling - slow reaction to preserve margins
+            (
+                Direction::Downward,
+                Some(percent_of(network_price, self.downward_buffer)),
+            )
+        } else {
+            // Within acceptable range
+            (Direction::None, None)
+        }
+    }
+}
+
+// Runtime change of a strategy; unset fields keep their current value
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StrategyPatch {
+    #[serde(default)]
+    pub upward_threshold: Option<u128>,
+    #[serde(default)]
+    pub downward_threshold: Option<u128>,
+    #[serde(default)]
+    pub upward_buffer: Option<u128>,
+    #[serde(default)]
+    pub downward_buffer: Option<u128>,
+    #[serde(default)]
+    pub max_change_percent: Option<u128>,
+    #[serde(default)]
+    pub min_price: Option<u128>,
+    #[serde(default)]
+    pub max_price: Option<u128>,
+}
+
+impl StrategyPatch {
+    pub fn apply(&self, strategy: ThresholdStrategy) -> ThresholdStrategy {
+        ThresholdStrategy {
+            upward_threshold: self.upward_threshold.unwrap_or(strategy.upward_threshold),
+            downward_threshold: self
+                .downward_threshold
+                .unwrap_or(strategy.downward_threshold),
+            upward_buffer: self.upward_buffer.unwrap_or(strategy.upward_buffer),
+            downward_buffer: self.downward_buffer.unwrap_or(strategy.downward_buffer),
+            max_change_percent: self.max_change_percent.or(strategy.max_change_percent),
+            min_price: self.min_price.or(strategy.min_price),
+            max_price: self.max_price.or(strategy.max_price),
+        }
+    }
+}
+
+// Component as written in the config file; unset strategy fields fall back to the CLI values
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ComponentConfig {
+    pub name: String,
+    pub source: PriceSource,
+    #[serde(default)]
+    pub flat_value: Option<u128>,
+    pub getter: String,
+    pub setter: String,
+    // Privacy pool the component lives on, when not PRIVACY_POOL_ADDRESS
+    #[serde(default)]
+    pub pool: Option<Felt>,
+    // Fixed arguments passed ahead of the price, e.g. a token address
+    #[serde(default)]
+    pub getter_args: Vec<Felt>,
+    #[serde(default)]
+    pub setter_args: Vec<Felt>,
+    #[serde(default)]
+    pub encoding: PriceEncoding,
+    // Expand into one component per configured fee token, passing the token address
+    // ahead of the other arguments
+    #[serde(default)]
+    pub per_token: bool,
+    #[serde(default)]
+    pub upward_threshold: Option<u128>,
+    #[serde(default)]
+    pub downward_threshold: Option<u128>,
+    #[serde(default)]
+    pub upward_buffer: Option<u128>,
+    #[serde(default)]
+    pub downward_buffer: Option<u128>,
+    #[serde(default)]
+    pub max_change_percent: Option<u128>,
+    #[serde(default)]
+    pub min_price: Option<u128>,
+    #[serde(default)]
+    pub max_price: Option<u128>,
+}
+
+impl ComponentConfig {
+    // Component configured on the command line rather than in the config file; everything
+    // besides the entry points takes the same default as an omitted config field
+    pub fn builtin(name: &str, source: PriceSource, getter: &str, setter: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            source,
+            flat_value: None,
+            getter: getter.to_string(),
+            setter: setter.to_string(),
+            pool: None,
+            getter_args: Vec::new(),
+            setter_args: Vec::new(),
+            encoding: PriceEncoding::default(),
+            per_token: false,
+            upward_threshold: None,
+            downward_threshold: None,
+            upward_buffer: None,
+            downward_buffer: None,
+            max_change_percent: None,
+            min_price: None,
+            max_price: None,
+        }
+    }
+
+    pub fn resolve(&self, defaults: ThresholdStrategy) -> Result<FeeComponent, UpdaterError> {
+        if self.source == PriceSource::Flat && self.flat_value.is_none() {
+            return Err(UpdaterError::Configuration(format!(
+                "Component `{}` uses the flat source but has no flat_value",
+                self.name
+            )));
+        }
+        let min_price = self.min_price.or(defaults.min_price);
+        let max_price = self.max_price.or(defaults.max_price);
+        if let (Some(min_price), Some(max_price)) = (min_price, max_price) {
+            if min_price > max_price {
+                return Err(UpdaterError::Configuration(format!(
+                    "Component `{}` has min_price {} above max_price {}",
+                    self.name, min_price, max_price
+                )));
+            }
+        }
+
+        Ok(FeeComponent {
+            name: self.name.clone(),
+            source: self.source,
+            flat_value: self.flat_value,
+            getter: selector(&self.getter)?,
+            setter: selector(&self.setter)?,
+            pool: self.pool,
+            layout: CalldataLayout {
+                getter_args: self.getter_args.clone(),
+                setter_args: self.setter_args.clone(),
+                encoding: self.encoding,
+                binding: self.binding(),
+            },
+            token: None,
+            strategy: ThresholdStrategy {
+                upward_threshold: self.upward_threshold.unwrap_or(defaults.upward_threshold),
+                downward_threshold: self
+                    .downward_threshold
+                    .unwrap_or(defaults.downward_threshold),
+                upward_buffer: self.upward_buffer.unwrap_or(defaults.upward_buffer),
+                downward_buffer: self.downward_buffer.unwrap_or(defaults.downward_buffer),
+                max_change_percent: self.max_change_percent.or(defaults.max_change_percent),
+                min_price,
+                max_price,
+            },
+        })
+    }
+
+    // The pool's own entry points, taking nothing but a u256 price, go through its bindings
+    fn binding(&self) -> Option<PoolPrice> {
+        if !self.getter_args.is_empty()
+            || !self.setter_args.is_empty()
+            || self.encoding != PriceEncoding::U256
+        {
+            return None;
+        }
+        PoolPrice::for_entry_points(self.source, &self.getter, &self.setter)
+    }
+
+    pub fn resolve_for_token(
+        &self,
+        defaults: ThresholdStrategy,
+        token: &FeeToken,
+    ) -> Result<FeeComponent, UpdaterError> {
+        let mut component = self.resolve(defaults)?;
+        component.name = format!("{}:{}", self.name, token.symbol);
+        component.layout.getter_args.insert(0, token.address);
+        component.layout.setter_args.insert(0, token.address);
+        component.layout.binding = None;
+        component.token = Some(token.clone());
+        Ok(component)
+    }
+}
+
+// A tracked on-chain value with its own strategy and getter/setter entrypoints
+#[derive(Debug, Clone)]
+pub struct FeeComponent {
+    pub name: String,
+    pub source: PriceSource,
+    pub flat_value: Option<u128>,
+    pub getter: Felt,
+    pub setter: Felt,
+    // Contract other than the configured privacy pool, if any
+    pub pool: Option<Felt>,
+    pub layout: CalldataLayout,
+    // Fee token whose units the network price is converted into
+    pub token: Option<FeeToken>,
+    pub strategy: ThresholdStrategy,
+}
+
+impl FeeComponent {
+    // The network-side value this component tracks; flat values are taken as configured
+    pub fn network_price(
+        &self,
+        denomination: Denomination,
+        l1_gas_price: &ResourcePrice,
+        l1_data_gas_price: &ResourcePrice,
+        l2_gas_price: &ResourcePrice,
+    ) -> Felt {
+        match self.source {
+            PriceSource::L1Gas => denomination.price(l1_gas_price),
+            PriceSource::L1DataGas => denomination.price(l1_data_gas_price),
+            PriceSource::L2Gas => denomination.price(l2_gas_price),
+            PriceSource::Flat => Felt::from(self.flat_value.unwrap_or_default()),
+        }
+    }
+}
+
+// Every contract the components live on, the default pool first
+pub fn pools(default_pool: Felt, components: &[FeeComponent]) -> Vec<Felt> {
+    let mut pools = vec![default_pool];
+    for component in components {
+        let pool = component.pool.unwrap_or(default_pool);
+        if !pools.contains(&pool) {
+            pools.push(pool);
+        }
+    }
+    pools
+}
+
+// `value * percent / 100` rounded down, without the intermediate product overflowing for
+// large fri values; a result beyond u128 saturates at `u128::MAX`
+pub fn percent_of(value: u128, percent: u128) -> u128 {
+    // With value = 100a + r and percent = 100b + s the result is
+    // 100ab + as + rb + rs / 100, where only the terms can overflow
+    let (a, r) = (value / 100, value % 100);
+    let (b, s) = (percent / 100, percent % 100);
+    a.checked_mul(b)
+        .and_then(|ab| ab.checked_mul(100))
+        .and_then(|sum| sum.checked_add(a.checked_mul(s)?))
+        .and_then(|sum| sum.checked_add(r.checked_mul(b)?))
+        .and_then(|sum| sum.checked_add(r * s / 100))
+        .unwrap_or(u128::MAX)
+}
+
+pub fn selector(name: &str) -> Result<Felt, UpdaterError> {
+    get_selector_from_name(name)
+        .map_err(|e| UpdaterError::Configuration(format!("Invalid selector `{name}`: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn strategy() -> ThresholdStrategy {
+        ThresholdStrategy {
+            upward_threshold: 105,
+            downward_threshold: 85,
+            upward_buffer: 110,
+            downward_buffer: 110,
+            max_change_percent: None,
+            min_price: None,
+            max_price: None,
+        }
+    }
+
+    // Prices spread over small, fri-sized and extreme values
+    fn price() -> impl Strategy<Value = u128> {
+        prop_oneof![
+            0..1_000u128,
+            0..100_000_000_000_000u128,
+            any::<u128>(),
+            Just(u128::MAX),
+        ]
+    }
+
+    // Thresholds around the contract price and buffers that never undercut the network
+    fn any_strategy() -> impl Strategy<Value = ThresholdStrategy> {
+        (100..=200u128, 1..=100u128, 100..=300u128, 100..=300u128).prop_map(
+            |(upward_threshold, downward_threshold, upward_buffer, downward_buffer)| {
+                ThresholdStrategy {
+                    upward_threshold,
+                    downward_threshold,
+                    upward_buffer,
+                    downward_buffer,
+                    ..strategy()
+                }
+            },
+        )
+    }
+
+    // A strategy, a contract price and a network price inside its dead-band, edges included
+    fn within_the_dead_band() -> impl Strategy<Value = (ThresholdStrategy, u128, u128)> {
+        (any_strategy(), price()).prop_flat_map(|(strategy, contract)| {
+            let low = percent_of(contract, strategy.downward_threshold);
+            let high = percent_of(contract, strategy.upward_threshold);
+            (Just(strategy), Just(contract), low..=high)
+        })
+    }
+
+    #[test]
+    fn target_follows_the_readme_examples() {
+        assert_eq!(strategy().target(100, 95), (Direction::Upward, Some(110)));
+        assert_eq!(strategy().target(80, 100), (Direction::Downward, Some(88)));
+        assert_eq!(strategy().target(100, 100), (Direction::None, None));
+    }
+
+    #[test]
+    fn target_is_due_only_outside_the_dead_band() {
+        // 105% and 85% of 1000 are the edges, which still count as within the band
+        assert_eq!(strategy().target(1050, 1000), (Direction::None, None));
+        assert_eq!(strategy().target(850, 1000), (Direction::None, None));
+        assert_eq!(strategy().target(1051, 1000).0, Direction::Upward);
+        assert_eq!(strategy().target(849, 1000).0, Direction::Downward);
+    }
+
+    #[test]
+    fn refresh_target_ignores_the_dead_band() {
+        assert_eq!(
+            strategy().refresh_target(100, 100),
+            (Direction::Upward, 110)
+        );
+        assert_eq!(
+            strategy().refresh_target(99, 100),
+            (Direction::Downward, 108)
+        );
+        let asymmetric = ThresholdStrategy {
+            downward_buffer: 120,
+            ..strategy()
+        };
+        assert_eq!(
+            asymmetric.refresh_target(50, 100),
+            (Direction::Downward, 60)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn buffered_target_is_monotonic_in_the_network_price(
+            strategy in any_strategy(),
+            contract in price(),
+            low in price(),
+            high in price(),
+        ) {
+            let (low, high) = (low.min(high), low.max(high));
+            if let ((low_direction, Some(low_target)), (high_direction, Some(high_target))) =
+                (strategy.target(low, contract), strategy.target(high, contract))
+            {
+                if low_direction == high_direction {
+                    prop_assert!(low_target <= high_target, "{low_target} above {high_target}");
+                }
+            }
+            let (low_direction, low_target) = strategy.refresh_target(low, contract);
+            let (high_direction, high_target) = strategy.refresh_target(high, contract);
+            if low_direction == high_direction {
+                prop_assert!(low_target <= high_target);
+            }
+        }
+
+        #[test]
+        fn upward_target_is_never_below_the_network_price(
+            strategy in any_strategy(),
+            contract in price(),
+            network in price(),
+        ) {
+            if let (Direction::Upward, Some(target)) = strategy.target(network, contract) {
+                prop_assert!(target >= network, "{network} buffered to {target}");
+            }
+            // Without a change limit or a ceiling nothing pulls it back below
+            if let (Direction::Upward, Some(target)) = strategy.decide(network, contract) {
+                prop_assert!(target >= network);
+            }
+        }
+
+        #[test]
+        fn dead_band_is_a_no_op((strategy, contract, network) in within_the_dead_band()) {
+            prop_assert_eq!(strategy.target(network, contract), (Direction::None, None));
+            prop_assert_eq!(strategy.decide(network, contract), (Direction::None, None));
+        }
+
+        #[test]
+        fn bound_stays_within_the_floor_and_ceiling(
+            min in price(),
+            max in price(),
+            target in price(),
+        ) {
+            let (min, max) = (min.min(max), min.max(max));
+            let bounded = ThresholdStrategy {
+                min_price: Some(min),
+                max_price: Some(max),
+                ..strategy()
+            };
+            let result = bounded.bound(target);
+            prop_assert!((min..=max).contains(&result), "{target} bounded to {result}");
+            if (min..=max).contains(&target) {
+                prop_assert_eq!(result, target);
+            }
+        }
+
+        #[test]
+        fn bound_without_limits_keeps_the_target(target in price()) {
+            prop_assert_eq!(strategy().bound(target), target);
+        }
+
+        #[test]
+        fn limit_change_never_moves_further_than_the_step(
+            max_change in prop_oneof![0..=250u128, Just(u128::MAX)],
+            contract in price(),
+            target in price(),
+        ) {
+            let limited = ThresholdStrategy {
+                max_change_percent: Some(max_change),
+                ..strategy()
+            };
+            let result = limited.limit_change(contract, target);
+            if contract == 0 {
+                prop_assert_eq!(result, target);
+            } else {
+                let step = percent_of(contract, max_change);
+                prop_assert!(result.abs_diff(contract) <= step, "{contract} -> {result}");
+                // Moves towards the target, never past it
+                prop_assert!(result.abs_diff(target) <= contract.abs_diff(target));
+            }
+        }
+
+        #[test]
+        fn decide_never_returns_the_contract_price(
+            strategy in any_strategy(),
+            max_change in proptest::option::of(0..=100u128),
+            floor in proptest::option::of(price()),
+            contract in price(),
+            network in price(),
+        ) {
+            let strategy = ThresholdStrategy {
+                max_change_percent: max_change,
+                min_price: floor,
+                max_price: floor.map(|floor| floor.saturating_mul(2)),
+                ..strategy
+            };
+            let (_, target) = strategy.decide(network, contract);
+            prop_assert_ne!(target, Some(contract), "{} vs {}", network, contract);
+        }
+    }
+
+    #[test]
+    fn limit_change_caps_a_single_update() {
+        let limited = ThresholdStrategy {
+            max_change_percent: Some(20),
+            ..strategy()
+        };
+        assert_eq!(limited.limit_change(100, 500), 120);
+        assert_eq!(limited.limit_change(100, 10), 80);
+        assert_eq!(limited.limit_change(100, 110), 110);
+    }
+
+    #[test]
+    fn percent_of_matches_the_naive_product_for_small_inputs() {
+        for value in (0..2_000).chain([u64::MAX.into(), u128::from(u64::MAX) * 1_000]) {
+            for percent in (0..=300).chain([1_000, 10_000]) {
+                assert_eq!(
+                    percent_of(value, percent),
+                    value * percent / 100,
+                    "{value} * {percent}%"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn percent_of_is_exact_up_to_u128_max() {
+        assert_eq!(percent_of(u128::MAX, 100), u128::MAX);
+        assert_eq!(percent_of(u128::MAX / 2, 200), u128::MAX - 1);
+        assert_eq!(percent_of(u128::MAX, 50), u128::MAX / 2);
+        assert_eq!(percent_of(u128::MAX, 0), 0);
+        assert_eq!(percent_of(0, u128::MAX), 0);
+    }
+
+    #[test]
+    fn percent_of_saturates_beyond_u128() {
+        assert_eq!(percent_of(u128::MAX, 101), u128::MAX);
+        assert_eq!(percent_of(u128::MAX / 2, 201), u128::MAX);
+        assert_eq!(percent_of(u128::MAX, u128::MAX), u128::MAX);
+    }
+
+    #[test]
+    fn decide_holds_when_the_clamp_lands_on_the_contract_price() {
+        // The ceiling pulls the upward target back to the price already written
+        let capped = ThresholdStrategy {
+            max_price: Some(1_000),
+            ..strategy()
+        };
+        assert_eq!(capped.decide(2_000, 1_000), (Direction::Upward, None));
+        assert_eq!(capped.decide(2_000, 500), (Direction::Upward, Some(1_000)));
+    }
+}
@@ -0,0 +1,61 @@
+use std::path::Path;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{ComponentConfig, FeeComponent, ThresholdStrategy};
+use crate::sources::PriceSourcesConfig;
+use crate::tokens::TokenConfig;
+use crate::updater::UpdaterError;
+
+// Settings loaded from the optional TOML config file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    #[serde(default)]
+    pub components: Vec<ComponentConfig>,
+    // Fee tokens of a multi-token paymaster, used by `per_token` components
+    #[serde(default)]
+    pub tokens: Vec<TokenConfig>,
+    // Gas price sources folded into the block prices
+    #[serde(default)]
+    pub price_sources: Option<PriceSourcesConfig>,
+}
+
+impl FileConfig {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse config file {}", path.display()))
+    }
+    // Per-token components are expanded into one component per fee token
+    pub fn resolve_components(
+        &self,
+        defaults: ThresholdStrategy,
+    ) -> Result<Vec<FeeComponent>, UpdaterError> {
+        let tokens = self
+            .tokens
+            .iter()
+            .map(TokenConfig::resolve)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut components = Vec::new();
+        for component in &self.components {
+            if !component.per_token {
+                components.push(component.resolve(defaults)?);
+                continue;
+            }
+            if tokens.is_empty() {
+                return Err(UpdaterError::Configuration(format!(
+                    "Component `{}` is per token but no tokens are configured",
+                    component.name
+                )));
+            }
+            for token in &tokens {
+                components.push(component.resolve_for_token(defaults, token)?);
+            }
+        }
+        Ok(components)
+    }
+}
@@ -0,0 +1,220 @@
+use cainome::cairo_serde::{ContractAddress, U256};
+use cainome::rs::abigen;
+use starknet::{
+    accounts::ConnectedAccount,
+    core::types::{BlockId, BlockTag, Call, Felt},
+};
+
+use crate::calldata::{u256_limbs, u256_to_felt};
+use crate::components::PriceSource;
+use crate::endpoints::RpcClient;
+use crate::updater::UpdaterError;
+
+// Typed bindings generated from the contract ABIs under abi/, so the calldata of the calls
+// below and the decoding of their results are checked at compile time
+abigen!(PrivacyPool, "./abi/privacy_pool.json");
+abigen!(Erc20, "./abi/erc20.json");
+
+// A price the privacy pool exposes through its own getter and setter. Components calling
+// other entry points, or with extra arguments, keep building their calldata from a
+// `CalldataLayout`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolPrice {
+    L1Gas,
+    L1DataGas,
+    L2Gas,
+}
+
+impl PoolPrice {
+    // The binding of a component, when its entry points are the pool's own for its source
+    pub fn for_entry_points(source: PriceSource, getter: &str, setter: &str) -> Option<Self> {
+        let price = match source {
+            PriceSource::L1Gas => PoolPrice::L1Gas,
+            PriceSource::L1DataGas => PoolPrice::L1DataGas,
+            PriceSource::L2Gas => PoolPrice::L2Gas,
+            PriceSource::Flat => return None,
+        };
+        let (pool_getter, pool_setter) = price.entry_points();
+        (getter == pool_getter && setter == pool_setter).then_some(price)
+    }
+
+    fn entry_points(self) -> (&'static str, &'static str) {
+        match self {
+            PoolPrice::L1Gas => ("get_current_gas_price", "set_current_gas_price"),
+            PoolPrice::L1DataGas => ("get_current_data_gas_price", "set_current_data_gas_price"),
+            PoolPrice::L2Gas => ("get_l2_gas_price", "set_l2_gas_price"),
+        }
+    }
+
+    pub async fn read(self, provider: &RpcClient, pool: Felt) -> Result<Felt, UpdaterError> {
+        let reader = PrivacyPoolReader::new(pool, provider.clone());
+        let call = match self {
+            PoolPrice::L1Gas => reader.get_current_gas_price(),
+            PoolPrice::L1DataGas => reader.get_current_data_gas_price(),
+            PoolPrice::L2Gas => reader.get_l2_gas_price(),
+        };
+        let price = call
+            .block_id(BlockId::Tag(BlockTag::Latest))
+            .call()
+            .await
+            .map_err(contract_error)?;
+        u256_to_felt(price.low, price.high)
+    }
+
+    pub fn setter_call<A: ConnectedAccount + Sync>(
+        self,
+        account: &A,
+        pool: Felt,
+        price: Felt,
+    ) -> Call {
+        let contract = PrivacyPool::new(pool, account);
+        let (low, high) = u256_limbs(price);
+        let price = U256 { low, high };
+        match self {
+            PoolPrice::L1Gas => contract.set_current_gas_price_getcall(&price),
+            PoolPrice::L1DataGas => contract.set_current_data_gas_price_getcall(&price),
+            PoolPrice::L2Gas => contract.set_l2_gas_price_getcall(&price),
+        }
+    }
+}
+
+// ERC20 balance of `account`, as a u256
+pub async fn erc20_balance(
+    provider: &RpcClient,
+    token: Felt,
+    account: Felt,
+) -> Result<U256, UpdaterError> {
+    Erc20Reader::new(token, provider.clone())
+        .balance_of(&ContractAddress(account))
+        .block_id(BlockId::Tag(BlockTag::Latest))
+        .call()
+        .await
+        .map_err(contract_error)
+}
+
+fn contract_error(e: cainome::cairo_serde::Error) -> UpdaterError {
+    match e {
+        cainome::cairo_serde::Error::Provider(e) => UpdaterError::Provider(e),
+        e => UpdaterError::InvalidGasPrice(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use starknet::{
+        accounts::{ExecutionEncoding, SingleOwnerAccount},
+        signers::{LocalWallet, SigningKey},
+    };
+
+    use super::*;
+    use crate::calldata::{encode_u256, PriceEncoding};
+    use crate::components::{selector, ComponentConfig, ThresholdStrategy};
+    use crate::fixtures::{Reply, StubNode};
+    use crate::tokens::{FeeToken, RateSource};
+    use crate::transport::RetryPolicy;
+
+    const POOL: Felt = Felt::from_hex_unchecked("0x9001");
+
+    fn strategy() -> ThresholdStrategy {
+        ThresholdStrategy {
+            upward_threshold: 105,
+            downward_threshold: 85,
+            upward_buffer: 110,
+            downward_buffer: 110,
+            max_change_percent: None,
+            min_price: None,
+            max_price: None,
+        }
+    }
+
+    // Node answering every contract call with `result`
+    fn provider(result: serde_json::Value) -> RpcClient {
+        let node = StubNode::new(move |method, _| match method {
+            "starknet_call" => Reply::Result(result.clone()),
+            _ => Reply::Error(-32601),
+        });
+        StubNode::pool(&[&node], RetryPolicy::default()).client()
+    }
+
+    #[test]
+    fn only_the_pool_entry_points_of_the_source_are_bound() {
+        assert_eq!(
+            PoolPrice::for_entry_points(PriceSource::L2Gas, "get_l2_gas_price", "set_l2_gas_price"),
+            Some(PoolPrice::L2Gas)
+        );
+        assert_eq!(
+            PoolPrice::for_entry_points(PriceSource::L1Gas, "get_l2_gas_price", "set_l2_gas_price"),
+            None
+        );
+        assert_eq!(
+            PoolPrice::for_entry_points(PriceSource::L2Gas, "get_l2_gas_price", "set_fee"),
+            None
+        );
+        assert_eq!(
+            PoolPrice::for_entry_points(PriceSource::Flat, "get_l2_gas_price", "set_l2_gas_price"),
+            None
+        );
+    }
+
+    #[test]
+    fn components_with_extra_arguments_or_felt_prices_are_not_bound() {
+        let builtin = ComponentConfig::builtin(
+            "l1_gas",
+            PriceSource::L1Gas,
+            "get_current_gas_price",
+            "set_current_gas_price",
+        );
+        let resolved = builtin.resolve(strategy()).unwrap();
+        assert_eq!(resolved.layout.binding, Some(PoolPrice::L1Gas));
+
+        let felt = ComponentConfig {
+            encoding: PriceEncoding::Felt,
+            ..builtin.clone()
+        };
+        assert_eq!(felt.resolve(strategy()).unwrap().layout.binding, None);
+
+        let token = FeeToken {
+            symbol: "ETH".to_string(),
+            address: Felt::ONE,
+            rate: RateSource::Fixed(1),
+            rate_decimals: 0,
+        };
+        let per_token = builtin.resolve_for_token(strategy(), &token).unwrap();
+        assert_eq!(per_token.layout.binding, None);
+    }
+
+    #[tokio::test]
+    async fn bound_setter_call_matches_the_u256_calldata() {
+        let account = SingleOwnerAccount::new(
+            provider(json!([])),
+            LocalWallet::from(SigningKey::from_secret_scalar(Felt::TWO)),
+            Felt::ONE,
+            Felt::ONE,
+            ExecutionEncoding::New,
+        );
+        let price = Felt::from(u128::MAX) + Felt::from(8u8);
+
+        let call = PoolPrice::L2Gas.setter_call(&account, POOL, price);
+        assert_eq!(call.to, POOL);
+        assert_eq!(call.selector, selector("set_l2_gas_price").unwrap());
+        assert_eq!(call.calldata, encode_u256(price).to_vec());
+    }
+
+    #[tokio::test]
+    async fn bound_read_decodes_the_u256() {
+        let price = PoolPrice::L1DataGas
+            .read(&provider(json!(["0x7", "0x1"])), POOL)
+            .await
+            .unwrap();
+        assert_eq!(price, Felt::from(u128::MAX) + Felt::from(8u8));
+    }
+
+    #[tokio::test]
+    async fn balance_is_read_as_a_u256() {
+        let balance = erc20_balance(&provider(json!(["0x2a", "0x0"])), POOL, Felt::ONE)
+            .await
+            .unwrap();
+        assert_eq!((balance.low, balance.high), (42, 0));
+    }
+}
@@ -0,0 +1,65 @@
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::components::StrategyPatch;
+
+// Operator commands applied by the event loop between fee checks, so they never race
+// a check or send in progress
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ControlCommand {
+    Status,
+    Pause,
+    Resume,
+    // Run a fee check now instead of waiting for the next block
+    Check,
+    // Change thresholds and buffers of one component, or of all when none is named
+    SetStrategy {
+        #[serde(default)]
+        component: Option<String>,
+        strategy: StrategyPatch,
+    },
+    // Write a price regardless of the policy
+    SetPrice {
+        component: String,
+        price: u128,
+    },
+    // Let the next update of a component through the divergence guard
+    ConfirmUpdate {
+        component: String,
+    },
+}
+
+pub type ControlResponse = Result<Value, String>;
+
+#[derive(Debug)]
+pub struct ControlRequest {
+    pub command: ControlCommand,
+    pub reply: oneshot::Sender<ControlResponse>,
+}
+
+// Cloneable sender the control interfaces hand their commands to
+#[derive(Debug, Clone)]
+pub struct ControlHandle {
+    sender: mpsc::Sender<ControlRequest>,
+}
+
+impl ControlHandle {
+    pub async fn send(&self, command: ControlCommand) -> ControlResponse {
+        let (reply, response) = oneshot::channel();
+        self.sender
+            .send(ControlRequest { command, reply })
+            .await
+            .map_err(|_| "the updater is shutting down".to_string())?;
+        response
+            .await
+            .map_err(|_| "the updater dropped the command".to_string())?
+    }
+}
+
+pub fn channel() -> (ControlHandle, mpsc::Receiver<ControlRequest>) {
+    // Commands are rare; a small buffer is plenty
+    let (sender, receiver) = mpsc::channel(16);
+    (ControlHandle { sender }, receiver)
+}
@@ -0,0 +1,149 @@
+use std::time::{Duration, Instant};
+
+// Quiet period after a confirmed update, so gas oscillating around a threshold does not
+// churn the fee and burn transaction costs. Every configured limit has to elapse.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cooldown {
+    pub blocks: Option<u64>,
+    pub duration: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LastUpdate {
+    block: Option<u64>,
+    at: Instant,
+}
+
+#[derive(Debug, Default)]
+pub struct CooldownTracker {
+    policy: Cooldown,
+    last_update: Option<LastUpdate>,
+}
+
+impl CooldownTracker {
+    pub fn new(policy: Cooldown) -> Self {
+        Self {
+            policy,
+            last_update: None,
+        }
+    }
+
+    pub fn on_confirmed(&mut self, block: Option<u64>) {
+        if self.policy.blocks.is_some() || self.policy.duration.is_some() {
+            self.last_update = Some(LastUpdate {
+                block,
+                at: Instant::now(),
+            });
+        }
+    }
+
+    // What is left of the cooldown at `block`, if anything
+    pub fn remaining(&self, block: Option<u64>) -> Option<String> {
+        let last_update = self.last_update?;
+        let mut remaining = Vec::new();
+
+        if let (Some(blocks), Some(from), Some(block)) =
+            (self.policy.blocks, last_update.block, block)
+        {
+            let left = (from + blocks).saturating_sub(block);
+            if left > 0 {
+                remaining.push(format!("{left} block(s)"));
+            }
+        }
+        if let Some(duration) = self.policy.duration {
+            let left = duration.saturating_sub(last_update.at.elapsed());
+            if !left.is_zero() {
+                remaining.push(format!("{}s", left.as_secs().max(1)));
+            }
+        }
+
+        (!remaining.is_empty()).then(|| remaining.join(" and "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocks(blocks: u64) -> CooldownTracker {
+        CooldownTracker::new(Cooldown {
+            blocks: Some(blocks),
+            duration: None,
+        })
+    }
+
+    #[test]
+    fn nothing_remains_before_the_first_update() {
+        assert_eq!(blocks(5).remaining(Some(100)), None);
+    }
+
+    #[test]
+    fn without_limits_updates_are_not_recorded() {
+        let mut tracker = CooldownTracker::default();
+        tracker.on_confirmed(Some(100));
+        assert_eq!(tracker.remaining(Some(100)), None);
+    }
+
+    #[test]
+    fn block_cooldown_ends_at_exactly_the_boundary_block() {
+        let mut tracker = blocks(5);
+        tracker.on_confirmed(Some(100));
+        assert_eq!(tracker.remaining(Some(100)).as_deref(), Some("5 block(s)"));
+        assert_eq!(tracker.remaining(Some(104)).as_deref(), Some("1 block(s)"));
+        assert_eq!(tracker.remaining(Some(105)), None);
+        assert_eq!(tracker.remaining(Some(106)), None);
+    }
+
+    #[test]
+    fn block_cooldown_needs_both_blocks_known() {
+        let mut tracker = blocks(5);
+        tracker.on_confirmed(None);
+        assert_eq!(tracker.remaining(Some(100)), None);
+        tracker.on_confirmed(Some(100));
+        assert_eq!(tracker.remaining(None), None);
+    }
+
+    #[test]
+    fn later_update_restarts_the_cooldown() {
+        let mut tracker = blocks(5);
+        tracker.on_confirmed(Some(100));
+        tracker.on_confirmed(Some(103));
+        assert_eq!(tracker.remaining(Some(105)).as_deref(), Some("3 block(s)"));
+    }
+
+    #[test]
+    fn duration_cooldown_rounds_the_last_second_up() {
+        let mut tracker = CooldownTracker::new(Cooldown {
+            blocks: None,
+            duration: Some(Duration::from_millis(500)),
+        });
+        tracker.on_confirmed(None);
+        assert_eq!(tracker.remaining(None).as_deref(), Some("1s"));
+        std::thread::sleep(Duration::from_millis(600));
+        assert_eq!(tracker.remaining(None), None);
+    }
+
+    #[test]
+    fn zero_duration_is_over_at_once() {
+        let mut tracker = CooldownTracker::new(Cooldown {
+            blocks: None,
+            duration: Some(Duration::ZERO),
+        });
+        tracker.on_confirmed(None);
+        assert_eq!(tracker.remaining(None), None);
+    }
+
+    #[test]
+    fn every_limit_left_is_reported() {
+        let mut tracker = CooldownTracker::new(Cooldown {
+            blocks: Some(5),
+            duration: Some(Duration::from_secs(3600)),
+        });
+        tracker.on_confirmed(Some(100));
+        let remaining = tracker.remaining(Some(102)).unwrap();
+        assert!(remaining.starts_with("3 block(s) and "), "{remaining}");
+        // The duration still holds once the blocks are past
+        let remaining = tracker.remaining(Some(105)).unwrap();
+        assert!(!remaining.contains("block"), "{remaining}");
+    }
+}
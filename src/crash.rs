@@ -0,0 +1,127 @@
+use std::{
+    backtrace::Backtrace,
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, TryLockError},
+};
+
+use serde::Serialize;
+use tracing::{error, info};
+
+use crate::history::{unix_timestamp, DecisionRecord};
+
+#[derive(Debug, Default, Clone, Serialize)]
+struct CrashState {
+    last_block: Option<u64>,
+    pending_transactions: Vec<String>,
+    recent_decisions: VecDeque<DecisionRecord>,
+}
+
+#[derive(Debug, Serialize)]
+struct CrashReport<'a> {
+    timestamp: u64,
+    reason: &'a str,
+    message: String,
+    location: Option<String>,
+    config_fingerprint: &'a str,
+    state: CrashState,
+    backtrace: String,
+}
+
+// Keeps enough recent state around to write a post-mortem report on panic or fatal error
+#[derive(Debug, Clone)]
+pub struct CrashReporter {
+    dir: PathBuf,
+    max_decisions: usize,
+    config_fingerprint: Arc<String>,
+    state: Arc<Mutex<CrashState>>,
+}
+
+impl CrashReporter {
+    pub fn new(dir: PathBuf, max_decisions: usize, config_fingerprint: String) -> Self {
+        Self {
+            dir,
+            max_decisions,
+            config_fingerprint: Arc::new(config_fingerprint),
+            state: Arc::new(Mutex::new(CrashState::default())),
+        }
+    }
+
+    // Write a report for any panic, then defer to the previously installed hook
+    pub fn install_panic_hook(&self) {
+        let reporter = self.clone();
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |panic_info| {
+            let message = panic_info
+                .payload()
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| panic_info.payload().downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic payload".to_string());
+            let location = panic_info
+                .location()
+                .map(|location| format!("{}:{}", location.file(), location.line()));
+            reporter.write("panic", message, location);
+            previous(panic_info);
+        }));
+    }
+
+    pub fn record_decision(&self, decision: DecisionRecord) {
+        let mut state = self.lock();
+        state.last_block = decision.block_number.or(state.last_block);
+        state.recent_decisions.push_back(decision);
+        while state.recent_decisions.len() > self.max_decisions {
+            state.recent_decisions.pop_front();
+        }
+    }
+
+    pub fn set_pending_transactions(&self, tx_hashes: Vec<String>) {
+        self.lock().pending_transactions = tx_hashes;
+    }
+
+    pub fn report_fatal_error(&self, error: &anyhow::Error) {
+        self.write("fatal_error", format!("{error:?}"), None);
+    }
+
+    fn write(&self, reason: &str, message: String, location: Option<String>) {
+        let report = CrashReport {
+            timestamp: unix_timestamp(),
+            reason,
+            message,
+            location,
+            config_fingerprint: &self.config_fingerprint,
+            state: self.snapshot(),
+            backtrace: Backtrace::force_capture().to_string(),
+        };
+
+        let path = self.dir.join(format!(
+            "crash-{}-{}.json",
+            report.timestamp,
+            std::process::id()
+        ));
+        match write_report(&self.dir, &path, &report) {
+            Ok(()) => info!("💾 Crash report written to {}", path.display()),
+            Err(e) => error!("Failed to write crash report: {:?}", e),
+        }
+    }
+
+    // Never blocks: the panicking thread may itself hold the lock
+    fn snapshot(&self) -> CrashState {
+        match self.state.try_lock() {
+            Ok(state) => state.clone(),
+            Err(TryLockError::Poisoned(e)) => e.into_inner().clone(),
+            Err(TryLockError::WouldBlock) => CrashState::default(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CrashState> {
+        // A panic while holding the lock must not prevent the report from being written
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn write_report(dir: &Path, path: &Path, report: &CrashReport) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let contents = serde_json::to_string_pretty(report).map_err(std::io::Error::other)?;
+    std::fs::write(path, contents)
+}
@@ -0,0 +1,524 @@
+use serde_json::json;
+use starknet::providers::Provider;
+use starknet_types_core::felt::Felt;
+use tracing::{error, info, warn};
+
+use crate::alerts::{AlertSeverity, Alerter};
+use crate::audit::{AuditEntry, AuditLog};
+use crate::balance::BalanceMonitor;
+use crate::candidate::CandidateRun;
+use crate::checkpoint::BlockCheckpoint;
+use crate::components::FeeComponent;
+use crate::control::{ControlCommand, ControlResponse};
+use crate::crash::CrashReporter;
+use crate::endpoints::EndpointPool;
+use crate::events::{EventStream, FeeEvent};
+use crate::history::{
+    unix_timestamp, DecisionRecord, HistoryRecord, HistoryStore, OutsideChangeRecord,
+    TransactionRecord,
+};
+use crate::latency::HeadTimer;
+use crate::listener::PriceEvent;
+use crate::live::LiveView;
+use crate::logging::BLOCKS;
+use crate::overrides::ChangeOrigin;
+use crate::queue::{QueuedUpdate, SendQueue};
+use crate::sources::CompositeSource;
+use crate::updater::{
+    block_prices_at, BlockPrices, FeeDecision, PendingUpdate, ReplayMode, Updater, UpdaterError,
+};
+use crate::upgrades::UpgradeWatcher;
+
+// Batching limits of the sends of one cycle
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CycleOptions {
+    pub max_calls_per_tx: Option<usize>,
+    pub max_txs_per_cycle: Option<usize>,
+}
+
+// Everything a fee cycle touches, driven by new heads, replayed blocks and operator
+// commands alike
+pub struct Daemon<'a> {
+    pub updater: Updater,
+    pub rpc: EndpointPool,
+    pub alerter: Alerter,
+    pub balance_monitor: BalanceMonitor,
+    pub upgrade_watcher: UpgradeWatcher,
+    pub history: Option<HistoryStore>,
+    pub checkpoint: BlockCheckpoint,
+    pub events: EventStream,
+    pub audit: Option<AuditLog>,
+    pub crash: Option<&'a CrashReporter>,
+    pub candidate: Option<CandidateRun>,
+    pub live: LiveView,
+    pub options: CycleOptions,
+}
+
+impl Daemon<'_> {
+    // Checks the fee at a new head and sends the updates it calls for; returns the hashes
+    // of the sent transactions so their status can be subscribed to
+    pub async fn process_block(
+        &mut self,
+        block_number: Option<u64>,
+        header_prices: Option<BlockPrices>,
+        timer: Option<HeadTimer>,
+    ) -> anyhow::Result<Vec<Felt>> {
+        self.updater.set_head_timer(timer.map(|mut timer| {
+            timer.lap("queue");
+            timer
+        }));
+        self.live.on_block(block_number);
+        match self.balance_monitor.check(&self.rpc, &self.alerter).await {
+            Ok(Some(balance)) => self.live.on_balance(balance),
+            Ok(None) => {}
+            Err(e) => error!("Failed to check owner balance: {:?}", e),
+        }
+        if let Err(e) = self
+            .upgrade_watcher
+            .check(&self.rpc, &self.alerter, self.updater.components())
+            .await
+        {
+            error!("Failed to check the pool class hashes: {:?}", e);
+        }
+        let check_fee = self
+            .updater
+            .check_fee_update(block_number, header_prices)
+            .await;
+        self.report_outside_changes().await;
+        self.report_resolved();
+        let check_fee = match check_fee {
+            Ok(result) => result,
+            Err(e) if e.class().is_retryable() => {
+                error!("Failed to check fee update: {:?}", e);
+                return Ok(Vec::new());
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if let Some(block_number) = block_number {
+            self.checkpoint.advance(block_number);
+        }
+        self.live.set_pending(self.updater.pending_updates());
+
+        let Some(decisions) = check_fee else {
+            return Ok(Vec::new());
+        };
+        let queue = self.observe(block_number, decisions);
+
+        if queue.is_empty() {
+            info!(target: BLOCKS, "✅ Fee is up to date, no update needed");
+            return Ok(Vec::new());
+        }
+        for update in queue.batches.iter().flatten() {
+            warn!(
+                "⚠️ Fee update needed! [{}] New gas price: {}",
+                update.component, update.gas_price
+            );
+        }
+        for deferred in &queue.deferred {
+            warn!(
+                "⏭️ [{}] Update deferred to a later block (per-cycle transaction cap)",
+                deferred.update.component
+            );
+        }
+        let already_pending = self.updater.pending_updates().len();
+        let updated = self.updater.update_fee(queue).await;
+        // A batch can fail after earlier ones were sent; those are recorded either way
+        let sent = self.on_sent(block_number, already_pending);
+        match updated {
+            Ok(()) => {}
+            Err(e) if e.class().is_retryable() => {
+                error!("Failed to update fee: {:?}", e);
+            }
+            Err(e) => return Err(e.into()),
+        }
+        Ok(sent)
+    }
+
+    // The pool announced a price update: unless it was ours, the components of that pool
+    // are re-read and, when one moved, the fee is checked right away
+    pub async fn on_price_event(&mut self, event: PriceEvent) -> anyhow::Result<Vec<Felt>> {
+        if self.updater.is_own_transaction(event.tx_hash) {
+            return Ok(Vec::new());
+        }
+        match self
+            .updater
+            .reconcile_pool(event.pool, event.tx_hash, event.block_number)
+            .await
+        {
+            Ok(true) => self.process_block(None, None, None).await,
+            Ok(false) => Ok(Vec::new()),
+            Err(e) if e.class().is_retryable() => {
+                error!("Failed to re-read the pool after a price update: {:?}", e);
+                Ok(Vec::new())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // Alerts and records the contract values someone else wrote
+    async fn report_outside_changes(&mut self) {
+        for change in self.updater.take_outside_changes() {
+            let severity = match change.origin {
+                ChangeOrigin::Attack => AlertSeverity::Critical,
+                _ => AlertSeverity::Warning,
+            };
+            let by = match (change.tx_hash, change.sender) {
+                (Some(tx_hash), Some(sender)) => format!(" by {sender:#x} in {tx_hash:#x}"),
+                (Some(tx_hash), None) => format!(" in {tx_hash:#x}"),
+                _ => String::new(),
+            };
+            self.alerter
+                .raise(
+                    severity,
+                    "external_price_change",
+                    format!(
+                        "[{}] Contract price changed from {} to {} outside this updater{} ({})",
+                        change.component, change.previous, change.current, by, change.origin
+                    ),
+                )
+                .await;
+            record_history(
+                self.history.as_ref(),
+                HistoryRecord::OutsideChange(OutsideChangeRecord {
+                    timestamp: unix_timestamp(),
+                    block_number: change.block_number,
+                    component: change.component,
+                    previous: change.previous.to_biguint().try_into().unwrap_or_default(),
+                    current: change.current.to_biguint().try_into().unwrap_or_default(),
+                    origin: change.origin,
+                    tx_hash: change.tx_hash.map(|tx_hash| format!("{tx_hash:#x}")),
+                    sender: change.sender.map(|sender| format!("{sender:#x}")),
+                }),
+            );
+        }
+    }
+
+    // Publishes how the transactions that left the pending set ended
+    fn report_resolved(&mut self) {
+        for resolved in self.updater.take_resolved() {
+            self.audit(&AuditEntry::resolved(&resolved));
+            self.events.publish(FeeEvent::TransactionResolved {
+                transaction: transaction_record(resolved.block_number, &resolved.pending),
+                outcome: resolved.outcome,
+            });
+        }
+    }
+
+    // Runs the historical heads up to the latest block through the updater one by one, so
+    // the live subscription takes over where the replay stops
+    pub async fn replay(&mut self, from: u64, mode: ReplayMode) -> anyhow::Result<()> {
+        let client = self.rpc.client();
+        let mut latest = client.block_number().await.map_err(UpdaterError::from)?;
+        info!("⏪ Replaying blocks {}..={} ({:?})", from, latest, mode);
+        let mut block_number = from;
+        while block_number <= latest {
+            let prices = block_prices_at(&client, block_number).await?;
+            let check_fee = match self
+                .updater
+                .check_replayed_block(block_number, prices)
+                .await
+            {
+                Ok(result) => result,
+                Err(e) if e.class().is_retryable() => {
+                    error!(
+                        "Failed to check fee update for block {}: {:?}",
+                        block_number, e
+                    );
+                    None
+                }
+                Err(e) => return Err(e.into()),
+            };
+            self.report_outside_changes().await;
+            self.report_resolved();
+
+            if let Some(decisions) = check_fee {
+                let queue = self.observe(Some(block_number), decisions);
+                for update in queue.batches.iter().flatten() {
+                    warn!(
+                        "⚠️ Fee update needed at block {}! [{}] New gas price: {}",
+                        block_number, update.component, update.gas_price
+                    );
+                }
+                if mode == ReplayMode::Live && !queue.is_empty() {
+                    let already_pending = self.updater.pending_updates().len();
+                    let updated = self.updater.update_fee(queue).await;
+                    self.on_sent(Some(block_number), already_pending);
+                    match updated {
+                        Ok(()) => {}
+                        Err(e) if e.class().is_retryable() => {
+                            error!("Failed to update fee: {:?}", e);
+                        }
+                        Err(e) => return Err(e.into()),
+                    }
+                }
+            }
+
+            self.checkpoint.advance(block_number);
+            block_number += 1;
+            // Blocks produced during the replay are caught up before subscribing
+            if block_number > latest {
+                latest = client.block_number().await.map_err(UpdaterError::from)?;
+            }
+        }
+        info!(
+            "⏩ Replay caught up at block {}, switching to live heads",
+            latest
+        );
+        Ok(())
+    }
+
+    // After a (re)subscription, checks the fee at the latest block right away when blocks
+    // went by since the last one checked, instead of waiting for the next head
+    pub async fn catch_up(&mut self) -> anyhow::Result<Vec<Felt>> {
+        let Some(last) = self.checkpoint.last() else {
+            return Ok(Vec::new());
+        };
+        let client = self.rpc.client();
+        let latest = match client.block_number().await {
+            Ok(latest) if latest > last => latest,
+            Ok(_) => return Ok(Vec::new()),
+            Err(e) => {
+                error!("Failed to read the latest block to catch up: {:?}", e);
+                return Ok(Vec::new());
+            }
+        };
+        let prices = match block_prices_at(&client, latest).await {
+            Ok(prices) => Some(prices),
+            // The check then reads the prices itself
+            Err(e) => {
+                warn!("Failed to read block {} to catch up: {:?}", latest, e);
+                None
+            }
+        };
+        self.process_block(Some(latest), prices, None).await
+    }
+
+    // Applies an operator command; also returns the hashes of any sent transactions
+    pub async fn control(
+        &mut self,
+        command: ControlCommand,
+    ) -> anyhow::Result<(ControlResponse, Vec<Felt>)> {
+        info!("🎛️ Operator command: {:?}", command);
+        let mut sent = Vec::new();
+        let response = match command {
+            ControlCommand::Status => Ok(self.status()),
+            ControlCommand::Pause => {
+                self.set_paused(true);
+                Ok(self.status())
+            }
+            ControlCommand::Resume => {
+                self.set_paused(false);
+                Ok(self.status())
+            }
+            ControlCommand::Check => {
+                sent = self.process_block(None, None, None).await?;
+                Ok(json!({ "sent": hashes(&sent) }))
+            }
+            ControlCommand::SetStrategy {
+                component,
+                strategy,
+            } => self
+                .updater
+                .set_strategy(component.as_deref(), &strategy)
+                .map(|changed| {
+                    self.live.set_config(self.updater.components());
+                    json!({ "changed": changed })
+                })
+                .map_err(|e| e.to_string()),
+            ControlCommand::ConfirmUpdate { component } => self
+                .updater
+                .confirm_divergent(&component)
+                .map(|()| json!({ "confirmed": component }))
+                .map_err(|e| e.to_string()),
+            ControlCommand::SetPrice { component, price } => {
+                let already_pending = self.updater.pending_updates().len();
+                let forced = self.updater.force_price(&component, price).await;
+                sent = self.on_sent(None, already_pending);
+                match forced {
+                    Ok(()) => Ok(json!({ "sent": hashes(&sent) })),
+                    Err(e) => Err(e.to_string()),
+                }
+            }
+        };
+        Ok((response, sent))
+    }
+
+    // Blocks are still checked and recorded while paused, but nothing is sent
+    pub fn set_paused(&mut self, paused: bool) {
+        self.updater.set_paused(paused);
+        self.live.set_paused(paused);
+        if paused {
+            warn!("⏸️ Updates paused by the operator");
+        } else {
+            info!("▶️ Updates resumed by the operator");
+        }
+    }
+
+    // Takes over a reloaded config between two checks
+    pub fn reload(
+        &mut self,
+        components: Vec<FeeComponent>,
+        price_sources: Option<CompositeSource>,
+    ) {
+        info!(
+            "🔄 Config reloaded, tracking {} fee component(s): {}",
+            components.len(),
+            components
+                .iter()
+                .map(|component| component.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        self.live.set_config(&components);
+        self.updater.reload(components, price_sources);
+    }
+
+    pub fn status(&self) -> serde_json::Value {
+        let live = self.live.snapshot();
+        json!({
+            "paused": self.updater.is_paused(),
+            "leader": self.updater.is_leader(),
+            "block": live.block,
+            "pending": live.pending,
+            "components": live.config,
+            "recent_decisions": live.recent_decisions,
+        })
+    }
+
+    // Records and shows the decisions, then queues the components that moved, most
+    // mispriced first
+    fn observe(&mut self, block_number: Option<u64>, decisions: Vec<FeeDecision>) -> SendQueue {
+        self.live.on_decisions(&decisions);
+        for decision in &decisions {
+            let thresholds = self
+                .updater
+                .components()
+                .iter()
+                .find(|component| component.name == decision.component)
+                .map(|component| component.strategy);
+            self.audit(&AuditEntry::decision(block_number, decision, thresholds));
+        }
+        record_decisions(
+            self.history.as_ref(),
+            &self.events,
+            self.crash,
+            &self.live,
+            block_number,
+            &decisions,
+        );
+        if let Some(candidate) = self.candidate.as_mut() {
+            candidate.observe(block_number, &decisions);
+        }
+        SendQueue::build(
+            decisions
+                .into_iter()
+                .filter_map(QueuedUpdate::from_decision)
+                .collect(),
+            self.options.max_calls_per_tx,
+            self.options.max_txs_per_cycle,
+        )
+    }
+
+    // Book-keeping for the transactions sent past the first `already_pending`
+    fn on_sent(&self, block_number: Option<u64>, already_pending: usize) -> Vec<Felt> {
+        let pending = self.updater.pending_updates();
+        if let Some(crash) = self.crash {
+            crash.set_pending_transactions(
+                pending
+                    .iter()
+                    .map(|pending| format!("{:#x}", pending.tx_hash))
+                    .collect(),
+            );
+        }
+        let sent = &pending[already_pending..];
+        for pending in sent {
+            self.audit(&AuditEntry::sent(block_number, pending));
+        }
+        record_transactions(self.history.as_ref(), &self.events, block_number, sent);
+        self.live.on_sent(block_number, sent);
+        self.live.set_pending(pending);
+        sent.iter().map(|pending| pending.tx_hash).collect()
+    }
+
+    fn audit(&self, entry: &AuditEntry) {
+        if let Some(audit) = &self.audit {
+            if let Err(e) = audit.append(entry) {
+                metrics::counter!("pp_fee_updater_audit_write_errors_total").increment(1);
+                error!("Failed to write the audit log: {:?}", e);
+            }
+        }
+    }
+}
+
+fn hashes(sent: &[Felt]) -> Vec<String> {
+    sent.iter().map(|tx_hash| format!("{tx_hash:#x}")).collect()
+}
+
+pub fn record_history(history: Option<&HistoryStore>, record: HistoryRecord) {
+    if let Some(history) = history {
+        if let Err(e) = history.append(&record) {
+            error!("Failed to record history: {:?}", e);
+        }
+    }
+}
+
+fn record_decisions(
+    history: Option<&HistoryStore>,
+    events: &EventStream,
+    crash: Option<&CrashReporter>,
+    live: &LiveView,
+    block_number: Option<u64>,
+    decisions: &[FeeDecision],
+) {
+    for decision in decisions {
+        let record = DecisionRecord {
+            timestamp: unix_timestamp(),
+            block_number,
+            component: decision.component.clone(),
+            network_gas_price: decision.network_gas_price,
+            contract_gas_price: decision.contract_gas_price,
+            should_update: decision.should_update,
+            new_gas_price: decision
+                .should_update
+                .then(|| decision.new_gas_price.to_biguint())
+                .and_then(|price| price.try_into().ok()),
+        };
+        if let Some(crash) = crash {
+            crash.record_decision(record.clone());
+        }
+        live.record_decision(record.clone());
+        events.publish(FeeEvent::Decision(record.clone()));
+        record_history(history, HistoryRecord::Decision(record));
+    }
+}
+
+fn record_transactions(
+    history: Option<&HistoryStore>,
+    events: &EventStream,
+    block_number: Option<u64>,
+    sent: &[PendingUpdate],
+) {
+    for pending in sent {
+        let record = transaction_record(block_number, pending);
+        events.publish(FeeEvent::TransactionSent(record.clone()));
+        record_history(history, HistoryRecord::Transaction(record));
+    }
+}
+
+fn transaction_record(block_number: Option<u64>, pending: &PendingUpdate) -> TransactionRecord {
+    TransactionRecord {
+        timestamp: unix_timestamp(),
+        block_number,
+        tx_hash: format!("{:#x}", pending.tx_hash),
+        prices: pending
+            .updates
+            .iter()
+            .map(|update| {
+                (
+                    update.component.clone(),
+                    update.gas_price.to_biguint().try_into().unwrap_or_default(),
+                )
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,197 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Borders, List, ListItem, Paragraph, Sparkline},
+    DefaultTerminal, Frame,
+};
+use tokio::sync::Notify;
+use tracing::error;
+
+use crate::live::{ComponentPrices, LiveState, LiveView, RECENT_UPDATES};
+
+const REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+
+// Live terminal view of the updater, drawn by a separate thread
+#[derive(Debug)]
+pub struct Dashboard {
+    stop: Arc<AtomicBool>,
+    quit: Arc<Notify>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Dashboard {
+    // Takes over the terminal until dropped
+    pub fn start(view: LiveView) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let quit = Arc::new(Notify::new());
+
+        let thread = {
+            let stop = stop.clone();
+            let quit = quit.clone();
+            std::thread::spawn(move || {
+                let mut terminal = ratatui::init();
+                if let Err(e) = render_loop(&mut terminal, &view, &stop) {
+                    error!("Dashboard failed: {:?}", e);
+                }
+                ratatui::restore();
+                quit.notify_one();
+            })
+        };
+
+        Self {
+            stop,
+            quit,
+            thread: Some(thread),
+        }
+    }
+
+    // Resolves once the operator closed the dashboard
+    pub async fn closed(&self) {
+        self.quit.notified().await;
+    }
+}
+
+impl Drop for Dashboard {
+    // Hands the terminal back before the process goes on logging or exits
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn render_loop(
+    terminal: &mut DefaultTerminal,
+    view: &LiveView,
+    stop: &AtomicBool,
+) -> std::io::Result<()> {
+    while !stop.load(Ordering::Relaxed) {
+        {
+            let state = view.lock();
+            terminal.draw(|frame| draw(frame, &state))?;
+        }
+        if event::poll(REFRESH_INTERVAL)? {
+            if let Event::Key(key) = event::read()? {
+                // Raw mode swallows Ctrl-C, so it closes the dashboard like `q`
+                let ctrl_c =
+                    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+                if key.kind == KeyEventKind::Press
+                    && (matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) || ctrl_c)
+                {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn draw(frame: &mut Frame, state: &LiveState) {
+    let [header, charts, updates] = Layout::vertical([
+        Constraint::Length(4),
+        Constraint::Min(8),
+        Constraint::Length(RECENT_UPDATES as u16 / 2 + 2),
+    ])
+    .areas(frame.area());
+
+    let status = vec![
+        Line::from(format!(
+            "Block: {}    Owner balance: {} fri",
+            display(state.block),
+            display(state.balance)
+        )),
+        Line::from(match state.pending.as_slice() {
+            [] => "Pending: none".to_string(),
+            pending => format!("Pending: {}", pending.join(", ")),
+        }),
+    ];
+    frame.render_widget(
+        Paragraph::new(status).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" pp-fee-updater (q to quit) "),
+        ),
+        header,
+    );
+
+    if !state.prices.is_empty() {
+        let rows = Layout::vertical(
+            state
+                .prices
+                .iter()
+                .map(|_| Constraint::Ratio(1, state.prices.len() as u32)),
+        )
+        .split(charts);
+        for ((name, prices), area) in state.prices.iter().zip(rows.iter()) {
+            draw_component(frame, *area, name, prices);
+        }
+    }
+
+    let items: Vec<ListItem> = state
+        .recent_updates
+        .iter()
+        .map(|update| {
+            ListItem::new(format!(
+                "block {}  [{}] -> {}  tx {}",
+                display(update.block_number),
+                update.component,
+                update.gas_price,
+                update.tx_hash
+            ))
+        })
+        .collect();
+    frame.render_widget(
+        List::new(items).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Recent updates "),
+        ),
+        updates,
+    );
+}
+
+fn draw_component(frame: &mut Frame, area: Rect, name: &str, prices: &ComponentPrices) {
+    let block = Block::default().borders(Borders::ALL).title(format!(
+        " {}  network {}  contract {} ",
+        name,
+        display(prices.network.back()),
+        display(prices.contract.back())
+    ));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let [network, contract] =
+        Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(inner);
+    let network_data: Vec<u64> = prices.network.iter().copied().collect();
+    let contract_data: Vec<u64> = prices.contract.iter().copied().collect();
+    frame.render_widget(
+        Sparkline::default()
+            .data(&network_data)
+            .style(Style::default().fg(Color::Yellow)),
+        network,
+    );
+    frame.render_widget(
+        Sparkline::default()
+            .data(&contract_data)
+            .style(Style::default().fg(Color::Cyan)),
+        contract,
+    );
+}
+
+fn display<T: ToString>(value: Option<T>) -> String {
+    value
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
@@ -0,0 +1,252 @@
+use std::{
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use starknet::{
+    accounts::{Account, ConnectedAccount, ExecutionEncoding, SingleOwnerAccount},
+    contract::ContractFactory,
+    core::types::{
+        contract::{CompiledClass, SierraClass},
+        BlockId, BlockTag, ExecutionResult, Felt, StarknetError,
+    },
+    providers::{Provider, ProviderError},
+    signers::{LocalWallet, SigningKey},
+};
+use tracing::info;
+use url::Url;
+
+use crate::calldata::encode_u256;
+use crate::endpoints::{EndpointPool, RpcClient};
+use crate::transport::{RetryPolicy, TlsOptions, TransportOptions};
+use crate::updater::{SenderKey, UpdaterError};
+
+// Scarb project of the mock pool, relative to the repository root
+pub const MOCK_POOL_DIR: &str = "tests/fixtures/devnet/mock_pool";
+
+// What `scarb build` writes for it
+const MOCK_POOL_CLASS: &str = "target/dev/mock_pool_MockPool.contract_class.json";
+const MOCK_POOL_CASM: &str = "target/dev/mock_pool_MockPool.compiled_contract_class.json";
+
+// Devnet mines every transaction right away, so this is only hit when something is wrong
+const INCLUSION_TIMEOUT: Duration = Duration::from_secs(30);
+const INCLUSION_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// JSON-RPC endpoint of a devnet served at `base`
+pub fn rpc_url(base: &Url) -> Url {
+    base.join("rpc").expect("`rpc` is a valid relative URL")
+}
+
+// WebSocket endpoint of a devnet served at `base`
+pub fn websocket_url(base: &Url) -> Url {
+    let mut url = base.join("ws").expect("`ws` is a valid relative URL");
+    let scheme = if base.scheme() == "https" {
+        "wss"
+    } else {
+        "ws"
+    };
+    // Both schemes are special, so the switch cannot fail
+    let _ = url.set_scheme(scheme);
+    url
+}
+
+// Gas prices a devnet charges, in fri
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DevnetPrices {
+    pub l1_gas: u128,
+    pub l1_data_gas: u128,
+    pub l2_gas: u128,
+}
+
+// A local starknet-devnet: its predeployed accounts, its gas prices and a mock pool
+// deployed on it, for the `--devnet` mode and the integration tests
+#[derive(Debug, Clone)]
+pub struct Devnet {
+    client: reqwest::Client,
+    rpc: EndpointPool,
+    url: Url,
+}
+
+impl Devnet {
+    pub fn new(base: &Url) -> Result<Self, UpdaterError> {
+        let transport = TransportOptions {
+            headers: Vec::new(),
+            proxy: None,
+            tls: TlsOptions::default(),
+            timeout: Duration::from_secs(30),
+            retry: RetryPolicy::default(),
+            rate_limit: None,
+        };
+        let url = rpc_url(base);
+        let rpc = EndpointPool::new(std::slice::from_ref(&url), &transport)
+            .map_err(|e| UpdaterError::Devnet(format!("Failed to build the HTTP client: {e}")))?;
+        let client = transport
+            .http_client()
+            .map_err(|e| UpdaterError::Devnet(format!("Failed to build the HTTP client: {e}")))?;
+        Ok(Self { client, rpc, url })
+    }
+
+    pub fn rpc(&self) -> &EndpointPool {
+        &self.rpc
+    }
+
+    // The first of the accounts devnet funds at startup
+    pub async fn predeployed_account(&self) -> Result<SenderKey, UpdaterError> {
+        let accounts = self
+            .call("devnet_getPredeployedAccounts", json!({}))
+            .await?;
+        let account = accounts.get(0).ok_or_else(|| {
+            UpdaterError::Devnet("Devnet has no predeployed accounts".to_string())
+        })?;
+        let felt = |key: &str| {
+            account
+                .get(key)
+                .and_then(Value::as_str)
+                .and_then(|value| Felt::from_hex(value).ok())
+                .ok_or_else(|| {
+                    UpdaterError::Devnet(format!("Predeployed account has no valid `{key}`"))
+                })
+        };
+        Ok(SenderKey {
+            address: felt("address")?,
+            private_key: felt("private_key")?,
+        })
+    }
+
+    // Charge these prices from a newly generated block on
+    pub async fn set_gas_prices(&self, prices: DevnetPrices) -> Result<(), UpdaterError> {
+        self.call(
+            "devnet_setGasPrice",
+            json!({
+                "gas_price_fri": prices.l1_gas,
+                "data_gas_price_fri": prices.l1_data_gas,
+                "l2_gas_price_fri": prices.l2_gas,
+                "generate_block": true,
+            }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    // Declares the mock pool built in `contract_dir` unless devnet already knows the class,
+    // then deploys a pool owned by `owner` that starts out at `prices`
+    pub async fn deploy_mock_pool(
+        &self,
+        contract_dir: &Path,
+        owner: SenderKey,
+        prices: DevnetPrices,
+    ) -> Result<Felt, UpdaterError> {
+        let provider = self.rpc.client();
+        let chain_id = provider.chain_id().await?;
+        let account = SingleOwnerAccount::new(
+            provider.clone(),
+            LocalWallet::from(SigningKey::from_secret_scalar(owner.private_key)),
+            owner.address,
+            chain_id,
+            ExecutionEncoding::New,
+        );
+
+        let class = read_artifact::<SierraClass>(&contract_dir.join(MOCK_POOL_CLASS))?
+            .flatten()
+            .map_err(|e| UpdaterError::Devnet(format!("Invalid mock pool class: {e}")))?;
+        let compiled_class_hash =
+            read_artifact::<CompiledClass>(&contract_dir.join(MOCK_POOL_CASM))?
+                .class_hash()
+                .map_err(|e| UpdaterError::Devnet(format!("Invalid mock pool CASM: {e}")))?;
+        let class_hash = class.class_hash();
+
+        match provider
+            .get_class(BlockId::Tag(BlockTag::Latest), class_hash)
+            .await
+        {
+            Ok(_) => {}
+            Err(ProviderError::StarknetError(StarknetError::ClassHashNotFound)) => {
+                let declared = account
+                    .declare_v3(Arc::new(class), compiled_class_hash)
+                    .send()
+                    .await
+                    .map_err(|e| {
+                        UpdaterError::Account(format!("Failed to declare the mock pool: {e}"))
+                    })?;
+                wait_for_inclusion(&provider, declared.transaction_hash).await?;
+                info!("🧪 Declared the mock pool class {:#x}", class_hash);
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        let mut constructor_calldata = vec![owner.address];
+        for price in [prices.l1_gas, prices.l1_data_gas, prices.l2_gas] {
+            constructor_calldata.extend(encode_u256(Felt::from(price)));
+        }
+        // A fresh salt per deployment, so a long-running devnet takes any number of pools
+        let salt = account.get_nonce().await?;
+        let factory = ContractFactory::new(class_hash, &account);
+        let deployment = factory.deploy_v3(constructor_calldata, salt, true);
+        let address = deployment.deployed_address();
+        let deployed = deployment
+            .send()
+            .await
+            .map_err(|e| UpdaterError::Account(format!("Failed to deploy the mock pool: {e}")))?;
+        wait_for_inclusion(&provider, deployed.transaction_hash).await?;
+        info!("🧪 Deployed a mock pool at {:#x}", address);
+        Ok(address)
+    }
+
+    async fn call(&self, method: &str, params: Value) -> Result<Value, UpdaterError> {
+        let response: Value = self
+            .client
+            .post(self.url.clone())
+            .json(&json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params}))
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| UpdaterError::Devnet(format!("{method} failed: {e}")))?
+            .json()
+            .await
+            .map_err(|e| UpdaterError::Devnet(format!("{method} failed: {e}")))?;
+        if let Some(error) = response.get("error") {
+            return Err(UpdaterError::Devnet(format!("{method} failed: {error}")));
+        }
+        response
+            .get("result")
+            .cloned()
+            .ok_or_else(|| UpdaterError::Devnet(format!("{method} returned no result")))
+    }
+}
+
+async fn wait_for_inclusion(provider: &RpcClient, tx_hash: Felt) -> Result<(), UpdaterError> {
+    let deadline = Instant::now() + INCLUSION_TIMEOUT;
+    loop {
+        match provider.get_transaction_receipt(tx_hash).await {
+            Ok(receipt) => {
+                return match receipt.receipt.execution_result() {
+                    ExecutionResult::Succeeded => Ok(()),
+                    ExecutionResult::Reverted { reason } => Err(UpdaterError::Devnet(format!(
+                        "Transaction {tx_hash:#x} reverted: {reason}"
+                    ))),
+                };
+            }
+            Err(ProviderError::StarknetError(StarknetError::TransactionHashNotFound))
+                if Instant::now() < deadline =>
+            {
+                tokio::time::sleep(INCLUSION_POLL_INTERVAL).await;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+fn read_artifact<T: DeserializeOwned>(path: &Path) -> Result<T, UpdaterError> {
+    let json = std::fs::read_to_string(path).map_err(|e| {
+        UpdaterError::Devnet(format!(
+            "Failed to read {} (is the mock pool built with `scarb build`?): {e}",
+            path.display()
+        ))
+    })?;
+    serde_json::from_str(&json).map_err(|e| {
+        UpdaterError::Devnet(format!("Invalid contract artifact {}: {e}", path.display()))
+    })
+}
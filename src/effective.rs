@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Command};
+use serde::Serialize;
+use url::Url;
+
+use crate::config::FileConfig;
+use crate::sources::SourceKind;
+
+// Arguments whose values are replaced wholesale
+const SECRET_ARGS: [&str; 5] = [
+    "owner_private_key",
+    "sender_private_keys",
+    "audit_signing_key",
+    "admin_token",
+    "rpc_headers",
+];
+const REDACTED: &str = "<redacted>";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+}
+
+// The configuration a daemon started with the same arguments and environment would use
+#[derive(Debug, Serialize)]
+pub struct EffectiveConfig {
+    arguments: BTreeMap<String, Setting>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    config_file: Option<FileConfig>,
+}
+
+#[derive(Debug, Serialize)]
+struct Setting {
+    value: SettingValue,
+    // `command line`, `environment`, `default` or `preset`
+    source: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum SettingValue {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl EffectiveConfig {
+    // Every argument of `command` that has a value, keyed by its long name; `skip` names
+    // arguments of the command itself
+    pub fn from_matches(command: &Command, matches: &ArgMatches, skip: &[&str]) -> Self {
+        let mut arguments = BTreeMap::new();
+        for arg in command.get_arguments() {
+            let id = arg.get_id().as_str();
+            if skip.contains(&id) {
+                continue;
+            }
+            let Some(source) = matches.value_source(id) else {
+                continue;
+            };
+            let Ok(Some(raw)) = matches.try_get_raw(id) else {
+                continue;
+            };
+            let values: Vec<String> = raw
+                .map(|value| redact(id, &value.to_string_lossy()))
+                .collect();
+            let source = match source {
+                ValueSource::CommandLine => "command line",
+                ValueSource::EnvVariable => "environment",
+                ValueSource::DefaultValue => "default",
+                _ => "other",
+            };
+            let name = arg.get_long().map(str::to_string);
+            let name = name.unwrap_or_else(|| id.replace('_', "-"));
+            arguments.insert(name, Setting::new(values, source));
+        }
+        Self {
+            arguments,
+            config_file: None,
+        }
+    }
+
+    // Sets an argument nothing else gave a value
+    pub fn fill(&mut self, name: &str, values: Vec<String>, source: &'static str) {
+        let values = values.iter().map(|value| redact(name, value)).collect();
+        self.arguments
+            .entry(name.to_string())
+            .or_insert_with(|| Setting::new(values, source));
+    }
+
+    pub fn with_config_file(mut self, mut config_file: FileConfig) -> Self {
+        if let Some(price_sources) = &mut config_file.price_sources {
+            for source in &mut price_sources.sources {
+                if let SourceKind::Ethereum { url } | SourceKind::Http { url, .. } =
+                    &mut source.kind
+                {
+                    *url = redact_url(url);
+                }
+            }
+        }
+        self.config_file = Some(config_file);
+        self
+    }
+
+    pub fn render(&self, format: ConfigFormat) -> anyhow::Result<String> {
+        Ok(match format {
+            ConfigFormat::Toml => toml::to_string_pretty(self)?,
+            ConfigFormat::Json => serde_json::to_string_pretty(self)?,
+        })
+    }
+}
+
+impl Setting {
+    fn new(mut values: Vec<String>, source: &'static str) -> Self {
+        let value = match values.len() {
+            1 => SettingValue::One(values.remove(0)),
+            _ => SettingValue::Many(values),
+        };
+        Self { value, source }
+    }
+}
+
+fn redact(id: &str, value: &str) -> String {
+    if SECRET_ARGS.contains(&id.replace('-', "_").as_str()) {
+        return REDACTED.to_string();
+    }
+    redact_url(value)
+}
+
+// Providers put API keys in the path or query and proxies and Redis take credentials, so
+// only the scheme, host and port of a URL are shown
+fn redact_url(value: &str) -> String {
+    let Ok(url) = Url::parse(value) else {
+        return value.to_string();
+    };
+    let Some(host) = url.host_str() else {
+        return value.to_string();
+    };
+    let port = url
+        .port()
+        .map(|port| format!(":{port}"))
+        .unwrap_or_default();
+    let hidden = !url.username().is_empty()
+        || url.password().is_some()
+        || url.path() != "/"
+        || url.query().is_some();
+    if hidden {
+        format!("{}://{}{}/{}", url.scheme(), host, port, REDACTED)
+    } else {
+        format!("{}://{}{}/", url.scheme(), host, port)
+    }
+}
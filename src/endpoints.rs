@@ -0,0 +1,850 @@
+use std::{
+    fmt,
+    future::Future,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::json;
+use starknet::{
+    core::types::Felt,
+    providers::{
+        jsonrpc::{
+            HttpTransport, HttpTransportError, JsonRpcMethod, JsonRpcResponse, JsonRpcTransport,
+        },
+        JsonRpcClient, ProviderRequestData, Url,
+    },
+};
+use thiserror::Error;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::http::{HeaderName, HeaderValue};
+use tracing::{debug, error, info, warn};
+
+use crate::ratelimit::{RateLimit, RateLimiter};
+use crate::transport::{RetryPolicy, TransportOptions};
+use crate::wsrpc::{WsTransport, WsTransportError};
+
+pub type RpcClient = JsonRpcClient<EndpointPool>;
+
+// Weight of the newest sample in the moving averages
+const EWMA_ALPHA: f64 = 0.2;
+// How strongly the error rate penalizes an endpoint's latency score
+const ERROR_PENALTY: f64 = 10.0;
+// Every Nth request goes to a non-best endpoint to keep its score fresh
+const EXPLORE_EVERY: u64 = 20;
+// How often failed endpoints are probed so they can rejoin the pool
+pub const HEALTH_PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+// Extra request header such as an API key, sent to every endpoint or only to one host
+#[derive(Clone)]
+pub struct EndpointHeader {
+    pub host: Option<String>,
+    pub name: String,
+    pub value: String,
+}
+
+impl EndpointHeader {
+    pub fn applies_to(&self, url: &Url) -> bool {
+        self.host
+            .as_deref()
+            .is_none_or(|host| url.host_str() == Some(host))
+    }
+}
+
+impl FromStr for EndpointHeader {
+    type Err = String;
+
+    // Accepts `Name: value` or `host=Name: value`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (host, header) = match s.split_once('=') {
+            Some((host, header)) if !host.contains(':') => (Some(host.trim().to_string()), header),
+            _ => (None, s),
+        };
+        let (name, value) = header
+            .split_once(':')
+            .ok_or_else(|| format!("invalid header `{s}`, expected `[host=]Name: value`"))?;
+        let (name, value) = (name.trim(), value.trim());
+        HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| format!("invalid header name `{name}`: {e}"))?;
+        HeaderValue::from_str(value)
+            .map_err(|e| format!("invalid value for header `{name}`: {e}"))?;
+        Ok(Self {
+            host,
+            name: name.to_string(),
+            value: value.to_string(),
+        })
+    }
+}
+
+// Header values usually carry secrets, so they are never printed
+impl fmt::Debug for EndpointHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EndpointHeader")
+            .field("host", &self.host)
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct EndpointStats {
+    latency_ms: Option<f64>,
+    error_rate: f64,
+}
+
+impl EndpointStats {
+    // Lower is better; untried endpoints score zero so they get tried first
+    fn score(&self) -> f64 {
+        self.latency_ms.unwrap_or_default() * (1.0 + ERROR_PENALTY * self.error_rate)
+    }
+
+    fn record(&mut self, latency: Duration, success: bool) {
+        let latency_ms = latency.as_secs_f64() * 1000.0;
+        self.latency_ms = Some(match self.latency_ms {
+            Some(previous) => previous + EWMA_ALPHA * (latency_ms - previous),
+            None => latency_ms,
+        });
+        let error = if success { 0.0 } else { 1.0 };
+        self.error_rate += EWMA_ALPHA * (error - self.error_rate);
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum TransportError {
+    #[error(transparent)]
+    Http(#[from] HttpTransportError),
+    #[error(transparent)]
+    Ws(#[from] WsTransportError),
+    #[error("no RPC endpoint serves the expected chain")]
+    NoEndpoint,
+}
+
+impl TransportError {
+    // Label of the failure in the RPC error metrics
+    fn class(&self) -> &'static str {
+        match self {
+            TransportError::Http(HttpTransportError::Reqwest(e)) => match e.status() {
+                Some(status) if status.as_u16() == 429 => "rate_limited",
+                Some(status) if status.is_server_error() => "server_error",
+                Some(_) => "http_status",
+                None if e.is_timeout() => "timeout",
+                None if e.is_connect() => "connection",
+                None => "request",
+            },
+            TransportError::Http(e) if matches!(e, HttpTransportError::Json(_)) => "decode",
+            TransportError::Http(_) => "other",
+            TransportError::Ws(WsTransportError::Timeout(_)) => "timeout",
+            TransportError::Ws(WsTransportError::Connect(_) | WsTransportError::Closed) => {
+                "connection"
+            }
+            TransportError::Ws(WsTransportError::Json(_)) => "decode",
+            TransportError::Ws(WsTransportError::BatchUnsupported) => "unsupported",
+            TransportError::NoEndpoint => "unavailable",
+        }
+    }
+}
+
+// One node behind the pool, answering a JSON-RPC request or batch with the raw responses.
+// `ws://` and `wss://` endpoints carry JSON-RPC over a WebSocket, the rest over HTTP; unit
+// tests put in-memory nodes behind the pool instead.
+#[async_trait]
+pub trait NodeTransport: fmt::Debug + Send + Sync {
+    async fn send_request(
+        &self,
+        method: JsonRpcMethod,
+        params: &serde_json::Value,
+    ) -> Result<JsonRpcResponse<serde_json::Value>, TransportError>;
+
+    async fn send_requests(
+        &self,
+        requests: &[ProviderRequestData],
+    ) -> Result<Vec<JsonRpcResponse<serde_json::Value>>, TransportError>;
+}
+
+#[async_trait]
+impl NodeTransport for HttpTransport {
+    async fn send_request(
+        &self,
+        method: JsonRpcMethod,
+        params: &serde_json::Value,
+    ) -> Result<JsonRpcResponse<serde_json::Value>, TransportError> {
+        Ok(JsonRpcTransport::send_request(self, method, params).await?)
+    }
+
+    async fn send_requests(
+        &self,
+        requests: &[ProviderRequestData],
+    ) -> Result<Vec<JsonRpcResponse<serde_json::Value>>, TransportError> {
+        Ok(JsonRpcTransport::send_requests(self, requests).await?)
+    }
+}
+
+#[async_trait]
+impl NodeTransport for WsTransport {
+    async fn send_request(
+        &self,
+        method: JsonRpcMethod,
+        params: &serde_json::Value,
+    ) -> Result<JsonRpcResponse<serde_json::Value>, TransportError> {
+        Ok(JsonRpcTransport::send_request(self, method, params).await?)
+    }
+
+    async fn send_requests(
+        &self,
+        requests: &[ProviderRequestData],
+    ) -> Result<Vec<JsonRpcResponse<serde_json::Value>>, TransportError> {
+        Ok(JsonRpcTransport::send_requests(self, requests).await?)
+    }
+}
+
+// The node of one endpoint, with results read into the type the caller expects
+#[derive(Debug)]
+struct EndpointTransport(Box<dyn NodeTransport>);
+
+impl EndpointTransport {
+    // A result that does not parse fails like a transport error, so the call fails over
+    async fn send_request<R: DeserializeOwned>(
+        &self,
+        method: JsonRpcMethod,
+        params: &serde_json::Value,
+    ) -> Result<JsonRpcResponse<R>, TransportError> {
+        Ok(match self.0.send_request(method, params).await? {
+            JsonRpcResponse::Success { id, result } => JsonRpcResponse::Success {
+                id,
+                result: serde_json::from_value(result).map_err(HttpTransportError::Json)?,
+            },
+            JsonRpcResponse::Error { id, error } => JsonRpcResponse::Error { id, error },
+        })
+    }
+
+    async fn send_requests(
+        &self,
+        requests: &[ProviderRequestData],
+    ) -> Result<Vec<JsonRpcResponse<serde_json::Value>>, TransportError> {
+        self.0.send_requests(requests).await
+    }
+}
+
+#[derive(Debug)]
+struct Endpoint {
+    url: Url,
+    label: String,
+    transport: EndpointTransport,
+    stats: Mutex<EndpointStats>,
+    healthy: AtomicBool,
+    // Answered the health probe with another chain id; never called until that changes
+    wrong_chain: AtomicBool,
+}
+
+impl Endpoint {
+    fn stats(&self) -> EndpointStats {
+        *self.stats.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Relaxed)
+    }
+
+    fn is_wrong_chain(&self) -> bool {
+        self.wrong_chain.load(Ordering::Relaxed)
+    }
+}
+
+// JSON-RPC transport routing every call to the currently best scored endpoint,
+// failing over to the next one when a request errors or times out
+#[derive(Debug, Clone)]
+pub struct EndpointPool {
+    endpoints: Arc<Vec<Endpoint>>,
+    requests: Arc<AtomicU64>,
+    pinned: Option<usize>,
+    retry: RetryPolicy,
+    limiter: Option<Arc<RateLimiter>>,
+    // Chain every endpoint has to serve, once verified at startup
+    chain_id: Arc<OnceLock<Felt>>,
+    // Requests sent again after a failed attempt, to another endpoint or in a later round
+    resends: Arc<AtomicU64>,
+}
+
+impl EndpointPool {
+    pub fn new(urls: &[Url], options: &TransportOptions) -> Result<Self, reqwest::Error> {
+        let client = options.http_client()?;
+        let nodes = urls
+            .iter()
+            .map(|url| {
+                let node: Box<dyn NodeTransport> = match url.scheme() {
+                    "ws" | "wss" => Box::new(WsTransport::new(url.clone(), options.clone())),
+                    _ => {
+                        let mut transport =
+                            HttpTransport::new_with_client(url.clone(), client.clone());
+                        for header in options.headers_for(url) {
+                            transport.add_header(header.name.clone(), header.value.clone());
+                        }
+                        Box::new(transport)
+                    }
+                };
+                (url.clone(), node)
+            })
+            .collect();
+        Ok(Self::with_nodes(nodes, options.retry, options.rate_limit))
+    }
+
+    // Pool over nodes already set up, each known by its URL in the logs and metrics
+    pub fn with_nodes(
+        nodes: Vec<(Url, Box<dyn NodeTransport>)>,
+        retry: RetryPolicy,
+        rate_limit: Option<RateLimit>,
+    ) -> Self {
+        let endpoints = nodes
+            .into_iter()
+            .map(|(url, node)| Endpoint {
+                label: endpoint_label(&url),
+                url,
+                transport: EndpointTransport(node),
+                stats: Mutex::new(EndpointStats::default()),
+                healthy: AtomicBool::new(true),
+                wrong_chain: AtomicBool::new(false),
+            })
+            .collect();
+        Self {
+            endpoints: Arc::new(endpoints),
+            requests: Arc::new(AtomicU64::new(0)),
+            pinned: None,
+            retry,
+            limiter: rate_limit.map(|limit| Arc::new(RateLimiter::new(limit))),
+            chain_id: Arc::new(OnceLock::new()),
+            resends: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    // From now on a failed endpoint only rejoins once it serves this chain again
+    pub fn expect_chain_id(&self, chain_id: Felt) {
+        let _ = self.chain_id.set(chain_id);
+    }
+
+    pub fn urls(&self) -> Vec<String> {
+        self.endpoints
+            .iter()
+            .map(|endpoint| endpoint.url.to_string())
+            .collect()
+    }
+
+    pub fn client(&self) -> RpcClient {
+        JsonRpcClient::new(self.clone())
+    }
+
+    // Clients bound to the `count` best ranked endpoints, without failover, so quorum
+    // reads really come from distinct providers
+    pub fn pinned_clients(&self, count: usize) -> Vec<RpcClient> {
+        self.attempt_order()
+            .into_iter()
+            .take(count)
+            .map(|index| self.pinned_client(index))
+            .collect()
+    }
+
+    // Client bound to one endpoint, without failover
+    pub fn pinned_client(&self, endpoint: usize) -> RpcClient {
+        JsonRpcClient::new(Self {
+            pinned: Some(endpoint),
+            ..self.clone()
+        })
+    }
+
+    // Whether a failed call is already resent, to the other endpoints or in retry rounds
+    pub fn fails_over(&self) -> bool {
+        self.pinned.is_none() && (self.endpoints.len() > 1 || self.retry.retries > 0)
+    }
+
+    // Requests resent so far, across every client of the pool; a caller comparing it
+    // before and after a call learns whether the call went out more than once
+    pub fn resends(&self) -> u64 {
+        self.resends.load(Ordering::Relaxed)
+    }
+
+    // The endpoint calls currently go to first, leaving out the periodic exploration
+    pub fn current(&self) -> usize {
+        self.pinned.unwrap_or_else(|| self.ranked()[0].2)
+    }
+
+    pub fn label(&self, endpoint: usize) -> &str {
+        &self.endpoints[endpoint].label
+    }
+
+    // Periodically probe failed endpoints; one that answers again rejoins the pool
+    pub fn spawn_health_probe(&self, interval: Duration) -> JoinHandle<()> {
+        let pool = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                for endpoint in pool.endpoints.iter().filter(|e| !e.is_healthy()) {
+                    pool.throttle(1).await;
+                    let started = Instant::now();
+                    let answered = pool.probe(endpoint).await;
+                    pool.record(endpoint, started.elapsed(), answered);
+                }
+            }
+        })
+    }
+
+    // Once the chain is known the probe asks for the chain id, so an endpoint repointed
+    // at another network while it was down stays out of the pool
+    async fn probe(&self, endpoint: &Endpoint) -> bool {
+        let Some(expected) = self.chain_id.get() else {
+            return endpoint
+                .transport
+                .send_request::<serde_json::Value>(JsonRpcMethod::BlockNumber, &json!([]))
+                .await
+                .is_ok();
+        };
+        let response = endpoint
+            .transport
+            .send_request::<serde_json::Value>(JsonRpcMethod::ChainId, &json!([]))
+            .await;
+        let chain_id = match response {
+            Ok(JsonRpcResponse::Success { result, .. }) => {
+                result.as_str().and_then(|hex| Felt::from_hex(hex).ok())
+            }
+            _ => return false,
+        };
+        if chain_id == Some(*expected) {
+            endpoint.wrong_chain.store(false, Ordering::Relaxed);
+            return true;
+        }
+        if !endpoint.wrong_chain.swap(true, Ordering::Relaxed) {
+            error!(
+                "🚫 RPC endpoint {} answers with chain id {} instead of {:#x}, keeping it out of the pool",
+                endpoint.label,
+                chain_id.map_or_else(|| "?".to_string(), |chain_id| format!("{chain_id:#x}")),
+                expected
+            );
+        }
+        metrics::counter!(
+            "pp_fee_updater_rpc_wrong_chain_total",
+            "endpoint" => endpoint.label.clone()
+        )
+        .increment(1);
+        false
+    }
+
+    // Healthy endpoints best score first, then the failed ones as a last resort, except
+    // those serving another chain. Every Nth request starts on another healthy endpoint to
+    // keep its score fresh.
+    fn attempt_order(&self) -> Vec<usize> {
+        if let Some(index) = self.pinned {
+            return vec![index];
+        }
+        let request = self.requests.fetch_add(1, Ordering::Relaxed);
+        let mut ranked = self.ranked();
+        ranked.retain(|(_, _, index)| !self.endpoints[*index].is_wrong_chain());
+
+        let healthy = ranked.iter().filter(|(healthy, _, _)| *healthy).count();
+        if healthy > 1 && request % EXPLORE_EVERY == EXPLORE_EVERY - 1 {
+            let offset = 1 + (request / EXPLORE_EVERY) as usize % (healthy - 1);
+            ranked.swap(0, offset);
+        }
+        ranked
+            .into_iter()
+            .map(|(_, _, endpoint)| endpoint)
+            .collect()
+    }
+
+    // Health, score and index of every endpoint, healthy ones first, best score first
+    fn ranked(&self) -> Vec<(bool, f64, usize)> {
+        let mut ranked: Vec<(bool, f64, usize)> = self
+            .endpoints
+            .iter()
+            .enumerate()
+            .map(|(index, endpoint)| (endpoint.is_healthy(), endpoint.stats().score(), index))
+            .collect();
+        ranked.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.total_cmp(&b.1)));
+        ranked
+    }
+
+    async fn throttle(&self, cost: u32) {
+        if let Some(limiter) = &self.limiter {
+            limiter.acquire(cost).await;
+        }
+    }
+
+    // `cost` is the number of calls one attempt makes, as providers count batch entries
+    async fn with_failover<'a, T, F, Fut>(
+        &'a self,
+        cost: u32,
+        method: &str,
+        send: F,
+    ) -> Result<T, TransportError>
+    where
+        F: Fn(&'a Endpoint) -> Fut,
+        Fut: Future<Output = Result<T, TransportError>>,
+    {
+        let mut last_error = None;
+        let mut attempted = false;
+        for retry in 0..=self.retry.retries {
+            if retry > 0 {
+                let delay = self.retry.delay(retry - 1);
+                warn!(
+                    "🔁 All RPC endpoints failed, retry {}/{} in {:?}",
+                    retry, self.retry.retries, delay
+                );
+                tokio::time::sleep(delay).await;
+            }
+            for index in self.attempt_order() {
+                let endpoint = &self.endpoints[index];
+                if attempted {
+                    self.resends.fetch_add(1, Ordering::Relaxed);
+                }
+                attempted = true;
+                self.throttle(cost).await;
+                let started = Instant::now();
+                let result = send(endpoint).await;
+                let latency = started.elapsed();
+                self.record(endpoint, latency, result.is_ok());
+                record_call(endpoint, method, latency, result.as_ref().err());
+                match result {
+                    Ok(response) => return Ok(response),
+                    Err(e) => {
+                        warn!("RPC request via {} failed: {}", endpoint.label, e);
+                        last_error = Some(e);
+                    }
+                }
+            }
+        }
+        // Only when every endpoint serves another chain
+        Err(last_error.unwrap_or(TransportError::NoEndpoint))
+    }
+
+    fn record(&self, endpoint: &Endpoint, latency: Duration, success: bool) {
+        let stats = {
+            let mut stats = endpoint.stats.lock().unwrap_or_else(|e| e.into_inner());
+            stats.record(latency, success);
+            *stats
+        };
+
+        let was_healthy = endpoint.healthy.swap(success, Ordering::Relaxed);
+        if was_healthy && !success {
+            warn!(
+                "⚠️ RPC endpoint {} marked unhealthy, failing over",
+                endpoint.label
+            );
+        } else if !was_healthy && success {
+            info!("✅ RPC endpoint {} recovered", endpoint.label);
+        }
+
+        let label = endpoint.label.clone();
+        metrics::gauge!("pp_fee_updater_rpc_endpoint_score", "endpoint" => label.clone())
+            .set(stats.score());
+        metrics::gauge!("pp_fee_updater_rpc_endpoint_latency_ms", "endpoint" => label.clone())
+            .set(stats.latency_ms.unwrap_or_default());
+        metrics::gauge!("pp_fee_updater_rpc_endpoint_error_rate", "endpoint" => label.clone())
+            .set(stats.error_rate);
+        metrics::gauge!("pp_fee_updater_rpc_endpoint_healthy", "endpoint" => label)
+            .set(if success { 1.0 } else { 0.0 });
+    }
+}
+
+#[async_trait]
+impl JsonRpcTransport for EndpointPool {
+    type Error = TransportError;
+
+    async fn send_request<P, R>(
+        &self,
+        method: JsonRpcMethod,
+        params: P,
+    ) -> Result<JsonRpcResponse<R>, Self::Error>
+    where
+        P: Serialize + Send + Sync,
+        R: DeserializeOwned,
+    {
+        // Serialized once so the same params can be resent to another endpoint
+        let params = &serde_json::to_value(params).map_err(HttpTransportError::Json)?;
+        let name = &method_name(method);
+        self.with_failover(1, name, |endpoint| async move {
+            debug!("RPC {:?} via {}", method, endpoint.label);
+            let response = endpoint.transport.send_request(method, params).await?;
+            // The endpoint answered, so the error only counts in the metrics
+            if let JsonRpcResponse::Error { .. } = &response {
+                record_error(endpoint, name, "jsonrpc");
+            }
+            Ok(response)
+        })
+        .await
+    }
+
+    async fn send_requests<R>(
+        &self,
+        requests: R,
+    ) -> Result<Vec<JsonRpcResponse<serde_json::Value>>, Self::Error>
+    where
+        R: AsRef<[ProviderRequestData]> + Send + Sync,
+    {
+        let requests = requests.as_ref();
+        let cost = u32::try_from(requests.len()).unwrap_or(u32::MAX);
+        self.with_failover(cost, "batch", |endpoint| {
+            endpoint.transport.send_requests(requests)
+        })
+        .await
+    }
+}
+
+// Per endpoint and method call counts, latencies and failures by class
+fn record_call(
+    endpoint: &Endpoint,
+    method: &str,
+    latency: Duration,
+    error: Option<&TransportError>,
+) {
+    let labels = [
+        ("endpoint", endpoint.label.clone()),
+        ("method", method.to_string()),
+    ];
+    metrics::counter!("pp_fee_updater_rpc_requests_total", &labels).increment(1);
+    metrics::histogram!("pp_fee_updater_rpc_request_duration_seconds", &labels)
+        .record(latency.as_secs_f64());
+    if let Some(error) = error {
+        record_error(endpoint, method, error.class());
+    }
+}
+
+fn record_error(endpoint: &Endpoint, method: &str, class: &'static str) {
+    metrics::counter!(
+        "pp_fee_updater_rpc_errors_total",
+        "endpoint" => endpoint.label.clone(),
+        "method" => method.to_string(),
+        "class" => class
+    )
+    .increment(1);
+}
+
+// The JSON-RPC name of a method, e.g. `starknet_call`
+pub(crate) fn method_name(method: JsonRpcMethod) -> String {
+    serde_json::to_value(method)
+        .ok()
+        .and_then(|name| name.as_str().map(str::to_string))
+        .unwrap_or_else(|| format!("{method:?}"))
+}
+
+// Metric label for an endpoint; only the host, since paths often embed API keys.
+// WebSocket endpoints keep their scheme so they stay apart from the same host over HTTP.
+fn endpoint_label(url: &Url) -> String {
+    let host = match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => format!("{host}:{port}"),
+        (Some(host), None) => host.to_string(),
+        _ => return url.scheme().to_string(),
+    };
+    match url.scheme() {
+        scheme @ ("ws" | "wss") => format!("{scheme}://{host}"),
+        _ => host,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::{
+        core::types::{
+            requests::{BlockNumberRequest, ChainIdRequest},
+            BlockId, BlockTag, FunctionCall,
+        },
+        providers::Provider,
+    };
+    use wiremock::ResponseTemplate;
+
+    use super::*;
+    use crate::fixtures::{MockNode, Reply, StubNode};
+
+    fn block_number(number: u64) -> StubNode {
+        StubNode::new(move |method, _| match method {
+            "starknet_blockNumber" => Reply::Result(json!(number)),
+            "starknet_chainId" => Reply::Result(json!("0x534e5f5345504f4c4941")),
+            _ => Reply::Error(-32601),
+        })
+    }
+
+    #[tokio::test]
+    async fn failed_endpoint_is_failed_over_and_marked_unhealthy() {
+        let down = StubNode::new(|_, _| Reply::Down);
+        let up = block_number(7);
+        let pool = StubNode::pool(&[&down, &up], RetryPolicy::default());
+
+        assert_eq!(pool.client().block_number().await.unwrap(), 7);
+        assert_eq!(down.requests("starknet_blockNumber").len(), 1);
+        assert_eq!(pool.resends(), 1);
+        assert!(!pool.endpoints[0].is_healthy());
+        // The healthy endpoint now ranks first
+        assert_eq!(pool.client().block_number().await.unwrap(), 7);
+        assert_eq!(down.requests("starknet_blockNumber").len(), 1);
+        assert_eq!(up.requests("starknet_blockNumber").len(), 2);
+    }
+
+    #[tokio::test]
+    async fn jsonrpc_errors_do_not_fail_over() {
+        let first = StubNode::new(|_, _| Reply::Error(20));
+        let second = block_number(7);
+        let pool = StubNode::pool(&[&first, &second], RetryPolicy::default());
+
+        let result = pool
+            .client()
+            .get_class_hash_at(BlockId::Number(1), Felt::ONE)
+            .await;
+        assert!(result.is_err());
+        assert_eq!(pool.resends(), 0);
+        assert!(pool.endpoints[0].is_healthy());
+        assert!(second.requests("starknet_getClassHashAt").is_empty());
+    }
+
+    #[tokio::test]
+    async fn result_that_does_not_parse_is_failed_over() {
+        let broken = StubNode::new(|_, _| Reply::Result(json!({ "block": "seven" })));
+        let pool = StubNode::pool(&[&broken, &block_number(7)], RetryPolicy::default());
+
+        assert_eq!(pool.client().block_number().await.unwrap(), 7);
+        assert!(!pool.endpoints[0].is_healthy());
+    }
+
+    #[tokio::test]
+    async fn failed_round_is_retried() {
+        let down = StubNode::new(|_, _| Reply::Down);
+        let retry = RetryPolicy {
+            retries: 2,
+            backoff: Duration::ZERO,
+        };
+        let pool = StubNode::pool(&[&down], retry);
+
+        assert!(pool.client().block_number().await.is_err());
+        assert_eq!(down.requests("starknet_blockNumber").len(), 3);
+        assert_eq!(pool.resends(), 2);
+    }
+
+    #[tokio::test]
+    async fn batch_is_answered_in_order_and_failed_over_as_a_whole() {
+        let down = StubNode::new(|_, _| Reply::Down);
+        let up = block_number(7);
+        let pool = StubNode::pool(&[&down, &up], RetryPolicy::default());
+
+        let responses = pool
+            .send_requests([
+                ProviderRequestData::BlockNumber(BlockNumberRequest),
+                ProviderRequestData::ChainId(ChainIdRequest),
+            ])
+            .await
+            .unwrap();
+        let results: Vec<_> = responses
+            .into_iter()
+            .map(|response| match response {
+                JsonRpcResponse::Success { result, .. } => result,
+                JsonRpcResponse::Error { error, .. } => panic!("batch entry failed: {error:?}"),
+            })
+            .collect();
+        assert_eq!(results, vec![json!(7), json!("0x534e5f5345504f4c4941")]);
+        assert_eq!(up.requests("starknet_blockNumber").len(), 1);
+        assert_eq!(up.requests("starknet_chainId").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn probe_keeps_endpoints_of_another_chain_out() {
+        let pool = StubNode::pool(
+            &[&block_number(1), &block_number(2)],
+            RetryPolicy::default(),
+        );
+        pool.expect_chain_id(Felt::from_hex("0x534e5f4d41494e").unwrap());
+
+        assert!(!pool.probe(&pool.endpoints[0]).await);
+        assert!(pool.endpoints[0].is_wrong_chain());
+        assert!(!pool.probe(&pool.endpoints[1]).await);
+        assert!(pool.attempt_order().is_empty());
+        assert!(matches!(
+            pool.client().block_number().await,
+            Err(starknet::providers::ProviderError::Other(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn probe_lets_an_endpoint_back_once_it_serves_the_chain() {
+        let pool = StubNode::pool(&[&block_number(1)], RetryPolicy::default());
+        pool.expect_chain_id(Felt::from_hex("0x534e5f5345504f4c4941").unwrap());
+        pool.endpoints[0].wrong_chain.store(true, Ordering::Relaxed);
+
+        assert!(pool.probe(&pool.endpoints[0]).await);
+        assert!(!pool.endpoints[0].is_wrong_chain());
+        assert_eq!(pool.attempt_order(), vec![0]);
+    }
+
+    #[test]
+    fn pinned_clients_take_the_best_ranked_endpoints() {
+        let pool = StubNode::pool(
+            &[&block_number(1), &block_number(2), &block_number(3)],
+            RetryPolicy::default(),
+        );
+        pool.record(&pool.endpoints[0], Duration::from_millis(10), false);
+
+        assert_eq!(pool.pinned_clients(2).len(), 2);
+        assert_eq!(pool.attempt_order()[2], 0);
+    }
+
+    #[tokio::test]
+    async fn rate_limited_http_endpoint_is_failed_over() {
+        let limited = MockNode::start().await;
+        limited
+            .respond(
+                "starknet_chainId",
+                ResponseTemplate::new(429).set_body_string("Too Many Requests"),
+            )
+            .await;
+        let node = MockNode::start().await;
+        node.replay("starknet_chainId", "chain_id_sepolia").await;
+        let pool = MockNode::pool(&[&limited, &node], RetryPolicy::default());
+
+        let chain_id = pool.client().chain_id().await.unwrap();
+        assert_eq!(chain_id, Felt::from_hex("0x534e5f5345504f4c4941").unwrap());
+        assert_eq!(limited.requests("starknet_chainId").await.len(), 1);
+        assert!(!pool.endpoints[0].is_healthy());
+        assert_eq!(pool.current(), 1);
+    }
+
+    #[tokio::test]
+    async fn failing_http_endpoint_is_retried_in_rounds() {
+        let node = MockNode::start().await;
+        node.respond_first("starknet_chainId", 1, ResponseTemplate::new(503))
+            .await;
+        node.replay("starknet_chainId", "chain_id_sepolia").await;
+        let retry = RetryPolicy {
+            retries: 1,
+            backoff: Duration::ZERO,
+        };
+        let pool = MockNode::pool(&[&node], retry);
+
+        assert!(pool.client().chain_id().await.is_ok());
+        assert_eq!(node.requests("starknet_chainId").await.len(), 2);
+        assert!(pool.endpoints[0].is_healthy());
+    }
+
+    #[tokio::test]
+    async fn malformed_http_result_is_failed_over() {
+        let broken = MockNode::start().await;
+        broken.replay("starknet_call", "call_malformed").await;
+        let node = MockNode::start().await;
+        node.replay("starknet_call", "call_price").await;
+        let pool = MockNode::pool(&[&broken, &node], RetryPolicy::default());
+
+        let result = pool
+            .client()
+            .call(
+                FunctionCall {
+                    contract_address: Felt::ONE,
+                    entry_point_selector: Felt::TWO,
+                    calldata: Vec::new(),
+                },
+                BlockId::Tag(BlockTag::Latest),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result, vec![Felt::from(40_000_000u32), Felt::ZERO]);
+        assert!(!pool.endpoints[0].is_healthy());
+    }
+}
@@ -0,0 +1,75 @@
+use std::path::PathBuf;
+
+use crate::errors::ErrorClass;
+
+const DEFAULT_ENV_FILE: &str = ".env";
+
+// Variables of the `.env` file become defaults for the env-backed arguments; ones already
+// set in the environment win. Runs before clap, so `--env-file` is picked out by hand.
+pub fn load() -> anyhow::Result<Option<PathBuf>> {
+    let (path, explicit) = match env_file_arg().or_else(|| std::env::var_os("ENV_FILE")) {
+        Some(path) => (PathBuf::from(path), true),
+        None => (PathBuf::from(DEFAULT_ENV_FILE), false),
+    };
+    match dotenvy::from_path(&path) {
+        Ok(()) => Ok(Some(path)),
+        // Only a file that was asked for has to exist
+        Err(e) if e.not_found() && !explicit => Ok(None),
+        Err(e) => Err(
+            anyhow::anyhow!("Failed to load the env file {}: {}", path.display(), e)
+                .context(ErrorClass::Configuration),
+        ),
+    }
+}
+
+fn env_file_arg() -> Option<std::ffi::OsString> {
+    let mut args = std::env::args_os();
+    while let Some(arg) = args.next() {
+        if arg == "--env-file" {
+            return args.next();
+        }
+        if let Some(path) = arg.to_str().and_then(|arg| arg.strip_prefix("--env-file=")) {
+            return Some(path.into());
+        }
+    }
+    None
+}
+
+// `<NAME>_FILE` holds the path of a file with the value of the env-backed argument
+// `<NAME>`, as Docker and Kubernetes mount secrets, so they never sit in the environment
+pub fn load_secret_files(command: &clap::Command) -> anyhow::Result<Vec<String>> {
+    let mut names = Vec::new();
+    env_names(command, &mut names);
+    names.sort();
+    names.dedup();
+
+    let mut loaded = Vec::new();
+    for name in names {
+        let file_var = format!("{name}_FILE");
+        let Some(path) = std::env::var_os(&file_var) else {
+            continue;
+        };
+        if std::env::var_os(&name).is_some() {
+            return Err(anyhow::anyhow!("Both {} and {} are set", name, file_var)
+                .context(ErrorClass::Configuration));
+        }
+        let value = std::fs::read_to_string(&path).map_err(|e| {
+            anyhow::anyhow!("Failed to read {} from {:?}: {}", file_var, path, e)
+                .context(ErrorClass::Configuration)
+        })?;
+        std::env::set_var(&name, value.trim_end_matches(['\r', '\n']));
+        loaded.push(name);
+    }
+    Ok(loaded)
+}
+
+fn env_names(command: &clap::Command, names: &mut Vec<String>) {
+    for arg in command.get_arguments() {
+        if let Some(name) = arg.get_env().and_then(|name| name.to_str()) {
+            names.push(name.to_string());
+        }
+    }
+    for subcommand in command.get_subcommands() {
+        env_names(subcommand, names);
+    }
+}
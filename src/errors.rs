@@ -0,0 +1,124 @@
+use std::fmt;
+
+use starknet::{
+    core::types::StarknetError,
+    providers::{jsonrpc::JsonRpcClientError, ProviderError},
+};
+
+use crate::endpoints::TransportError;
+use crate::history::HistoryError;
+use crate::preflight::PreflightError;
+use crate::retry::is_transient_provider_error;
+use crate::updater::UpdaterError;
+
+// Broad error categories: whether the daemon can carry on, and how it exits if not.
+// Errors can also be tagged explicitly with `anyhow::Context::context(ErrorClass::…)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    Network,
+    Auth,
+    ContractRevert,
+    Signer,
+    Configuration,
+    Internal,
+}
+
+impl ErrorClass {
+    // Retryable errors skip the current block; fatal ones stop the daemon
+    pub fn is_retryable(self) -> bool {
+        matches!(
+            self,
+            ErrorClass::Network | ErrorClass::ContractRevert | ErrorClass::Internal
+        )
+    }
+
+    // Distinct process exit codes so supervisors can tell fatal classes apart
+    pub fn exit_code(self) -> u8 {
+        match self {
+            ErrorClass::Internal => 1,
+            ErrorClass::Configuration => 2,
+            ErrorClass::Auth => 3,
+            ErrorClass::Signer => 4,
+            ErrorClass::Network => 5,
+            ErrorClass::ContractRevert => 6,
+        }
+    }
+
+    // An explicit tag wins, otherwise the class of the outermost recognised error
+    pub fn of(error: &anyhow::Error) -> Self {
+        if let Some(class) = error.downcast_ref::<ErrorClass>() {
+            return *class;
+        }
+        for cause in error.chain() {
+            if let Some(e) = cause.downcast_ref::<UpdaterError>() {
+                return e.class();
+            }
+            if let Some(e) = cause.downcast_ref::<PreflightError>() {
+                return e.class();
+            }
+            if let Some(e) = cause.downcast_ref::<ProviderError>() {
+                return classify_provider_error(e);
+            }
+            if cause.is::<HistoryError>() {
+                return ErrorClass::Configuration;
+            }
+            if cause.is::<std::io::Error>()
+                || cause.is::<tokio_tungstenite::tungstenite::Error>()
+                || cause.is::<reqwest::Error>()
+            {
+                return ErrorClass::Network;
+            }
+        }
+        ErrorClass::Internal
+    }
+}
+
+impl fmt::Display for ErrorClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ErrorClass::Network => "network error",
+            ErrorClass::Auth => "authentication error",
+            ErrorClass::ContractRevert => "contract revert",
+            ErrorClass::Signer => "signer error",
+            ErrorClass::Configuration => "configuration error",
+            ErrorClass::Internal => "internal error",
+        })
+    }
+}
+
+pub fn classify_provider_error(error: &ProviderError) -> ErrorClass {
+    if is_auth_error(error) {
+        return ErrorClass::Auth;
+    }
+    if is_transient_provider_error(error) {
+        return ErrorClass::Network;
+    }
+    match error {
+        ProviderError::StarknetError(
+            StarknetError::ContractError(_)
+            | StarknetError::TransactionExecutionError(_)
+            | StarknetError::ContractNotFound
+            | StarknetError::EntrypointNotFound,
+        ) => ErrorClass::ContractRevert,
+        ProviderError::StarknetError(StarknetError::ValidationFailure(_)) => ErrorClass::Signer,
+        _ => ErrorClass::Internal,
+    }
+}
+
+// Providers report missing or bad API keys as JSON-RPC errors with varying codes, so
+// this goes by the message
+fn is_auth_error(error: &ProviderError) -> bool {
+    let ProviderError::Other(inner) = error else {
+        return false;
+    };
+    let Some(JsonRpcClientError::JsonRpcError(e)) = inner
+        .as_any()
+        .downcast_ref::<JsonRpcClientError<TransportError>>()
+    else {
+        return false;
+    };
+    let message = e.message.to_ascii_lowercase();
+    ["unauthorized", "authenticat", "api key", "forbidden"]
+        .iter()
+        .any(|needle| message.contains(needle))
+}
@@ -0,0 +1,120 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use starknet::core::types::ResourcePrice;
+use tracing::debug;
+use url::Url;
+
+use crate::components::Denomination;
+use crate::sources::{to_u128, GasPriceSource, SourceContext, SourcePrices};
+use crate::updater::UpdaterError;
+
+// Current Ethereum fees, in wei
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EthFees {
+    pub base_fee: u128,
+    // Absent on nodes that predate EIP-4844
+    pub blob_base_fee: Option<u128>,
+}
+
+// Auxiliary Ethereum JSON-RPC source: Starknet L1 prices lag the Ethereum fees, so blending
+// them in lets the contract price move ahead of predictable increases
+#[derive(Debug, Clone)]
+pub struct EthGasOracle {
+    client: reqwest::Client,
+    url: Url,
+}
+
+impl EthGasOracle {
+    pub fn new(client: reqwest::Client, url: Url) -> Self {
+        Self { client, url }
+    }
+
+    pub async fn fees(&self) -> Result<EthFees, UpdaterError> {
+        let block = self
+            .call("eth_getBlockByNumber", json!(["latest", false]))
+            .await?;
+        let base_fee = block
+            .get("baseFeePerGas")
+            .and_then(Value::as_str)
+            .ok_or_else(|| UpdaterError::Oracle("Ethereum block has no base fee".to_string()))
+            .and_then(parse_quantity)?;
+        let blob_base_fee = match self.call("eth_blobBaseFee", json!([])).await {
+            Ok(value) => value.as_str().map(parse_quantity).transpose()?,
+            Err(e) => {
+                debug!("Ethereum node returned no blob base fee: {}", e);
+                None
+            }
+        };
+        Ok(EthFees {
+            base_fee,
+            blob_base_fee,
+        })
+    }
+
+    async fn call(&self, method: &str, params: Value) -> Result<Value, UpdaterError> {
+        let response: Value = self
+            .client
+            .post(self.url.clone())
+            .json(&json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params}))
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| UpdaterError::Oracle(format!("Ethereum {method} failed: {e}")))?
+            .json()
+            .await
+            .map_err(|e| UpdaterError::Oracle(format!("Ethereum {method} failed: {e}")))?;
+        if let Some(error) = response.get("error") {
+            return Err(UpdaterError::Oracle(format!(
+                "Ethereum {method} failed: {error}"
+            )));
+        }
+        response
+            .get("result")
+            .cloned()
+            .ok_or_else(|| UpdaterError::Oracle(format!("Ethereum {method} returned no result")))
+    }
+}
+
+// The base fee prices L1 gas and the blob base fee L1 data gas, converted into the
+// denomination at the block's own fri/wei ratio
+#[async_trait]
+impl GasPriceSource for EthGasOracle {
+    fn name(&self) -> &str {
+        "ethereum"
+    }
+
+    async fn prices(&self, ctx: SourceContext<'_>) -> Result<SourcePrices, UpdaterError> {
+        let fees = self.fees().await?;
+        debug!(
+            "Ethereum base fee: {} wei, blob base fee: {:?} wei",
+            fees.base_fee, fees.blob_base_fee
+        );
+        Ok(SourcePrices {
+            l1_gas: eth_in_denomination(fees.base_fee, ctx.denomination, &ctx.block.l1_gas),
+            l1_data_gas: fees.blob_base_fee.and_then(|blob_base_fee| {
+                eth_in_denomination(blob_base_fee, ctx.denomination, &ctx.block.l1_data_gas)
+            }),
+            l2_gas: None,
+        })
+    }
+}
+
+fn eth_in_denomination(
+    eth_fee: u128,
+    denomination: Denomination,
+    resource_price: &ResourcePrice,
+) -> Option<u128> {
+    match denomination {
+        Denomination::Wei => Some(eth_fee),
+        Denomination::Fri => {
+            let price_in_wei = to_u128(resource_price.price_in_wei).filter(|wei| *wei > 0)?;
+            let price_in_fri = to_u128(resource_price.price_in_fri)?;
+            Some(eth_fee.checked_mul(price_in_fri)? / price_in_wei)
+        }
+    }
+}
+
+fn parse_quantity(quantity: &str) -> Result<u128, UpdaterError> {
+    u128::from_str_radix(quantity.trim_start_matches("0x"), 16)
+        .map_err(|e| UpdaterError::Oracle(format!("Invalid Ethereum quantity `{quantity}`: {e}")))
+}
@@ -0,0 +1,150 @@
+use std::{sync::Arc, time::Duration};
+
+use rdkafka::{
+    producer::{FutureProducer, FutureRecord},
+    ClientConfig,
+};
+use serde::Serialize;
+use tokio::sync::{mpsc, Mutex};
+use tracing::{error, info};
+use url::Url;
+
+use crate::errors::ErrorClass;
+use crate::history::{DecisionRecord, TransactionRecord};
+use crate::mqtt::MqttPublisher;
+use crate::retry::BackoffPolicy;
+use crate::supervisor;
+use crate::updater::TxOutcome;
+
+const KAFKA_DEFAULT_PORT: u16 = 9092;
+// How long Kafka keeps retrying a message before reporting it failed
+const KAFKA_MESSAGE_TIMEOUT: Duration = Duration::from_secs(30);
+
+// A fee decision or a step in the life of one of our transactions, published as one JSON
+// message
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum FeeEvent {
+    Decision(DecisionRecord),
+    TransactionSent(TransactionRecord),
+    TransactionResolved {
+        #[serde(flatten)]
+        transaction: TransactionRecord,
+        outcome: TxOutcome,
+    },
+}
+
+impl FeeEvent {
+    // Kafka partition key, keeping the events of a component or a transaction in order
+    fn key(&self) -> &str {
+        match self {
+            FeeEvent::Decision(decision) => &decision.component,
+            FeeEvent::TransactionSent(transaction)
+            | FeeEvent::TransactionResolved { transaction, .. } => &transaction.tx_hash,
+        }
+    }
+}
+
+// Publishes fee events to a Kafka topic or a NATS subject, and the prices and updates
+// among them to MQTT, from delivery tasks, so a slow or unreachable broker never holds up
+// a fee check
+#[derive(Debug, Clone, Default)]
+pub struct EventStream {
+    events: Option<mpsc::UnboundedSender<FeeEvent>>,
+    mqtt: Option<MqttPublisher>,
+}
+
+impl EventStream {
+    // `url` is `kafka://host:port` or `nats://host:port`; `topic` is the Kafka topic or the
+    // NATS subject
+    pub fn new(url: Option<Url>, topic: String, restarts: BackoffPolicy) -> anyhow::Result<Self> {
+        let Some(url) = url else {
+            return Ok(Self::default());
+        };
+        if !matches!(url.scheme(), "kafka" | "nats") {
+            return Err(anyhow::anyhow!(
+                "Unsupported event stream {}, expected a kafka:// or nats:// URL",
+                url.scheme()
+            )
+            .context(ErrorClass::Configuration));
+        }
+        info!("📣 Publishing fee events to {} `{}`", url.scheme(), topic);
+
+        let (events, pending) = mpsc::unbounded_channel();
+        // Events queued while the broker is unreachable wait for the reconnect
+        let pending = Arc::new(Mutex::new(pending));
+        supervisor::supervise("event stream", restarts, move || {
+            let pending = pending.clone();
+            let url = url.clone();
+            let topic = topic.clone();
+            async move {
+                let sink = Sink::connect(&url).await?;
+                let mut pending = pending.lock().await;
+                while let Some(event) = pending.recv().await {
+                    if let Err(e) = sink.publish(&topic, &event).await {
+                        error!("Failed to publish fee event: {:?}", e);
+                    }
+                }
+                Ok(())
+            }
+        });
+        Ok(Self {
+            events: Some(events),
+            mqtt: None,
+        })
+    }
+
+    pub fn with_mqtt(mut self, mqtt: MqttPublisher) -> Self {
+        self.mqtt = Some(mqtt);
+        self
+    }
+
+    pub fn publish(&self, event: FeeEvent) {
+        if let Some(mqtt) = &self.mqtt {
+            mqtt.publish(&event);
+        }
+        if let Some(events) = &self.events {
+            let _ = events.send(event);
+        }
+    }
+}
+
+enum Sink {
+    Kafka(FutureProducer),
+    Nats(async_nats::Client),
+}
+
+impl Sink {
+    async fn connect(url: &Url) -> anyhow::Result<Self> {
+        if url.scheme() == "nats" {
+            return Ok(Sink::Nats(async_nats::connect(url.as_str()).await?));
+        }
+        let host = url
+            .host_str()
+            .ok_or_else(|| anyhow::anyhow!("Kafka URL {} has no host", url))?;
+        let brokers = format!("{}:{}", host, url.port().unwrap_or(KAFKA_DEFAULT_PORT));
+        let producer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set(
+                "message.timeout.ms",
+                KAFKA_MESSAGE_TIMEOUT.as_millis().to_string(),
+            )
+            .create()?;
+        Ok(Sink::Kafka(producer))
+    }
+
+    async fn publish(&self, topic: &str, event: &FeeEvent) -> anyhow::Result<()> {
+        let payload = serde_json::to_vec(event)?;
+        match self {
+            Sink::Kafka(producer) => {
+                let record = FutureRecord::to(topic).key(event.key()).payload(&payload);
+                producer
+                    .send(record, Duration::ZERO)
+                    .await
+                    .map_err(|(e, _)| e)?;
+            }
+            Sink::Nats(client) => client.publish(topic.to_string(), payload.into()).await?,
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+use std::io::Write;
+
+use crate::history::{AnnotationTarget, HistoryError, HistoryRecord};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// One row per decision, transaction component, outside change or annotation, with a
+    /// shared header
+    Csv,
+    /// The history records as they are stored, one JSON object per line
+    Jsonl,
+}
+
+const CSV_HEADER: &str = "kind,timestamp,block_number,component,network_gas_price,contract_gas_price,should_update,new_gas_price,tx_hash,text";
+
+pub fn record_timestamp(record: &HistoryRecord) -> u64 {
+    match record {
+        HistoryRecord::Decision(decision) => decision.timestamp,
+        HistoryRecord::Transaction(tx) => tx.timestamp,
+        HistoryRecord::Annotation(annotation) => annotation.timestamp,
+        HistoryRecord::OutsideChange(change) => change.timestamp,
+    }
+}
+
+pub fn write_records(
+    records: &[HistoryRecord],
+    format: ExportFormat,
+    out: &mut impl Write,
+) -> Result<(), HistoryError> {
+    match format {
+        ExportFormat::Jsonl => {
+            for record in records {
+                serde_json::to_writer(&mut *out, record)?;
+                out.write_all(b"\n")?;
+            }
+        }
+        ExportFormat::Csv => {
+            writeln!(out, "{CSV_HEADER}")?;
+            for record in records {
+                for row in csv_rows(record) {
+                    writeln!(out, "{}", row.join(","))?;
+                }
+            }
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+// A transaction gets one row per component it wrote
+fn csv_rows(record: &HistoryRecord) -> Vec<[String; 10]> {
+    let opt = |value: Option<u128>| value.map(|value| value.to_string()).unwrap_or_default();
+    match record {
+        HistoryRecord::Decision(decision) => vec![[
+            "decision".to_string(),
+            decision.timestamp.to_string(),
+            opt(decision.block_number.map(u128::from)),
+            csv_field(&decision.component),
+            decision.network_gas_price.to_string(),
+            decision.contract_gas_price.to_string(),
+            decision.should_update.to_string(),
+            opt(decision.new_gas_price),
+            String::new(),
+            String::new(),
+        ]],
+        HistoryRecord::Transaction(tx) => tx
+            .prices
+            .iter()
+            .map(|(component, price)| {
+                [
+                    "transaction".to_string(),
+                    tx.timestamp.to_string(),
+                    opt(tx.block_number.map(u128::from)),
+                    csv_field(component),
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                    price.to_string(),
+                    tx.tx_hash.clone(),
+                    String::new(),
+                ]
+            })
+            .collect(),
+        // The previous value goes under the contract price, the origin under the text
+        HistoryRecord::OutsideChange(change) => vec![[
+            "outside_change".to_string(),
+            change.timestamp.to_string(),
+            opt(change.block_number.map(u128::from)),
+            csv_field(&change.component),
+            String::new(),
+            change.previous.to_string(),
+            String::new(),
+            change.current.to_string(),
+            change.tx_hash.clone().unwrap_or_default(),
+            change.origin.to_string(),
+        ]],
+        HistoryRecord::Annotation(annotation) => {
+            let (block_number, tx_hash) = match &annotation.target {
+                AnnotationTarget::Block { block_number } => (Some(*block_number), String::new()),
+                AnnotationTarget::Transaction { tx_hash } => (None, tx_hash.clone()),
+                AnnotationTarget::TimeRange { .. } => (None, String::new()),
+            };
+            vec![[
+                "annotation".to_string(),
+                annotation.timestamp.to_string(),
+                opt(block_number.map(u128::from)),
+                String::new(),
+                String::new(),
+                String::new(),
+                String::new(),
+                String::new(),
+                csv_field(&tx_hash),
+                csv_field(&annotation.text),
+            ]]
+        }
+    }
+}
+
+// Quotes fields containing separators, quotes or line breaks (RFC 4180)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
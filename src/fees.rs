@@ -0,0 +1,156 @@
+use std::{fmt, str::FromStr};
+
+use starknet::accounts::{AccountError, ConnectedAccount, ExecutionV3, PreparedExecutionV3};
+
+// Library defaults for bounds derived from the fee estimate
+pub const DEFAULT_AMOUNT_MULTIPLIER: f64 = 1.5;
+pub const DEFAULT_PRICE_MULTIPLIER: f64 = 1.5;
+
+// A resource bound taken from the fee estimate or fixed by the operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Bound<T> {
+    #[default]
+    Auto,
+    Fixed(T),
+}
+
+impl<T: FromStr> FromStr for Bound<T>
+where
+    T::Err: fmt::Display,
+{
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value.eq_ignore_ascii_case("auto") {
+            return Ok(Bound::Auto);
+        }
+        value
+            .parse()
+            .map(Bound::Fixed)
+            .map_err(|e| format!("expected `auto` or a number: {e}"))
+    }
+}
+
+impl<T> Bound<T> {
+    fn fixed(self) -> Option<T> {
+        match self {
+            Bound::Auto => None,
+            Bound::Fixed(value) => Some(value),
+        }
+    }
+}
+
+// Resource bounds of the v3 invokes. Auto bounds are the fee estimate times the
+// multipliers; with every bound fixed no estimate is made at all.
+#[derive(Debug, Clone, Copy)]
+pub struct ResourceBounds {
+    pub l1_gas: Bound<u64>,
+    pub l1_gas_price: Bound<u128>,
+    pub l2_gas: Bound<u64>,
+    pub l2_gas_price: Bound<u128>,
+    pub l1_data_gas: Bound<u64>,
+    pub l1_data_gas_price: Bound<u128>,
+    pub amount_multiplier: f64,
+    pub price_multiplier: f64,
+}
+
+impl Default for ResourceBounds {
+    fn default() -> Self {
+        Self {
+            l1_gas: Bound::Auto,
+            l1_gas_price: Bound::Auto,
+            l2_gas: Bound::Auto,
+            l2_gas_price: Bound::Auto,
+            l1_data_gas: Bound::Auto,
+            l1_data_gas_price: Bound::Auto,
+            amount_multiplier: DEFAULT_AMOUNT_MULTIPLIER,
+            price_multiplier: DEFAULT_PRICE_MULTIPLIER,
+        }
+    }
+}
+
+impl ResourceBounds {
+    pub fn apply<'a, A>(&self, mut execution: ExecutionV3<'a, A>) -> ExecutionV3<'a, A> {
+        if let Some(l1_gas) = self.l1_gas.fixed() {
+            execution = execution.l1_gas(l1_gas);
+        }
+        if let Some(l1_gas_price) = self.l1_gas_price.fixed() {
+            execution = execution.l1_gas_price(l1_gas_price);
+        }
+        if let Some(l2_gas) = self.l2_gas.fixed() {
+            execution = execution.l2_gas(l2_gas);
+        }
+        if let Some(l2_gas_price) = self.l2_gas_price.fixed() {
+            execution = execution.l2_gas_price(l2_gas_price);
+        }
+        if let Some(l1_data_gas) = self.l1_data_gas.fixed() {
+            execution = execution.l1_data_gas(l1_data_gas);
+        }
+        if let Some(l1_data_gas_price) = self.l1_data_gas_price.fixed() {
+            execution = execution.l1_data_gas_price(l1_data_gas_price);
+        }
+        execution
+            .gas_estimate_multiplier(self.amount_multiplier)
+            .gas_price_estimate_multiplier(self.price_multiplier)
+    }
+
+    // Resolves the auto bounds with a single fee estimate, so every resend is the very same
+    // transaction and its hash is known before it is sent. `execution` must carry its nonce.
+    pub async fn prepare<'a, A>(
+        &self,
+        execution: ExecutionV3<'a, A>,
+    ) -> Result<PreparedExecutionV3<'a, A>, AccountError<A::SignError>>
+    where
+        A: ConnectedAccount + Sync,
+    {
+        let mut execution = self.apply(execution);
+        if !self.all_fixed() {
+            let estimate = execution.estimate_fee().await?;
+            let amount = |consumed: u64| (consumed as f64 * self.amount_multiplier) as u64;
+            let price = |price: u128| (price as f64 * self.price_multiplier) as u128;
+            execution = execution
+                .l1_gas(
+                    self.l1_gas
+                        .fixed()
+                        .unwrap_or_else(|| amount(estimate.l1_gas_consumed)),
+                )
+                .l1_gas_price(
+                    self.l1_gas_price
+                        .fixed()
+                        .unwrap_or_else(|| price(estimate.l1_gas_price)),
+                )
+                .l2_gas(
+                    self.l2_gas
+                        .fixed()
+                        .unwrap_or_else(|| amount(estimate.l2_gas_consumed)),
+                )
+                .l2_gas_price(
+                    self.l2_gas_price
+                        .fixed()
+                        .unwrap_or_else(|| price(estimate.l2_gas_price)),
+                )
+                .l1_data_gas(
+                    self.l1_data_gas
+                        .fixed()
+                        .unwrap_or_else(|| amount(estimate.l1_data_gas_consumed)),
+                )
+                .l1_data_gas_price(
+                    self.l1_data_gas_price
+                        .fixed()
+                        .unwrap_or_else(|| price(estimate.l1_data_gas_price)),
+                );
+        }
+        Ok(execution
+            .prepared()
+            .expect("the nonce and every resource bound are set"))
+    }
+
+    fn all_fixed(&self) -> bool {
+        self.l1_gas.fixed().is_some()
+            && self.l1_gas_price.fixed().is_some()
+            && self.l2_gas.fixed().is_some()
+            && self.l2_gas_price.fixed().is_some()
+            && self.l1_data_gas.fixed().is_some()
+            && self.l1_data_gas_price.fixed().is_some()
+    }
+}
@@ -0,0 +1,249 @@
+use std::{
+    fmt,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use starknet::providers::{
+    jsonrpc::{HttpTransportError, JsonRpcMethod, JsonRpcResponse},
+    ProviderRequestData,
+};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+use url::Url;
+use wiremock::{
+    matchers::{body_partial_json, method},
+    Mock, MockServer, ResponseTemplate,
+};
+
+use crate::endpoints::{method_name, EndpointPool, NodeTransport, TransportError};
+use crate::transport::{RetryPolicy, TlsOptions, TransportOptions};
+use crate::wsrpc::WsTransportError;
+
+// How the in-memory node answers one request
+#[derive(Debug, Clone)]
+pub enum Reply {
+    Result(Value),
+    // A JSON-RPC error with this code
+    Error(i64),
+    // No answer at all, like a dropped connection
+    Down,
+}
+
+type Answer = dyn Fn(&str, &Value) -> Reply + Send + Sync;
+
+// In-memory JSON-RPC node answering every request by its method name and params, and
+// recording them in order
+#[derive(Clone)]
+pub struct StubNode {
+    answer: Arc<Answer>,
+    requests: Arc<Mutex<Vec<(String, Value)>>>,
+}
+
+impl StubNode {
+    pub fn new(answer: impl Fn(&str, &Value) -> Reply + Send + Sync + 'static) -> Self {
+        Self {
+            answer: Arc::new(answer),
+            requests: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    // Pool over the nodes, in order
+    pub fn pool(nodes: &[&StubNode], retry: RetryPolicy) -> EndpointPool {
+        let nodes = nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| {
+                let url = Url::parse(&format!("http://stub-{index}")).expect("stub URL");
+                let node: Box<dyn NodeTransport> = Box::new((*node).clone());
+                (url, node)
+            })
+            .collect();
+        EndpointPool::with_nodes(nodes, retry, None)
+    }
+
+    // Params of every request of the method received so far
+    pub fn requests(&self, method: &str) -> Vec<Value> {
+        self.requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .filter(|(name, _)| name == method)
+            .map(|(_, params)| params.clone())
+            .collect()
+    }
+
+    // The response to one request, or `None` when the node is down
+    fn respond(&self, id: u64, method: &str, params: &Value) -> Option<Value> {
+        let reply = (self.answer)(method, params);
+        self.requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push((method.to_string(), params.clone()));
+        match reply {
+            Reply::Result(result) => Some(json!({ "jsonrpc": "2.0", "id": id, "result": result })),
+            Reply::Error(code) => Some(json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": "stub error" }
+            })),
+            Reply::Down => None,
+        }
+    }
+}
+
+#[async_trait]
+impl NodeTransport for StubNode {
+    async fn send_request(
+        &self,
+        method: JsonRpcMethod,
+        params: &Value,
+    ) -> Result<JsonRpcResponse<Value>, TransportError> {
+        let response = self
+            .respond(1, &method_name(method), params)
+            .ok_or(WsTransportError::Closed)?;
+        Ok(serde_json::from_value(response).map_err(HttpTransportError::Json)?)
+    }
+
+    // Answers every entry in order; a node that is down for one of them drops the batch
+    async fn send_requests(
+        &self,
+        requests: &[ProviderRequestData],
+    ) -> Result<Vec<JsonRpcResponse<Value>>, TransportError> {
+        let mut responses = Vec::with_capacity(requests.len());
+        for (id, request) in (0u64..).zip(requests) {
+            let request = serde_json::to_value(request).map_err(HttpTransportError::Json)?;
+            let method = request["method"].as_str().unwrap_or_default();
+            let response = self
+                .respond(id, method, &request["params"])
+                .ok_or(WsTransportError::Closed)?;
+            responses.push(serde_json::from_value(response).map_err(HttpTransportError::Json)?);
+        }
+        Ok(responses)
+    }
+}
+
+impl fmt::Debug for StubNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StubNode").finish_non_exhaustive()
+    }
+}
+
+// A JSON-RPC response body under tests/fixtures/rpc, as a Starknet node sends it
+pub fn fixture(name: &str) -> Value {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/rpc")
+        .join(format!("{name}.json"));
+    let body = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("failed to read {}: {e}", path.display()));
+    serde_json::from_str(&body).unwrap_or_else(|e| panic!("{} is not JSON: {e}", path.display()))
+}
+
+pub fn transport_options(retry: RetryPolicy) -> TransportOptions {
+    TransportOptions {
+        headers: Vec::new(),
+        proxy: None,
+        tls: TlsOptions::default(),
+        timeout: Duration::from_secs(5),
+        retry,
+        rate_limit: None,
+    }
+}
+
+// HTTP JSON-RPC node answering each method with a fixture or a canned response
+pub struct MockNode {
+    server: MockServer,
+}
+
+impl MockNode {
+    pub async fn start() -> Self {
+        Self {
+            server: MockServer::start().await,
+        }
+    }
+
+    pub fn url(&self) -> Url {
+        Url::parse(&self.server.uri()).expect("mock server URL")
+    }
+
+    // Pool over the nodes, in order, going through the real HTTP transport
+    pub fn pool(nodes: &[&MockNode], retry: RetryPolicy) -> EndpointPool {
+        let urls: Vec<Url> = nodes.iter().map(|node| node.url()).collect();
+        EndpointPool::new(&urls, &transport_options(retry)).expect("HTTP client")
+    }
+
+    // Every call of the method gets the fixture
+    pub async fn replay(&self, rpc_method: &str, name: &str) {
+        self.respond(
+            rpc_method,
+            ResponseTemplate::new(200).set_body_json(fixture(name)),
+        )
+        .await;
+    }
+
+    pub async fn respond(&self, rpc_method: &str, response: ResponseTemplate) {
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({ "method": rpc_method })))
+            .respond_with(response)
+            .mount(&self.server)
+            .await;
+    }
+
+    // The first `times` calls of the method get `response`, ahead of any other response
+    pub async fn respond_first(&self, rpc_method: &str, times: u64, response: ResponseTemplate) {
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({ "method": rpc_method })))
+            .respond_with(response)
+            .up_to_n_times(times)
+            .with_priority(1)
+            .mount(&self.server)
+            .await;
+    }
+
+    // Bodies of the calls of the method received so far
+    pub async fn requests(&self, rpc_method: &str) -> Vec<Value> {
+        self.server
+            .received_requests()
+            .await
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|request| request.body_json::<Value>().ok())
+            .filter(|body| body["method"] == rpc_method)
+            .collect()
+    }
+}
+
+// WebSocket node answering every request with `result`, for the startup checks
+pub async fn ws_node(result: Value) -> Url {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+    let addr = listener.local_addr().expect("local address");
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let result = result.clone();
+            tokio::spawn(async move {
+                let Ok(mut ws_stream) = tokio_tungstenite::accept_async(stream).await else {
+                    return;
+                };
+                while let Some(Ok(Message::Text(text))) = ws_stream.next().await {
+                    let Ok(request) = serde_json::from_str::<Value>(&text) else {
+                        continue;
+                    };
+                    let response =
+                        json!({ "jsonrpc": "2.0", "id": request["id"], "result": result });
+                    if ws_stream
+                        .send(Message::Text(response.to_string()))
+                        .await
+                        .is_err()
+                    {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+    Url::parse(&format!("ws://{addr}")).expect("WebSocket URL")
+}
@@ -0,0 +1,168 @@
+use std::{
+    collections::BTreeMap,
+    fs::OpenOptions,
+    io::{BufRead, BufReader, Write},
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::overrides::ChangeOrigin;
+
+#[derive(Error, Debug)]
+pub enum HistoryError {
+    #[error("History file error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Malformed history record on line {line}: {source}")]
+    Malformed {
+        line: usize,
+        source: serde_json::Error,
+    },
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+// A single entry of the history file, one JSON object per line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum HistoryRecord {
+    Decision(DecisionRecord),
+    Transaction(TransactionRecord),
+    Annotation(Annotation),
+    OutsideChange(OutsideChangeRecord),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecisionRecord {
+    pub timestamp: u64,
+    pub block_number: Option<u64>,
+    #[serde(default)]
+    pub component: String,
+    pub network_gas_price: u128,
+    pub contract_gas_price: u128,
+    pub should_update: bool,
+    pub new_gas_price: Option<u128>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionRecord {
+    pub timestamp: u64,
+    pub block_number: Option<u64>,
+    pub tx_hash: String,
+    // New value per component written by the transaction
+    pub prices: BTreeMap<String, u128>,
+}
+
+// A contract value written by someone other than this updater
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutsideChangeRecord {
+    pub timestamp: u64,
+    pub block_number: Option<u64>,
+    pub component: String,
+    pub previous: u128,
+    pub current: u128,
+    pub origin: ChangeOrigin,
+    pub tx_hash: Option<String>,
+    pub sender: Option<String>,
+}
+
+// Free-text operator note attached to an update, a block or a time range
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Annotation {
+    pub timestamp: u64,
+    pub text: String,
+    pub target: AnnotationTarget,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnnotationTarget {
+    Transaction { tx_hash: String },
+    Block { block_number: u64 },
+    TimeRange { from: u64, to: u64 },
+}
+
+impl AnnotationTarget {
+    // Whether the annotation applies to the given history record
+    pub fn matches(&self, record: &HistoryRecord) -> bool {
+        match (self, record) {
+            (AnnotationTarget::Transaction { tx_hash }, HistoryRecord::Transaction(tx)) => {
+                tx.tx_hash.eq_ignore_ascii_case(tx_hash)
+            }
+            (AnnotationTarget::Block { block_number }, HistoryRecord::Decision(decision)) => {
+                decision.block_number == Some(*block_number)
+            }
+            (AnnotationTarget::Block { block_number }, HistoryRecord::Transaction(tx)) => {
+                tx.block_number == Some(*block_number)
+            }
+            (AnnotationTarget::TimeRange { from, to }, HistoryRecord::Decision(decision)) => {
+                (*from..=*to).contains(&decision.timestamp)
+            }
+            (AnnotationTarget::TimeRange { from, to }, HistoryRecord::Transaction(tx)) => {
+                (*from..=*to).contains(&tx.timestamp)
+            }
+            (AnnotationTarget::Block { block_number }, HistoryRecord::OutsideChange(change)) => {
+                change.block_number == Some(*block_number)
+            }
+            (AnnotationTarget::TimeRange { from, to }, HistoryRecord::OutsideChange(change)) => {
+                (*from..=*to).contains(&change.timestamp)
+            }
+            _ => false,
+        }
+    }
+}
+
+// Append-only JSONL store for decisions, transactions, outside changes and annotations
+#[derive(Debug, Clone)]
+pub struct HistoryStore {
+    path: PathBuf,
+}
+
+impl HistoryStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn append(&self, record: &HistoryRecord) -> Result<(), HistoryError> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn load(&self) -> Result<Vec<HistoryRecord>, HistoryError> {
+        let file = match std::fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut records = Vec::new();
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record = serde_json::from_str(&line).map_err(|source| HistoryError::Malformed {
+                line: index + 1,
+                source,
+            })?;
+            records.push(record);
+        }
+        Ok(records)
+    }
+}
+
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,39 @@
+use std::time::Instant;
+
+// Times a head from its notification frame to the gateway accepting the update it led to.
+// Each phase is recorded as the time since the previous one ended:
+// - `parse`: the frame decoded into a head
+// - `queue`: waiting for the updater to take the head
+// - `price_read`: balance and pending transaction checks, block and contract price reads
+// - `decision`: the threshold policy of every component
+// - `prepare`: re-reading the targets, the nonce read and the simulation
+// - `send`: fee estimate, signing and the gateway accepting the transaction
+#[derive(Debug, Clone)]
+pub struct HeadTimer {
+    received: Instant,
+    last: Instant,
+}
+
+impl HeadTimer {
+    pub fn start() -> Self {
+        let now = Instant::now();
+        Self {
+            received: now,
+            last: now,
+        }
+    }
+
+    pub fn lap(&mut self, phase: &'static str) {
+        let now = Instant::now();
+        metrics::histogram!("pp_fee_updater_head_phase_seconds", "phase" => phase)
+            .record(now.duration_since(self.last).as_secs_f64());
+        self.last = now;
+    }
+
+    // The update of the head was accepted by the gateway
+    pub fn broadcast(mut self) {
+        self.lap("send");
+        metrics::histogram!("pp_fee_updater_head_to_broadcast_seconds")
+            .record(self.received.elapsed().as_secs_f64());
+    }
+}
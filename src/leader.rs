@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use redis::{aio::MultiplexedConnection, Script};
+use tokio::sync::watch;
+use tracing::{error, info, warn};
+use url::Url;
+
+use crate::history::unix_timestamp;
+
+// Extends the lock only while this instance still holds it
+const RENEW_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("PEXPIRE", KEYS[1], ARGV[2])
+end
+return 0
+"#;
+
+const RELEASE_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("DEL", KEYS[1])
+end
+return 0
+"#;
+
+// Leader election through a lock key in Redis: the replica holding the key sends
+// transactions, the others only observe. The holder renews the key every third of its
+// TTL; when it dies, the key expires and another replica takes over.
+pub struct LeaderLock {
+    connection: MultiplexedConnection,
+    key: String,
+    id: String,
+    ttl: Duration,
+}
+
+impl LeaderLock {
+    pub async fn connect(url: &Url, key: String, ttl: Duration) -> anyhow::Result<Self> {
+        let client = redis::Client::open(url.as_str())?;
+        let connection = client.get_multiplexed_async_connection().await?;
+        let id = format!(
+            "{}-{}-{}",
+            std::env::var("HOSTNAME").unwrap_or_else(|_| "pp-fee-updater".to_string()),
+            std::process::id(),
+            unix_timestamp()
+        );
+        Ok(Self {
+            connection,
+            key,
+            id,
+            ttl,
+        })
+    }
+
+    // Takes the lock when free, or extends it when already ours
+    async fn acquire(&mut self) -> redis::RedisResult<bool> {
+        let ttl_ms = self.ttl.as_millis() as u64;
+        let acquired: Option<String> = redis::cmd("SET")
+            .arg(&self.key)
+            .arg(&self.id)
+            .arg("NX")
+            .arg("PX")
+            .arg(ttl_ms)
+            .query_async(&mut self.connection)
+            .await?;
+        if acquired.is_some() {
+            return Ok(true);
+        }
+        let renewed: i64 = Script::new(RENEW_SCRIPT)
+            .key(&self.key)
+            .arg(&self.id)
+            .arg(ttl_ms)
+            .invoke_async(&mut self.connection)
+            .await?;
+        Ok(renewed == 1)
+    }
+
+    async fn release(&mut self) {
+        let released: redis::RedisResult<i64> = Script::new(RELEASE_SCRIPT)
+            .key(&self.key)
+            .arg(&self.id)
+            .invoke_async(&mut self.connection)
+            .await;
+        if let Err(e) = released {
+            warn!("Failed to release the leader lock: {}", e);
+        }
+    }
+
+    // Runs the election in the background; the receiver tells whether this instance
+    // currently leads. Redis errors count as lost leadership, since another replica may
+    // take over once the key expires.
+    pub async fn start(mut self) -> watch::Receiver<bool> {
+        let leading = self.acquire().await.unwrap_or_else(|e| {
+            error!("Leader lock check failed: {}", e);
+            false
+        });
+        announce(leading);
+        let (sender, receiver) = watch::channel(leading);
+        let mut interval = tokio::time::interval(self.ttl / 3);
+        interval.tick().await;
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    // Hand over right away on shutdown instead of waiting for the TTL
+                    _ = sender.closed() => {
+                        if *sender.borrow() {
+                            self.release().await;
+                        }
+                        return;
+                    }
+                }
+                let leading = self.acquire().await.unwrap_or_else(|e| {
+                    error!("Leader lock check failed: {}", e);
+                    false
+                });
+                if leading != *sender.borrow() {
+                    announce(leading);
+                    sender.send_replace(leading);
+                }
+            }
+        });
+        receiver
+    }
+}
+
+fn announce(leading: bool) {
+    if leading {
+        info!("👑 Holding the leader lock, sending updates");
+    } else {
+        warn!("🪑 Another replica holds the leader lock, standing by");
+    }
+}
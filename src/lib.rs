@@ -0,0 +1,63 @@
+pub mod admin;
+pub mod alerts;
+pub mod audit;
+pub mod backtest;
+pub mod balance;
+pub mod breaches;
+pub mod calldata;
+pub mod candidate;
+pub mod checkpoint;
+pub mod components;
+pub mod config;
+pub mod contracts;
+pub mod control;
+pub mod cooldown;
+pub mod crash;
+pub mod daemon;
+pub mod dashboard;
+pub mod devnet;
+pub mod effective;
+pub mod endpoints;
+pub mod envfile;
+pub mod errors;
+pub mod ethereum;
+pub mod events;
+pub mod export;
+pub mod fees;
+pub mod history;
+pub mod latency;
+pub mod leader;
+pub mod listener;
+pub mod live;
+pub mod logging;
+pub mod metadata;
+pub mod mqtt;
+pub mod network;
+pub mod oracle;
+pub mod overrides;
+pub mod percentile;
+pub mod preflight;
+pub mod queue;
+pub mod quorum;
+pub mod ratelimit;
+pub mod retry;
+pub mod scaffold;
+pub mod socket;
+pub mod sources;
+pub mod spec;
+pub mod spikes;
+pub mod staleness;
+pub mod supervisor;
+pub mod systemd;
+pub mod telemetry;
+pub mod tokens;
+pub mod transport;
+pub mod trend;
+pub mod updater;
+pub mod upgrades;
+pub mod web;
+pub mod websocket;
+pub mod wsrpc;
+
+#[cfg(test)]
+mod fixtures;
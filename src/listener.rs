@@ -0,0 +1,448 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use futures_util::{SinkExt, StreamExt};
+use starknet_types_core::felt::Felt;
+use tokio::{sync::mpsc, task::JoinHandle};
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{error, info, warn};
+use url::Url;
+
+use crate::endpoints::{EndpointPool, HEALTH_PROBE_INTERVAL};
+use crate::errors::ErrorClass;
+use crate::latency::HeadTimer;
+use crate::logging::BLOCKS;
+use crate::metadata::ChainMetadata;
+use crate::network::{self, Network};
+use crate::preflight::WsStream;
+use crate::retry::BackoffPolicy;
+use crate::spec::{self, Notification};
+use crate::supervisor;
+use crate::transport::TransportOptions;
+use crate::updater::{BlockPrices, TransactionStatus};
+use crate::websocket::{self, Heartbeat, Subscriptions, NEW_HEADS_STALL_TIMEOUT};
+
+// Heads and statuses waiting for the updater; a full queue holds the reader back
+const EVENT_BUFFER: usize = 256;
+
+#[derive(Debug, Clone)]
+pub struct Head {
+    pub block_number: Option<u64>,
+    // Absent when the node's headers lack some gas prices
+    pub prices: Option<BlockPrices>,
+    pub timer: HeadTimer,
+}
+
+// The pool emitted its price update event
+#[derive(Debug, Clone)]
+pub struct PriceEvent {
+    pub pool: Felt,
+    pub tx_hash: Felt,
+    pub block_number: Option<u64>,
+}
+
+impl PriceEvent {
+    fn from_notification(event: &serde_json::Value) -> Option<Self> {
+        let felt = |key: &str| {
+            event
+                .get(key)
+                .and_then(serde_json::Value::as_str)
+                .and_then(|value| Felt::from_hex(value).ok())
+        };
+        Some(Self {
+            pool: felt("from_address")?,
+            tx_hash: felt("transaction_hash")?,
+            block_number: event
+                .get("block_number")
+                .and_then(serde_json::Value::as_u64),
+        })
+    }
+}
+
+// What the listener hands to the updater task
+#[derive(Debug)]
+pub enum NodeEvent {
+    NewHead(Head),
+    TransactionStatus(Felt, TransactionStatus),
+    // The new heads subscription was confirmed on this endpoint
+    Subscribed(Url),
+    Reconnecting,
+    PriceChanged(PriceEvent),
+}
+
+enum ListenerCommand {
+    Watch(Felt),
+    Shutdown,
+}
+
+// Owns the subscription WebSocket: reads frames, keeps the connection alive and reconnects,
+// so a slow fee update never delays reading from the node
+pub struct Listener {
+    pub urls: Vec<Url>,
+    pub transport: TransportOptions,
+    pub network: Option<Network>,
+    pub rpc: EndpointPool,
+    pub chain_metadata: ChainMetadata,
+    pub ping_interval: Duration,
+    pub stall_timeout: Duration,
+    // Key of the pools' price update event, when watching for outside changes
+    pub price_event: Option<Felt>,
+    pub pools: Vec<Felt>,
+}
+
+pub struct ListenerHandle {
+    commands: mpsc::UnboundedSender<ListenerCommand>,
+    task: JoinHandle<anyhow::Result<()>>,
+}
+
+impl ListenerHandle {
+    // Subscribes to the status of sent transactions, now and after every reconnect
+    pub fn watch(&self, tx_hashes: impl IntoIterator<Item = Felt>) {
+        for tx_hash in tx_hashes {
+            let _ = self.commands.send(ListenerCommand::Watch(tx_hash));
+        }
+    }
+
+    // Resolves when the listener stops on its own, which only an error makes it do
+    pub async fn finished(&mut self) -> anyhow::Result<()> {
+        match (&mut self.task).await {
+            Ok(Ok(())) => Err(anyhow::anyhow!("the WebSocket listener stopped")),
+            Ok(Err(e)) => Err(e),
+            Err(e) => Err(anyhow::anyhow!("the WebSocket listener panicked: {e}")),
+        }
+    }
+
+    // Closes the subscriptions and the connection
+    pub async fn shutdown(self) -> anyhow::Result<()> {
+        let _ = self.commands.send(ListenerCommand::Shutdown);
+        self.task
+            .await
+            .map_err(|e| anyhow::anyhow!("the WebSocket listener panicked: {e}"))?
+    }
+}
+
+impl Listener {
+    // Runs under the supervisor, so a panic or a lost connection starts a fresh run that
+    // keeps the queues and the watched transactions
+    pub fn spawn(
+        self,
+        ws_stream: WsStream,
+        ws_index: usize,
+        watched: impl IntoIterator<Item = Felt>,
+        restarts: BackoffPolicy,
+    ) -> (ListenerHandle, mpsc::Receiver<NodeEvent>) {
+        let (commands, command_receiver) = mpsc::unbounded_channel();
+        let (events, event_receiver) = mpsc::channel(EVENT_BUFFER);
+        let state = Arc::new(ListenerState {
+            chain_metadata: tokio::sync::Mutex::new(self.chain_metadata.clone()),
+            listener: self,
+            connection: Mutex::new(Some((ws_stream, ws_index))),
+            watched: Mutex::new(watched.into_iter().collect()),
+            commands: tokio::sync::Mutex::new(command_receiver),
+            events,
+        });
+        let task = supervisor::supervise("WebSocket listener", restarts, move || {
+            let state = state.clone();
+            async move { state.run().await }
+        });
+        (ListenerHandle { commands, task }, event_receiver)
+    }
+}
+
+// What outlives a single run of the listener
+struct ListenerState {
+    listener: Listener,
+    // Re-read on a reconnect when the RPC endpoint in use changed meanwhile
+    chain_metadata: tokio::sync::Mutex<ChainMetadata>,
+    // The connection made at startup, taken by the first run
+    connection: Mutex<Option<(WsStream, usize)>>,
+    watched: Mutex<HashSet<Felt>>,
+    commands: tokio::sync::Mutex<mpsc::UnboundedReceiver<ListenerCommand>>,
+    events: mpsc::Sender<NodeEvent>,
+}
+
+impl ListenerState {
+    fn watched(&self) -> MutexGuard<'_, HashSet<Felt>> {
+        self.watched.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Runs after every reconnect. Another RPC endpoint may have taken over meanwhile; its
+    // chain is checked, as transactions are signed for the chain id read at startup.
+    async fn verify_connection(&self, ws_stream: &mut WsStream) -> anyhow::Result<()> {
+        let listener = &self.listener;
+        let mut metadata = self.chain_metadata.lock().await;
+        let chain_id = metadata.chain_id;
+        if metadata.refresh_on_reconnect(&listener.rpc).await? && metadata.chain_id != chain_id {
+            return Err(anyhow::anyhow!(
+                "The RPC endpoint now in use serves chain id {:#x}, but transactions are signed for {:#x}",
+                metadata.chain_id,
+                chain_id
+            )
+            .context(ErrorClass::Configuration));
+        }
+        if let Some(expected_network) = listener.network {
+            network::verify_network(expected_network, &listener.rpc, ws_stream).await?;
+        }
+        Ok(())
+    }
+
+    async fn run(&self) -> anyhow::Result<()> {
+        let listener = &self.listener;
+        let events = &self.events;
+        let mut commands = self.commands.lock().await;
+        let connection = self
+            .connection
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        let (mut ws_stream, mut ws_index) = match connection {
+            Some(connection) => connection,
+            // A restarted run connects anew, starting with the preferred URL
+            None => {
+                if events.send(NodeEvent::Reconnecting).await.is_err() {
+                    return Ok(());
+                }
+                let (mut ws_stream, ws_index) =
+                    websocket::connect(&listener.urls, &listener.transport, 0).await?;
+                self.verify_connection(&mut ws_stream).await?;
+                (ws_stream, ws_index)
+            }
+        };
+        loop {
+            // Nodes behind different URLs may serve different spec versions
+            let spec = spec::negotiate(&mut ws_stream).await;
+            let (mut write, mut read) = ws_stream.split();
+            // Subscriptions do not survive a reconnect, so every connection starts afresh
+            let mut subscriptions = Subscriptions::new(spec);
+
+            info!("Subscribing to new block notifications...");
+            write.send(subscriptions.subscribe_new_heads()).await?;
+
+            if let Some(price_event) = listener.price_event {
+                for pool in &listener.pools {
+                    match subscriptions.subscribe_events(*pool, price_event) {
+                        Some(subscribe) => write.send(subscribe).await?,
+                        None => {
+                            warn!(
+                                "⚠️ The node's spec has no event subscription, outside price changes are only noticed by the checks"
+                            );
+                            break;
+                        }
+                    }
+                }
+            }
+
+            // Renew the subscriptions of unresolved transactions
+            let watched = self.watched().iter().copied().collect::<Vec<_>>();
+            for tx_hash in watched {
+                if let Some(subscribe) = subscriptions.subscribe_transaction_status(tx_hash) {
+                    write.send(subscribe).await?;
+                }
+            }
+
+            // Listen for new blocks, failing back to a preferred WebSocket when it recovers
+            let mut failback = tokio::time::interval_at(
+                tokio::time::Instant::now() + HEALTH_PROBE_INTERVAL,
+                HEALTH_PROBE_INTERVAL,
+            );
+            let mut stall_check = tokio::time::interval(NEW_HEADS_STALL_TIMEOUT / 4);
+            let mut heartbeat = Heartbeat::new(listener.stall_timeout);
+            let mut ping = tokio::time::interval_at(
+                tokio::time::Instant::now() + listener.ping_interval,
+                listener.ping_interval,
+            );
+            let preferred = loop {
+                let msg = tokio::select! {
+                    // Queued frames are read before the heartbeat is judged
+                    biased;
+                    msg = read.next() => msg,
+                    Some(command) = commands.recv() => {
+                        match command {
+                            ListenerCommand::Watch(tx_hash) => {
+                                self.watched().insert(tx_hash);
+                                if let Some(subscribe) =
+                                    subscriptions.subscribe_transaction_status(tx_hash)
+                                {
+                                    write.send(subscribe).await?;
+                                }
+                            }
+                            ListenerCommand::Shutdown => {
+                                info!("🛑 Closing WebSocket subscriptions");
+                                for message in subscriptions.unsubscribe_all() {
+                                    write.send(message).await?;
+                                }
+                                let _ = write.send(Message::Close(None)).await;
+                                return Ok(());
+                            }
+                        }
+                        continue;
+                    }
+                    _ = ping.tick() => {
+                        if heartbeat.is_stalled() {
+                            warn!(
+                                "⚠️ No WebSocket frame for {:?}, assuming the connection is dead",
+                                heartbeat.silence()
+                            );
+                            break None;
+                        }
+                        if let Err(e) = write.send(Message::Ping(Vec::new())).await {
+                            error!("WebSocket ping failed: {}", e);
+                            break None;
+                        }
+                        continue;
+                    }
+                    _ = stall_check.tick() => {
+                        if subscriptions.new_heads_stalled() {
+                            warn!(
+                                "⚠️ No new heads notification for {:?}, renewing the subscription",
+                                NEW_HEADS_STALL_TIMEOUT
+                            );
+                            for message in subscriptions.renew_new_heads() {
+                                write.send(message).await?;
+                            }
+                        }
+                        continue;
+                    }
+                    _ = failback.tick(), if ws_index > 0 => {
+                        let preferred = websocket::connect_preferred(
+                            &listener.urls,
+                            &listener.transport,
+                            ws_index,
+                        )
+                        .await;
+                        match preferred {
+                            Some(preferred) => break Some(preferred),
+                            None => continue,
+                        }
+                    }
+                };
+                let Some(msg) = msg else {
+                    break None;
+                };
+                heartbeat.on_frame();
+                let timer = HeadTimer::start();
+                let text = match msg {
+                    Ok(Message::Text(text)) => text,
+                    Ok(Message::Close(_)) => {
+                        warn!("WebSocket connection closed by server");
+                        break None;
+                    }
+                    Ok(Message::Ping(data)) => {
+                        write.send(Message::Pong(data)).await?;
+                        continue;
+                    }
+                    Ok(_) => continue,
+                    Err(e) => {
+                        error!("WebSocket error: {}", e);
+                        break None;
+                    }
+                };
+                let Ok(json_value) = serde_json::from_str::<serde_json::Value>(&text) else {
+                    continue;
+                };
+
+                if json_value.get("method").is_none() {
+                    let was_live = subscriptions.new_heads_live();
+                    subscriptions.on_response(&json_value);
+                    if !was_live && subscriptions.new_heads_live() {
+                        let url = listener.urls[ws_index].clone();
+                        if events.send(NodeEvent::Subscribed(url)).await.is_err() {
+                            return Ok(());
+                        }
+                    }
+                    continue;
+                }
+                match spec.notification(&json_value) {
+                    Some(Notification::NewHeads {
+                        subscription_id,
+                        header,
+                    }) => {
+                        if !subscriptions.is_current_new_heads(subscription_id) {
+                            continue;
+                        }
+                        if let Some(block_number) = header.get("block_number") {
+                            info!(
+                                target: BLOCKS,
+                                "📦 New Starknet block received: {}",
+                                block_number
+                            );
+                        }
+                        if let Some(block_hash) = header.get("block_hash") {
+                            info!(target: BLOCKS, "   Block hash: {}", block_hash);
+                        }
+                        let mut head = Head {
+                            block_number: header
+                                .get("block_number")
+                                .and_then(serde_json::Value::as_u64),
+                            // Headers without all gas prices leave them to a block fetch
+                            prices: spec
+                                .header_has_prices()
+                                .then(|| BlockPrices::from_header(header))
+                                .flatten(),
+                            timer,
+                        };
+                        head.timer.lap("parse");
+                        // A closed queue means the updater is shutting down
+                        if events.send(NodeEvent::NewHead(head)).await.is_err() {
+                            return Ok(());
+                        }
+                    }
+                    Some(Notification::TransactionStatus {
+                        subscription_id,
+                        result,
+                    }) => {
+                        let Some((tx_hash, status)) = TransactionStatus::from_notification(result)
+                        else {
+                            warn!("Unrecognised transaction status notification: {}", text);
+                            continue;
+                        };
+                        if status.is_final() {
+                            self.watched().remove(&tx_hash);
+                            let unsubscribe =
+                                subscriptions.finish_transaction(tx_hash).or_else(|| {
+                                    subscription_id.map(|id| subscriptions.unsubscribe(id))
+                                });
+                            if let Some(unsubscribe) = unsubscribe {
+                                write.send(unsubscribe).await?;
+                            }
+                        }
+                        let event = NodeEvent::TransactionStatus(tx_hash, status);
+                        if events.send(event).await.is_err() {
+                            return Ok(());
+                        }
+                    }
+                    Some(Notification::Event { event, .. }) => {
+                        let Some(price_event) = PriceEvent::from_notification(event) else {
+                            warn!("Unrecognised event notification: {}", text);
+                            continue;
+                        };
+                        if events
+                            .send(NodeEvent::PriceChanged(price_event))
+                            .await
+                            .is_err()
+                        {
+                            return Ok(());
+                        }
+                    }
+                    None => {}
+                }
+            };
+
+            // Reconnect, starting with the next URL unless failing back
+            (ws_stream, ws_index) = match preferred {
+                Some(preferred) => preferred,
+                None => {
+                    warn!("WebSocket connection terminated, reconnecting");
+                    if events.send(NodeEvent::Reconnecting).await.is_err() {
+                        return Ok(());
+                    }
+                    websocket::connect(&listener.urls, &listener.transport, ws_index + 1).await?
+                }
+            };
+            self.verify_connection(&mut ws_stream).await?;
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use serde::Serialize;
+use starknet_types_core::felt::Felt;
+
+use crate::components::{FeeComponent, PriceSource, ThresholdStrategy};
+use crate::history::{unix_timestamp, DecisionRecord};
+use crate::updater::{FeeDecision, PendingUpdate};
+
+// Prices kept per component for the charts
+const PRICE_HISTORY: usize = 120;
+pub const RECENT_UPDATES: usize = 20;
+pub const DEFAULT_DECISION_HISTORY: usize = 50;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ComponentPrices {
+    pub network: VecDeque<u64>,
+    pub contract: VecDeque<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecentUpdate {
+    pub timestamp: u64,
+    pub block_number: Option<u64>,
+    pub component: String,
+    pub gas_price: String,
+    pub tx_hash: String,
+}
+
+// A decision of a recent check, with the transaction that carried it out if any
+#[derive(Debug, Clone, Serialize)]
+pub struct RecentDecision {
+    #[serde(flatten)]
+    pub decision: DecisionRecord,
+    pub tx_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ComponentSummary {
+    pub name: String,
+    pub source: PriceSource,
+    pub pool: String,
+    pub strategy: ThresholdStrategy,
+}
+
+// A transaction sent and not yet resolved
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingTransaction {
+    pub tx_hash: String,
+    pub sender: String,
+    // New value per component written by the transaction
+    pub prices: BTreeMap<String, String>,
+}
+
+// State of the new heads subscription
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum Subscription {
+    #[default]
+    Connecting,
+    Subscribed {
+        url: String,
+        since: u64,
+    },
+    Reconnecting {
+        since: u64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct LatestPrices {
+    pub network: Option<u64>,
+    pub contract: Option<u64>,
+}
+
+// Machine-readable state for runbooks and external dashboards
+#[derive(Debug, Clone, Serialize)]
+pub struct Status {
+    pub started_at: u64,
+    pub uptime_secs: u64,
+    pub paused: bool,
+    pub block: Option<u64>,
+    pub last_block_at: Option<u64>,
+    pub subscription: Subscription,
+    pub pending: Vec<PendingTransaction>,
+    pub components: Vec<ComponentSummary>,
+    // Latest network and contract price per pool and component
+    pub pools: BTreeMap<String, BTreeMap<String, LatestPrices>>,
+}
+
+// What the dashboards show of the running updater
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LiveState {
+    pub started_at: u64,
+    pub paused: bool,
+    pub block: Option<u64>,
+    pub last_block_at: Option<u64>,
+    pub subscription: Subscription,
+    pub balance: Option<u128>,
+    pub pending: Vec<String>,
+    pub pending_transactions: Vec<PendingTransaction>,
+    pub prices: BTreeMap<String, ComponentPrices>,
+    pub recent_updates: VecDeque<RecentUpdate>,
+    // Newest first
+    pub recent_decisions: VecDeque<RecentDecision>,
+    pub config: Vec<ComponentSummary>,
+}
+
+// Shared handle the daemon feeds and the dashboards read
+#[derive(Debug, Clone)]
+pub struct LiveView {
+    state: Arc<Mutex<LiveState>>,
+    started: Instant,
+    // Pool of the components that name none
+    default_pool: Felt,
+    // Decisions kept in memory
+    decision_history: usize,
+}
+
+impl LiveView {
+    pub fn new(default_pool: Felt, decision_history: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(LiveState {
+                started_at: unix_timestamp(),
+                ..LiveState::default()
+            })),
+            started: Instant::now(),
+            default_pool,
+            decision_history,
+        }
+    }
+
+    pub fn set_config(&self, components: &[FeeComponent]) {
+        self.lock().config = components
+            .iter()
+            .map(|component| ComponentSummary {
+                name: component.name.clone(),
+                source: component.source,
+                pool: format!("{:#x}", component.pool.unwrap_or(self.default_pool)),
+                strategy: component.strategy,
+            })
+            .collect();
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.lock().paused = paused;
+    }
+
+    pub fn on_subscription(&self, subscription: Subscription) {
+        self.lock().subscription = subscription;
+    }
+
+    pub fn on_block(&self, block: Option<u64>) {
+        if let Some(block) = block {
+            let mut state = self.lock();
+            state.block = Some(block);
+            state.last_block_at = Some(unix_timestamp());
+        }
+    }
+
+    pub fn on_balance(&self, balance: u128) {
+        self.lock().balance = Some(balance);
+    }
+
+    pub fn on_decisions(&self, decisions: &[FeeDecision]) {
+        let mut state = self.lock();
+        for decision in decisions {
+            let prices = state.prices.entry(decision.component.clone()).or_default();
+            push_bounded(&mut prices.network, saturate(decision.network_gas_price));
+            push_bounded(&mut prices.contract, saturate(decision.contract_gas_price));
+        }
+    }
+
+    pub fn record_decision(&self, decision: DecisionRecord) {
+        let mut state = self.lock();
+        state.recent_decisions.push_front(RecentDecision {
+            decision,
+            tx_hash: None,
+        });
+        state.recent_decisions.truncate(self.decision_history);
+    }
+
+    pub fn on_sent(&self, block_number: Option<u64>, sent: &[PendingUpdate]) {
+        let mut state = self.lock();
+        for pending in sent {
+            for update in &pending.updates {
+                state.recent_updates.push_front(RecentUpdate {
+                    timestamp: unix_timestamp(),
+                    block_number,
+                    component: update.component.clone(),
+                    gas_price: update.gas_price.to_biguint().to_string(),
+                    tx_hash: format!("{:#x}", pending.tx_hash),
+                });
+                if let Some(recent) = state.recent_decisions.iter_mut().find(|recent| {
+                    recent.tx_hash.is_none()
+                        && recent.decision.should_update
+                        && recent.decision.block_number == block_number
+                        && recent.decision.component == update.component
+                }) {
+                    recent.tx_hash = Some(format!("{:#x}", pending.tx_hash));
+                }
+            }
+        }
+        state.recent_updates.truncate(RECENT_UPDATES);
+    }
+
+    pub fn set_pending(&self, pending: &[PendingUpdate]) {
+        let mut state = self.lock();
+        state.pending = pending
+            .iter()
+            .map(|pending| format!("{:#x}", pending.tx_hash))
+            .collect();
+        state.pending_transactions = pending
+            .iter()
+            .map(|pending| PendingTransaction {
+                tx_hash: format!("{:#x}", pending.tx_hash),
+                sender: format!("{:#x}", pending.sender),
+                prices: pending
+                    .updates
+                    .iter()
+                    .map(|update| {
+                        (
+                            update.component.clone(),
+                            update.gas_price.to_biguint().to_string(),
+                        )
+                    })
+                    .collect(),
+            })
+            .collect();
+    }
+
+    pub fn snapshot(&self) -> LiveState {
+        self.lock().clone()
+    }
+
+    pub fn status(&self) -> Status {
+        let state = self.snapshot();
+        let mut pools: BTreeMap<String, BTreeMap<String, LatestPrices>> = BTreeMap::new();
+        for component in &state.config {
+            let prices = state.prices.get(&component.name);
+            pools.entry(component.pool.clone()).or_default().insert(
+                component.name.clone(),
+                LatestPrices {
+                    network: prices.and_then(|prices| prices.network.back().copied()),
+                    contract: prices.and_then(|prices| prices.contract.back().copied()),
+                },
+            );
+        }
+        Status {
+            started_at: state.started_at,
+            uptime_secs: self.started.elapsed().as_secs(),
+            paused: state.paused,
+            block: state.block,
+            last_block_at: state.last_block_at,
+            subscription: state.subscription,
+            pending: state.pending_transactions,
+            components: state.config,
+            pools,
+        }
+    }
+
+    pub fn lock(&self) -> std::sync::MutexGuard<'_, LiveState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn push_bounded(values: &mut VecDeque<u64>, value: u64) {
+    if values.len() == PRICE_HISTORY {
+        values.pop_front();
+    }
+    values.push_back(value);
+}
+
+fn saturate(value: u128) -> u64 {
+    value.try_into().unwrap_or(u64::MAX)
+}
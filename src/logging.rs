@@ -0,0 +1,28 @@
+use tracing_subscriber::{filter::ParseError, EnvFilter};
+
+// Target of the routine per-block logs (new heads, prices read, "fee is up to date"), so
+// `--log-filter pp_fee_updater::blocks=warn` quiets them and keeps decisions and
+// transactions
+pub const BLOCKS: &str = "pp_fee_updater::blocks";
+
+const LEVELS: [&str; 5] = ["error", "warn", "info", "debug", "trace"];
+const DEFAULT_LEVEL: usize = 2;
+
+// `RUST_LOG` first, then the level set by `-v`/`-q` and finally the `--log-filter`
+// directives, each overriding the one before for the same target. `-v` only raises the
+// updater's own logs, `-q` lowers the dependencies' too.
+pub fn filter(verbose: u8, quiet: u8, log_filter: Option<&str>) -> Result<EnvFilter, ParseError> {
+    let level = (DEFAULT_LEVEL + usize::from(verbose))
+        .saturating_sub(usize::from(quiet))
+        .min(LEVELS.len() - 1);
+    let mut filter = EnvFilter::from_default_env()
+        .add_directive(format!("pp_fee_updater={}", LEVELS[level]).parse()?)
+        .add_directive(LEVELS[level.min(DEFAULT_LEVEL)].parse()?);
+    for directive in log_filter.into_iter().flat_map(|filter| filter.split(',')) {
+        let directive = directive.trim();
+        if !directive.is_empty() {
+            filter = filter.add_directive(directive.parse()?);
+        }
+    }
+    Ok(filter)
+}
@@ -1,29 +1,394 @@
-use clap::Parser;
-use futures_util::{SinkExt, StreamExt};
-use serde_json::json;
+use std::{
+    net::SocketAddr,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Duration,
+};
+
+use anyhow::Context;
+use clap::{ArgAction, ArgGroup, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
+use starknet::signers::SigningKey;
 use starknet_types_core::felt::Felt;
-use tokio_tungstenite::{connect_async, tungstenite::Message};
+use tokio::signal::unix::{signal, SignalKind};
 use tracing::{error, info, warn};
 use url::Url;
 
-use crate::updater::{check_fee_update, update_fee, PendingUpdate};
-
-pub mod updater;
+use pp_fee_updater::alerts::Alerter;
+use pp_fee_updater::audit::AuditLog;
+use pp_fee_updater::balance::{BalanceMonitor, STRK_TOKEN_ADDRESS};
+use pp_fee_updater::breaches::BreachConfirmations;
+use pp_fee_updater::calldata::PriceEncoding;
+use pp_fee_updater::candidate::{matching_components, CandidateRun};
+use pp_fee_updater::checkpoint::BlockCheckpoint;
+use pp_fee_updater::components::{
+    pools, selector, ComponentConfig, Denomination, FeeComponent, PriceSource, ThresholdStrategy,
+};
+use pp_fee_updater::config::FileConfig;
+use pp_fee_updater::cooldown::Cooldown;
+use pp_fee_updater::crash::CrashReporter;
+use pp_fee_updater::daemon::{CycleOptions, Daemon};
+use pp_fee_updater::dashboard::Dashboard;
+use pp_fee_updater::devnet::{Devnet, DevnetPrices};
+use pp_fee_updater::effective::{ConfigFormat, EffectiveConfig};
+use pp_fee_updater::endpoints::{EndpointHeader, EndpointPool, HEALTH_PROBE_INTERVAL};
+use pp_fee_updater::errors::ErrorClass;
+use pp_fee_updater::ethereum::EthGasOracle;
+use pp_fee_updater::events::EventStream;
+use pp_fee_updater::export::ExportFormat;
+use pp_fee_updater::fees::{
+    Bound, ResourceBounds, DEFAULT_AMOUNT_MULTIPLIER, DEFAULT_PRICE_MULTIPLIER,
+};
+use pp_fee_updater::history::{
+    unix_timestamp, Annotation, AnnotationTarget, HistoryRecord, HistoryStore,
+};
+use pp_fee_updater::leader::LeaderLock;
+use pp_fee_updater::listener::{Head, Listener, NodeEvent, PriceEvent};
+use pp_fee_updater::live::{LiveView, Subscription, DEFAULT_DECISION_HISTORY};
+use pp_fee_updater::metadata::ChainMetadata;
+use pp_fee_updater::mqtt::MqttPublisher;
+use pp_fee_updater::network::{Network, Preset};
+use pp_fee_updater::overrides::OverridePolicy;
+use pp_fee_updater::percentile::PercentileTarget;
+use pp_fee_updater::preflight::{run_preflight, PreflightMode};
+use pp_fee_updater::quorum::Quorum;
+use pp_fee_updater::ratelimit::{parse_rate, RateLimit};
+use pp_fee_updater::retry::BackoffPolicy;
+use pp_fee_updater::sources::{Combinator, CompositeSource};
+use pp_fee_updater::spikes::SpikeFilter;
+use pp_fee_updater::staleness::MaxStaleness;
+use pp_fee_updater::supervisor::{PanicGuard, DEFAULT_MAX_RESTARTS};
+use pp_fee_updater::systemd::Notifier;
+use pp_fee_updater::transport::{
+    parse_proxy, RetryPolicy, RpcTransport, TlsOptions, TransportOptions,
+};
+use pp_fee_updater::trend::TrendPricing;
+use pp_fee_updater::updater::{
+    AccountEncoding, DivergenceGuard, PriceBlock, ReplayMode, SenderBalance, SenderKey, Updater,
+    UpdaterError, DEFAULT_CHECK_CONCURRENCY, DEFAULT_PENDING_EXPIRY_BLOCKS,
+};
+use pp_fee_updater::upgrades::UpgradeWatcher;
+use pp_fee_updater::{
+    admin, audit, backtest, control, devnet, envfile, export, logging, network, preflight,
+    scaffold, socket, supervisor, telemetry, web, websocket,
+};
 
 #[derive(Parser, Debug)]
 #[command(name = "pp-fee-updater")]
 #[command(about = "A Starknet WebSocket block listener")]
+#[command(args_conflicts_with_subcommands = true)]
+struct Cli {
+    /// Load variables from this file before parsing; `.env` is read when present
+    #[arg(long, env = "ENV_FILE", global = true)]
+    env_file: Option<PathBuf>,
+    /// More detailed logs of the updater; `-vv` for trace
+    #[arg(long, short = 'v', action = ArgAction::Count, global = true)]
+    verbose: u8,
+    /// Fewer logs; `-q` for warnings and errors only, `-qq` for errors
+    #[arg(long, short = 'q', action = ArgAction::Count, global = true, conflicts_with = "verbose")]
+    quiet: u8,
+    /// Comma-separated log directives applied over `RUST_LOG` and the verbosity, e.g.
+    /// `pp_fee_updater::blocks=warn` to drop the per-block logs
+    #[arg(long, env = "LOG_FILTER", global = true)]
+    log_filter: Option<String>,
+    /// Threads of the async runtime; one per CPU core by default
+    #[arg(long, env = "WORKER_THREADS", global = true)]
+    worker_threads: Option<NonZeroUsize>,
+    /// Run everything on the main thread, for the smallest footprint
+    #[arg(
+        long,
+        env = "SINGLE_THREAD",
+        global = true,
+        conflicts_with = "worker_threads"
+    )]
+    single_thread: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    args: Option<Args>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Attach an operator annotation to the decision history
+    Annotate(AnnotateArgs),
+    /// Replay the gas prices of a historical block range through the configured policy
+    Backtest(BacktestArgs),
+    /// Dump the decision history as CSV or JSONL for offline analysis
+    Export(ExportArgs),
+    /// Check the seals of a signed audit file against the audit public key
+    VerifyAudit(VerifyAuditArgs),
+    /// Send a command to a running updater over its control socket
+    Ctl(CtlArgs),
+    /// Print the configuration the daemon would run with, secrets redacted
+    PrintConfig(PrintConfigArgs),
+    /// Print a shell completion script
+    Completions {
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+    /// Write a commented starter .env listing every setting with its default
+    Init(InitArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct CtlArgs {
+    #[arg(long, env = "CONTROL_SOCKET")]
+    socket: PathBuf,
+    #[command(subcommand)]
+    command: CtlCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum CtlCommand {
+    /// Show the paused state, last block, pending transactions, strategies and recent
+    /// decisions
+    Status,
+    /// Keep checking blocks but send no updates
+    Pause,
+    /// Send updates again
+    Resume,
+    /// Run a fee check now
+    Check,
+    /// Write a price to a component right away, bypassing the policy and the pause
+    SetFee { component: String, price: u128 },
+    /// Let the next update of a component through the divergence guard
+    Confirm { component: String },
+}
+
+#[derive(clap::Args, Debug)]
+#[command(group(ArgGroup::new("target").required(true).args(["tx_hash", "block", "from"])))]
+struct AnnotateArgs {
+    #[arg(long, env = "HISTORY_FILE")]
+    history_file: PathBuf,
+    /// Annotate the update sent in this transaction
+    #[arg(long)]
+    tx_hash: Option<Felt>,
+    /// Annotate the decision taken for this block
+    #[arg(long)]
+    block: Option<u64>,
+    /// Start of the annotated time range (unix seconds)
+    #[arg(long, requires = "to")]
+    from: Option<u64>,
+    /// End of the annotated time range (unix seconds)
+    #[arg(long, requires = "from")]
+    to: Option<u64>,
+    /// Annotation text, e.g. "manual override during incident #123"
+    text: String,
+}
+
+#[derive(clap::Args, Debug)]
+struct VerifyAuditArgs {
+    #[arg(long, env = "AUDIT_FILE")]
+    audit_file: PathBuf,
+    /// Public key of the audit signing key, as logged at startup
+    #[arg(long, env = "AUDIT_PUBLIC_KEY")]
+    public_key: Felt,
+}
+
+#[derive(clap::Args, Debug)]
+struct InitArgs {
+    #[arg(long, short = 'o', default_value = ".env")]
+    output: PathBuf,
+    /// Overwrite the file if it exists
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct PrintConfigArgs {
+    #[arg(long, value_enum, default_value_t = ConfigFormat::Toml)]
+    format: ConfigFormat,
+    #[command(flatten)]
+    args: Args,
+}
+
+#[derive(clap::Args, Debug)]
+struct ExportArgs {
+    #[arg(long, env = "HISTORY_FILE")]
+    history_file: PathBuf,
+    #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+    format: ExportFormat,
+    /// Only records at or after this time (unix seconds)
+    #[arg(long)]
+    since: Option<u64>,
+    /// Only records at or before this time (unix seconds)
+    #[arg(long)]
+    until: Option<u64>,
+    /// Written to stdout when not given
+    #[arg(long, short = 'o')]
+    output: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+struct BacktestArgs {
+    /// First block of the replayed range
+    #[arg(long)]
+    from: u64,
+    /// Last block of the replayed range
+    #[arg(long)]
+    to: u64,
+    #[arg(long, short = 'u', env = "API_URL", value_delimiter = ',', required = true)]
+    api_url: Vec<Url>,
+    #[arg(long, env = "DENOMINATION", value_enum, default_value_t = Denomination::Fri)]
+    denomination: Denomination,
+    #[arg(long, env = "UPWARD_THRESHOLD")]
+    upward_threshold: u128,
+    #[arg(long, env = "DOWNWARD_THRESHOLD")]
+    downward_threshold: u128,
+    #[arg(long, env = "UPWARD_BUFFER")]
+    upward_buffer: u128,
+    #[arg(long, env = "DOWNWARD_BUFFER")]
+    downward_buffer: u128,
+    #[arg(long, env = "MAX_CHANGE_PERCENT")]
+    max_change_percent: Option<u128>,
+    #[arg(long, env = "MIN_GAS_PRICE")]
+    min_gas_price: Option<u128>,
+    #[arg(long, env = "MAX_GAS_PRICE")]
+    max_gas_price: Option<u128>,
+    #[arg(long, env = "CONFIG_FILE")]
+    config: Option<PathBuf>,
+    /// Contract price at the start of the range, defaults to the first block's network price
+    #[arg(long)]
+    initial_price: Option<u128>,
+    /// Cost of one update transaction, multiplied by the simulated transaction count
+    #[arg(long)]
+    tx_cost: Option<u128>,
+    #[arg(long, default_value = "backtest-report.json")]
+    report: PathBuf,
+    /// Push the run's metrics to this Prometheus Pushgateway when it ends
+    #[arg(long, env = "PUSHGATEWAY_URL")]
+    pushgateway_url: Option<Url>,
+    /// Job the metrics are pushed under
+    #[arg(long, env = "PUSHGATEWAY_JOB", default_value = "pp-fee-updater-backtest")]
+    pushgateway_job: String,
+}
+
+#[derive(clap::Args, Debug)]
 struct Args {
-    #[arg(long, short = 'w', env = "WS_URL")]
-    websocket_url: Url,
-    #[arg(long, short = 'u', env = "API_URL")]
-    api_url: Url,
-    #[arg(long, short = 'c', env = "PP_ADDRESS")]
-    privacy_pool_address: Felt,
-    #[arg(long, short = 'o', env = "OWNER_ADDRESS")]
-    owner_address: Felt,
-    #[arg(long, short = 'p', env = "OWNER_PRIVATE_KEY")]
-    owner_private_key: Felt,
+    /// Fill in the public endpoints and chain of a well-known network
+    #[arg(long, env = "PRESET", value_enum)]
+    preset: Option<Preset>,
+    /// Run against a local starknet-devnet: its endpoints, its first predeployed account
+    /// as the owner and a freshly deployed mock pool
+    #[arg(long, env = "DEVNET", conflicts_with = "preset")]
+    devnet: bool,
+    /// Where the devnet is served
+    #[arg(long, env = "DEVNET_URL", default_value = "http://127.0.0.1:5050")]
+    devnet_url: Url,
+    /// Scarb project of the mock pool deployed in devnet mode, built with `scarb build`
+    #[arg(long, env = "DEVNET_CONTRACT_DIR", default_value = devnet::MOCK_POOL_DIR)]
+    devnet_contract_dir: PathBuf,
+    #[arg(long, short = 'w', env = "WS_URL", value_delimiter = ',')]
+    websocket_url: Vec<Url>,
+    /// JSON-RPC endpoints; ws:// and wss:// URLs are called over a WebSocket
+    #[arg(long, short = 'u', env = "API_URL", value_delimiter = ',')]
+    api_url: Vec<Url>,
+    /// Which endpoints serve JSON-RPC calls
+    #[arg(long, env = "RPC_TRANSPORT", value_enum, default_value_t = RpcTransport::Http)]
+    rpc_transport: RpcTransport,
+    /// How often the WebSocket is pinged, in seconds
+    #[arg(long, env = "WS_PING_INTERVAL_SECS", default_value_t = 15, value_parser = clap::value_parser!(u64).range(1..))]
+    ws_ping_interval_secs: u64,
+    /// Reconnect when no frame, pong included, arrived for this many seconds
+    #[arg(long, env = "WS_STALL_TIMEOUT_SECS", default_value_t = 45, value_parser = clap::value_parser!(u64).range(1..))]
+    ws_stall_timeout_secs: u64,
+    /// Contract reads in flight at once while checking many components or pools
+    #[arg(long, env = "CHECK_CONCURRENCY", default_value_t = DEFAULT_CHECK_CONCURRENCY, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    check_concurrency: usize,
+    /// Drop a sent transaction the node still does not know after this many blocks, and send
+    /// its update again
+    #[arg(long, env = "PENDING_EXPIRY_BLOCKS", default_value_t = DEFAULT_PENDING_EXPIRY_BLOCKS, value_parser = clap::value_parser!(u64).range(1..))]
+    pending_expiry_blocks: u64,
+    /// Check the fee when no new head arrived for this many seconds
+    #[arg(long, env = "CHECK_INTERVAL_SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    check_interval_secs: Option<u64>,
+    /// Name of the pools' price update event; its emissions are subscribed to so that
+    /// outside price changes are noticed right away
+    #[arg(long, env = "PRICE_EVENT")]
+    price_event: Option<String>,
+    /// Restarts in a row of a panicked or failed background task before the daemon exits
+    #[arg(long, env = "TASK_MAX_RESTARTS", default_value_t = DEFAULT_MAX_RESTARTS)]
+    task_max_restarts: u32,
+    /// Extra header for the HTTP and WebSocket endpoints, as `Name: value` or
+    /// `host=Name: value` to send it to one host only
+    #[arg(long = "rpc-header", env = "RPC_HEADERS", value_delimiter = ',')]
+    rpc_headers: Vec<EndpointHeader>,
+    /// Proxy for all RPC and WebSocket traffic: http://, socks5:// or socks5h://
+    #[arg(long, env = "PROXY_URL", value_parser = parse_proxy)]
+    proxy: Option<Url>,
+    /// PEM bundle of extra CA certificates trusted for https:// and wss:// endpoints
+    #[arg(long, env = "TLS_CA_CERT")]
+    tls_ca_cert: Option<PathBuf>,
+    /// PEM client certificate for mTLS, used with --tls-client-key
+    #[arg(long, env = "TLS_CLIENT_CERT", requires = "tls_client_key")]
+    tls_client_cert: Option<PathBuf>,
+    /// PKCS#8 PEM private key of the client certificate
+    #[arg(long, env = "TLS_CLIENT_KEY", requires = "tls_client_cert")]
+    tls_client_key: Option<PathBuf>,
+    /// Per-request timeout for RPC calls, in milliseconds
+    #[arg(long, env = "RPC_TIMEOUT_MS", default_value_t = 10_000)]
+    rpc_timeout_ms: u64,
+    /// Extra rounds through all endpoints after every one of them failed a request
+    #[arg(long, env = "RPC_RETRIES", default_value_t = 2)]
+    rpc_retries: u32,
+    /// Delay before the first retry round, doubled for every further one, in milliseconds
+    #[arg(long, env = "RPC_RETRY_BACKOFF_MS", default_value_t = 500)]
+    rpc_retry_backoff_ms: u64,
+    /// Client-side limit on outgoing RPC calls per second, shared by all endpoints
+    #[arg(long, env = "RPC_RATE_LIMIT", value_parser = parse_rate)]
+    rpc_rate_limit: Option<f64>,
+    /// Calls allowed in a burst above the rate limit, defaults to one second's worth
+    #[arg(long, env = "RPC_BURST", requires = "rpc_rate_limit", value_parser = clap::value_parser!(u32).range(1..))]
+    rpc_burst: Option<u32>,
+    /// Retries of a fee check or update step that failed with a transient RPC error
+    #[arg(long, env = "TRANSIENT_RETRIES", default_value_t = 3)]
+    transient_retries: u32,
+    /// Base delay of the jittered exponential backoff between those retries, in milliseconds
+    #[arg(long, env = "TRANSIENT_BACKOFF_MS", default_value_t = 200)]
+    transient_backoff_ms: u64,
+    /// Upper bound of that backoff, in milliseconds
+    #[arg(long, env = "TRANSIENT_BACKOFF_MAX_MS", default_value_t = 5_000)]
+    transient_backoff_max_ms: u64,
+    // Devnet mode brings its own pool and owner, so they are required everywhere else
+    #[arg(
+        long,
+        short = 'c',
+        env = "PP_ADDRESS",
+        required = true,
+        conflicts_with = "devnet"
+    )]
+    privacy_pool_address: Option<Felt>,
+    #[arg(
+        long,
+        short = 'o',
+        env = "OWNER_ADDRESS",
+        required = true,
+        conflicts_with = "devnet"
+    )]
+    owner_address: Option<Felt>,
+    #[arg(
+        long,
+        short = 'p',
+        env = "OWNER_PRIVATE_KEY",
+        required = true,
+        conflicts_with = "devnet"
+    )]
+    owner_private_key: Option<Felt>,
+    /// Further accounts the updates are sent from in turn with the owner
+    #[arg(long, env = "SENDER_ADDRESSES", value_delimiter = ',')]
+    sender_addresses: Vec<Felt>,
+    /// Private keys of those accounts, in the same order
+    #[arg(long, env = "SENDER_PRIVATE_KEYS", value_delimiter = ',')]
+    sender_private_keys: Vec<Felt>,
+    /// Calldata encoding of the sender accounts' multicalls
+    #[arg(long, env = "ACCOUNT_ENCODING", value_enum, default_value_t = AccountEncoding::Auto)]
+    account_encoding: AccountEncoding,
+    #[arg(long, env = "PRICE_BLOCK", value_enum, default_value_t = PriceBlock::Latest)]
+    price_block: PriceBlock,
+    /// Unit of the gas prices read from the network and written to the contract
+    #[arg(long, env = "DENOMINATION", value_enum, default_value_t = Denomination::Fri)]
+    denomination: Denomination,
     #[arg(long, env = "UPWARD_THRESHOLD")]
     upward_threshold: u128,
     #[arg(long, env = "DOWNWARD_THRESHOLD")]
@@ -32,121 +397,1422 @@ struct Args {
     upward_buffer: u128,
     #[arg(long, env = "DOWNWARD_BUFFER")]
     downward_buffer: u128,
+    /// Largest change of a contract price in a single update, in percent
+    #[arg(long, env = "MAX_CHANGE_PERCENT")]
+    max_change_percent: Option<u128>,
+    /// Floor of the written prices; lower targets are raised to it
+    #[arg(long, env = "MIN_GAS_PRICE")]
+    min_gas_price: Option<u128>,
+    /// Ceiling of the written prices; higher targets are lowered to it
+    #[arg(long, env = "MAX_GAS_PRICE")]
+    max_gas_price: Option<u128>,
+    #[arg(long, env = "L1_GAS_GETTER", default_value = "get_current_gas_price")]
+    l1_gas_getter: String,
+    #[arg(long, env = "L1_GAS_SETTER", default_value = "set_current_gas_price")]
+    l1_gas_setter: String,
+    /// How the setters of the CLI-configured components take the price
+    #[arg(long, env = "PRICE_ENCODING", value_enum, default_value_t = PriceEncoding::U256)]
+    price_encoding: PriceEncoding,
+    /// Also track the L1 data gas price, written in the same multicall as the L1 gas price
+    #[arg(long, env = "TRACK_L1_DATA_GAS", conflicts_with = "config")]
+    track_l1_data_gas: bool,
+    #[arg(long, env = "L1_DATA_GAS_GETTER", default_value = "get_current_data_gas_price")]
+    l1_data_gas_getter: String,
+    #[arg(long, env = "L1_DATA_GAS_SETTER", default_value = "set_current_data_gas_price")]
+    l1_data_gas_setter: String,
+    /// L1 data gas thresholds and buffers; unset ones follow the L1 gas values
+    #[arg(long, env = "L1_DATA_GAS_UPWARD_THRESHOLD")]
+    l1_data_gas_upward_threshold: Option<u128>,
+    #[arg(long, env = "L1_DATA_GAS_DOWNWARD_THRESHOLD")]
+    l1_data_gas_downward_threshold: Option<u128>,
+    #[arg(long, env = "L1_DATA_GAS_UPWARD_BUFFER")]
+    l1_data_gas_upward_buffer: Option<u128>,
+    #[arg(long, env = "L1_DATA_GAS_DOWNWARD_BUFFER")]
+    l1_data_gas_downward_buffer: Option<u128>,
+    /// Also track the L2 gas price, for paymasters pricing user fees on L2 gas
+    #[arg(long, env = "TRACK_L2_GAS", conflicts_with = "config")]
+    track_l2_gas: bool,
+    #[arg(long, env = "L2_GAS_GETTER", default_value = "get_l2_gas_price")]
+    l2_gas_getter: String,
+    #[arg(long, env = "L2_GAS_SETTER", default_value = "set_l2_gas_price")]
+    l2_gas_setter: String,
+    /// L2 gas thresholds and buffers; unset ones follow the L1 gas values
+    #[arg(long, env = "L2_GAS_UPWARD_THRESHOLD")]
+    l2_gas_upward_threshold: Option<u128>,
+    #[arg(long, env = "L2_GAS_DOWNWARD_THRESHOLD")]
+    l2_gas_downward_threshold: Option<u128>,
+    #[arg(long, env = "L2_GAS_UPWARD_BUFFER")]
+    l2_gas_upward_buffer: Option<u128>,
+    #[arg(long, env = "L2_GAS_DOWNWARD_BUFFER")]
+    l2_gas_downward_buffer: Option<u128>,
+    #[arg(long, env = "CONFIG_FILE")]
+    config: Option<PathBuf>,
+    #[arg(long, env = "CANDIDATE_CONFIG_FILE")]
+    candidate_config: Option<PathBuf>,
+    #[arg(long, env = "DIVERGENCE_REPORT", default_value = "divergence-report.json")]
+    divergence_report: PathBuf,
+    #[arg(long, env = "CANDIDATE_TRIAL_BLOCKS")]
+    candidate_trial_blocks: Option<u64>,
+    #[arg(long, env = "HISTORY_FILE")]
+    history_file: Option<PathBuf>,
+    /// Append-only JSONL audit trail of every decision and transaction
+    #[arg(long, env = "AUDIT_FILE")]
+    audit_file: Option<PathBuf>,
+    /// Stark private key sealing every audit entry, dedicated to the audit trail
+    #[arg(long, env = "AUDIT_SIGNING_KEY", requires = "audit_file")]
+    audit_signing_key: Option<Felt>,
+    /// Signed audit entries between two checkpoints; 0 disables checkpoints
+    #[arg(long, env = "AUDIT_CHECKPOINT_EVERY", default_value_t = 100)]
+    audit_checkpoint_every: u64,
+    #[arg(long, env = "METRICS_ADDR")]
+    metrics_addr: Option<SocketAddr>,
+    /// StatsD/DogStatsD agent (`host:port`) to send metrics to, alongside or instead of
+    /// the Prometheus endpoint
+    #[arg(long, env = "STATSD_ADDR")]
+    statsd_addr: Option<String>,
+    #[arg(long, env = "STATSD_PREFIX", requires = "statsd_addr")]
+    statsd_prefix: Option<String>,
+    /// `key:value` tags added to every StatsD metric
+    #[arg(
+        long,
+        env = "STATSD_TAGS",
+        value_delimiter = ',',
+        requires = "statsd_addr"
+    )]
+    statsd_tags: Vec<String>,
+    /// Do not export uptime, task, memory and file descriptor metrics of the process itself,
+    /// sampled every 15s to Prometheus and StatsD alike; memory and descriptors are only
+    /// read on Linux
+    #[arg(long, env = "SKIP_PROCESS_METRICS")]
+    skip_process_metrics: bool,
+    /// Unix socket accepting local control commands, readable by the process owner only
+    #[arg(long, env = "CONTROL_SOCKET")]
+    control_socket: Option<PathBuf>,
+    /// Serve the admin API on this address
+    #[arg(long, env = "ADMIN_ADDR", requires = "admin_token")]
+    admin_addr: Option<SocketAddr>,
+    /// Bearer token required by every admin API request
+    #[arg(long, env = "ADMIN_TOKEN", value_parser = clap::builder::NonEmptyStringValueParser::new())]
+    admin_token: Option<String>,
+    /// Redis holding the leader lock; only the replica holding it sends transactions
+    #[arg(long, env = "LEADER_LOCK_URL")]
+    leader_lock_url: Option<Url>,
+    #[arg(long, env = "LEADER_LOCK_KEY", default_value = "pp-fee-updater:leader")]
+    leader_lock_key: String,
+    /// Seconds a dead leader keeps the lock before another replica takes over
+    #[arg(long, env = "LEADER_LOCK_TTL_SECS", default_value_t = 15, value_parser = clap::value_parser!(u64).range(3..))]
+    leader_lock_ttl_secs: u64,
+    /// Serve the web dashboard on this address
+    #[arg(long, env = "DASHBOARD_ADDR")]
+    dashboard_addr: Option<SocketAddr>,
+    /// Recent decisions kept in memory for the status command and the dashboards
+    #[arg(long, env = "DECISION_HISTORY", default_value_t = DEFAULT_DECISION_HISTORY)]
+    decision_history: usize,
+    #[arg(long, env = "ALERT_WEBHOOK_URL")]
+    alert_webhook_url: Option<Url>,
+    /// Kafka broker (`kafka://host:port`) or NATS server (`nats://host:port`) to publish
+    /// every decision and transaction event to as JSON
+    #[arg(long, env = "EVENT_STREAM_URL")]
+    event_stream_url: Option<Url>,
+    /// Kafka topic or NATS subject of the events
+    #[arg(
+        long,
+        env = "EVENT_STREAM_TOPIC",
+        default_value = "pp-fee-updater.events"
+    )]
+    event_stream_topic: String,
+    /// MQTT broker (`mqtt://[user:password@]host[:port]`) to publish prices and updates to
+    #[arg(long, env = "MQTT_URL")]
+    mqtt_url: Option<Url>,
+    #[arg(long, env = "MQTT_CLIENT_ID", default_value = "pp-fee-updater")]
+    mqtt_client_id: String,
+    /// Prefix of the MQTT topics
+    #[arg(long, env = "MQTT_TOPIC_PREFIX", default_value = "pp-fee-updater")]
+    mqtt_topic_prefix: String,
+    #[arg(long, env = "STRK_TOKEN_ADDRESS", default_value = STRK_TOKEN_ADDRESS)]
+    strk_token_address: Felt,
+    #[arg(long, env = "MIN_OWNER_BALANCE")]
+    min_owner_balance: Option<u128>,
+    /// Seconds between reads of the owner balance, when MIN_OWNER_BALANCE is set
+    #[arg(long, env = "BALANCE_CHECK_INTERVAL_SECS", default_value_t = 60, value_parser = clap::value_parser!(u64).range(1..))]
+    balance_check_interval_secs: u64,
+    /// Seconds between checks of the pool class hashes for upgrades; 0 disables them
+    #[arg(long, env = "CLASS_CHECK_INTERVAL_SECS", default_value_t = 300)]
+    class_check_interval_secs: u64,
+    #[arg(long, env = "MAX_CALLS_PER_TX")]
+    max_calls_per_tx: Option<usize>,
+    #[arg(long, env = "MAX_TXS_PER_CYCLE")]
+    max_txs_per_cycle: Option<usize>,
+    /// Price on this percentile of the recent network prices instead of the latest one
+    #[arg(long, env = "TARGET_PERCENTILE", value_parser = clap::value_parser!(u8).range(1..=100))]
+    target_percentile: Option<u8>,
+    /// Network prices the target percentile is taken over
+    #[arg(long, env = "TARGET_WINDOW", default_value_t = 20)]
+    target_window: usize,
+    /// Add to an upward update what a steady rise of gas projects over this many blocks
+    #[arg(long, env = "TREND_HORIZON_BLOCKS")]
+    trend_horizon_blocks: Option<u64>,
+    /// Network prices the gas trend is fitted over
+    #[arg(long, env = "TREND_WINDOW", default_value_t = 10)]
+    trend_window: usize,
+    /// Hold updates while the network and contract prices are this many times apart
+    #[arg(long, env = "MAX_DIVERGENCE_FACTOR", value_parser = clap::value_parser!(u128).range(2..))]
+    max_divergence_factor: Option<u128>,
+    /// Send divergent updates anyway, only alerting
+    #[arg(
+        long = "force",
+        env = "FORCE_DIVERGENT_UPDATES",
+        requires = "max_divergence_factor"
+    )]
+    force_divergent_updates: bool,
+    /// Consecutive checks the upward threshold has to stay breached before an update
+    #[arg(long, env = "CONFIRM_UPWARD_BLOCKS", default_value_t = 1)]
+    confirm_upward_blocks: u64,
+    /// Consecutive checks the downward threshold has to stay breached before an update
+    #[arg(long, env = "CONFIRM_DOWNWARD_BLOCKS", default_value_t = 1)]
+    confirm_downward_blocks: u64,
+    /// Blocks to wait after a confirmed update before sending another one
+    #[arg(long, env = "COOLDOWN_BLOCKS")]
+    cooldown_blocks: Option<u64>,
+    /// Seconds to wait after a confirmed update before sending another one
+    #[arg(long, env = "COOLDOWN_SECS")]
+    cooldown_secs: Option<u64>,
+    /// Force a refresh of a contract value not written for this many blocks
+    #[arg(long, env = "MAX_STALENESS_BLOCKS")]
+    max_staleness_blocks: Option<u64>,
+    /// Force a refresh of a contract value not written for this many seconds
+    #[arg(long, env = "MAX_STALENESS_SECS")]
+    max_staleness_secs: Option<u64>,
+    /// Ignore a network price more than this many standard deviations off the recent trend
+    #[arg(long, env = "SPIKE_MAX_Z_SCORE")]
+    spike_max_z_score: Option<f64>,
+    /// Ignore a network price more than this many times above or below the recent trend
+    #[arg(long, env = "SPIKE_MAX_MULTIPLE")]
+    spike_max_multiple: Option<f64>,
+    /// Readings the spike filter takes the trend over
+    #[arg(long, env = "SPIKE_WINDOW", default_value_t = 20)]
+    spike_window: usize,
+    /// Consecutive outlying readings after which the spike filter follows the move
+    #[arg(long, env = "SPIKE_PERSIST_BLOCKS", default_value_t = 3)]
+    spike_persist_blocks: u64,
+    /// Broadcast transactions without simulating them first
+    #[arg(long, env = "SKIP_SIMULATION")]
+    skip_simulation: bool,
+    /// Max L1 gas amount of a transaction, or `auto` to derive it from the fee estimate
+    #[arg(long, env = "TX_L1_GAS", default_value = "auto")]
+    tx_l1_gas: Bound<u64>,
+    /// Max price per unit of L1 gas in fri, or `auto`
+    #[arg(long, env = "TX_L1_GAS_PRICE", default_value = "auto")]
+    tx_l1_gas_price: Bound<u128>,
+    /// Max L2 gas amount of a transaction, or `auto`
+    #[arg(long, env = "TX_L2_GAS", default_value = "auto")]
+    tx_l2_gas: Bound<u64>,
+    /// Max price per unit of L2 gas in fri, or `auto`
+    #[arg(long, env = "TX_L2_GAS_PRICE", default_value = "auto")]
+    tx_l2_gas_price: Bound<u128>,
+    /// Max L1 data gas amount of a transaction, or `auto`
+    #[arg(long, env = "TX_L1_DATA_GAS", default_value = "auto")]
+    tx_l1_data_gas: Bound<u64>,
+    /// Max price per unit of L1 data gas in fri, or `auto`
+    #[arg(long, env = "TX_L1_DATA_GAS_PRICE", default_value = "auto")]
+    tx_l1_data_gas_price: Bound<u128>,
+    /// Factor applied to the estimated gas amounts of `auto` bounds
+    #[arg(long, env = "TX_GAS_MULTIPLIER", default_value_t = DEFAULT_AMOUNT_MULTIPLIER)]
+    tx_gas_multiplier: f64,
+    /// Factor applied to the estimated gas prices of `auto` bounds
+    #[arg(long, env = "TX_GAS_PRICE_MULTIPLIER", default_value_t = DEFAULT_PRICE_MULTIPLIER)]
+    tx_gas_price_multiplier: f64,
+    /// Accounts whose price writes count as manual overrides
+    #[arg(long, env = "OPERATOR_ADDRESSES", value_delimiter = ',')]
+    operator_addresses: Vec<Felt>,
+    /// Keep a price written outside this updater for this many seconds, then re-apply ours
+    #[arg(long, env = "OVERRIDE_GRACE_SECS")]
+    override_grace_secs: Option<u64>,
+    #[arg(long, env = "NETWORK")]
+    network: Option<Network>,
+    #[arg(long, env = "PREFLIGHT", value_enum, default_value_t = PreflightMode::Strict)]
+    preflight: PreflightMode,
+    #[arg(long, env = "QUORUM_SIZE", value_parser = clap::value_parser!(u16).range(2..))]
+    quorum_size: Option<u16>,
+    #[arg(long, env = "QUORUM_TOLERANCE_BPS", default_value_t = 100)]
+    quorum_tolerance_bps: u128,
+    /// Ethereum JSON-RPC endpoint whose base and blob fees are blended into the L1 prices
+    #[arg(long, env = "ETH_RPC_URL")]
+    eth_rpc_url: Option<Url>,
+    /// Share of the Ethereum fee in the blended L1 prices, in percent
+    #[arg(long, env = "ETH_GAS_WEIGHT", default_value_t = 50, requires = "eth_rpc_url", value_parser = clap::value_parser!(u64).range(0..=100))]
+    eth_gas_weight: u64,
+    /// Keep the last checked block in this file, so the fee is reconciled right away after
+    /// a restart that missed blocks
+    #[arg(long, env = "LAST_BLOCK_FILE")]
+    last_block_file: Option<PathBuf>,
+    /// Process the heads from this block on over HTTP before subscribing to new ones
+    #[arg(long, env = "REPLAY_FROM")]
+    replay_from: Option<u64>,
+    /// Whether replayed heads only record their decisions or also send updates
+    #[arg(long, env = "REPLAY_MODE", value_enum, default_value_t = ReplayMode::DryRun, requires = "replay_from")]
+    replay_mode: ReplayMode,
+    /// Render a live terminal dashboard; logs go to --tui-log-file meanwhile
+    #[arg(long, env = "TUI")]
+    tui: bool,
+    #[arg(long, env = "TUI_LOG_FILE", default_value = "pp-fee-updater.log")]
+    tui_log_file: PathBuf,
+    #[arg(long, env = "CRASH_REPORT_DIR")]
+    crash_report_dir: Option<PathBuf>,
+    #[arg(long, env = "CRASH_REPORT_DECISIONS", default_value_t = 50)]
+    crash_report_decisions: usize,
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    // Initialize tracing with better configuration
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::from_default_env()
-                .add_directive("pp_fee_updater=info".parse().unwrap())
-                .add_directive("info".parse().unwrap()),
+fn main() -> ExitCode {
+    // Both run before parsing so their variables back the arguments
+    let env_file = envfile::load();
+    let secret_files = envfile::load_secret_files(&Cli::command());
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let runtime = if cli.single_thread {
+        tokio::runtime::Builder::new_current_thread()
+    } else {
+        let mut builder = tokio::runtime::Builder::new_multi_thread();
+        if let Some(worker_threads) = cli.worker_threads {
+            builder.worker_threads(worker_threads.get());
+        }
+        builder
+    }
+    .enable_all()
+    .build();
+    match runtime {
+        Ok(runtime) => runtime.block_on(start(cli, matches, env_file, secret_files)),
+        Err(e) => {
+            eprintln!("Failed to start the async runtime: {e}");
+            ExitCode::from(ErrorClass::Configuration.exit_code())
+        }
+    }
+}
+
+async fn start(
+    cli: Cli,
+    matches: ArgMatches,
+    env_file: anyhow::Result<Option<PathBuf>>,
+    secret_files: anyhow::Result<Vec<String>>,
+) -> ExitCode {
+    // The dashboard owns the terminal, so logs go to a file while it runs
+    let log_file = cli
+        .args
+        .as_ref()
+        .filter(|args| args.tui)
+        .map(|args| args.tui_log_file.as_path());
+    let filter = match logging::filter(cli.verbose, cli.quiet, cli.log_filter.as_deref()) {
+        Ok(filter) => filter,
+        Err(e) => {
+            eprintln!("Invalid log filter: {e}");
+            return ExitCode::from(ErrorClass::Configuration.exit_code());
+        }
+    };
+    if let Err(e) = init_tracing(log_file, filter) {
+        eprintln!("Failed to open the log file: {e}");
+        return ExitCode::from(ErrorClass::Configuration.exit_code());
+    }
+    match env_file.and_then(|path| Ok((path, secret_files?))) {
+        Ok((path, secret_files)) => {
+            if let Some(path) = path {
+                info!("Loaded environment variables from {}", path.display());
+            }
+            for name in secret_files {
+                info!("🔒 Read {} from {}_FILE", name, name);
+            }
+        }
+        Err(e) => {
+            error!("💥 {:?}", e);
+            return ExitCode::from(ErrorClass::Configuration.exit_code());
+        }
+    }
+    let result = match (cli.command, cli.args) {
+        (Some(Command::Annotate(annotate_args)), _) => annotate(annotate_args),
+        (Some(Command::Backtest(backtest_args)), _) => run_backtest(backtest_args).await,
+        (Some(Command::Export(export_args)), _) => export(export_args),
+        (Some(Command::VerifyAudit(verify_args)), _) => verify_audit(verify_args),
+        (Some(Command::Ctl(ctl_args)), _) => ctl(ctl_args).await,
+        (Some(Command::PrintConfig(print_args)), _) => print_config(print_args, &matches),
+        (Some(Command::Completions { shell }), _) => {
+            clap_complete::generate(
+                shell,
+                &mut Cli::command(),
+                "pp-fee-updater",
+                &mut std::io::stdout(),
+            );
+            Ok(())
+        }
+        (Some(Command::Init(init_args)), _) => init(init_args),
+        (None, Some(args)) => run(args).await,
+        (None, None) => unreachable!("clap requires the daemon arguments without a subcommand"),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            let class = ErrorClass::of(&e);
+            error!("💥 Exiting on {} (exit code {}): {:?}", class, class.exit_code(), e);
+            ExitCode::from(class.exit_code())
+        }
+    }
+}
+
+fn init_tracing(
+    log_file: Option<&Path>,
+    filter: tracing_subscriber::EnvFilter,
+) -> std::io::Result<()> {
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+    match log_file {
+        Some(path) => {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?;
+            subscriber
+                .with_ansi(false)
+                .with_writer(std::sync::Mutex::new(file))
+                .init();
+        }
+        None => subscriber.init(),
+    }
+    Ok(())
+}
+
+fn annotate(args: AnnotateArgs) -> anyhow::Result<()> {
+    let target = match (args.tx_hash, args.block, args.from, args.to) {
+        (Some(tx_hash), _, _, _) => AnnotationTarget::Transaction {
+            tx_hash: format!("{tx_hash:#x}"),
+        },
+        (_, Some(block_number), _, _) => AnnotationTarget::Block { block_number },
+        (_, _, Some(from), Some(to)) if from <= to => AnnotationTarget::TimeRange { from, to },
+        (_, _, Some(_), Some(_)) => anyhow::bail!("--from must not be after --to"),
+        _ => anyhow::bail!("an annotation target is required"),
+    };
+
+    let store = HistoryStore::new(args.history_file);
+    let covered = store
+        .load()?
+        .iter()
+        .filter(|record| target.matches(record))
+        .count();
+
+    store.append(&HistoryRecord::Annotation(Annotation {
+        timestamp: unix_timestamp(),
+        text: args.text,
+        target,
+    }))?;
+
+    info!("📝 Annotation recorded ({} existing history records covered)", covered);
+    Ok(())
+}
+
+// Arguments are listed as clap resolved them, then the preset and the config file are
+// folded in the way the daemon does
+fn print_config(args: PrintConfigArgs, matches: &ArgMatches) -> anyhow::Result<()> {
+    let command = Cli::command();
+    let (command, matches) = command
+        .find_subcommand("print-config")
+        .zip(matches.subcommand_matches("print-config"))
+        .context(ErrorClass::Internal)?;
+    let mut config = EffectiveConfig::from_matches(command, matches, &["format"]);
+    if let Some(preset) = args.args.preset {
+        config.fill(
+            "websocket-url",
+            vec![preset.websocket_url().to_string()],
+            "preset",
+        );
+        config.fill("api-url", vec![preset.api_url().to_string()], "preset");
+        config.fill("network", vec![preset.network().to_string()], "preset");
+    }
+    if args.args.devnet {
+        let url = &args.args.devnet_url;
+        config.fill(
+            "websocket-url",
+            vec![devnet::websocket_url(url).to_string()],
+            "devnet",
+        );
+        config.fill("api-url", vec![devnet::rpc_url(url).to_string()], "devnet");
+    }
+    if args.args.config.is_some() {
+        config = config.with_config_file(load_file_config(&args.args)?);
+    }
+    print!("{}", config.render(args.format)?);
+    Ok(())
+}
+
+fn init(args: InitArgs) -> anyhow::Result<()> {
+    if args.output.exists() && !args.force {
+        return Err(anyhow::anyhow!(
+            "{} already exists, pass --force to overwrite it",
+            args.output.display()
         )
-        .init();
-    let args = Args::parse();
-    let ws_starknet_url = &args.websocket_url;
-    let starknet_url = &args.api_url;
-    let privacy_pool_address = args.privacy_pool_address;
-    let owner_address = args.owner_address;
-    let owner_private_key = args.owner_private_key;
+        .context(ErrorClass::Configuration));
+    }
+    let mut command = Cli::command();
+    command.build();
+    std::fs::write(&args.output, scaffold::env_template(&command))
+        .with_context(|| format!("Failed to write {}", args.output.display()))?;
+    info!("📝 Wrote a starter configuration to {}", args.output.display());
+    Ok(())
+}
+
+fn verify_audit(args: VerifyAuditArgs) -> anyhow::Result<()> {
+    let verification = audit::verify(&args.audit_file, args.public_key)?;
+    info!(
+        "🔏 {} signed audit entries intact, {} checkpoint(s), head {:#x}",
+        verification.signed, verification.checkpoints, verification.head
+    );
+    if verification.unsigned > 0 {
+        warn!(
+            "{} line(s) before the first signed one are not covered",
+            verification.unsigned
+        );
+    }
+    Ok(())
+}
+
+fn export(args: ExportArgs) -> anyhow::Result<()> {
+    let records: Vec<HistoryRecord> = HistoryStore::new(args.history_file)
+        .load()?
+        .into_iter()
+        .filter(|record| {
+            let timestamp = export::record_timestamp(record);
+            !args.since.is_some_and(|since| timestamp < since)
+                && !args.until.is_some_and(|until| timestamp > until)
+        })
+        .collect();
+
+    match &args.output {
+        Some(path) => {
+            let file = std::fs::File::create(path)
+                .with_context(|| format!("Failed to create {}", path.display()))?;
+            export::write_records(&records, args.format, &mut std::io::BufWriter::new(file))?;
+            info!(
+                "📤 Exported {} history record(s) to {}",
+                records.len(),
+                path.display()
+            );
+        }
+        None => export::write_records(&records, args.format, &mut std::io::stdout().lock())?,
+    }
+    Ok(())
+}
 
-    let mut pending_fee_update: Option<PendingUpdate> = None;
+async fn ctl(args: CtlArgs) -> anyhow::Result<()> {
+    let command = match args.command {
+        CtlCommand::Status => serde_json::json!({ "command": "status" }),
+        CtlCommand::Pause => serde_json::json!({ "command": "pause" }),
+        CtlCommand::Resume => serde_json::json!({ "command": "resume" }),
+        CtlCommand::Check => serde_json::json!({ "command": "check" }),
+        CtlCommand::SetFee { component, price } => {
+            serde_json::json!({ "command": "set_price", "component": component, "price": price })
+        }
+        CtlCommand::Confirm { component } => {
+            serde_json::json!({ "command": "confirm_update", "component": component })
+        }
+    };
+    let reply = socket::request(&args.socket, &command)
+        .await
+        .with_context(|| format!("Failed to reach the updater at {}", args.socket.display()))?;
+    println!("{}", serde_json::to_string_pretty(&reply)?);
+    if let Some(error) = reply.get("error") {
+        anyhow::bail!("Command rejected: {}", error);
+    }
+    Ok(())
+}
 
-    info!("Connecting to Starknet WebSocket at: {}", ws_starknet_url);
+// Failed runs are pushed too, so a broken scheduled job shows up as such
+async fn run_backtest(args: BacktestArgs) -> anyhow::Result<()> {
+    let pushgateway = match args.pushgateway_url.clone() {
+        Some(url) => Some(
+            telemetry::Pushgateway::install(url, args.pushgateway_job.clone())
+                .context(ErrorClass::Configuration)?,
+        ),
+        None => None,
+    };
+    let result = backtest_report(args).await;
+    if let Some(pushgateway) = pushgateway {
+        let success = if result.is_ok() { 1.0 } else { 0.0 };
+        metrics::gauge!("pp_fee_updater_backtest_success").set(success);
+        if let Err(e) = pushgateway.push().await {
+            warn!("{:?}", e);
+        }
+    }
+    result
+}
 
-    let (ws_stream, _) = connect_async(ws_starknet_url).await?;
-    info!("Successfully connected to Starknet WebSocket");
+async fn backtest_report(args: BacktestArgs) -> anyhow::Result<()> {
+    if args.from > args.to {
+        return Err(
+            anyhow::anyhow!("--from must not be after --to").context(ErrorClass::Configuration)
+        );
+    }
+    let transport = TransportOptions {
+        headers: Vec::new(),
+        proxy: None,
+        tls: TlsOptions::default(),
+        timeout: Duration::from_secs(10),
+        retry: RetryPolicy {
+            retries: 2,
+            backoff: Duration::from_millis(500),
+        },
+        rate_limit: None,
+    };
+    let rpc = EndpointPool::new(&args.api_url, &transport).context(ErrorClass::Configuration)?;
 
-    let (mut write, mut read) = ws_stream.split();
+    let default_strategy = ThresholdStrategy {
+        upward_threshold: args.upward_threshold,
+        downward_threshold: args.downward_threshold,
+        upward_buffer: args.upward_buffer,
+        downward_buffer: args.downward_buffer,
+        max_change_percent: args.max_change_percent,
+        min_price: args.min_gas_price,
+        max_price: args.max_gas_price,
+    };
+    let file_config = match &args.config {
+        Some(path) => FileConfig::load(path).context(ErrorClass::Configuration)?,
+        None => FileConfig::default(),
+    };
+    let components = if file_config.components.is_empty() {
+        let l1_gas = ComponentConfig::builtin(
+            "l1_gas",
+            PriceSource::L1Gas,
+            "get_current_gas_price",
+            "set_current_gas_price",
+        );
+        vec![l1_gas.resolve(default_strategy)?]
+    } else {
+        file_config.resolve_components(default_strategy)?
+    };
 
-    // Subscribe to new blocks
-    let subscribe_msg = json!({
-        "jsonrpc": "2.0",
-        "method": "starknet_subscribeNewHeads",
-        "params": [],
-        "id": 1
-    });
+    info!(
+        "⏪ Fetching gas prices of blocks {}..={}",
+        args.from, args.to
+    );
+    let blocks = backtest::fetch_blocks(&rpc.client(), args.from, args.to).await?;
+    let report = backtest::simulate(
+        &components,
+        args.denomination,
+        args.from,
+        &blocks,
+        args.initial_price,
+        args.tx_cost,
+    );
+    backtest::log_report(&report);
+    backtest::record_metrics(&report);
+    backtest::write_report(&report, &args.report)?;
+    info!("Backtest report written to {}", args.report.display());
+    Ok(())
+}
+
+async fn run(mut args: Args) -> anyhow::Result<()> {
+    apply_preset(&mut args)?;
+    apply_devnet(&mut args).await?;
+    let crash = match &args.crash_report_dir {
+        Some(dir) => {
+            let reporter = CrashReporter::new(
+                dir.clone(),
+                args.crash_report_decisions,
+                config_fingerprint(&args),
+            );
+            reporter.install_panic_hook();
+            Some(reporter)
+        }
+        None => None,
+    };
+
+    let result = run_daemon(args, crash.as_ref()).await;
+    if let (Err(e), Some(crash)) = (&result, &crash) {
+        crash.report_fatal_error(e);
+    }
+    result
+}
 
-    info!("Subscribing to new block notifications...");
-    write.send(Message::Text(subscribe_msg.to_string())).await?;
-
-    // Listen for new blocks
-    while let Some(msg) = read.next().await {
-        match msg {
-            Ok(Message::Text(text)) => {
-                // Parse JSON response
-                if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(&text) {
-                    if let Some(method) = json_value.get("method") {
-                        if method == "starknet_subscriptionNewHeads" {
-                            if let Some(params) = json_value.get("params") {
-                                if let Some(result) = params.get("result") {
-                                    if let Some(block_number) = result.get("block_number") {
-                                        info!("📦 New Starknet block received: {}", block_number);
-                                    }
-                                    if let Some(block_hash) = result.get("block_hash") {
-                                        info!("   Block hash: {}", block_hash);
-                                    }
-                                }
-                                let check_fee = match check_fee_update(
-                                    starknet_url.clone(),
-                                    privacy_pool_address,
-                                    &mut pending_fee_update,
-                                    args.upward_threshold,
-                                    args.downward_threshold,
-                                    args.upward_buffer,
-                                    args.downward_buffer,
-                                )
-                                .await
-                                {
-                                    Ok(result) => result,
-                                    Err(e) => {
-                                        error!("Failed to check fee update: {:?}", e);
-                                        continue;
-                                    }
-                                };
-
-                                if check_fee.0 {
-                                    warn!("⚠️ Fee update needed! New gas price: {}", check_fee.1);
-                                    if let Err(e) = update_fee(
-                                        starknet_url.clone(),
-                                        check_fee.1,
-                                        privacy_pool_address,
-                                        owner_address,
-                                        owner_private_key,
-                                        &mut pending_fee_update,
-                                    )
-                                    .await
-                                    {
-                                        error!("Failed to update fee: {:?}", e);
-                                    }
-                                } else {
-                                    info!("✅ Fee is up to date, no update needed");
-                                }
-                            }
+// The preset only fills in what was not given explicitly
+fn apply_preset(args: &mut Args) -> anyhow::Result<()> {
+    if let Some(preset) = args.preset {
+        if args.websocket_url.is_empty() {
+            args.websocket_url = vec![preset.websocket_url()];
+        }
+        if args.api_url.is_empty() {
+            args.api_url = vec![preset.api_url()];
+        }
+        args.network.get_or_insert(preset.network());
+        info!("🧭 Using the {} preset", preset.network());
+    }
+    if args.devnet {
+        if args.websocket_url.is_empty() {
+            args.websocket_url = vec![devnet::websocket_url(&args.devnet_url)];
+        }
+        if args.api_url.is_empty() {
+            args.api_url = vec![devnet::rpc_url(&args.devnet_url)];
+        }
+    }
+    if args.websocket_url.is_empty() {
+        return Err(
+            anyhow::anyhow!("WS_URL is required unless --preset or --devnet is set")
+                .context(ErrorClass::Configuration),
+        );
+    }
+    Ok(())
+}
+
+// Devnet mode sends from the first predeployed account and deploys a mock pool it owns;
+// the pool starts out unset, so the first head already runs a full update cycle
+async fn apply_devnet(args: &mut Args) -> anyhow::Result<()> {
+    if !args.devnet {
+        return Ok(());
+    }
+    let devnet = Devnet::new(&args.devnet_url)?;
+    let owner = devnet.predeployed_account().await?;
+    info!(
+        "🧪 Devnet mode: sending from the predeployed account {:#x}",
+        owner.address
+    );
+    let pool = devnet
+        .deploy_mock_pool(&args.devnet_contract_dir, owner, DevnetPrices::default())
+        .await?;
+    args.privacy_pool_address = Some(pool);
+    args.owner_address = Some(owner.address);
+    args.owner_private_key = Some(owner.private_key);
+    Ok(())
+}
+
+// Identifies the effective configuration in crash reports without leaking the private key
+fn config_fingerprint(args: &Args) -> String {
+    use std::hash::{DefaultHasher, Hash, Hasher};
+
+    let mut hasher = DefaultHasher::new();
+    for url in &args.websocket_url {
+        url.as_str().hash(&mut hasher);
+    }
+    for url in &args.api_url {
+        url.as_str().hash(&mut hasher);
+    }
+    args.rpc_transport.hash(&mut hasher);
+    args.privacy_pool_address.hash(&mut hasher);
+    args.owner_address.hash(&mut hasher);
+    args.sender_addresses.hash(&mut hasher);
+    args.upward_threshold.hash(&mut hasher);
+    args.downward_threshold.hash(&mut hasher);
+    args.price_block.hash(&mut hasher);
+    args.denomination.hash(&mut hasher);
+    args.l1_gas_getter.hash(&mut hasher);
+    args.l1_gas_setter.hash(&mut hasher);
+    args.price_encoding.hash(&mut hasher);
+    args.track_l1_data_gas.hash(&mut hasher);
+    args.l1_data_gas_getter.hash(&mut hasher);
+    args.l1_data_gas_setter.hash(&mut hasher);
+    args.l1_data_gas_upward_threshold.hash(&mut hasher);
+    args.l1_data_gas_downward_threshold.hash(&mut hasher);
+    args.l1_data_gas_upward_buffer.hash(&mut hasher);
+    args.l1_data_gas_downward_buffer.hash(&mut hasher);
+    args.track_l2_gas.hash(&mut hasher);
+    args.l2_gas_getter.hash(&mut hasher);
+    args.l2_gas_setter.hash(&mut hasher);
+    args.l2_gas_upward_threshold.hash(&mut hasher);
+    args.l2_gas_downward_threshold.hash(&mut hasher);
+    args.l2_gas_upward_buffer.hash(&mut hasher);
+    args.l2_gas_downward_buffer.hash(&mut hasher);
+    args.upward_buffer.hash(&mut hasher);
+    args.downward_buffer.hash(&mut hasher);
+    args.max_change_percent.hash(&mut hasher);
+    args.min_gas_price.hash(&mut hasher);
+    args.max_gas_price.hash(&mut hasher);
+    args.max_calls_per_tx.hash(&mut hasher);
+    args.max_txs_per_cycle.hash(&mut hasher);
+    args.target_percentile.hash(&mut hasher);
+    args.target_window.hash(&mut hasher);
+    args.trend_horizon_blocks.hash(&mut hasher);
+    args.trend_window.hash(&mut hasher);
+    args.max_divergence_factor.hash(&mut hasher);
+    args.force_divergent_updates.hash(&mut hasher);
+    args.confirm_upward_blocks.hash(&mut hasher);
+    args.confirm_downward_blocks.hash(&mut hasher);
+    args.cooldown_blocks.hash(&mut hasher);
+    args.cooldown_secs.hash(&mut hasher);
+    args.max_staleness_blocks.hash(&mut hasher);
+    args.max_staleness_secs.hash(&mut hasher);
+    args.spike_max_z_score.map(f64::to_bits).hash(&mut hasher);
+    args.spike_max_multiple.map(f64::to_bits).hash(&mut hasher);
+    args.spike_window.hash(&mut hasher);
+    args.spike_persist_blocks.hash(&mut hasher);
+    args.operator_addresses.hash(&mut hasher);
+    args.override_grace_secs.hash(&mut hasher);
+    args.quorum_size.hash(&mut hasher);
+    args.quorum_tolerance_bps.hash(&mut hasher);
+    args.eth_rpc_url.as_ref().map(Url::as_str).hash(&mut hasher);
+    args.eth_gas_weight.hash(&mut hasher);
+    args.replay_from.hash(&mut hasher);
+    args.replay_mode.hash(&mut hasher);
+    if let Some(path) = &args.config {
+        std::fs::read(path).unwrap_or_default().hash(&mut hasher);
+    }
+    format!("{:016x}", hasher.finish())
+}
+
+async fn run_daemon(args: Args, crash: Option<&CrashReporter>) -> anyhow::Result<()> {
+    let transport = TransportOptions {
+        headers: args.rpc_headers.clone(),
+        proxy: args.proxy.clone(),
+        tls: TlsOptions::load(
+            args.tls_ca_cert.as_deref(),
+            args.tls_client_cert.as_deref(),
+            args.tls_client_key.as_deref(),
+        )
+        .context(ErrorClass::Configuration)?,
+        timeout: Duration::from_millis(args.rpc_timeout_ms),
+        retry: RetryPolicy {
+            retries: args.rpc_retries,
+            backoff: Duration::from_millis(args.rpc_retry_backoff_ms),
+        },
+        rate_limit: args.rpc_rate_limit.map(|per_second| RateLimit {
+            per_second,
+            burst: args.rpc_burst.unwrap_or(per_second.ceil() as u32).max(1),
+        }),
+    };
+    let rpc_urls = args
+        .rpc_transport
+        .endpoints(&args.api_url, &args.websocket_url);
+    if rpc_urls.is_empty() {
+        return Err(anyhow::anyhow!("API_URL is required unless --rpc-transport is ws")
+            .context(ErrorClass::Configuration));
+    }
+    let rpc = EndpointPool::new(&rpc_urls, &transport).context(ErrorClass::Configuration)?;
+    info!(
+        "JSON-RPC transport: {:?}, {} endpoint(s)",
+        args.rpc_transport,
+        rpc_urls.len()
+    );
+    let _health_probe = rpc.spawn_health_probe(HEALTH_PROBE_INTERVAL);
+    let (Some(privacy_pool_address), Some(owner_address), Some(owner_private_key)) = (
+        args.privacy_pool_address,
+        args.owner_address,
+        args.owner_private_key,
+    ) else {
+        return Err(anyhow::anyhow!(
+            "PP_ADDRESS, OWNER_ADDRESS and OWNER_PRIVATE_KEY are required unless --devnet is set"
+        )
+        .context(ErrorClass::Configuration));
+    };
+    let history = args.history_file.clone().map(HistoryStore::new);
+    let restarts = supervisor::restart_policy(args.task_max_restarts);
+    let alerter = Alerter::new(args.alert_webhook_url.clone(), restarts);
+    let mut events = EventStream::new(
+        args.event_stream_url.clone(),
+        args.event_stream_topic.clone(),
+        restarts,
+    )?;
+    if let Some(mqtt_url) = args.mqtt_url.clone() {
+        events = events.with_mqtt(MqttPublisher::new(
+            mqtt_url,
+            args.mqtt_client_id.clone(),
+            args.mqtt_topic_prefix.clone(),
+            restarts,
+        )?);
+    }
+    let balance_monitor = BalanceMonitor::new(
+        args.strk_token_address,
+        owner_address,
+        args.min_owner_balance,
+        Duration::from_secs(args.balance_check_interval_secs),
+    );
+    let upgrade_watcher = UpgradeWatcher::new(
+        privacy_pool_address,
+        (args.class_check_interval_secs > 0)
+            .then(|| Duration::from_secs(args.class_check_interval_secs)),
+    );
+
+    let statsd = args
+        .statsd_addr
+        .clone()
+        .map(|addr| telemetry::StatsdOptions {
+            addr,
+            prefix: args.statsd_prefix.clone(),
+            tags: args.statsd_tags.clone(),
+        });
+    telemetry::install_metrics_exporter(
+        args.metrics_addr,
+        statsd.as_ref(),
+        !args.skip_process_metrics,
+        restarts,
+    )
+    .await
+    .context(ErrorClass::Configuration)?;
+    let live = LiveView::new(privacy_pool_address, args.decision_history);
+    let dashboard = args.tui.then(|| Dashboard::start(live.clone()));
+    if let Some(dashboard_addr) = args.dashboard_addr {
+        web::serve_dashboard(dashboard_addr, live.clone())
+            .await
+            .context(ErrorClass::Configuration)?;
+    }
+
+    let file_config = load_file_config(&args)?;
+    let components = resolve_components(&args, &file_config)?;
+    info!(
+        "Tracking {} fee component(s): {}",
+        components.len(),
+        components
+            .iter()
+            .map(|component| component.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    let price_event = args.price_event.as_deref().map(selector).transpose()?;
+
+    let candidate = match &args.candidate_config {
+        Some(path) => {
+            let candidate_components = FileConfig::load(path)
+                .context(ErrorClass::Configuration)?
+                .resolve_components(default_strategy(&args))?;
+            let candidate_components = matching_components(&components, candidate_components);
+            if candidate_components.is_empty() {
+                anyhow::bail!("Candidate config defines no component tracked by the live config");
+            }
+            info!(
+                "🧪 Evaluating candidate config {} alongside the live one",
+                path.display()
+            );
+            Some(CandidateRun::new(
+                candidate_components,
+                args.divergence_report.clone(),
+                args.candidate_trial_blocks,
+            ))
+        }
+        None => None,
+    };
+
+    let (mut ws_stream, mut ws_index) =
+        websocket::connect(&args.websocket_url, &transport, 0).await?;
+
+    let chain_metadata = ChainMetadata::fetch(&rpc).await?;
+
+    match args.network {
+        Some(expected_network) => {
+            network::verify_network(expected_network, &rpc, &mut ws_stream).await?;
+        }
+        None => network::verify_rpc_endpoints(&rpc, chain_metadata.chain_id).await?,
+    }
+
+    if args.sender_addresses.len() != args.sender_private_keys.len() {
+        return Err(UpdaterError::Configuration(format!(
+            "Got {} sender address(es) but {} sender private key(s)",
+            args.sender_addresses.len(),
+            args.sender_private_keys.len()
+        ))
+        .into());
+    }
+    let senders: Vec<SenderKey> = std::iter::once((owner_address, owner_private_key))
+        .chain(
+            args.sender_addresses
+                .iter()
+                .copied()
+                .zip(args.sender_private_keys.iter().copied()),
+        )
+        .map(|(address, private_key)| SenderKey {
+            address,
+            private_key,
+        })
+        .collect();
+    if senders.len() > 1 {
+        info!("🔁 Rotating updates over {} sender accounts", senders.len());
+    }
+
+    for pool in pools(privacy_pool_address, &components) {
+        preflight::verify_contract_owner(&rpc, pool, owner_address).await?;
+    }
+
+    if args.preflight != PreflightMode::Skip {
+        info!("Running preflight checks...");
+        let failures = run_preflight(
+            &rpc,
+            &chain_metadata,
+            &mut ws_stream,
+            privacy_pool_address,
+            &components,
+            &senders,
+        )
+        .await;
+        preflight::evaluate(args.preflight, &failures)?;
+    }
+
+    live.set_config(&components);
+    let mut updater = Updater::new(
+        &rpc,
+        privacy_pool_address,
+        &senders,
+        chain_metadata.chain_id,
+        components,
+        args.account_encoding,
+    )
+    .await?
+    .with_backoff(BackoffPolicy {
+        attempts: args.transient_retries,
+        base_delay: Duration::from_millis(args.transient_backoff_ms),
+        max_delay: Duration::from_millis(args.transient_backoff_max_ms),
+    })
+    .with_price_block(args.price_block)
+    .with_denomination(args.denomination)
+    .with_breach_confirmations(BreachConfirmations {
+        upward: args.confirm_upward_blocks,
+        downward: args.confirm_downward_blocks,
+    })
+    .with_divergence_guard(DivergenceGuard {
+        max_factor: args.max_divergence_factor,
+        force: args.force_divergent_updates,
+    })
+    .with_cooldown(Cooldown {
+        blocks: args.cooldown_blocks,
+        duration: args.cooldown_secs.map(Duration::from_secs),
+    })
+    .with_max_staleness(MaxStaleness {
+        blocks: args.max_staleness_blocks,
+        duration: args.max_staleness_secs.map(Duration::from_secs),
+    })
+    .with_spike_filter(SpikeFilter {
+        window: args.spike_window,
+        max_z_score: args.spike_max_z_score,
+        max_multiple: args.spike_max_multiple,
+        persist_blocks: args.spike_persist_blocks,
+    })
+    .with_simulation(!args.skip_simulation)
+    .with_resource_bounds(ResourceBounds {
+        l1_gas: args.tx_l1_gas,
+        l1_gas_price: args.tx_l1_gas_price,
+        l2_gas: args.tx_l2_gas,
+        l2_gas_price: args.tx_l2_gas_price,
+        l1_data_gas: args.tx_l1_data_gas,
+        l1_data_gas_price: args.tx_l1_data_gas_price,
+        amount_multiplier: args.tx_gas_multiplier,
+        price_multiplier: args.tx_gas_price_multiplier,
+    })
+    .with_overrides(OverridePolicy {
+        operators: args.operator_addresses.clone(),
+        grace: args.override_grace_secs.map(Duration::from_secs),
+    })
+    .with_alerter(alerter.clone())
+    .with_check_concurrency(args.check_concurrency)
+    .with_pending_expiry(args.pending_expiry_blocks);
+    if let Some(min) = args.min_owner_balance {
+        updater = updater.with_sender_balance(SenderBalance {
+            token: args.strk_token_address,
+            min,
+        });
+    }
+    if let Some(percentile) = args.target_percentile {
+        updater = updater.with_percentile_target(PercentileTarget {
+            percentile,
+            window: args.target_window,
+        });
+    }
+    if let Some(horizon) = args.trend_horizon_blocks {
+        updater = updater.with_trend_pricing(TrendPricing {
+            window: args.trend_window,
+            horizon,
+        });
+    }
+    if args.price_block == PriceBlock::Pending {
+        warn!("⚡ Reading gas prices from the pending block; they may still change");
+    }
+    if let Some(quorum_size) = args.quorum_size {
+        let quorum_size = usize::from(quorum_size);
+        if quorum_size > rpc_urls.len() {
+            anyhow::bail!(
+                "--quorum-size {} needs at least as many RPC URLs, got {}",
+                quorum_size,
+                rpc_urls.len()
+            );
+        }
+        info!(
+            "🗳️ Quorum reads across {} providers, tolerance {} bps",
+            quorum_size, args.quorum_tolerance_bps
+        );
+        updater = updater.with_quorum(Quorum::new(
+            rpc.clone(),
+            quorum_size,
+            args.quorum_tolerance_bps,
+            alerter.clone(),
+        ));
+    }
+    let price_sources = resolve_price_sources(&args, &file_config, &transport)?;
+    if let Some(price_sources) = price_sources {
+        updater = updater.with_price_sources(price_sources);
+    }
+
+    let (control, mut control_requests) = control::channel();
+    if let Some(admin_addr) = args.admin_addr {
+        let admin_token = args.admin_token.clone().unwrap_or_default();
+        admin::serve(admin_addr, admin_token, control.clone())
+            .await
+            .context(ErrorClass::Configuration)?;
+    }
+
+    if let Some(path) = &args.control_socket {
+        socket::serve(path, control.clone()).context(ErrorClass::Configuration)?;
+    }
+
+    let audit = match (args.audit_file.clone(), args.audit_signing_key) {
+        (Some(path), Some(key)) => Some(
+            AuditLog::new(path)
+                .with_signer(
+                    SigningKey::from_secret_scalar(key),
+                    args.audit_checkpoint_every,
+                )
+                .context(ErrorClass::Configuration)?,
+        ),
+        (path, _) => path.map(AuditLog::new),
+    };
+    let checkpoint =
+        BlockCheckpoint::load(args.last_block_file.clone()).context(ErrorClass::Configuration)?;
+    let mut daemon = Daemon {
+        updater,
+        rpc,
+        alerter,
+        balance_monitor,
+        upgrade_watcher,
+        history,
+        checkpoint,
+        events,
+        audit,
+        crash,
+        candidate,
+        live,
+        options: CycleOptions {
+            max_calls_per_tx: args.max_calls_per_tx,
+            max_txs_per_cycle: args.max_txs_per_cycle,
+        },
+    };
+
+    // Without a lock every instance leads; the unused sender keeps the channel open
+    let (_leader, mut leadership) = tokio::sync::watch::channel(true);
+    if let Some(url) = &args.leader_lock_url {
+        let lock = LeaderLock::connect(
+            url,
+            args.leader_lock_key.clone(),
+            Duration::from_secs(args.leader_lock_ttl_secs),
+        )
+        .await
+        .context(ErrorClass::Configuration)?;
+        leadership = lock.start().await;
+    }
+    daemon.updater.set_leader(*leadership.borrow_and_update());
+
+    if let Some(replay_from) = args.replay_from {
+        daemon.replay(replay_from, args.replay_mode).await?;
+    }
+
+    let shutdown = async {
+        match &dashboard {
+            Some(dashboard) => tokio::select! {
+                _ = shutdown_signal() => {}
+                _ = dashboard.closed() => {}
+            },
+            None => shutdown_signal().await,
+        }
+    };
+    tokio::pin!(shutdown);
+    let mut hangup = signal(SignalKind::hangup()).context(ErrorClass::Configuration)?;
+    let mut user1 = signal(SignalKind::user_defined1()).context(ErrorClass::Configuration)?;
+    // Fallback checks while no new heads arrive; every processed head pushes it back
+    let check_interval = args.check_interval_secs.map(Duration::from_secs);
+    let check_period = check_interval.unwrap_or(Duration::from_secs(60));
+    let mut check_timer =
+        tokio::time::interval_at(tokio::time::Instant::now() + check_period, check_period);
+    check_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut notifier = Notifier::from_env();
+    let watchdog_interval = notifier.watchdog_interval();
+    let mut watchdog = tokio::time::interval(watchdog_interval.unwrap_or(Duration::from_secs(60)));
+
+    // The WebSocket is read by its own task, which hands heads and statuses over here
+    let pending = daemon
+        .updater
+        .pending_updates()
+        .iter()
+        .map(|pending| pending.tx_hash)
+        .collect::<Vec<_>>();
+    let (mut listener, mut events) = Listener {
+        urls: args.websocket_url.clone(),
+        transport: transport.clone(),
+        network: args.network,
+        rpc: daemon.rpc.clone(),
+        chain_metadata,
+        ping_interval: Duration::from_secs(args.ws_ping_interval_secs),
+        stall_timeout: Duration::from_secs(args.ws_stall_timeout_secs),
+        price_event,
+        pools: pools(privacy_pool_address, daemon.updater.components()),
+    }
+    .spawn(ws_stream, ws_index, pending, restarts);
+    let mut updater_guard = PanicGuard::new("updater", args.task_max_restarts);
+
+    loop {
+        tokio::select! {
+            _ = &mut shutdown => {
+                info!("🛑 Shutting down");
+                notifier.stopping();
+                // Unblocks a listener waiting on a full queue
+                drop(events);
+                return listener.shutdown().await;
+            }
+            result = listener.finished() => return result,
+            Some(event) = events.recv() => {
+                let mut price_events = Vec::new();
+                let mut catch_up = false;
+                let mut head = on_node_event(
+                    event,
+                    &mut daemon,
+                    &mut notifier,
+                    &mut price_events,
+                    &mut catch_up,
+                );
+                // Heads that queued up during a slow update are superseded by the latest
+                while let Ok(event) = events.try_recv() {
+                    let next = on_node_event(
+                        event,
+                        &mut daemon,
+                        &mut notifier,
+                        &mut price_events,
+                        &mut catch_up,
+                    );
+                    if let Some(next) = next {
+                        if let Some(skipped) = head.as_ref().and_then(|head| head.block_number) {
+                            info!("⏭️ Skipping block {}, a newer head is waiting", skipped);
                         }
-                    } else if json_value.get("result").is_some() {
-                        info!("✅ WebSocket subscription confirmed");
-                    } else if let Some(error) = json_value.get("error") {
-                        error!("❌ WebSocket JSON-RPC error: {}", error);
+                        head = Some(next);
                     }
                 }
+                for price_event in price_events {
+                    let cycle = daemon.on_price_event(price_event);
+                    listener.watch(updater_guard.run(cycle).await?.unwrap_or_default());
+                }
+                let Some(head) = head else {
+                    // A head that came along is checked anyway and reconciles just the same
+                    if catch_up {
+                        check_timer.reset();
+                        let cycle = daemon.catch_up();
+                        listener.watch(updater_guard.run(cycle).await?.unwrap_or_default());
+                    }
+                    continue;
+                };
+                check_timer.reset();
+                let cycle = daemon.process_block(head.block_number, head.prices, Some(head.timer));
+                listener.watch(updater_guard.run(cycle).await?.unwrap_or_default());
+            }
+            Some(request) = control_requests.recv() => {
+                // A panicked command drops the reply, which the client sees as an error
+                let cycle = daemon.control(request.command);
+                if let Some((response, sent)) = updater_guard.run(cycle).await? {
+                    listener.watch(sent);
+                    let _ = request.reply.send(response);
+                }
+            }
+            // Petted from the updater loop, so a stuck update gets the service restarted
+            _ = watchdog.tick(), if watchdog_interval.is_some() => notifier.watchdog(),
+            _ = hangup.recv() => reload_config(&args, &transport, &mut daemon),
+            _ = user1.recv() => {
+                let paused = !daemon.updater.is_paused();
+                daemon.set_paused(paused);
+            }
+            _ = check_timer.tick(), if check_interval.is_some() => {
+                warn!(
+                    "⏰ No new head for {:?}, checking the fee anyway",
+                    check_period
+                );
+                let cycle = daemon.process_block(None, None, None);
+                listener.watch(updater_guard.run(cycle).await?.unwrap_or_default());
             }
-            Ok(Message::Close(_)) => {
-                warn!("WebSocket connection closed by server");
-                break;
+            Ok(()) = leadership.changed() => {
+                daemon.updater.set_leader(*leadership.borrow_and_update());
             }
-            Ok(Message::Ping(data)) => {
-                write.send(Message::Pong(data)).await?;
+        }
+    }
+}
+
+// Applies a status or connection event right away; heads are returned for a fee check and
+// price updates collected for reconciliation
+fn on_node_event(
+    event: NodeEvent,
+    daemon: &mut Daemon<'_>,
+    notifier: &mut Notifier,
+    price_events: &mut Vec<PriceEvent>,
+    catch_up: &mut bool,
+) -> Option<Head> {
+    match event {
+        NodeEvent::NewHead(head) => return Some(head),
+        NodeEvent::PriceChanged(price_event) => price_events.push(price_event),
+        NodeEvent::TransactionStatus(tx_hash, status) => {
+            daemon.updater.on_transaction_status(tx_hash, status);
+        }
+        NodeEvent::Subscribed(url) => {
+            *catch_up = true;
+            daemon.live.on_subscription(Subscription::Subscribed {
+                url: url.to_string(),
+                since: unix_timestamp(),
+            });
+            notifier.ready(&format!("Following new heads on {url}"));
+        }
+        NodeEvent::Reconnecting => {
+            daemon.live.on_subscription(Subscription::Reconnecting {
+                since: unix_timestamp(),
+            });
+            notifier.status("Reconnecting the WebSocket");
+        }
+    }
+    None
+}
+
+// Resolves on Ctrl-C or SIGTERM
+async fn shutdown_signal() {
+    let terminate = async {
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
             }
-            Ok(_) => {}
             Err(e) => {
-                error!("WebSocket error: {}", e);
-                break;
+                error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
             }
         }
+    };
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate => {}
     }
+}
 
-    info!("WebSocket connection terminated");
-    Ok(())
+// Re-reads the config file on SIGHUP; a config that fails to load keeps the running one
+fn reload_config(args: &Args, transport: &TransportOptions, daemon: &mut Daemon<'_>) {
+    let Some(path) = &args.config else {
+        warn!("🔄 SIGHUP received without a CONFIG_FILE, nothing to reload");
+        return;
+    };
+    info!("🔄 Reloading {}", path.display());
+    let reloaded = load_file_config(args).and_then(|file_config| {
+        let components = resolve_components(args, &file_config)?;
+        let price_sources = resolve_price_sources(args, &file_config, transport)?;
+        Ok((components, price_sources))
+    });
+    match reloaded {
+        Ok((components, price_sources)) => daemon.reload(components, price_sources),
+        Err(e) => error!(
+            "Failed to reload {}, keeping the running config: {:?}",
+            path.display(),
+            e
+        ),
+    }
+}
+
+fn default_strategy(args: &Args) -> ThresholdStrategy {
+    ThresholdStrategy {
+        upward_threshold: args.upward_threshold,
+        downward_threshold: args.downward_threshold,
+        upward_buffer: args.upward_buffer,
+        downward_buffer: args.downward_buffer,
+        max_change_percent: args.max_change_percent,
+        min_price: args.min_gas_price,
+        max_price: args.max_gas_price,
+    }
+}
+
+fn load_file_config(args: &Args) -> anyhow::Result<FileConfig> {
+    Ok(match &args.config {
+        Some(path) => FileConfig::load(path).context(ErrorClass::Configuration)?,
+        None => FileConfig::default(),
+    })
+}
+
+// Components of the config file, or the ones configured on the command line
+fn resolve_components(
+    args: &Args,
+    file_config: &FileConfig,
+) -> Result<Vec<FeeComponent>, UpdaterError> {
+    let default_strategy = default_strategy(args);
+    if !file_config.components.is_empty() {
+        return file_config.resolve_components(default_strategy);
+    }
+
+    let l1_gas = ComponentConfig {
+        encoding: args.price_encoding,
+        ..ComponentConfig::builtin(
+            "l1_gas",
+            PriceSource::L1Gas,
+            &args.l1_gas_getter,
+            &args.l1_gas_setter,
+        )
+    };
+    let mut components = vec![l1_gas.resolve(default_strategy)?];
+    if args.track_l1_data_gas {
+        let l1_data_gas = ComponentConfig {
+            encoding: args.price_encoding,
+            upward_threshold: args.l1_data_gas_upward_threshold,
+            downward_threshold: args.l1_data_gas_downward_threshold,
+            upward_buffer: args.l1_data_gas_upward_buffer,
+            downward_buffer: args.l1_data_gas_downward_buffer,
+            ..ComponentConfig::builtin(
+                "l1_data_gas",
+                PriceSource::L1DataGas,
+                &args.l1_data_gas_getter,
+                &args.l1_data_gas_setter,
+            )
+        };
+        components.push(l1_data_gas.resolve(default_strategy)?);
+    }
+    if args.track_l2_gas {
+        let l2_gas = ComponentConfig {
+            encoding: args.price_encoding,
+            upward_threshold: args.l2_gas_upward_threshold,
+            downward_threshold: args.l2_gas_downward_threshold,
+            upward_buffer: args.l2_gas_upward_buffer,
+            downward_buffer: args.l2_gas_downward_buffer,
+            ..ComponentConfig::builtin(
+                "l2_gas",
+                PriceSource::L2Gas,
+                &args.l2_gas_getter,
+                &args.l2_gas_setter,
+            )
+        };
+        components.push(l2_gas.resolve(default_strategy)?);
+    }
+    Ok(components)
+}
+
+// Gas price sources next to the block prices, from the config file or the ETH_RPC_URL
+// shorthand
+fn resolve_price_sources(
+    args: &Args,
+    file_config: &FileConfig,
+    transport: &TransportOptions,
+) -> anyhow::Result<Option<CompositeSource>> {
+    Ok(match (&file_config.price_sources, &args.eth_rpc_url) {
+        (Some(_), Some(_)) => {
+            return Err(anyhow::anyhow!(
+                "ETH_RPC_URL cannot be combined with price_sources in the config file; list an ethereum source there instead"
+            )
+            .context(ErrorClass::Configuration));
+        }
+        (Some(price_sources), None) => {
+            info!(
+                "⛽ Combining {} price source(s) with {:?}",
+                price_sources.sources.len(),
+                price_sources.combine
+            );
+            let client = transport.http_client().context(ErrorClass::Configuration)?;
+            Some(price_sources.resolve(&client)?)
+        }
+        // The Ethereum fees weigh in next to the block prices
+        (None, Some(eth_rpc_url)) => {
+            info!(
+                "⛽ Blending Ethereum fees from {} into the L1 prices at {}%",
+                eth_rpc_url, args.eth_gas_weight
+            );
+            let weight = u128::from(args.eth_gas_weight);
+            let client = transport.http_client().context(ErrorClass::Configuration)?;
+            Some(
+                CompositeSource::new(Combinator::Weighted, Some(100 - weight)).with_source(
+                    Box::new(EthGasOracle::new(client, eth_rpc_url.clone())),
+                    weight,
+                ),
+            )
+        }
+        (None, None) => None,
+    })
 }
@@ -0,0 +1,67 @@
+use starknet::{
+    core::types::Felt,
+    providers::{Provider, ProviderError},
+};
+use tracing::{info, warn};
+
+use crate::endpoints::EndpointPool;
+
+// Chain facts that never change for a given endpoint, fetched once instead of per call
+#[derive(Debug, Clone)]
+pub struct ChainMetadata {
+    pub chain_id: Felt,
+    pub spec_version: String,
+    // Endpoint of the pool the values were read from
+    endpoint: usize,
+}
+
+impl ChainMetadata {
+    // Read from the endpoint calls currently go to; a failed read marks it unhealthy, so the
+    // next attempt goes to another one
+    pub async fn fetch(rpc: &EndpointPool) -> Result<Self, ProviderError> {
+        let mut attempts = rpc.urls().len();
+        loop {
+            let endpoint = rpc.current();
+            match Self::fetch_from(rpc, endpoint).await {
+                Ok(metadata) => return Ok(metadata),
+                Err(e) if attempts > 1 => {
+                    warn!(
+                        "Failed to read the chain metadata via {}: {}",
+                        rpc.label(endpoint),
+                        e
+                    );
+                    attempts -= 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    async fn fetch_from(rpc: &EndpointPool, endpoint: usize) -> Result<Self, ProviderError> {
+        let provider = rpc.pinned_client(endpoint);
+        let metadata = Self {
+            chain_id: provider.chain_id().await?,
+            spec_version: provider.spec_version().await?,
+            endpoint,
+        };
+        info!(
+            "🔗 Chain id {:#x}, RPC spec version {} via {}",
+            metadata.chain_id,
+            metadata.spec_version,
+            rpc.label(endpoint)
+        );
+        Ok(metadata)
+    }
+
+    // Keep the cached values unless calls moved to another endpoint since they were fetched
+    pub async fn refresh_on_reconnect(
+        &mut self,
+        rpc: &EndpointPool,
+    ) -> Result<bool, ProviderError> {
+        if self.endpoint == rpc.current() {
+            return Ok(false);
+        }
+        *self = Self::fetch(rpc).await?;
+        Ok(true)
+    }
+}
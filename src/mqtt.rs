@@ -0,0 +1,134 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Context;
+use rumqttc::{AsyncClient, MqttOptions, QoS};
+use tokio::sync::{mpsc, Mutex};
+use tracing::{error, info};
+use url::Url;
+
+use crate::errors::ErrorClass;
+use crate::events::FeeEvent;
+use crate::retry::BackoffPolicy;
+use crate::supervisor;
+
+const DEFAULT_PORT: u16 = 1883;
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+// Messages the client buffers before the event loop sends them
+const CLIENT_CAPACITY: usize = 256;
+
+struct Message {
+    topic: String,
+    payload: Vec<u8>,
+    // Prices are retained, so a subscriber gets the latest value as soon as it connects
+    retain: bool,
+}
+
+// Publishes the prices of every check and the updates to an MQTT broker:
+// - `<prefix>/<component>/contract_price` and `<prefix>/<component>/network_price`,
+//   retained, as plain numbers
+// - `<prefix>/updates`: the transaction events, as JSON
+#[derive(Debug, Clone)]
+pub struct MqttPublisher {
+    prefix: String,
+    messages: mpsc::UnboundedSender<Message>,
+}
+
+impl MqttPublisher {
+    // `url` is `mqtt://[user:password@]host[:port]`
+    pub fn new(
+        url: Url,
+        client_id: String,
+        prefix: String,
+        restarts: BackoffPolicy,
+    ) -> anyhow::Result<Self> {
+        let options = mqtt_options(&url, &client_id).context(ErrorClass::Configuration)?;
+        info!(
+            "📡 Publishing prices to MQTT {} under `{}`",
+            url.host_str().unwrap_or_default(),
+            prefix
+        );
+
+        let (messages, pending) = mpsc::unbounded_channel::<Message>();
+        // Messages queued while the broker is unreachable wait for the reconnect
+        let pending = Arc::new(Mutex::new(pending));
+        supervisor::supervise("mqtt publisher", restarts, move || {
+            let pending = pending.clone();
+            let options = options.clone();
+            async move {
+                let (client, mut eventloop) = AsyncClient::new(options, CLIENT_CAPACITY);
+                let mut pending = pending.lock().await;
+                // The event loop does the sending, so it is polled alongside the queue and
+                // messages are handed over without waiting
+                loop {
+                    tokio::select! {
+                        notification = eventloop.poll() => {
+                            notification?;
+                        }
+                        message = pending.recv() => {
+                            let Some(message) = message else {
+                                return Ok(());
+                            };
+                            let published = client.try_publish(
+                                message.topic,
+                                QoS::AtLeastOnce,
+                                message.retain,
+                                message.payload,
+                            );
+                            if let Err(e) = published {
+                                error!("Failed to publish to MQTT: {:?}", e);
+                            }
+                        }
+                    }
+                }
+            }
+        });
+        Ok(Self { prefix, messages })
+    }
+
+    pub fn publish(&self, event: &FeeEvent) {
+        match event {
+            FeeEvent::Decision(decision) => {
+                let component = format!("{}/{}", self.prefix, decision.component);
+                self.send(
+                    format!("{component}/contract_price"),
+                    decision.contract_gas_price.to_string().into_bytes(),
+                    true,
+                );
+                self.send(
+                    format!("{component}/network_price"),
+                    decision.network_gas_price.to_string().into_bytes(),
+                    true,
+                );
+            }
+            FeeEvent::TransactionSent(_) | FeeEvent::TransactionResolved { .. } => {
+                match serde_json::to_vec(event) {
+                    Ok(payload) => self.send(format!("{}/updates", self.prefix), payload, false),
+                    Err(e) => error!("Failed to serialize an update event: {:?}", e),
+                }
+            }
+        }
+    }
+
+    fn send(&self, topic: String, payload: Vec<u8>, retain: bool) {
+        let _ = self.messages.send(Message {
+            topic,
+            payload,
+            retain,
+        });
+    }
+}
+
+fn mqtt_options(url: &Url, client_id: &str) -> anyhow::Result<MqttOptions> {
+    if url.scheme() != "mqtt" {
+        anyhow::bail!("Unsupported MQTT URL scheme {}", url.scheme());
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| anyhow::anyhow!("MQTT URL {} has no host", url))?;
+    let mut options = MqttOptions::new(client_id, host, url.port().unwrap_or(DEFAULT_PORT));
+    options.set_keep_alive(KEEP_ALIVE);
+    if !url.username().is_empty() {
+        options.set_credentials(url.username(), url.password().unwrap_or_default());
+    }
+    Ok(options)
+}
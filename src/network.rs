@@ -0,0 +1,230 @@
+use std::{fmt, str::FromStr};
+
+use starknet::{
+    core::{chain_id, types::Felt, utils::cairo_short_string_to_felt},
+    providers::Provider,
+};
+use tracing::{info, warn};
+use url::Url;
+
+use crate::endpoints::EndpointPool;
+use crate::errors::ErrorClass;
+use crate::preflight::{ws_chain_id, WsStream};
+
+// The network the operator expects the configured endpoints to serve
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Network {
+    Mainnet,
+    Sepolia,
+    Custom(Felt),
+}
+
+impl Network {
+    pub fn chain_id(&self) -> Felt {
+        match self {
+            Network::Mainnet => chain_id::MAINNET,
+            Network::Sepolia => chain_id::SEPOLIA,
+            Network::Custom(chain_id) => *chain_id,
+        }
+    }
+}
+
+// Public endpoints and chain of a well-known network, each overridable by its own flag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Preset {
+    Mainnet,
+    Sepolia,
+}
+
+impl Preset {
+    pub fn network(self) -> Network {
+        match self {
+            Preset::Mainnet => Network::Mainnet,
+            Preset::Sepolia => Network::Sepolia,
+        }
+    }
+
+    fn path(self) -> &'static str {
+        match self {
+            Preset::Mainnet => "mainnet-juno/v0_8",
+            Preset::Sepolia => "sepolia-juno/v0_8",
+        }
+    }
+
+    // Nethermind's free public nodes, rate limited and fine for a single updater
+    pub fn api_url(self) -> Url {
+        Url::parse(&format!("https://free-rpc.nethermind.io/{}", self.path()))
+            .expect("preset URLs are valid")
+    }
+
+    pub fn websocket_url(self) -> Url {
+        Url::parse(&format!("wss://free-rpc.nethermind.io/{}", self.path()))
+            .expect("preset URLs are valid")
+    }
+}
+
+impl FromStr for Network {
+    type Err = String;
+
+    // Accepts `mainnet`, `sepolia`, a hex chain id or a short string like `SN_MAIN`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "mainnet" => Ok(Network::Mainnet),
+            "sepolia" => Ok(Network::Sepolia),
+            _ if s.starts_with("0x") => Felt::from_hex(s)
+                .map(Network::Custom)
+                .map_err(|e| format!("invalid chain id `{s}`: {e}")),
+            _ => cairo_short_string_to_felt(s)
+                .map(Network::Custom)
+                .map_err(|e| format!("invalid chain id `{s}`: {e}")),
+        }
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Network::Mainnet => write!(f, "mainnet"),
+            Network::Sepolia => write!(f, "sepolia"),
+            Network::Custom(chain_id) => write!(f, "{chain_id:#x}"),
+        }
+    }
+}
+
+// Abort unless every endpoint serves the expected network
+pub async fn verify_network(
+    expected: Network,
+    rpc: &EndpointPool,
+    ws_stream: &mut WsStream,
+) -> anyhow::Result<()> {
+    verify_rpc_endpoints(rpc, expected.chain_id()).await?;
+    let ws_chain_id = ws_chain_id(ws_stream).await?;
+    if ws_chain_id != expected.chain_id() {
+        return Err(anyhow::anyhow!(
+            "WebSocket endpoint serves chain id {:#x} but --network expects {} ({:#x}), refusing to sign transactions for the wrong chain",
+            ws_chain_id,
+            expected,
+            expected.chain_id()
+        )
+        .context(ErrorClass::Configuration));
+    }
+
+    info!("🌐 Endpoints serve the expected network: {}", expected);
+    Ok(())
+}
+
+// Every RPC endpoint is asked directly, since failover would hide one serving another chain
+// behind the one answering. One that cannot be reached is left to the health probe, which
+// checks its chain before it rejoins the pool.
+pub async fn verify_rpc_endpoints(rpc: &EndpointPool, chain_id: Felt) -> anyhow::Result<()> {
+    for endpoint in 0..rpc.urls().len() {
+        match rpc.pinned_client(endpoint).chain_id().await {
+            Ok(served) if served == chain_id => {}
+            Ok(served) => {
+                return Err(anyhow::anyhow!(
+                    "RPC endpoint {} serves chain id {:#x} but {:#x} is expected, refusing to sign transactions for the wrong chain",
+                    rpc.label(endpoint),
+                    served,
+                    chain_id
+                )
+                .context(ErrorClass::Configuration));
+            }
+            Err(e) => warn!(
+                "Failed to read the chain id of RPC endpoint {}: {}",
+                rpc.label(endpoint),
+                e
+            ),
+        }
+    }
+    rpc.expect_chain_id(chain_id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::fixtures::{transport_options, ws_node, MockNode};
+    use crate::transport::RetryPolicy;
+    use crate::websocket::connect_one;
+
+    async fn node(chain_id: &str) -> MockNode {
+        let node = MockNode::start().await;
+        node.replay("starknet_chainId", chain_id).await;
+        node
+    }
+
+    async fn ws(chain_id: Felt) -> WsStream {
+        let url = ws_node(json!(format!("{chain_id:#x}"))).await;
+        connect_one(&url, &transport_options(RetryPolicy::default()))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn every_rpc_endpoint_is_asked_for_its_chain() {
+        let sepolia = node("chain_id_sepolia").await;
+        let mainnet = node("chain_id_mainnet").await;
+        let rpc = MockNode::pool(&[&sepolia, &mainnet], RetryPolicy::default());
+
+        let error = verify_rpc_endpoints(&rpc, Network::Sepolia.chain_id())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<ErrorClass>(),
+            Some(&ErrorClass::Configuration)
+        );
+        assert_eq!(sepolia.requests("starknet_chainId").await.len(), 1);
+        assert_eq!(mainnet.requests("starknet_chainId").await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn unreachable_rpc_endpoint_is_left_to_the_probe() {
+        let sepolia = node("chain_id_sepolia").await;
+        let unreachable = Url::parse("http://127.0.0.1:1").unwrap();
+        let rpc = EndpointPool::new(
+            &[sepolia.url(), unreachable],
+            &transport_options(RetryPolicy::default()),
+        )
+        .unwrap();
+
+        verify_rpc_endpoints(&rpc, Network::Sepolia.chain_id())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn websocket_must_serve_the_network_too() {
+        let sepolia = node("chain_id_sepolia").await;
+        let rpc = MockNode::pool(&[&sepolia], RetryPolicy::default());
+
+        let mut ws_stream = ws(chain_id::SEPOLIA).await;
+        verify_network(Network::Sepolia, &rpc, &mut ws_stream)
+            .await
+            .unwrap();
+
+        let mut ws_stream = ws(chain_id::MAINNET).await;
+        let error = verify_network(Network::Sepolia, &rpc, &mut ws_stream)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<ErrorClass>(),
+            Some(&ErrorClass::Configuration)
+        );
+    }
+
+    #[test]
+    fn network_accepts_names_hex_and_short_strings() {
+        assert_eq!("Mainnet".parse::<Network>(), Ok(Network::Mainnet));
+        assert_eq!(
+            "SN_SEPOLIA".parse::<Network>(),
+            Ok(Network::Custom(chain_id::SEPOLIA))
+        );
+        assert_eq!(
+            "0x534e5f4d41494e".parse::<Network>(),
+            Ok(Network::Custom(chain_id::MAINNET))
+        );
+        assert!("0xnot-hex".parse::<Network>().is_err());
+    }
+}
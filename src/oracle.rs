@@ -0,0 +1,101 @@
+use starknet::{
+    core::{
+        types::{BlockId, BlockTag, Felt, FunctionCall},
+        utils::{cairo_short_string_to_felt, get_selector_from_name},
+    },
+    providers::Provider,
+};
+
+use crate::endpoints::RpcClient;
+use crate::history::unix_timestamp;
+use crate::updater::UpdaterError;
+
+// `DataType::SpotEntry` variant index in the Pragma oracle calldata
+const SPOT_ENTRY: Felt = Felt::ZERO;
+
+// Median spot price of a pair as aggregated by a Pragma oracle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PragmaPrice {
+    pub price: u128,
+    pub decimals: u32,
+    pub last_updated_timestamp: u64,
+}
+
+// Pragma oracle contract on Starknet, queried for a single pair such as `STRK/USD`
+#[derive(Debug, Clone)]
+pub struct PragmaOracle {
+    pub address: Felt,
+    pub pair: String,
+    pair_id: Felt,
+    // Reject prices older than this, in seconds
+    max_age_secs: Option<u64>,
+}
+
+impl PragmaOracle {
+    pub fn new(address: Felt, pair: &str, max_age_secs: Option<u64>) -> Result<Self, UpdaterError> {
+        let pair_id = cairo_short_string_to_felt(pair).map_err(|e| {
+            UpdaterError::Configuration(format!("Invalid Pragma pair `{pair}`: {e}"))
+        })?;
+        Ok(Self {
+            address,
+            pair: pair.to_string(),
+            pair_id,
+            max_age_secs,
+        })
+    }
+
+    pub async fn spot_median(&self, provider: &RpcClient) -> Result<PragmaPrice, UpdaterError> {
+        let result = provider
+            .call(
+                FunctionCall {
+                    calldata: vec![SPOT_ENTRY, self.pair_id],
+                    contract_address: self.address,
+                    entry_point_selector: get_selector_from_name("get_data_median").map_err(
+                        |e| UpdaterError::Configuration(format!("Invalid selector: {e}")),
+                    )?,
+                },
+                BlockId::Tag(BlockTag::Latest),
+            )
+            .await?;
+
+        // PragmaPricesResponse: price, decimals, last_updated_timestamp, num_sources_aggregated, ...
+        let [price, decimals, last_updated_timestamp, num_sources, ..] = result.as_slice() else {
+            return Err(UpdaterError::Oracle(format!(
+                "Unexpected get_data_median result length for {}: {}",
+                self.pair,
+                result.len()
+            )));
+        };
+        let price = PragmaPrice {
+            price: felt_to(*price, &self.pair, "price")?,
+            decimals: felt_to(*decimals, &self.pair, "decimals")?,
+            last_updated_timestamp: felt_to(*last_updated_timestamp, &self.pair, "timestamp")?,
+        };
+
+        if *num_sources == Felt::ZERO || price.price == 0 {
+            return Err(UpdaterError::Oracle(format!(
+                "Pragma has no price for {}",
+                self.pair
+            )));
+        }
+        if let Some(max_age_secs) = self.max_age_secs {
+            let age = unix_timestamp().saturating_sub(price.last_updated_timestamp);
+            if age > max_age_secs {
+                return Err(UpdaterError::Oracle(format!(
+                    "Pragma price for {} is {}s old (max {}s)",
+                    self.pair, age, max_age_secs
+                )));
+            }
+        }
+        Ok(price)
+    }
+}
+
+fn felt_to<T: TryFrom<u128>>(value: Felt, pair: &str, field: &str) -> Result<T, UpdaterError> {
+    value
+        .to_biguint()
+        .try_into()
+        .ok()
+        .and_then(|value: u128| T::try_from(value).ok())
+        .ok_or_else(|| UpdaterError::Oracle(format!("Pragma {pair} {field} out of range")))
+}
@@ -0,0 +1,101 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
+
+// Who wrote a contract value this updater did not send
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeOrigin {
+    // Sent from one of the operator accounts
+    Manual,
+    // Sent from one of our sender accounts by something else holding its key, such as a
+    // script or another updater
+    Automation,
+    // Sent from an account with no business writing the price
+    Attack,
+    // Noticed by a read, so the sender is not known
+    Unknown,
+}
+
+impl ChangeOrigin {
+    pub fn classify(sender: Option<Felt>, senders: &[Felt], operators: &[Felt]) -> Self {
+        match sender {
+            None => ChangeOrigin::Unknown,
+            Some(sender) if operators.contains(&sender) => ChangeOrigin::Manual,
+            Some(sender) if senders.contains(&sender) => ChangeOrigin::Automation,
+            Some(_) => ChangeOrigin::Attack,
+        }
+    }
+}
+
+impl fmt::Display for ChangeOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ChangeOrigin::Manual => "manual override",
+            ChangeOrigin::Automation => "other automation",
+            ChangeOrigin::Attack => "possible attack",
+            ChangeOrigin::Unknown => "unknown origin",
+        })
+    }
+}
+
+// How outside price changes are treated: writes from `operators` count as manual
+// overrides, and with a `grace` period such a value is kept that long before our target is
+// re-applied
+#[derive(Debug, Clone, Default)]
+pub struct OverridePolicy {
+    pub operators: Vec<Felt>,
+    pub grace: Option<Duration>,
+}
+
+#[derive(Debug, Default)]
+pub struct OverrideTracker {
+    policy: OverridePolicy,
+    // When each held component was changed from outside
+    changed: HashMap<String, Instant>,
+}
+
+impl OverrideTracker {
+    pub fn new(policy: OverridePolicy) -> Self {
+        Self {
+            policy,
+            changed: HashMap::new(),
+        }
+    }
+
+    pub fn classify(&self, sender: Option<Felt>, senders: &[Felt]) -> ChangeOrigin {
+        ChangeOrigin::classify(sender, senders, &self.policy.operators)
+    }
+
+    // Values that look like an attack are corrected right away, the others are held
+    pub fn on_outside_change(&mut self, component: &str, origin: ChangeOrigin) {
+        if self.policy.grace.is_some() && origin != ChangeOrigin::Attack {
+            self.changed.insert(component.to_string(), Instant::now());
+        }
+    }
+
+    // What is left of the component's grace period, if it is held
+    pub fn remaining(&self, component: &str) -> Option<Duration> {
+        let changed = self.changed.get(component)?;
+        let left = self.policy.grace?.saturating_sub(changed.elapsed());
+        (!left.is_zero()).then_some(left)
+    }
+
+    // Whether the grace period of the component just ran out; true only once
+    pub fn take_expired(&mut self, component: &str) -> bool {
+        if self.changed.contains_key(component) && self.remaining(component).is_none() {
+            self.changed.remove(component);
+            return true;
+        }
+        false
+    }
+
+    pub fn retain(&mut self, keep: impl Fn(&str) -> bool) {
+        self.changed.retain(|component, _| keep(component));
+    }
+}
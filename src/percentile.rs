@@ -0,0 +1,154 @@
+use std::collections::{HashMap, VecDeque};
+
+use starknet_types_core::felt::Felt;
+use tracing::info;
+
+use crate::logging::BLOCKS;
+
+// Price a component on a percentile of its recent network prices instead of the latest
+// one, for user fees that stay put yet sit safely above the typical price
+#[derive(Debug, Clone, Copy)]
+pub struct PercentileTarget {
+    // 1 to 100
+    pub percentile: u8,
+    // Readings the percentile is taken over
+    pub window: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct PercentileTracker {
+    policy: Option<PercentileTarget>,
+    readings: HashMap<String, VecDeque<u128>>,
+}
+
+impl PercentileTracker {
+    pub fn new(policy: PercentileTarget) -> Self {
+        Self {
+            policy: Some(policy),
+            readings: HashMap::new(),
+        }
+    }
+
+    // Records the reading and returns the percentile of the window, the reading itself
+    // when no target is set. Until the window fills it covers the readings so far.
+    pub fn target(&mut self, component: &str, price: Felt) -> Felt {
+        let Some(policy) = self.policy else {
+            return price;
+        };
+        let Ok(reading) = u128::try_from(price.to_biguint()) else {
+            return price;
+        };
+        let readings = self.readings.entry(component.to_string()).or_default();
+        readings.push_back(reading);
+        while readings.len() > policy.window.max(1) {
+            readings.pop_front();
+        }
+
+        let mut sorted: Vec<u128> = readings.iter().copied().collect();
+        sorted.sort_unstable();
+        // Nearest rank
+        let rank = (usize::from(policy.percentile) * sorted.len()).div_ceil(100);
+        let target = sorted[rank.clamp(1, sorted.len()) - 1];
+        info!(
+            target: BLOCKS,
+            "[{}] p{} of the last {} network price(s): {} (latest {})",
+            component,
+            policy.percentile,
+            sorted.len(),
+            target,
+            reading
+        );
+        Felt::from(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker(percentile: u8, window: usize) -> PercentileTracker {
+        PercentileTracker::new(PercentileTarget { percentile, window })
+    }
+
+    fn feed(tracker: &mut PercentileTracker, readings: &[u64]) -> Vec<Felt> {
+        readings
+            .iter()
+            .map(|reading| tracker.target("l2_gas", Felt::from(*reading)))
+            .collect()
+    }
+
+    #[test]
+    fn without_a_target_the_reading_is_used() {
+        let mut tracker = PercentileTracker::default();
+        assert_eq!(
+            feed(&mut tracker, &[30, 10]),
+            vec![Felt::from(30u8), Felt::from(10u8)]
+        );
+    }
+
+    #[test]
+    fn single_sample_is_its_own_percentile() {
+        for percentile in [0, 1, 50, 100] {
+            let mut tracker = tracker(percentile, 10);
+            assert_eq!(feed(&mut tracker, &[42]), vec![Felt::from(42u8)]);
+        }
+    }
+
+    #[test]
+    fn percentile_bounds_pick_the_lowest_and_highest_reading() {
+        let readings = [40, 10, 30, 20];
+        let lowest = feed(&mut tracker(0, 10), &readings);
+        assert_eq!(lowest.last(), Some(&Felt::from(10u8)));
+        let lowest = feed(&mut tracker(1, 10), &readings);
+        assert_eq!(lowest.last(), Some(&Felt::from(10u8)));
+        let highest = feed(&mut tracker(100, 10), &readings);
+        assert_eq!(highest.last(), Some(&Felt::from(40u8)));
+    }
+
+    #[test]
+    fn percentile_takes_the_nearest_rank() {
+        // Ranks 2 and 3 of 4
+        let median = feed(&mut tracker(50, 10), &[40, 10, 30, 20]);
+        assert_eq!(median.last(), Some(&Felt::from(20u8)));
+        let above = feed(&mut tracker(51, 10), &[40, 10, 30, 20]);
+        assert_eq!(above.last(), Some(&Felt::from(30u8)));
+    }
+
+    #[test]
+    fn window_covers_the_readings_so_far_until_it_fills() {
+        let mut tracker = tracker(100, 3);
+        assert_eq!(
+            feed(&mut tracker, &[50, 20, 10, 5]),
+            vec![
+                Felt::from(50u8),
+                Felt::from(50u8),
+                Felt::from(50u8),
+                // 50 has left the window
+                Felt::from(20u8),
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_window_still_holds_the_latest_reading() {
+        let mut tracker = tracker(100, 0);
+        assert_eq!(
+            feed(&mut tracker, &[50, 20]),
+            vec![Felt::from(50u8), Felt::from(20u8)]
+        );
+    }
+
+    #[test]
+    fn components_have_their_own_windows() {
+        let mut tracker = tracker(100, 10);
+        feed(&mut tracker, &[50]);
+        assert_eq!(tracker.target("l1_gas", Felt::from(20u8)), Felt::from(20u8));
+    }
+
+    #[test]
+    fn price_beyond_u128_passes_through() {
+        let mut tracker = tracker(50, 10);
+        let huge = Felt::from(u128::MAX) + Felt::ONE;
+        assert_eq!(tracker.target("l2_gas", huge), huge);
+    }
+}
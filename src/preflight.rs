@@ -0,0 +1,421 @@
+use starknet::{
+    core::{
+        types::{BlockId, BlockTag, ContractClass, Felt, FunctionCall},
+        utils::{cairo_short_string_to_felt, get_selector_from_name},
+    },
+    providers::{Provider, ProviderError},
+    signers::SigningKey,
+};
+use thiserror::Error;
+use tokio::net::TcpStream;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tracing::{debug, error, info, warn};
+
+use crate::components::{pools, FeeComponent};
+use crate::endpoints::{EndpointPool, RpcClient};
+use crate::errors::{classify_provider_error, ErrorClass};
+use crate::metadata::ChainMetadata;
+use crate::updater::{read_component_price, SenderKey};
+use crate::websocket;
+
+pub type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+// JSON-RPC id of the one-off chain id request over WS, sent before any subscription
+const WS_CHAIN_ID_REQUEST_ID: u64 = 0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum PreflightMode {
+    /// Refuse to start when any check fails
+    Strict,
+    /// Log failed checks and start anyway
+    Warn,
+    /// Do not run the checks
+    Skip,
+}
+
+#[derive(Error, Debug)]
+pub enum PreflightError {
+    #[error("No contract class deployed at pool address {0:#x}")]
+    PoolNotDeployed(Felt),
+    #[error("Getter of component `{component}` is not callable on the pool contract: {reason}")]
+    GetterNotCallable { component: String, reason: String },
+    #[error(
+        "Setter of component `{component}` is not an entry point of the pool contract {pool:#x}"
+    )]
+    SetterMissing { component: String, pool: Felt },
+    #[error("No account contract deployed at sender address {0:#x}; deploy the account before running the updater")]
+    SenderNotDeployed(Felt),
+    #[error("Contract at sender address {address:#x} is not an account (no `{missing}` entry point); configure the address of an ArgentX, Braavos or OpenZeppelin account")]
+    NotAnAccount {
+        address: Felt,
+        missing: &'static str,
+    },
+    #[error("Account {address:#x} has a guardian, which a single key cannot sign for; remove the guardian or use another account")]
+    GuardianSet { address: Felt },
+    #[error("Account {address:#x} is controlled by public key {account:#x}, but the configured private key belongs to {configured:#x}")]
+    KeyMismatch {
+        address: Felt,
+        account: Felt,
+        configured: Felt,
+    },
+    #[error("Sender key cannot produce a valid signature: {0}")]
+    Signer(String),
+    #[error("Account {0:#x} rejected a signature from the configured key")]
+    SignatureRejected(Felt),
+    #[error("WebSocket endpoint reports chain id {ws:#x} but HTTP endpoint reports {http:#x}")]
+    ChainIdMismatch { ws: Felt, http: Felt },
+    #[error("Could not query chain id over WebSocket: {0}")]
+    WsChainId(String),
+    #[error("Pool contract is owned by {contract_owner:#x}, not by the configured owner {configured:#x}; every update would revert")]
+    OwnerMismatch {
+        contract_owner: Felt,
+        configured: Felt,
+    },
+    #[error("Starknet provider error: {0}")]
+    Provider(#[from] ProviderError),
+}
+
+impl PreflightError {
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            PreflightError::Signer(_)
+            | PreflightError::SignatureRejected(_)
+            | PreflightError::GuardianSet { .. }
+            | PreflightError::KeyMismatch { .. } => ErrorClass::Signer,
+            PreflightError::WsChainId(_) => ErrorClass::Network,
+            PreflightError::Provider(e) => classify_provider_error(e),
+            PreflightError::PoolNotDeployed(_)
+            | PreflightError::GetterNotCallable { .. }
+            | PreflightError::SetterMissing { .. }
+            | PreflightError::SenderNotDeployed(_)
+            | PreflightError::NotAnAccount { .. }
+            | PreflightError::ChainIdMismatch { .. }
+            | PreflightError::OwnerMismatch { .. } => ErrorClass::Configuration,
+        }
+    }
+}
+
+// Run all startup checks and return the ones that failed
+pub async fn run_preflight(
+    rpc: &EndpointPool,
+    metadata: &ChainMetadata,
+    ws_stream: &mut WsStream,
+    contract_address: Felt,
+    components: &[FeeComponent],
+    senders: &[SenderKey],
+) -> Vec<PreflightError> {
+    let provider = rpc.client();
+    let mut failures = Vec::new();
+
+    for pool in pools(contract_address, components) {
+        match check_pool_deployed(&provider, pool).await {
+            Ok(()) => {
+                let on_pool: Vec<FeeComponent> = components
+                    .iter()
+                    .filter(|component| component.pool.unwrap_or(contract_address) == pool)
+                    .cloned()
+                    .collect();
+                failures.extend(check_pool_entry_points(&provider, pool, &on_pool).await);
+            }
+            Err(e) => failures.push(e),
+        }
+    }
+    for sender in senders {
+        if let Err(e) = check_sender_account(&provider, sender).await {
+            failures.push(e);
+        }
+    }
+    if let Err(e) = check_chain_ids(metadata.chain_id, ws_stream).await {
+        failures.push(e);
+    }
+
+    failures
+}
+
+// Log the outcome and decide whether startup may continue
+pub fn evaluate(mode: PreflightMode, failures: &[PreflightError]) -> anyhow::Result<()> {
+    if failures.is_empty() {
+        info!("✅ Preflight checks passed");
+        return Ok(());
+    }
+
+    for failure in failures {
+        match mode {
+            PreflightMode::Strict => error!("❌ Preflight check failed: {}", failure),
+            _ => warn!("⚠️ Preflight check failed: {}", failure),
+        }
+    }
+
+    match mode {
+        // The exit code follows the first failure
+        PreflightMode::Strict => Err(anyhow::anyhow!(
+            "{} preflight check(s) failed, refusing to start",
+            failures.len()
+        )
+        .context(failures[0].class())),
+        _ => Ok(()),
+    }
+}
+
+// Fail fast when the configured owner is not the pool contract's owner.
+// Contracts exposing neither `owner` nor `get_owner` are only warned about.
+pub async fn verify_contract_owner(
+    rpc: &EndpointPool,
+    contract_address: Felt,
+    owner_address: Felt,
+) -> Result<(), PreflightError> {
+    let provider = rpc.client();
+
+    for getter in ["owner", "get_owner"] {
+        let Ok(selector) = get_selector_from_name(getter) else {
+            continue;
+        };
+        let result = provider
+            .call(
+                FunctionCall {
+                    calldata: vec![],
+                    contract_address,
+                    entry_point_selector: selector,
+                },
+                BlockId::Tag(BlockTag::Latest),
+            )
+            .await;
+
+        let Some(contract_owner) = result.ok().and_then(|values| values.first().copied()) else {
+            continue;
+        };
+        if contract_owner != owner_address {
+            return Err(PreflightError::OwnerMismatch {
+                contract_owner,
+                configured: owner_address,
+            });
+        }
+        info!(
+            "✅ Configured owner matches the pool contract `{}()`",
+            getter
+        );
+        return Ok(());
+    }
+
+    warn!("⚠️ Pool contract exposes neither `owner()` nor `get_owner()`, cannot verify the configured owner");
+    Ok(())
+}
+
+async fn check_pool_deployed(
+    provider: &RpcClient,
+    contract_address: Felt,
+) -> Result<(), PreflightError> {
+    provider
+        .get_class_hash_at(BlockId::Tag(BlockTag::Latest), contract_address)
+        .await
+        .map_err(|_| PreflightError::PoolNotDeployed(contract_address))?;
+    Ok(())
+}
+
+// Getters and setters of the components living on `pool`; also re-run when the pool's
+// class changes, since an upgrade may rename them
+pub async fn check_pool_entry_points(
+    provider: &RpcClient,
+    pool: Felt,
+    components: &[FeeComponent],
+) -> Vec<PreflightError> {
+    let mut failures = Vec::new();
+    for component in components {
+        if let Err(e) = check_component_getter(provider, pool, component).await {
+            failures.push(e);
+        }
+    }
+    // Setters cannot be called without a transaction; Cairo 0 classes are not inspected
+    let Ok(ContractClass::Sierra(class)) = provider
+        .get_class_at(BlockId::Tag(BlockTag::Latest), pool)
+        .await
+    else {
+        return failures;
+    };
+    for component in components {
+        if !class
+            .entry_points_by_type
+            .external
+            .iter()
+            .any(|entry_point| entry_point.selector == component.setter)
+        {
+            failures.push(PreflightError::SetterMissing {
+                component: component.name.clone(),
+                pool,
+            });
+        }
+    }
+    failures
+}
+
+async fn check_component_getter(
+    provider: &RpcClient,
+    contract_address: Felt,
+    component: &FeeComponent,
+) -> Result<(), PreflightError> {
+    let not_callable = |reason: String| PreflightError::GetterNotCallable {
+        component: component.name.clone(),
+        reason,
+    };
+    let price = read_component_price(
+        provider,
+        contract_address,
+        component.getter,
+        &component.layout,
+    )
+    .await
+    .map_err(|e| not_callable(e.to_string()))?;
+    debug!(
+        "Preflight: pool contract returns {} price {}",
+        component.name, price
+    );
+    Ok(())
+}
+
+// Public key getters of the account families the updater can sign for: OpenZeppelin and
+// Braavos, then ArgentX (Cairo 1, Cairo 0)
+const PUBLIC_KEY_GETTERS: [&str; 4] = ["get_public_key", "getPublicKey", "get_owner", "getSigner"];
+const GUARDIAN_GETTERS: [&str; 2] = ["get_guardian", "getGuardian"];
+
+// The sender must be a deployed account a single STARK key can drive, otherwise every
+// update fails on signature validation with an opaque revert
+async fn check_sender_account(
+    provider: &RpcClient,
+    sender: &SenderKey,
+) -> Result<(), PreflightError> {
+    let address = sender.address;
+    let class = provider
+        .get_class_at(BlockId::Tag(BlockTag::Latest), address)
+        .await
+        .map_err(|_| PreflightError::SenderNotDeployed(address))?;
+    // Cairo 0 accounts sit behind a proxy whose `__default__` (selector 0) forwards every
+    // call, so their entry points are not known from the class
+    let entry_points: Option<Vec<Felt>> = match &class {
+        ContractClass::Sierra(class) => Some(
+            class
+                .entry_points_by_type
+                .external
+                .iter()
+                .map(|entry_point| entry_point.selector)
+                .collect(),
+        ),
+        ContractClass::Legacy(class) => {
+            let selectors: Vec<Felt> = class
+                .entry_points_by_type
+                .external
+                .iter()
+                .map(|entry_point| entry_point.selector)
+                .collect();
+            (!selectors.contains(&Felt::ZERO)).then_some(selectors)
+        }
+    };
+    if let Some(entry_points) = &entry_points {
+        for required in ["__validate__", "__execute__"] {
+            let selector = get_selector_from_name(required)
+                .map_err(|e| PreflightError::Signer(e.to_string()))?;
+            if !entry_points.contains(&selector) {
+                return Err(PreflightError::NotAnAccount {
+                    address,
+                    missing: required,
+                });
+            }
+        }
+    }
+    let entry_points = entry_points.as_deref();
+
+    let signing_key = SigningKey::from_secret_scalar(sender.private_key);
+    let configured = signing_key.verifying_key().scalar();
+    for getter in GUARDIAN_GETTERS {
+        if let Some(guardian) = call_view(provider, address, entry_points, getter).await {
+            if guardian != Felt::ZERO {
+                return Err(PreflightError::GuardianSet { address });
+            }
+            break;
+        }
+    }
+    for getter in PUBLIC_KEY_GETTERS {
+        if let Some(account) = call_view(provider, address, entry_points, getter).await {
+            if account != configured {
+                return Err(PreflightError::KeyMismatch {
+                    address,
+                    account,
+                    configured,
+                });
+            }
+            break;
+        }
+    }
+
+    let hash = cairo_short_string_to_felt("pp-fee-updater preflight")
+        .map_err(|e| PreflightError::Signer(e.to_string()))?;
+    let signature = signing_key
+        .sign(&hash)
+        .map_err(|e| PreflightError::Signer(e.to_string()))?;
+
+    // SRC-6 accounts answer 'VALID'; older accounts answer 1
+    let selector = get_selector_from_name("is_valid_signature")
+        .map_err(|e| PreflightError::Signer(e.to_string()))?;
+    let result = provider
+        .call(
+            FunctionCall {
+                calldata: vec![hash, Felt::TWO, signature.r, signature.s],
+                contract_address: address,
+                entry_point_selector: selector,
+            },
+            BlockId::Tag(BlockTag::Latest),
+        )
+        .await
+        .map_err(|_| PreflightError::SignatureRejected(address))?;
+
+    let valid = cairo_short_string_to_felt("VALID").unwrap_or(Felt::ONE);
+    match result.first() {
+        Some(value) if *value == valid || *value == Felt::ONE => Ok(()),
+        _ => Err(PreflightError::SignatureRejected(address)),
+    }
+}
+
+// First value returned by a view of the contract, `None` when its known entry points lack
+// it or the call fails
+async fn call_view(
+    provider: &RpcClient,
+    address: Felt,
+    entry_points: Option<&[Felt]>,
+    name: &str,
+) -> Option<Felt> {
+    let selector = get_selector_from_name(name).ok()?;
+    if entry_points.is_some_and(|entry_points| !entry_points.contains(&selector)) {
+        return None;
+    }
+    let result = provider
+        .call(
+            FunctionCall {
+                calldata: vec![],
+                contract_address: address,
+                entry_point_selector: selector,
+            },
+            BlockId::Tag(BlockTag::Latest),
+        )
+        .await
+        .ok()?;
+    result.first().copied()
+}
+
+async fn check_chain_ids(http: Felt, ws_stream: &mut WsStream) -> Result<(), PreflightError> {
+    let ws = ws_chain_id(ws_stream).await?;
+
+    if ws != http {
+        return Err(PreflightError::ChainIdMismatch { ws, http });
+    }
+    debug!("Preflight: both endpoints report chain id {:#x}", http);
+    Ok(())
+}
+
+pub async fn ws_chain_id(ws_stream: &mut WsStream) -> Result<Felt, PreflightError> {
+    let result = websocket::request(ws_stream, WS_CHAIN_ID_REQUEST_ID, "starknet_chainId")
+        .await
+        .map_err(PreflightError::WsChainId)?;
+    result
+        .as_str()
+        .and_then(|chain_id| Felt::from_hex(chain_id).ok())
+        .ok_or_else(|| PreflightError::WsChainId(format!("unexpected response: {result}")))
+}
@@ -0,0 +1,73 @@
+use crate::updater::{ComponentUpdate, FeeDecision};
+
+// An update waiting to be sent, ranked by how far the contract is from the network
+#[derive(Debug, Clone)]
+pub struct QueuedUpdate {
+    pub update: ComponentUpdate,
+    pub mispricing_bps: u128,
+}
+
+impl QueuedUpdate {
+    pub fn from_decision(decision: FeeDecision) -> Option<Self> {
+        let mispricing_bps =
+            mispricing_bps(decision.network_gas_price, decision.contract_gas_price);
+        decision.update.map(|update| Self {
+            update,
+            mispricing_bps,
+        })
+    }
+}
+
+// Relative distance between network and contract price in basis points;
+// an unset contract price is treated as maximally mispriced
+pub fn mispricing_bps(network_price: u128, contract_price: u128) -> u128 {
+    if contract_price == 0 {
+        return u128::MAX;
+    }
+    network_price
+        .abs_diff(contract_price)
+        .saturating_mul(10_000)
+        / contract_price
+}
+
+// Transactions to send this cycle, most mispriced first, each a single multicall
+#[derive(Debug, Default)]
+pub struct SendQueue {
+    pub batches: Vec<Vec<ComponentUpdate>>,
+    // Updates left for a later cycle because of the per-cycle transaction cap
+    pub deferred: Vec<QueuedUpdate>,
+}
+
+impl SendQueue {
+    pub fn build(
+        mut items: Vec<QueuedUpdate>,
+        max_calls_per_tx: Option<usize>,
+        max_txs_per_cycle: Option<usize>,
+    ) -> Self {
+        items.sort_by(|a, b| b.mispricing_bps.cmp(&a.mispricing_bps));
+
+        let chunk_size = max_calls_per_tx.unwrap_or(usize::MAX).max(1);
+        let max_txs = max_txs_per_cycle.unwrap_or(usize::MAX);
+
+        let mut queue = SendQueue::default();
+        let mut items = items.into_iter().peekable();
+        while items.peek().is_some() {
+            if queue.batches.len() >= max_txs {
+                queue.deferred.extend(items);
+                break;
+            }
+            queue.batches.push(
+                items
+                    .by_ref()
+                    .take(chunk_size)
+                    .map(|item| item.update)
+                    .collect(),
+            );
+        }
+        queue
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.batches.is_empty()
+    }
+}
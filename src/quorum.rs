@@ -0,0 +1,133 @@
+use futures_util::future::join_all;
+use starknet::core::types::Felt;
+use tracing::{debug, warn};
+
+use crate::alerts::{AlertSeverity, Alerter};
+use crate::calldata::CalldataLayout;
+use crate::endpoints::EndpointPool;
+use crate::updater::{block_prices, read_component_price, BlockPrices, PriceBlock, UpdaterError};
+
+// Reads the same value from several providers at once and settles on the median, so a
+// single malicious or buggy RPC cannot feed the updater bad prices
+#[derive(Debug, Clone)]
+pub struct Quorum {
+    pool: EndpointPool,
+    size: usize,
+    tolerance_bps: u128,
+    alerter: Alerter,
+}
+
+impl Quorum {
+    pub fn new(pool: EndpointPool, size: usize, tolerance_bps: u128, alerter: Alerter) -> Self {
+        Self {
+            pool,
+            size,
+            tolerance_bps,
+            alerter,
+        }
+    }
+
+    pub async fn block_prices(
+        &self,
+        price_block: PriceBlock,
+    ) -> Result<Vec<BlockPrices>, UpdaterError> {
+        let clients = self.pool.pinned_clients(self.size);
+        let results = join_all(
+            clients
+                .iter()
+                .map(|client| block_prices(client, price_block)),
+        )
+        .await;
+        self.successes("block prices", results)
+    }
+
+    pub async fn contract_prices(
+        &self,
+        contract_address: Felt,
+        getter: Felt,
+        layout: &CalldataLayout,
+    ) -> Result<Vec<Felt>, UpdaterError> {
+        let clients = self.pool.pinned_clients(self.size);
+        let results = join_all(
+            clients
+                .iter()
+                .map(|client| read_component_price(client, contract_address, getter, layout)),
+        )
+        .await;
+        self.successes("contract price", results)
+    }
+
+    // Median of the readings; alerts when any provider strays beyond the tolerance
+    pub async fn resolve(&self, component: &str, what: &str, values: Vec<Felt>) -> Felt {
+        let agreement = agree(values);
+        if agreement.spread_bps > self.tolerance_bps {
+            self.alerter
+                .raise(
+                    AlertSeverity::Warning,
+                    "provider_disagreement",
+                    format!(
+                        "[{}] Providers disagree on the {} by {} bps (tolerance {} bps), using median {}",
+                        component, what, agreement.spread_bps, self.tolerance_bps, agreement.median
+                    ),
+                )
+                .await;
+        } else {
+            debug!(
+                "[{}] Providers agree on the {} within {} bps",
+                component, what, agreement.spread_bps
+            );
+        }
+        agreement.median
+    }
+
+    // A majority of the queried providers must answer for the read to count
+    fn successes<T>(
+        &self,
+        what: &str,
+        results: Vec<Result<T, UpdaterError>>,
+    ) -> Result<Vec<T>, UpdaterError> {
+        let queried = results.len();
+        let mut values = Vec::with_capacity(queried);
+        for result in results {
+            match result {
+                Ok(value) => values.push(value),
+                Err(e) => warn!("⚠️ Quorum read of {} failed on one provider: {}", what, e),
+            }
+        }
+        if values.len() * 2 <= queried {
+            return Err(UpdaterError::Quorum(format!(
+                "only {} of {} providers returned the {}",
+                values.len(),
+                queried,
+                what
+            )));
+        }
+        Ok(values)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Agreement {
+    median: Felt,
+    // Largest deviation of a single reading from the median, in basis points
+    spread_bps: u128,
+}
+
+fn agree(mut values: Vec<Felt>) -> Agreement {
+    values.sort();
+    let median = values[(values.len() - 1) / 2];
+    let median_u128: u128 = median.to_biguint().try_into().unwrap_or(u128::MAX);
+    let spread_bps = values
+        .iter()
+        .map(|value| {
+            let value: u128 = value.to_biguint().try_into().unwrap_or(u128::MAX);
+            match median_u128 {
+                0 if value == 0 => 0,
+                0 => u128::MAX,
+                _ => value.abs_diff(median_u128).saturating_mul(10_000) / median_u128,
+            }
+        })
+        .max()
+        .unwrap_or_default();
+    Agreement { median, spread_bps }
+}
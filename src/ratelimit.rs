@@ -0,0 +1,83 @@
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use tokio::{sync::Mutex, time::Instant};
+use tracing::debug;
+
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub per_second: f64,
+    pub burst: u32,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+// Token bucket shared by every outgoing RPC call. Callers over the limit queue in
+// arrival order: the tokio mutex is fair and is held while a caller waits for tokens.
+#[derive(Debug)]
+pub struct RateLimiter {
+    limit: RateLimit,
+    bucket: Mutex<Bucket>,
+    queued: AtomicUsize,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            bucket: Mutex::new(Bucket {
+                tokens: f64::from(limit.burst),
+                refilled_at: Instant::now(),
+            }),
+            queued: AtomicUsize::new(0),
+        }
+    }
+
+    // Wait until `cost` tokens are available and take them
+    pub async fn acquire(&self, cost: u32) {
+        // A batch larger than the burst could never fit, so cap it at a full bucket
+        let cost = f64::from(cost.min(self.limit.burst));
+
+        let queued = self.queued.fetch_add(1, Ordering::Relaxed) + 1;
+        metrics::gauge!("pp_fee_updater_rpc_rate_limit_queued").set(queued as f64);
+        let mut bucket = self.bucket.lock().await;
+
+        self.refill(&mut bucket);
+        if bucket.tokens < cost {
+            let wait = Duration::from_secs_f64((cost - bucket.tokens) / self.limit.per_second);
+            debug!("RPC rate limit reached, delaying call by {:?}", wait);
+            metrics::counter!("pp_fee_updater_rpc_throttled_total").increment(1);
+            metrics::histogram!("pp_fee_updater_rpc_throttle_wait_seconds")
+                .record(wait.as_secs_f64());
+            tokio::time::sleep(wait).await;
+            self.refill(&mut bucket);
+        }
+        bucket.tokens = (bucket.tokens - cost).max(0.0);
+
+        let queued = self.queued.fetch_sub(1, Ordering::Relaxed) - 1;
+        metrics::gauge!("pp_fee_updater_rpc_rate_limit_queued").set(queued as f64);
+    }
+
+    fn refill(&self, bucket: &mut Bucket) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens =
+            (bucket.tokens + elapsed * self.limit.per_second).min(f64::from(self.limit.burst));
+        bucket.refilled_at = now;
+    }
+}
+
+pub fn parse_rate(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate > 0.0 => Ok(rate),
+        _ => Err(format!(
+            "invalid rate `{s}`, expected a positive number of requests per second"
+        )),
+    }
+}
@@ -0,0 +1,104 @@
+use std::{
+    collections::hash_map::RandomState,
+    future::Future,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+use starknet::{
+    core::types::StarknetError,
+    providers::{
+        jsonrpc::{HttpTransportError, JsonRpcClientError},
+        ProviderError,
+    },
+};
+use tracing::warn;
+
+use crate::endpoints::TransportError;
+use crate::wsrpc::WsTransportError;
+
+// JSON-RPC server error range, used by providers for overload and internal failures
+const SERVER_ERROR_CODES: std::ops::RangeInclusive<i64> = -32099..=-32000;
+const INTERNAL_ERROR_CODE: i64 = -32603;
+
+// Retries of a single check or update step that hit a transient error
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BackoffPolicy {
+    pub attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl BackoffPolicy {
+    // Exponential delay with full jitter, so several updaters don't retry in lockstep
+    pub(crate) fn delay(&self, attempt: u32) -> Duration {
+        let ceiling = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        let jitter = RandomState::new().build_hasher().finish() % 1_000;
+        ceiling.mul_f64(jitter as f64 / 1_000.0)
+    }
+}
+
+// Run `operation`, retrying it while it fails with an error `is_transient` accepts
+pub async fn retry_transient<T, E, F, Fut>(
+    policy: &BackoffPolicy,
+    what: &str,
+    is_transient: impl Fn(&E) -> bool,
+    mut operation: F,
+) -> Result<T, E>
+where
+    E: std::fmt::Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 0;
+    loop {
+        match operation().await {
+            Err(e) if attempt < policy.attempts && is_transient(&e) => {
+                let delay = policy.delay(attempt);
+                attempt += 1;
+                warn!(
+                    "🔁 Transient error during {}, retry {}/{} in {:?}: {}",
+                    what, attempt, policy.attempts, delay, e
+                );
+                metrics::counter!("pp_fee_updater_transient_retries_total").increment(1);
+                tokio::time::sleep(delay).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+// Rate limits, timeouts, connection failures and server-side errors are worth retrying;
+// contract errors and rejected transactions are not
+pub fn is_transient_provider_error(error: &ProviderError) -> bool {
+    match error {
+        ProviderError::RateLimited => true,
+        ProviderError::StarknetError(StarknetError::UnexpectedError(_)) => true,
+        ProviderError::StarknetError(_) | ProviderError::ArrayLengthMismatch => false,
+        ProviderError::Other(inner) => {
+            match inner
+                .as_any()
+                .downcast_ref::<JsonRpcClientError<TransportError>>()
+            {
+                Some(JsonRpcClientError::TransportError(TransportError::Http(
+                    HttpTransportError::Reqwest(e),
+                ))) => e
+                    .status()
+                    .is_none_or(|status| status.is_server_error() || status.as_u16() == 429),
+                Some(JsonRpcClientError::TransportError(TransportError::Ws(
+                    WsTransportError::Json(_) | WsTransportError::BatchUnsupported,
+                ))) => false,
+                Some(JsonRpcClientError::TransportError(_)) => true,
+                Some(JsonRpcClientError::JsonRpcError(e)) => {
+                    e.code == INTERNAL_ERROR_CODE || SERVER_ERROR_CODES.contains(&e.code)
+                }
+                Some(JsonRpcClientError::JsonError(_)) => false,
+                // Unknown implementation errors are most likely I/O
+                None => true,
+            }
+        }
+    }
+}
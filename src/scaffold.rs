@@ -0,0 +1,48 @@
+use std::fmt::Write;
+
+use clap::Command;
+
+// A starter `.env` listing every env-backed setting of `command` with its help and
+// default. Required settings are left to fill in, the rest are commented out at their
+// defaults.
+pub fn env_template(command: &Command) -> String {
+    let mut template = String::from(
+        "# pp-fee-updater configuration, loaded from .env in the working directory\n\
+         # Uncomment a setting to change it from its default\n",
+    );
+    for arg in command.get_arguments() {
+        let Some(name) = arg.get_env().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if name == "ENV_FILE" {
+            continue;
+        }
+        template.push('\n');
+        if let Some(help) = arg.get_help() {
+            for line in help.to_string().lines() {
+                let _ = writeln!(template, "# {line}");
+            }
+        }
+        let possible: Vec<String> = arg
+            .get_possible_values()
+            .iter()
+            .filter(|value| !value.is_hide_set())
+            .map(|value| value.get_name().to_string())
+            .collect();
+        // Flags list `true` and `false`, which says nothing
+        if !possible.is_empty() && arg.get_num_args().is_some_and(|num| num.takes_values()) {
+            let _ = writeln!(template, "# One of: {}", possible.join(", "));
+        }
+        let defaults: Vec<String> = arg
+            .get_default_values()
+            .iter()
+            .map(|value| value.to_string_lossy().into_owned())
+            .collect();
+        if arg.is_required_set() {
+            let _ = writeln!(template, "{name}=");
+        } else {
+            let _ = writeln!(template, "# {name}={}", defaults.join(","));
+        }
+    }
+    template
+}
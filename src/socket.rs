@@ -0,0 +1,84 @@
+use std::{os::unix::fs::PermissionsExt, path::Path};
+
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+};
+use tracing::{debug, error, info};
+
+use crate::control::{ControlCommand, ControlHandle};
+
+// Local control socket taking one JSON command per line, e.g. `{"command": "pause"}`, and
+// answering each with one JSON line. Only the owner of the process may connect.
+pub fn serve(path: &Path, control: ControlHandle) -> anyhow::Result<()> {
+    // A socket left behind by a previous run would make the bind fail
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    info!("🔌 Accepting control commands on {}", path.display());
+
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    let control = control.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = handle_connection(stream, &control).await {
+                            debug!("Control connection closed: {}", e);
+                        }
+                    });
+                }
+                Err(e) => {
+                    error!("Control socket stopped accepting connections: {}", e);
+                    return;
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+async fn handle_connection(stream: UnixStream, control: &ControlHandle) -> std::io::Result<()> {
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<ControlCommand>(&line) {
+            Ok(command) => control.send(command).await,
+            Err(e) => Err(format!("invalid command: {e}")),
+        };
+        let mut reply = response_line(response);
+        reply.push('\n');
+        write.write_all(reply.as_bytes()).await?;
+    }
+    Ok(())
+}
+
+fn response_line(response: Result<Value, String>) -> String {
+    match response {
+        Ok(result) => json!({ "ok": result }),
+        Err(message) => json!({ "error": message }),
+    }
+    .to_string()
+}
+
+// Sends one command to a running updater and returns its answer
+pub async fn request(path: &Path, command: &Value) -> anyhow::Result<Value> {
+    let stream = UnixStream::connect(path).await?;
+    let (read, mut write) = stream.into_split();
+    let mut line = command.to_string();
+    line.push('\n');
+    write.write_all(line.as_bytes()).await?;
+
+    let reply = BufReader::new(read)
+        .lines()
+        .next_line()
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("the updater closed the connection without answering"))?;
+    Ok(serde_json::from_str(&reply)?)
+}
@@ -0,0 +1,375 @@
+use std::{collections::VecDeque, fmt, sync::Mutex};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use starknet::core::types::Felt;
+use tracing::{debug, warn};
+use url::Url;
+
+use crate::components::{Denomination, PriceSource};
+use crate::ethereum::EthGasOracle;
+use crate::updater::{BlockPrices, UpdaterError};
+
+// Prices a source reports for one check, in the configured denomination; `None` where the
+// source has no opinion on a resource
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SourcePrices {
+    pub l1_gas: Option<u128>,
+    pub l1_data_gas: Option<u128>,
+    pub l2_gas: Option<u128>,
+}
+
+impl SourcePrices {
+    pub fn of_block(denomination: Denomination, block: &BlockPrices) -> Self {
+        let price = |resource| to_u128(denomination.price(resource));
+        Self {
+            l1_gas: price(&block.l1_gas),
+            l1_data_gas: price(&block.l1_data_gas),
+            l2_gas: price(&block.l2_gas),
+        }
+    }
+
+    pub fn get(&self, source: PriceSource) -> Option<u128> {
+        match source {
+            PriceSource::L1Gas => self.l1_gas,
+            PriceSource::L1DataGas => self.l1_data_gas,
+            PriceSource::L2Gas => self.l2_gas,
+            PriceSource::Flat => None,
+        }
+    }
+}
+
+// What a source may look at when asked for its prices
+#[derive(Debug, Clone, Copy)]
+pub struct SourceContext<'a> {
+    pub denomination: Denomination,
+    pub block: &'a BlockPrices,
+}
+
+// A gas price feed next to the block prices, fetched once per check
+#[async_trait]
+pub trait GasPriceSource: fmt::Debug + Send + Sync {
+    fn name(&self) -> &str;
+
+    async fn prices(&self, ctx: SourceContext<'_>) -> Result<SourcePrices, UpdaterError>;
+}
+
+// How the readings of the configured sources are folded into one price
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Combinator {
+    #[default]
+    Weighted,
+    Min,
+    Max,
+    Median,
+}
+
+impl Combinator {
+    // `readings` are (price, weight) pairs; weights only matter to the weighted mean
+    fn combine(self, mut readings: Vec<(u128, u128)>) -> Option<u128> {
+        match self {
+            Combinator::Weighted => {
+                let total_weight: u128 = readings.iter().map(|(_, weight)| weight).sum();
+                if total_weight == 0 {
+                    return None;
+                }
+                let weighted: u128 = readings
+                    .iter()
+                    .map(|(price, weight)| price.saturating_mul(*weight))
+                    .fold(0, u128::saturating_add);
+                Some(weighted / total_weight)
+            }
+            Combinator::Min => readings.iter().map(|(price, _)| *price).min(),
+            Combinator::Max => readings.iter().map(|(price, _)| *price).max(),
+            Combinator::Median => {
+                readings.sort_unstable();
+                readings
+                    .get(readings.len().checked_sub(1)? / 2)
+                    .map(|(price, _)| *price)
+            }
+        }
+    }
+}
+
+// The price sources section of the config file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PriceSourcesConfig {
+    #[serde(default)]
+    pub combine: Combinator,
+    pub sources: Vec<SourceConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceConfig {
+    #[serde(flatten)]
+    pub kind: SourceKind,
+    #[serde(default = "default_weight")]
+    pub weight: u128,
+}
+
+fn default_weight() -> u128 {
+    1
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SourceKind {
+    // The prices of the block being checked
+    Block,
+    // Median of the last `blocks` checked blocks
+    BlockMedian {
+        blocks: usize,
+    },
+    // Ethereum base fee and blob base fee
+    Ethereum {
+        url: String,
+    },
+    // External JSON feed; each resource is read at a JSON pointer, e.g. `/data/l1_gas`
+    Http {
+        url: String,
+        #[serde(default)]
+        l1_gas: Option<String>,
+        #[serde(default)]
+        l1_data_gas: Option<String>,
+        #[serde(default)]
+        l2_gas: Option<String>,
+    },
+}
+
+impl PriceSourcesConfig {
+    pub fn resolve(&self, client: &reqwest::Client) -> Result<CompositeSource, UpdaterError> {
+        let mut composite = CompositeSource {
+            combinator: self.combine,
+            block_weight: None,
+            sources: Vec::new(),
+        };
+        for source in &self.sources {
+            let feed: Box<dyn GasPriceSource> = match &source.kind {
+                SourceKind::Block => {
+                    composite.block_weight = Some(source.weight);
+                    continue;
+                }
+                SourceKind::BlockMedian { blocks } => {
+                    if *blocks == 0 {
+                        return Err(UpdaterError::Configuration(
+                            "block_median source needs at least one block".to_string(),
+                        ));
+                    }
+                    Box::new(BlockMedian::new(*blocks))
+                }
+                SourceKind::Ethereum { url } => {
+                    Box::new(EthGasOracle::new(client.clone(), parse_url(url)?))
+                }
+                SourceKind::Http {
+                    url,
+                    l1_gas,
+                    l1_data_gas,
+                    l2_gas,
+                } => Box::new(HttpFeed {
+                    client: client.clone(),
+                    url: parse_url(url)?,
+                    l1_gas: l1_gas.clone(),
+                    l1_data_gas: l1_data_gas.clone(),
+                    l2_gas: l2_gas.clone(),
+                }),
+            };
+            composite.sources.push((feed, source.weight));
+        }
+        if composite.block_weight.is_none() && composite.sources.is_empty() {
+            return Err(UpdaterError::Configuration(
+                "price_sources lists no sources".to_string(),
+            ));
+        }
+        Ok(composite)
+    }
+}
+
+// Several gas price sources folded into one target price per resource
+#[derive(Debug)]
+pub struct CompositeSource {
+    combinator: Combinator,
+    // The block prices take part with this weight; they are passed in per reading so that
+    // quorum reads stay per provider
+    block_weight: Option<u128>,
+    sources: Vec<(Box<dyn GasPriceSource>, u128)>,
+}
+
+impl CompositeSource {
+    pub fn new(combinator: Combinator, block_weight: Option<u128>) -> Self {
+        Self {
+            combinator,
+            block_weight,
+            sources: Vec::new(),
+        }
+    }
+
+    pub fn with_source(mut self, source: Box<dyn GasPriceSource>, weight: u128) -> Self {
+        self.sources.push((source, weight));
+        self
+    }
+
+    // Readings of every non-block source; a failing source sits the check out
+    pub async fn fetch(&self, ctx: SourceContext<'_>) -> Vec<(SourcePrices, u128)> {
+        let mut readings = Vec::with_capacity(self.sources.len());
+        for (source, weight) in &self.sources {
+            match source.prices(ctx).await {
+                Ok(prices) => {
+                    debug!("Price source {}: {:?}", source.name(), prices);
+                    readings.push((prices, *weight));
+                }
+                Err(e) => warn!(
+                    "⚠️ Price source {} failed, skipping it: {}",
+                    source.name(),
+                    e
+                ),
+            }
+        }
+        readings
+    }
+
+    // Combined price of one resource for one block reading; flat components and
+    // resources no source reports keep the block price
+    pub fn combine(
+        &self,
+        source: PriceSource,
+        block_price: Felt,
+        readings: &[(SourcePrices, u128)],
+    ) -> Felt {
+        if source == PriceSource::Flat {
+            return block_price;
+        }
+        let mut values: Vec<(u128, u128)> = readings
+            .iter()
+            .filter_map(|(prices, weight)| Some((prices.get(source)?, *weight)))
+            .collect();
+        if let (Some(weight), Some(price)) = (self.block_weight, to_u128(block_price)) {
+            values.push((price, weight));
+        }
+        match self.combinator.combine(values) {
+            Some(price) => Felt::from(price),
+            None => block_price,
+        }
+    }
+}
+
+// Median of the prices of the last checked blocks, smoothing single-block spikes
+#[derive(Debug)]
+pub struct BlockMedian {
+    blocks: usize,
+    history: Mutex<VecDeque<SourcePrices>>,
+}
+
+impl BlockMedian {
+    pub fn new(blocks: usize) -> Self {
+        Self {
+            blocks,
+            history: Mutex::new(VecDeque::with_capacity(blocks)),
+        }
+    }
+}
+
+#[async_trait]
+impl GasPriceSource for BlockMedian {
+    fn name(&self) -> &str {
+        "block_median"
+    }
+
+    async fn prices(&self, ctx: SourceContext<'_>) -> Result<SourcePrices, UpdaterError> {
+        let mut history = self
+            .history
+            .lock()
+            .map_err(|_| UpdaterError::Oracle("block median history poisoned".to_string()))?;
+        if history.len() == self.blocks {
+            history.pop_front();
+        }
+        history.push_back(SourcePrices::of_block(ctx.denomination, ctx.block));
+
+        let median = |get: fn(&SourcePrices) -> Option<u128>| {
+            let readings = history
+                .iter()
+                .filter_map(get)
+                .map(|price| (price, 1))
+                .collect();
+            Combinator::Median.combine(readings)
+        };
+        Ok(SourcePrices {
+            l1_gas: median(|prices| prices.l1_gas),
+            l1_data_gas: median(|prices| prices.l1_data_gas),
+            l2_gas: median(|prices| prices.l2_gas),
+        })
+    }
+}
+
+// External HTTP feed returning JSON with prices already in the configured denomination
+#[derive(Debug)]
+pub struct HttpFeed {
+    client: reqwest::Client,
+    url: Url,
+    l1_gas: Option<String>,
+    l1_data_gas: Option<String>,
+    l2_gas: Option<String>,
+}
+
+#[async_trait]
+impl GasPriceSource for HttpFeed {
+    fn name(&self) -> &str {
+        self.url.as_str()
+    }
+
+    async fn prices(&self, _ctx: SourceContext<'_>) -> Result<SourcePrices, UpdaterError> {
+        let body: Value = self
+            .client
+            .get(self.url.clone())
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| UpdaterError::Oracle(format!("Price feed {} failed: {e}", self.url)))?
+            .json()
+            .await
+            .map_err(|e| UpdaterError::Oracle(format!("Price feed {} failed: {e}", self.url)))?;
+
+        let read = |pointer: &Option<String>| -> Result<Option<u128>, UpdaterError> {
+            let Some(pointer) = pointer else {
+                return Ok(None);
+            };
+            let value = body.pointer(pointer).ok_or_else(|| {
+                UpdaterError::Oracle(format!("Price feed {} has no `{pointer}`", self.url))
+            })?;
+            parse_feed_value(value).map(Some).ok_or_else(|| {
+                UpdaterError::Oracle(format!(
+                    "Price feed {} `{pointer}` is not a price: {value}",
+                    self.url
+                ))
+            })
+        };
+        Ok(SourcePrices {
+            l1_gas: read(&self.l1_gas)?,
+            l1_data_gas: read(&self.l1_data_gas)?,
+            l2_gas: read(&self.l2_gas)?,
+        })
+    }
+}
+
+// Feeds return prices as JSON numbers, decimal strings or 0x-prefixed hex strings
+fn parse_feed_value(value: &Value) -> Option<u128> {
+    match value {
+        Value::Number(number) => number.as_u64().map(u128::from),
+        Value::String(s) => match s.strip_prefix("0x") {
+            Some(hex) => u128::from_str_radix(hex, 16).ok(),
+            None => s.parse().ok(),
+        },
+        _ => None,
+    }
+}
+
+pub(crate) fn to_u128(value: Felt) -> Option<u128> {
+    value.to_biguint().try_into().ok()
+}
+
+fn parse_url(url: &str) -> Result<Url, UpdaterError> {
+    Url::parse(url)
+        .map_err(|e| UpdaterError::Configuration(format!("Invalid price source URL `{url}`: {e}")))
+}
@@ -0,0 +1,182 @@
+use std::fmt;
+
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::preflight::WsStream;
+use crate::websocket;
+
+// JSON-RPC id of the one-off spec version request, sent before any subscription
+const WS_SPEC_VERSION_REQUEST_ID: u64 = 0;
+
+// Starknet JSON-RPC spec generations that differ in their WebSocket API
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecVersion {
+    // No standard subscriptions yet; nodes expose `pathfinder_subscribe`
+    V0_7,
+    // `starknet_subscribe*` methods and headers carrying all three gas prices
+    V0_8,
+}
+
+// A subscription notification, independent of how the spec version shapes it
+#[derive(Debug)]
+pub enum Notification<'a> {
+    NewHeads {
+        subscription_id: Option<&'a Value>,
+        header: &'a Value,
+    },
+    TransactionStatus {
+        subscription_id: Option<&'a Value>,
+        result: &'a Value,
+    },
+    Event {
+        subscription_id: Option<&'a Value>,
+        event: &'a Value,
+    },
+}
+
+impl SpecVersion {
+    // Versions newer than the last known one are assumed to keep its API
+    pub fn parse(version: &str) -> Option<Self> {
+        let mut parts = version.trim_start_matches('v').split('.');
+        let major: u32 = parts.next()?.parse().ok()?;
+        let minor: u32 = parts.next()?.parse().ok()?;
+        match (major, minor) {
+            (0, 0..=6) => None,
+            (0, 7) => Some(SpecVersion::V0_7),
+            _ => Some(SpecVersion::V0_8),
+        }
+    }
+
+    pub fn subscribe_new_heads(self) -> (&'static str, Value) {
+        match self {
+            SpecVersion::V0_7 => ("pathfinder_subscribe", json!({ "kind": "newHeads" })),
+            SpecVersion::V0_8 => ("starknet_subscribeNewHeads", json!([])),
+        }
+    }
+
+    // `None` when the version has no transaction status subscription, in which case
+    // pending transactions are polled
+    pub fn subscribe_transaction_status(self, tx_hash: &str) -> Option<(&'static str, Value)> {
+        match self {
+            SpecVersion::V0_7 => None,
+            SpecVersion::V0_8 => Some((
+                "starknet_subscribeTransactionStatus",
+                json!({ "transaction_hash": tx_hash }),
+            )),
+        }
+    }
+
+    // Events a contract emits with the given first key; `None` when the version has no
+    // event subscription
+    pub fn subscribe_events(self, from_address: &str, key: &str) -> Option<(&'static str, Value)> {
+        match self {
+            SpecVersion::V0_7 => None,
+            SpecVersion::V0_8 => Some((
+                "starknet_subscribeEvents",
+                json!({ "from_address": from_address, "keys": [[key]] }),
+            )),
+        }
+    }
+
+    pub fn unsubscribe(self, subscription_id: &Value) -> (&'static str, Value) {
+        match self {
+            SpecVersion::V0_7 => ("pathfinder_unsubscribe", json!({ "id": subscription_id })),
+            SpecVersion::V0_8 => (
+                "starknet_unsubscribe",
+                json!({ "subscription_id": subscription_id }),
+            ),
+        }
+    }
+
+    // Whether new heads headers carry the gas prices every component needs
+    pub fn header_has_prices(self) -> bool {
+        self == SpecVersion::V0_8
+    }
+
+    pub fn notification(self, message: &Value) -> Option<Notification<'_>> {
+        let method = message.get("method")?.as_str()?;
+        match (self, method) {
+            (SpecVersion::V0_8, "starknet_subscriptionNewHeads") => {
+                let params = message.get("params")?;
+                Some(Notification::NewHeads {
+                    subscription_id: params.get("subscription_id"),
+                    header: params.get("result")?,
+                })
+            }
+            (SpecVersion::V0_8, "starknet_subscriptionTransactionStatus") => {
+                let params = message.get("params")?;
+                Some(Notification::TransactionStatus {
+                    subscription_id: params.get("subscription_id"),
+                    result: params.get("result")?,
+                })
+            }
+            (SpecVersion::V0_8, "starknet_subscriptionEvents") => {
+                let params = message.get("params")?;
+                Some(Notification::Event {
+                    subscription_id: params.get("subscription_id"),
+                    event: params.get("result")?,
+                })
+            }
+            // Pathfinder puts the payload under `result`, tagged with its kind
+            (SpecVersion::V0_7, "pathfinder_subscription") => {
+                let payload = message.get("result").or_else(|| message.get("params"))?;
+                let header = payload.get("result")?;
+                let kind = payload.get("kind").or_else(|| header.get("kind"));
+                if kind.is_some_and(|kind| kind != "newHeads") {
+                    return None;
+                }
+                Some(Notification::NewHeads {
+                    subscription_id: payload.get("subscription"),
+                    header,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SpecVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpecVersion::V0_7 => write!(f, "0.7"),
+            SpecVersion::V0_8 => write!(f, "0.8"),
+        }
+    }
+}
+
+// Ask the node which spec it serves; one that doesn't say is assumed to be 0.8
+pub async fn negotiate(ws_stream: &mut WsStream) -> SpecVersion {
+    let version = websocket::request(
+        ws_stream,
+        WS_SPEC_VERSION_REQUEST_ID,
+        "starknet_specVersion",
+    )
+    .await;
+    match version {
+        Ok(Value::String(version)) => match SpecVersion::parse(&version) {
+            Some(spec) => {
+                info!(
+                    "Node serves JSON-RPC spec {}, using the {} API",
+                    version, spec
+                );
+                spec
+            }
+            None => {
+                warn!(
+                    "⚠️ Node serves unsupported JSON-RPC spec {}, trying the 0.8 API",
+                    version
+                );
+                SpecVersion::V0_8
+            }
+        },
+        Ok(other) => {
+            warn!("Unexpected spec version response {}, assuming 0.8", other);
+            SpecVersion::V0_8
+        }
+        Err(e) => {
+            warn!("Could not query the spec version ({}), assuming 0.8", e);
+            SpecVersion::V0_8
+        }
+    }
+}
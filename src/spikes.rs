@@ -0,0 +1,253 @@
+use std::collections::{HashMap, VecDeque};
+
+use starknet_types_core::felt::Felt;
+use tracing::{info, warn};
+
+// Readings the trend needs before anything counts as an outlier
+const MIN_SAMPLES: usize = 3;
+// Floor of the standard deviation as a share of the mean; gas often sits flat for many
+// blocks, where any move would otherwise be infinitely many deviations away
+const MIN_DEVIATION_SHARE: f64 = 0.01;
+
+// A network price far off the recent trend is ignored until it persists, so one odd block
+// or a manipulated sequencer reading never triggers an update. Either limit makes a
+// reading an outlier; neither set disables the filter.
+#[derive(Debug, Clone, Copy)]
+pub struct SpikeFilter {
+    // Accepted readings the trend is taken over
+    pub window: usize,
+    // Standard deviations from the mean
+    pub max_z_score: Option<f64>,
+    // Ratio to the mean, either way
+    pub max_multiple: Option<f64>,
+    // Consecutive outlying readings after which the move is accepted
+    pub persist_blocks: u64,
+}
+
+impl Default for SpikeFilter {
+    fn default() -> Self {
+        Self {
+            window: 20,
+            max_z_score: None,
+            max_multiple: None,
+            persist_blocks: 3,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Trend {
+    accepted: VecDeque<u128>,
+    // Outlying readings in a row
+    outlying: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct SpikeTracker {
+    policy: SpikeFilter,
+    trends: HashMap<String, Trend>,
+}
+
+impl SpikeTracker {
+    pub fn new(policy: SpikeFilter) -> Self {
+        Self {
+            policy,
+            trends: HashMap::new(),
+        }
+    }
+
+    // The network price to decide on: the reading, or while an outlier has not persisted,
+    // the last accepted one
+    pub fn filter(&mut self, component: &str, price: Felt) -> Felt {
+        if self.policy.max_z_score.is_none() && self.policy.max_multiple.is_none() {
+            return price;
+        }
+        let Ok(reading) = u128::try_from(price.to_biguint()) else {
+            return price;
+        };
+        let trend = self.trends.entry(component.to_string()).or_default();
+        let Some(mean) = outlier_mean(&self.policy, &trend.accepted, reading) else {
+            trend.outlying = 0;
+            accept(&self.policy, trend, reading);
+            return price;
+        };
+        trend.outlying += 1;
+        if trend.outlying >= self.policy.persist_blocks {
+            info!(
+                "📈 [{}] Network price {} off the trend ({:.0}) for {} reading(s), following it",
+                component, reading, mean, trend.outlying
+            );
+            accept(&self.policy, trend, reading);
+            return price;
+        }
+        // The window holds at least `MIN_SAMPLES` readings here
+        let held = trend.accepted.back().copied().unwrap_or(reading);
+        warn!(
+            "📛 [{}] Ignoring network price {}, off the trend ({:.0}), keeping {} ({}/{})",
+            component, reading, mean, held, trend.outlying, self.policy.persist_blocks
+        );
+        metrics::counter!(
+            "pp_fee_updater_spikes_ignored_total",
+            "component" => component.to_string()
+        )
+        .increment(1);
+        Felt::from(held)
+    }
+}
+
+fn accept(policy: &SpikeFilter, trend: &mut Trend, reading: u128) {
+    trend.accepted.push_back(reading);
+    while trend.accepted.len() > policy.window.max(MIN_SAMPLES) {
+        trend.accepted.pop_front();
+    }
+}
+
+// The mean of the trend when `reading` is an outlier
+fn outlier_mean(policy: &SpikeFilter, accepted: &VecDeque<u128>, reading: u128) -> Option<f64> {
+    if accepted.len() < MIN_SAMPLES {
+        return None;
+    }
+    let count = accepted.len() as f64;
+    let mean = accepted.iter().map(|price| *price as f64).sum::<f64>() / count;
+    let variance = accepted
+        .iter()
+        .map(|price| (*price as f64 - mean).powi(2))
+        .sum::<f64>()
+        / count;
+    let reading = reading as f64;
+    let z_outlier = policy.max_z_score.is_some_and(|max| {
+        let deviation = variance.sqrt().max(mean * MIN_DEVIATION_SHARE);
+        deviation > 0.0 && (reading - mean).abs() / deviation > max
+    });
+    let multiple_outlier = policy
+        .max_multiple
+        .is_some_and(|max| mean > 0.0 && (reading > mean * max || reading * max < mean));
+    (z_outlier || multiple_outlier).then_some(mean)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn multiple(max_multiple: f64) -> SpikeTracker {
+        SpikeTracker::new(SpikeFilter {
+            window: 5,
+            max_multiple: Some(max_multiple),
+            ..SpikeFilter::default()
+        })
+    }
+
+    fn feed(tracker: &mut SpikeTracker, readings: &[u128]) -> Vec<u128> {
+        readings
+            .iter()
+            .map(|reading| {
+                let price = tracker.filter("l1_gas", Felt::from(*reading));
+                u128::try_from(price.to_biguint()).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn without_limits_every_reading_passes() {
+        let mut tracker = SpikeTracker::new(SpikeFilter::default());
+        assert_eq!(
+            feed(&mut tracker, &[100, 100, 100, 10_000]),
+            vec![100, 100, 100, 10_000]
+        );
+    }
+
+    #[test]
+    fn nothing_is_an_outlier_before_the_trend_holds_three_readings() {
+        let mut tracker = multiple(2.0);
+        // An empty window, then a single sample, then two
+        assert_eq!(feed(&mut tracker, &[100, 1_000, 10]), vec![100, 1_000, 10]);
+    }
+
+    #[test]
+    fn single_spike_is_ignored_and_the_last_accepted_price_kept() {
+        let mut tracker = multiple(2.0);
+        assert_eq!(
+            feed(&mut tracker, &[100, 110, 105, 1_000, 104]),
+            vec![100, 110, 105, 105, 104]
+        );
+    }
+
+    #[test]
+    fn spike_persisting_for_the_configured_blocks_is_followed() {
+        let mut tracker = multiple(2.0);
+        assert_eq!(
+            feed(&mut tracker, &[100, 100, 100, 1_000, 1_000, 1_000]),
+            vec![100, 100, 100, 100, 100, 1_000]
+        );
+    }
+
+    #[test]
+    fn normal_reading_resets_the_outlier_count() {
+        let mut tracker = multiple(2.0);
+        assert_eq!(
+            feed(
+                &mut tracker,
+                &[100, 100, 100, 1_000, 1_000, 100, 1_000, 1_000]
+            ),
+            vec![100, 100, 100, 100, 100, 100, 100, 100]
+        );
+    }
+
+    #[test]
+    fn drops_count_as_outliers_too() {
+        let mut tracker = multiple(2.0);
+        assert_eq!(
+            feed(&mut tracker, &[100, 100, 100, 40]),
+            vec![100, 100, 100, 100]
+        );
+        // At exactly the multiple the reading is still within the limit
+        assert_eq!(feed(&mut tracker, &[50]), vec![50]);
+    }
+
+    #[test]
+    fn flat_trend_deviation_is_floored_at_one_percent_of_the_mean() {
+        let mut tracker = SpikeTracker::new(SpikeFilter {
+            max_z_score: Some(3.0),
+            ..SpikeFilter::default()
+        });
+        // With a zero deviation any move would be an outlier; the floor makes it 1
+        assert_eq!(
+            feed(&mut tracker, &[100, 100, 100, 103]),
+            vec![100, 100, 100, 103]
+        );
+        assert_eq!(feed(&mut tracker, &[110]), vec![103]);
+    }
+
+    #[test]
+    fn window_keeps_the_latest_readings_only() {
+        let mut tracker = SpikeTracker::new(SpikeFilter {
+            window: 3,
+            max_multiple: Some(2.0),
+            ..SpikeFilter::default()
+        });
+        // The early low readings have left the window, otherwise 45 would be off the trend
+        assert_eq!(
+            feed(&mut tracker, &[10, 10, 10, 15, 20, 20, 30]),
+            vec![10, 10, 10, 15, 20, 20, 30]
+        );
+        assert_eq!(feed(&mut tracker, &[45]), vec![45]);
+    }
+
+    #[test]
+    fn components_have_their_own_trends() {
+        let mut tracker = multiple(2.0);
+        feed(&mut tracker, &[100, 100, 100]);
+        assert_eq!(
+            tracker.filter("l2_gas", Felt::from(1_000u64)),
+            Felt::from(1_000u64)
+        );
+    }
+
+    #[test]
+    fn price_beyond_u128_passes_through() {
+        let mut tracker = multiple(2.0);
+        feed(&mut tracker, &[100, 100, 100]);
+        let huge = Felt::from(u128::MAX) + Felt::ONE;
+        assert_eq!(tracker.filter("l1_gas", huge), huge);
+    }
+}
@@ -0,0 +1,68 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+// How long a contract value may go without a refresh before one is forced, even inside
+// the dead-band; exceeding any configured limit counts
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MaxStaleness {
+    pub blocks: Option<u64>,
+    pub duration: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Refresh {
+    block: Option<u64>,
+    at: Instant,
+}
+
+impl Refresh {
+    fn now(block: Option<u64>) -> Self {
+        Self {
+            block,
+            at: Instant::now(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct StalenessTracker {
+    policy: MaxStaleness,
+    // Last confirmed write per component; a value counts as fresh when first seen
+    refreshed: HashMap<String, Refresh>,
+}
+
+impl StalenessTracker {
+    pub fn new(policy: MaxStaleness) -> Self {
+        Self {
+            policy,
+            refreshed: HashMap::new(),
+        }
+    }
+
+    pub fn on_refreshed(&mut self, component: &str, block: Option<u64>) {
+        self.refreshed
+            .insert(component.to_string(), Refresh::now(block));
+    }
+
+    pub fn is_stale(&mut self, component: &str, block: Option<u64>) -> bool {
+        if self.policy.blocks.is_none() && self.policy.duration.is_none() {
+            return false;
+        }
+        let refresh = *self
+            .refreshed
+            .entry(component.to_string())
+            .or_insert_with(|| Refresh::now(block));
+
+        let blocks_exceeded = match (self.policy.blocks, refresh.block, block) {
+            (Some(blocks), Some(from), Some(block)) => block.saturating_sub(from) >= blocks,
+            _ => false,
+        };
+        let duration_exceeded = self
+            .policy
+            .duration
+            .is_some_and(|duration| refresh.at.elapsed() >= duration);
+        blocks_exceeded || duration_exceeded
+    }
+}
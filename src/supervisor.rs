@@ -0,0 +1,135 @@
+use std::{
+    any::Any,
+    future::Future,
+    panic::AssertUnwindSafe,
+    time::{Duration, Instant},
+};
+
+use futures_util::FutureExt;
+use tokio::task::JoinHandle;
+use tracing::{error, warn};
+
+use crate::errors::ErrorClass;
+use crate::retry::BackoffPolicy;
+
+// A run lasting this long counts as healthy again and resets the restart count
+const HEALTHY_RUN: Duration = Duration::from_secs(60);
+
+pub const DEFAULT_MAX_RESTARTS: u32 = 5;
+
+// Restarts of a background task that panicked or failed with a retryable error
+pub fn restart_policy(max_restarts: u32) -> BackoffPolicy {
+    BackoffPolicy {
+        attempts: max_restarts,
+        base_delay: Duration::from_secs(1),
+        max_delay: Duration::from_secs(60),
+    }
+}
+
+// Runs the task `start` creates, starting a fresh one after a panic or a retryable error.
+// Fatal errors, and failures past `policy.attempts` restarts in a row, end the supervised
+// task with the error, so the daemon still exits with the matching code.
+pub fn supervise<F, Fut>(
+    name: &'static str,
+    policy: BackoffPolicy,
+    mut start: F,
+) -> JoinHandle<anyhow::Result<()>>
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut failures = 0;
+        loop {
+            let started = Instant::now();
+            let error = match tokio::spawn(start()).await {
+                Ok(Ok(())) => return Ok(()),
+                Ok(Err(e)) if !ErrorClass::of(&e).is_retryable() => return Err(e),
+                Ok(Err(e)) => e,
+                Err(e) if e.is_panic() => {
+                    anyhow::anyhow!("panicked: {}", panic_message(e.into_panic()))
+                }
+                Err(_) => return Err(anyhow::anyhow!("the {name} task was cancelled")),
+            };
+            if started.elapsed() >= HEALTHY_RUN {
+                failures = 0;
+            }
+            metrics::counter!("pp_fee_updater_task_restarts_total", "task" => name).increment(1);
+            if failures >= policy.attempts {
+                error!(
+                    "💥 The {} task failed {} times in a row, giving up: {:?}",
+                    name,
+                    failures + 1,
+                    error
+                );
+                return Err(error);
+            }
+            let delay = policy.delay(failures);
+            failures += 1;
+            warn!(
+                "💥 The {} task failed, restarting in {:?} ({}/{}): {:?}",
+                name, delay, failures, policy.attempts, error
+            );
+            tokio::time::sleep(delay).await;
+        }
+    })
+}
+
+// Catches panics of the updater's cycles, which run in the event loop and cannot be
+// restarted without losing the pending updates; the next head simply tries again
+pub struct PanicGuard {
+    name: &'static str,
+    max_panics: u32,
+    panics: u32,
+}
+
+impl PanicGuard {
+    pub fn new(name: &'static str, max_panics: u32) -> Self {
+        Self {
+            name,
+            max_panics,
+            panics: 0,
+        }
+    }
+
+    // None when the cycle panicked; an error once too many cycles panicked in a row
+    pub async fn run<T>(
+        &mut self,
+        cycle: impl Future<Output = anyhow::Result<T>>,
+    ) -> anyhow::Result<Option<T>> {
+        match AssertUnwindSafe(cycle).catch_unwind().await {
+            Ok(result) => {
+                self.panics = 0;
+                result.map(Some)
+            }
+            Err(payload) => {
+                let message = panic_message(payload);
+                metrics::counter!("pp_fee_updater_task_restarts_total", "task" => self.name)
+                    .increment(1);
+                if self.panics >= self.max_panics {
+                    error!(
+                        "💥 The {} panicked {} times in a row, giving up: {}",
+                        self.name,
+                        self.panics + 1,
+                        message
+                    );
+                    return Err(anyhow::anyhow!("the {} panicked: {}", self.name, message));
+                }
+                self.panics += 1;
+                warn!(
+                    "💥 The {} panicked, skipping the cycle ({}/{}): {}",
+                    self.name, self.panics, self.max_panics, message
+                );
+                Ok(None)
+            }
+        }
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic payload".to_string())
+}
@@ -0,0 +1,97 @@
+use std::{
+    ffi::OsStr,
+    os::linux::net::SocketAddrExt,
+    os::unix::{
+        ffi::OsStrExt,
+        net::{SocketAddr, UnixDatagram},
+    },
+    time::Duration,
+};
+
+use tracing::{debug, info, warn};
+
+// sd_notify(3) messages to the service manager; a no-op when not started by systemd with
+// `Type=notify`
+#[derive(Debug)]
+pub struct Notifier {
+    socket: Option<(UnixDatagram, SocketAddr)>,
+    watchdog: Option<Duration>,
+    ready: bool,
+}
+
+impl Notifier {
+    pub fn from_env() -> Self {
+        let socket = std::env::var_os("NOTIFY_SOCKET").and_then(|path| match connect(&path) {
+            Ok(socket) => Some(socket),
+            Err(e) => {
+                warn!("Failed to open NOTIFY_SOCKET {:?}: {}", path, e);
+                None
+            }
+        });
+        let watchdog = socket.as_ref().and_then(|_| watchdog_interval());
+        if let Some(interval) = watchdog {
+            info!("🐕 Petting the systemd watchdog every {:?}", interval);
+        }
+        Self {
+            socket,
+            watchdog,
+            ready: false,
+        }
+    }
+
+    // How often the event loop has to call `watchdog`, half the configured timeout
+    pub fn watchdog_interval(&self) -> Option<Duration> {
+        self.watchdog
+    }
+
+    // Startup is complete once the first new heads subscription is confirmed; later
+    // reconnects only update the status
+    pub fn ready(&mut self, status: &str) {
+        if self.ready {
+            self.notify(&format!("STATUS={status}"));
+        } else {
+            self.ready = true;
+            self.notify(&format!("READY=1\nSTATUS={status}"));
+        }
+    }
+
+    pub fn status(&self, status: &str) {
+        self.notify(&format!("STATUS={status}"));
+    }
+
+    pub fn watchdog(&self) {
+        self.notify("WATCHDOG=1");
+    }
+
+    pub fn stopping(&self) {
+        self.notify("STOPPING=1");
+    }
+
+    fn notify(&self, message: &str) {
+        if let Some((socket, addr)) = &self.socket {
+            if let Err(e) = socket.send_to_addr(message.as_bytes(), addr) {
+                debug!("Failed to notify systemd: {}", e);
+            }
+        }
+    }
+}
+
+fn connect(path: &OsStr) -> std::io::Result<(UnixDatagram, SocketAddr)> {
+    // A leading `@` names a socket in the abstract namespace
+    let addr = match path.as_bytes().strip_prefix(b"@") {
+        Some(name) => SocketAddr::from_abstract_name(name)?,
+        None => SocketAddr::from_pathname(path)?,
+    };
+    Ok((UnixDatagram::unbound()?, addr))
+}
+
+// WATCHDOG_USEC, unless WATCHDOG_PID names another process
+fn watchdog_interval() -> Option<Duration> {
+    if let Ok(pid) = std::env::var("WATCHDOG_PID") {
+        if pid.parse::<u32>().ok() != Some(std::process::id()) {
+            return None;
+        }
+    }
+    let usec = std::env::var("WATCHDOG_USEC").ok()?.parse::<u64>().ok()?;
+    (usec > 0).then(|| Duration::from_micros(usec / 2))
+}
@@ -0,0 +1,198 @@
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use axum::{routing::get, Router};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use metrics_exporter_statsd::StatsdBuilder;
+use metrics_util::layers::FanoutBuilder;
+use tokio::net::TcpListener;
+use tracing::info;
+use url::Url;
+
+use crate::retry::BackoffPolicy;
+use crate::supervisor;
+
+// How often histograms drop samples outside their window
+const UPKEEP_INTERVAL: Duration = Duration::from_secs(5);
+// How often the health of the process itself is sampled
+const PROCESS_METRICS_INTERVAL: Duration = Duration::from_secs(15);
+
+// A StatsD agent metrics are sent to over UDP; labels go out as DogStatsD tags
+#[derive(Debug, Clone)]
+pub struct StatsdOptions {
+    // `host:port` of the agent
+    pub addr: String,
+    pub prefix: Option<String>,
+    // `key:value` tags added to every metric
+    pub tags: Vec<String>,
+}
+
+// Install the recorder of every configured backend; both can run at once since the
+// recorders are fanned out. Nothing is recorded when neither is set.
+pub async fn install_metrics_exporter(
+    prometheus: Option<SocketAddr>,
+    statsd: Option<&StatsdOptions>,
+    process_metrics: bool,
+    restarts: BackoffPolicy,
+) -> anyhow::Result<()> {
+    if prometheus.is_none() && statsd.is_none() {
+        return Ok(());
+    }
+    let mut fanout = FanoutBuilder::default();
+    if let Some(statsd) = statsd {
+        fanout = fanout.add_recorder(statsd_recorder(statsd)?);
+        info!("📈 Sending StatsD metrics to {}", statsd.addr);
+    }
+    if let Some(addr) = prometheus {
+        let recorder = PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+        fanout = fanout.add_recorder(recorder);
+        serve_prometheus(addr, handle, restarts).await?;
+    }
+    metrics::set_global_recorder(fanout.build())
+        .map_err(|_| anyhow::anyhow!("A metrics recorder is already installed"))?;
+    if process_metrics {
+        sample_process_metrics(restarts);
+    }
+    Ok(())
+}
+
+// A restarted sampler keeps counting the uptime from the first start
+fn sample_process_metrics(restarts: BackoffPolicy) {
+    let started = Instant::now();
+    supervisor::supervise("process metrics", restarts, move || {
+        sample_process_metrics_every(started, PROCESS_METRICS_INTERVAL)
+    });
+}
+
+async fn sample_process_metrics_every(started: Instant, period: Duration) -> anyhow::Result<()> {
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        record_process_metrics(started);
+    }
+}
+
+// Memory, file descriptors and tasks of the process, so a leak in the long-running daemon
+// shows before it gets OOM-killed. Memory and descriptors come from /proc and are only
+// reported on Linux.
+fn record_process_metrics(started: Instant) {
+    metrics::gauge!("pp_fee_updater_uptime_seconds").set(started.elapsed().as_secs_f64());
+    let runtime = tokio::runtime::Handle::current().metrics();
+    metrics::gauge!("pp_fee_updater_tokio_alive_tasks").set(runtime.num_alive_tasks() as f64);
+    metrics::gauge!("pp_fee_updater_tokio_workers").set(runtime.num_workers() as f64);
+    // `VmRSS:     12345 kB`
+    let resident_kb = std::fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| {
+            status
+                .lines()
+                .find_map(|line| line.strip_prefix("VmRSS:"))
+                .and_then(|value| value.trim_end_matches("kB").trim().parse::<u64>().ok())
+        });
+    if let Some(resident_kb) = resident_kb {
+        metrics::gauge!("pp_fee_updater_process_resident_memory_bytes")
+            .set((resident_kb * 1024) as f64);
+    }
+    if let Ok(fds) = std::fs::read_dir("/proc/self/fd") {
+        metrics::gauge!("pp_fee_updater_process_open_fds").set(fds.count() as f64);
+    }
+}
+
+fn statsd_recorder(options: &StatsdOptions) -> anyhow::Result<impl metrics::Recorder> {
+    let (host, port) = options
+        .addr
+        .rsplit_once(':')
+        .and_then(|(host, port)| Some((host, port.parse::<u16>().ok()?)))
+        .with_context(|| format!("Invalid StatsD address {}", options.addr))?;
+    // Histograms become distributions, aggregated by the agent across replicas
+    let mut builder = StatsdBuilder::from(host, port).histogram_is_distribution();
+    for tag in &options.tags {
+        let (key, value) = tag
+            .split_once(':')
+            .with_context(|| format!("Invalid StatsD tag {tag}, expected key:value"))?;
+        builder = builder.with_default_tag(key.trim(), value.trim());
+    }
+    Ok(builder.build(options.prefix.as_deref())?)
+}
+
+// Serve Prometheus metrics on the given address; binding happens before returning so a
+// taken port fails startup, and a failed server is restarted on a fresh socket
+async fn serve_prometheus(
+    addr: SocketAddr,
+    handle: PrometheusHandle,
+    restarts: BackoffPolicy,
+) -> anyhow::Result<()> {
+    let bound = Arc::new(Mutex::new(Some(TcpListener::bind(addr).await?)));
+    info!("📈 Serving Prometheus metrics on http://{}/metrics", addr);
+
+    let upkeep = handle.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(UPKEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            upkeep.run_upkeep();
+        }
+    });
+    supervisor::supervise("metrics server", restarts, move || {
+        let handle = handle.clone();
+        let bound = bound.clone();
+        async move {
+            let bound = bound.lock().unwrap_or_else(|e| e.into_inner()).take();
+            let listener = match bound {
+                Some(listener) => listener,
+                None => TcpListener::bind(addr).await?,
+            };
+            let app = Router::new().route("/metrics", get(move || async move { handle.render() }));
+            axum::serve(listener, app).await?;
+            Err(anyhow::anyhow!("the metrics server stopped"))
+        }
+    });
+    Ok(())
+}
+
+// Metrics of a one-shot run, which no scrape would catch, pushed to a Prometheus
+// Pushgateway when the run ends
+pub struct Pushgateway {
+    url: Url,
+    job: String,
+    handle: PrometheusHandle,
+    started: Instant,
+}
+
+impl Pushgateway {
+    pub fn install(url: Url, job: String) -> anyhow::Result<Self> {
+        let handle = PrometheusBuilder::new().install_recorder()?;
+        Ok(Self {
+            url,
+            job,
+            handle,
+            started: Instant::now(),
+        })
+    }
+
+    // Replaces the metrics last pushed for the job; the process metrics are sampled once,
+    // at the end of the run
+    pub async fn push(&self) -> anyhow::Result<()> {
+        record_process_metrics(self.started);
+        let url = format!(
+            "{}/metrics/job/{}",
+            self.url.as_str().trim_end_matches('/'),
+            self.job
+        );
+        reqwest::Client::new()
+            .put(&url)
+            .header("Content-Type", "text/plain; version=0.0.4")
+            .body(self.handle.render())
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .with_context(|| format!("Failed to push metrics to {}", self.url))?;
+        info!("📤 Pushed metrics of job `{}` to {}", self.job, self.url);
+        Ok(())
+    }
+}
@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+use starknet::{
+    core::types::{BlockId, BlockTag, Felt, FunctionCall},
+    providers::Provider,
+};
+
+use crate::calldata::decode_u128;
+use crate::components::selector;
+use crate::endpoints::RpcClient;
+use crate::oracle::PragmaOracle;
+use crate::updater::UpdaterError;
+
+// A fee token the paymaster accepts, as written in the config file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TokenConfig {
+    pub symbol: String,
+    pub address: Felt,
+    pub rate: RateConfig,
+    // The rate is scaled by 10^decimals; Pragma rates carry their own decimals
+    #[serde(default)]
+    pub rate_decimals: u32,
+}
+
+// Where a token's exchange rate against the denomination (fri or wei) comes from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum RateConfig {
+    Fixed {
+        value: u128,
+    },
+    // An entrypoint returning the rate as a felt or u256
+    Contract {
+        address: Felt,
+        getter: String,
+        #[serde(default)]
+        args: Vec<Felt>,
+    },
+    // Median spot price of a Pragma pair, e.g. `STRK/USD`; `invert` divides by it instead,
+    // for pairs quoted the other way round such as `ETH/STRK`
+    Pragma {
+        oracle: Felt,
+        pair: String,
+        #[serde(default)]
+        invert: bool,
+        #[serde(default)]
+        max_age_secs: Option<u64>,
+    },
+}
+
+impl TokenConfig {
+    pub fn resolve(&self) -> Result<FeeToken, UpdaterError> {
+        let rate = match &self.rate {
+            RateConfig::Fixed { value } => RateSource::Fixed(*value),
+            RateConfig::Contract {
+                address,
+                getter,
+                args,
+            } => RateSource::Contract {
+                address: *address,
+                getter: selector(getter)?,
+                args: args.clone(),
+            },
+            RateConfig::Pragma {
+                oracle,
+                pair,
+                invert,
+                max_age_secs,
+            } => RateSource::Pragma {
+                oracle: PragmaOracle::new(*oracle, pair, *max_age_secs)?,
+                invert: *invert,
+            },
+        };
+        Ok(FeeToken {
+            symbol: self.symbol.clone(),
+            address: self.address,
+            rate,
+            rate_decimals: self.rate_decimals,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum RateSource {
+    Fixed(u128),
+    Contract {
+        address: Felt,
+        getter: Felt,
+        args: Vec<Felt>,
+    },
+    Pragma {
+        oracle: PragmaOracle,
+        invert: bool,
+    },
+}
+
+// An exchange rate as read for one check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rate {
+    pub value: u128,
+    pub decimals: u32,
+    pub invert: bool,
+}
+
+// A fee token with its resolved rate source; per-token components convert into its units
+#[derive(Debug, Clone)]
+pub struct FeeToken {
+    pub symbol: String,
+    pub address: Felt,
+    pub rate: RateSource,
+    pub rate_decimals: u32,
+}
+
+impl FeeToken {
+    pub async fn fetch_rate(&self, provider: &RpcClient) -> Result<Rate, UpdaterError> {
+        let rate = |value| Rate {
+            value,
+            decimals: self.rate_decimals,
+            invert: false,
+        };
+        match &self.rate {
+            RateSource::Fixed(value) => Ok(rate(*value)),
+            RateSource::Contract {
+                address,
+                getter,
+                args,
+            } => {
+                let result = provider
+                    .call(
+                        FunctionCall {
+                            calldata: args.clone(),
+                            contract_address: *address,
+                            entry_point_selector: *getter,
+                        },
+                        BlockId::Tag(BlockTag::Latest),
+                    )
+                    .await?;
+                let value = decode_u128(&result).map_err(|e| match e {
+                    UpdaterError::Conversion(reason) => UpdaterError::Conversion(format!(
+                        "{} exchange rate: {}",
+                        self.symbol, reason
+                    )),
+                    e => e,
+                })?;
+                Ok(rate(value))
+            }
+            RateSource::Pragma { oracle, invert } => {
+                let price = oracle.spot_median(provider).await?;
+                Ok(Rate {
+                    value: price.price,
+                    decimals: price.decimals,
+                    invert: *invert,
+                })
+            }
+        }
+    }
+
+    // Price in token units: price * rate / 10^decimals, or price * 10^decimals / rate
+    pub fn convert(&self, price: Felt, rate: Rate) -> Result<Felt, UpdaterError> {
+        let overflow =
+            || UpdaterError::Conversion(format!("{} price conversion overflows u128", self.symbol));
+        let price: u128 = price.to_biguint().try_into().map_err(|_| overflow())?;
+        let scale = 10u128.checked_pow(rate.decimals).ok_or_else(overflow)?;
+        let converted = if rate.invert {
+            if rate.value == 0 {
+                return Err(UpdaterError::Conversion(format!(
+                    "{} exchange rate is zero",
+                    self.symbol
+                )));
+            }
+            price.checked_mul(scale).ok_or_else(overflow)? / rate.value
+        } else {
+            price.checked_mul(rate.value).ok_or_else(overflow)? / scale
+        };
+        Ok(Felt::from(converted))
+    }
+}
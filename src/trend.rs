@@ -0,0 +1,150 @@
+use std::collections::{HashMap, VecDeque};
+
+use starknet_types_core::felt::Felt;
+
+// Readings a trend is fitted on at the least
+const MIN_SAMPLES: usize = 3;
+// Share of the variance the line has to explain for a rise to count as steady
+const MIN_R_SQUARED: f64 = 0.8;
+
+// Prices a steady rise ahead: a line is fitted over the recent network prices and, when
+// it climbs steadily, an upward update adds what it projects over the horizon
+#[derive(Debug, Clone, Copy)]
+pub struct TrendPricing {
+    // Readings the line is fitted over
+    pub window: usize,
+    // Readings ahead the headroom covers
+    pub horizon: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct TrendTracker {
+    policy: Option<TrendPricing>,
+    readings: HashMap<String, VecDeque<u128>>,
+}
+
+impl TrendTracker {
+    pub fn new(policy: TrendPricing) -> Self {
+        Self {
+            policy: Some(policy),
+            readings: HashMap::new(),
+        }
+    }
+
+    // Records the reading and returns the headroom of a steady rise, zero otherwise
+    pub fn headroom(&mut self, component: &str, price: Felt) -> u128 {
+        let Some(policy) = self.policy else {
+            return 0;
+        };
+        let Ok(reading) = u128::try_from(price.to_biguint()) else {
+            return 0;
+        };
+        let readings = self.readings.entry(component.to_string()).or_default();
+        readings.push_back(reading);
+        while readings.len() > policy.window.max(MIN_SAMPLES) {
+            readings.pop_front();
+        }
+        match rising_slope(readings) {
+            Some(slope) => (slope * policy.horizon as f64) as u128,
+            None => 0,
+        }
+    }
+}
+
+// Least-squares slope per reading, when positive and steady
+fn rising_slope(readings: &VecDeque<u128>) -> Option<f64> {
+    if readings.len() < MIN_SAMPLES {
+        return None;
+    }
+    let count = readings.len() as f64;
+    let mean_x = (count - 1.0) / 2.0;
+    let mean_y = readings.iter().map(|price| *price as f64).sum::<f64>() / count;
+    let (mut covariance, mut variance_x, mut variance_y) = (0.0, 0.0, 0.0);
+    for (index, price) in readings.iter().enumerate() {
+        let dx = index as f64 - mean_x;
+        let dy = *price as f64 - mean_y;
+        covariance += dx * dy;
+        variance_x += dx * dx;
+        variance_y += dy * dy;
+    }
+    if covariance <= 0.0 || variance_y == 0.0 {
+        return None;
+    }
+    let r_squared = covariance * covariance / (variance_x * variance_y);
+    (r_squared >= MIN_R_SQUARED).then_some(covariance / variance_x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker(window: usize, horizon: u64) -> TrendTracker {
+        TrendTracker::new(TrendPricing { window, horizon })
+    }
+
+    fn feed(tracker: &mut TrendTracker, readings: &[u64]) -> Vec<u128> {
+        readings
+            .iter()
+            .map(|reading| tracker.headroom("l1_gas", Felt::from(*reading)))
+            .collect()
+    }
+
+    #[test]
+    fn without_a_policy_there_is_no_headroom() {
+        let mut tracker = TrendTracker::default();
+        assert_eq!(feed(&mut tracker, &[100, 110, 120]), vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn no_line_is_fitted_on_fewer_than_three_readings() {
+        let mut tracker = tracker(10, 5);
+        assert_eq!(feed(&mut tracker, &[100, 110]), vec![0, 0]);
+    }
+
+    #[test]
+    fn steady_rise_is_projected_over_the_horizon() {
+        let mut tracker = tracker(10, 5);
+        assert_eq!(feed(&mut tracker, &[100, 110, 120]), vec![0, 0, 50]);
+    }
+
+    #[test]
+    fn flat_or_falling_prices_get_no_headroom() {
+        assert_eq!(feed(&mut tracker(10, 5), &[100, 100, 100]), vec![0, 0, 0]);
+        assert_eq!(feed(&mut tracker(10, 5), &[120, 110, 100]), vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn rise_the_line_does_not_explain_gets_no_headroom() {
+        // Rising on the whole, yet r² is 0.2
+        let mut tracker = tracker(10, 5);
+        assert_eq!(feed(&mut tracker, &[100, 200, 100, 200]), vec![0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn line_is_fitted_over_the_window_only() {
+        let mut tracker = tracker(3, 5);
+        assert_eq!(
+            feed(&mut tracker, &[300, 200, 100, 110, 120]),
+            vec![0, 0, 0, 0, 50]
+        );
+    }
+
+    #[test]
+    fn window_below_three_readings_still_fits_three() {
+        let mut tracker = tracker(0, 5);
+        assert_eq!(
+            feed(&mut tracker, &[100, 110, 120, 130]),
+            vec![0, 0, 50, 50]
+        );
+    }
+
+    #[test]
+    fn price_beyond_u128_gets_no_headroom() {
+        let mut tracker = tracker(10, 5);
+        feed(&mut tracker, &[100, 110]);
+        assert_eq!(
+            tracker.headroom("l1_gas", Felt::from(u128::MAX) + Felt::ONE),
+            0
+        );
+    }
+}
@@ -1,15 +1,41 @@
+use std::collections::{HashMap, HashSet};
+
+use futures_util::{stream, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
 use starknet::{
-    accounts::{Account, ExecutionEncoding, SingleOwnerAccount},
-    core::{
-        types::{BlockId, BlockTag, Call, Felt, FunctionCall},
-        utils::get_selector_from_name,
+    accounts::{Account, AccountError, ConnectedAccount, ExecutionEncoding, SingleOwnerAccount},
+    core::types::{
+        BlockHeader, BlockId, BlockTag, Call, ContractClass, ExecuteInvocation, Felt, FunctionCall,
+        InvokeTransaction, InvokeTransactionTrace, MaybePendingBlockWithTxHashes, ResourcePrice,
+        StarknetError, Transaction, TransactionTrace,
     },
-    providers::{jsonrpc::HttpTransport, JsonRpcClient, Provider, Url},
+    providers::{Provider, ProviderError},
     signers::{LocalWallet, SigningKey},
 };
 use thiserror::Error;
 use tracing::{debug, error, info, warn};
 
+use crate::alerts::{AlertSeverity, Alerter};
+use crate::balance::fetch_token_balance;
+use crate::breaches::{BreachConfirmations, BreachTracker};
+use crate::calldata::{decode_price, CalldataLayout};
+use crate::components::{percent_of, Denomination, Direction, FeeComponent, StrategyPatch};
+use crate::cooldown::{Cooldown, CooldownTracker};
+use crate::endpoints::{EndpointPool, RpcClient};
+use crate::errors::{classify_provider_error, ErrorClass};
+use crate::fees::ResourceBounds;
+use crate::latency::HeadTimer;
+use crate::logging::BLOCKS;
+use crate::overrides::{ChangeOrigin, OverridePolicy, OverrideTracker};
+use crate::percentile::{PercentileTarget, PercentileTracker};
+use crate::queue::SendQueue;
+use crate::quorum::Quorum;
+use crate::retry::{retry_transient, BackoffPolicy};
+use crate::sources::{CompositeSource, SourceContext};
+use crate::spikes::{SpikeFilter, SpikeTracker};
+use crate::staleness::{MaxStaleness, StalenessTracker};
+use crate::tokens::Rate;
+use crate::trend::{TrendPricing, TrendTracker};
 
 #[derive(Error, Debug)]
 pub enum UpdaterError {
@@ -23,105 +49,1523 @@ pub enum UpdaterError {
     InvalidGasPrice(String),
     #[error("Transaction failed or reverted")]
     TransactionFailed,
+    #[error("Quorum not reached: {0}")]
+    Quorum(String),
+    #[error("Configuration error: {0}")]
+    Configuration(String),
+    #[error("Price oracle error: {0}")]
+    Oracle(String),
+    #[error("Transaction would revert: {0}")]
+    Simulation(String),
+    #[error("Devnet error: {0}")]
+    Devnet(String),
+}
+
+impl UpdaterError {
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            UpdaterError::Provider(e) => classify_provider_error(e),
+            UpdaterError::Account(_) => ErrorClass::Signer,
+            UpdaterError::TransactionFailed | UpdaterError::Simulation(_) => {
+                ErrorClass::ContractRevert
+            }
+            UpdaterError::Quorum(_) => ErrorClass::Network,
+            UpdaterError::Configuration(_) | UpdaterError::Devnet(_) => ErrorClass::Configuration,
+            UpdaterError::Conversion(_)
+            | UpdaterError::InvalidGasPrice(_)
+            | UpdaterError::Oracle(_) => ErrorClass::Internal,
+        }
+    }
+
+    // The node already holds the transaction, or its nonce is used up
+    fn is_already_sent(&self) -> bool {
+        matches!(
+            self,
+            UpdaterError::Provider(ProviderError::StarknetError(
+                StarknetError::DuplicateTx | StarknetError::InvalidTransactionNonce
+            ))
+        )
+    }
+
+    // Whether the same call may succeed if simply tried again
+    pub fn is_transient(&self) -> bool {
+        self.class() == ErrorClass::Network
+    }
+}
+
+// Which block the network gas prices are read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, clap::ValueEnum)]
+pub enum PriceBlock {
+    /// The latest accepted block
+    Latest,
+    /// The pending block: reacts one block earlier, but its prices may still change
+    Pending,
+}
+
+impl PriceBlock {
+    fn tag(self) -> BlockTag {
+        match self {
+            PriceBlock::Latest => BlockTag::Latest,
+            PriceBlock::Pending => BlockTag::Pending,
+        }
+    }
+}
+
+// How the owner account expects the calls of a multicall to be encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, clap::ValueEnum)]
+pub enum AccountEncoding {
+    /// Legacy for a Cairo 0 account class, new otherwise
+    Auto,
+    /// Cairo 1 accounts
+    New,
+    /// Cairo 0 accounts
+    Legacy,
+}
+
+impl AccountEncoding {
+    async fn resolve(
+        self,
+        provider: &RpcClient,
+        account: Felt,
+    ) -> Result<ExecutionEncoding, UpdaterError> {
+        match self {
+            AccountEncoding::New => Ok(ExecutionEncoding::New),
+            AccountEncoding::Legacy => Ok(ExecutionEncoding::Legacy),
+            AccountEncoding::Auto => {
+                let class = provider
+                    .get_class_at(BlockId::Tag(BlockTag::Latest), account)
+                    .await?;
+                let (kind, encoding) = match class {
+                    ContractClass::Legacy(_) => ("Cairo 0", ExecutionEncoding::Legacy),
+                    ContractClass::Sierra(_) => ("Cairo 1", ExecutionEncoding::New),
+                };
+                info!(
+                    "🔑 Account {:#x} is a {} class, using the {} execution encoding",
+                    account,
+                    kind,
+                    match encoding {
+                        ExecutionEncoding::Legacy => "legacy",
+                        ExecutionEncoding::New => "new",
+                    }
+                );
+                Ok(encoding)
+            }
+        }
+    }
+}
+
+// What happens to the updates decided for replayed historical heads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, clap::ValueEnum)]
+pub enum ReplayMode {
+    /// Log and record the decisions without sending anything
+    DryRun,
+    /// Send the updates as for live heads
+    Live,
+}
+
+// Resource prices of a block, which every component is sourced from
+#[derive(Debug, Clone)]
+pub struct BlockPrices {
+    pub l1_gas: ResourcePrice,
+    pub l1_data_gas: ResourcePrice,
+    pub l2_gas: ResourcePrice,
+}
+
+impl BlockPrices {
+    // Prices carried by a `starknet_subscriptionNewHeads` notification
+    pub fn from_header(header: &serde_json::Value) -> Option<Self> {
+        match BlockHeader::deserialize(header) {
+            Ok(header) => Some(Self {
+                l1_gas: header.l1_gas_price,
+                l1_data_gas: header.l1_data_gas_price,
+                l2_gas: header.l2_gas_price,
+            }),
+            Err(e) => {
+                warn!("Could not read gas prices from the block header: {}", e);
+                None
+            }
+        }
+    }
+}
+
+// A single component write included in an update transaction
+#[derive(Debug, Clone)]
+pub struct ComponentUpdate {
+    pub component: String,
+    pub contract_address: Felt,
+    pub getter: Felt,
+    pub setter: Felt,
+    pub layout: CalldataLayout,
+    pub gas_price: Felt,
+}
+
+// Where a check takes the block prices from
+enum CheckPrices {
+    Header(Option<BlockPrices>),
+    Replayed(BlockPrices),
 }
 
 // Structure to track pending update with transaction hash
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct PendingUpdate {
-    pub gas_price: Felt,
+    pub updates: Vec<ComponentUpdate>,
     pub tx_hash: Felt,
+    // Account that sent it, busy until it resolves
+    pub sender: Felt,
+    // Latest status pushed over a transaction status subscription, if any
+    pub pushed_status: Option<TransactionStatus>,
+    // Head of the check that sent it
+    pub sent_block: Option<u64>,
+}
+
+// Outcome of a fee check against the network and contract prices
+#[derive(Debug, Clone)]
+pub struct FeeDecision {
+    pub component: String,
+    pub should_update: bool,
+    pub new_gas_price: Felt,
+    pub network_gas_price: u128,
+    pub contract_gas_price: u128,
+    // Side of the dead-band the network price is on
+    pub direction: Direction,
+    // Set when the target price was held back by the absolute bounds
+    pub clamp: Option<Clamp>,
+    pub update: Option<ComponentUpdate>,
+}
+
+impl FeeDecision {
+    // Keep the contract price this time
+    fn hold(&mut self) {
+        self.should_update = false;
+        self.update = None;
+        self.new_gas_price = Felt::ZERO;
+    }
+}
+
+// A contract value that moved without a transaction of ours
+#[derive(Debug, Clone)]
+pub struct PriceChange {
+    pub component: String,
+    pub block_number: Option<u64>,
+    pub previous: Felt,
+    pub current: Felt,
+    pub origin: ChangeOrigin,
+    // Known when the change was announced by the pool's event
+    pub tx_hash: Option<Felt>,
+    pub sender: Option<Felt>,
+}
+
+// A transaction of ours that stopped being tracked, and how it ended
+#[derive(Debug, Clone)]
+pub struct ResolvedUpdate {
+    pub pending: PendingUpdate,
+    // Head at which it was resolved
+    pub block_number: Option<u64>,
+    pub outcome: TxOutcome,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TxOutcome {
+    Confirmed,
+    Failed,
+    // The status check errored and the transaction was dropped from tracking
+    Unknown,
+    // Still unknown to the node once the pending expiry ran out, dropped so the update is
+    // sent again
+    Expired,
+}
+
+// Network and contract prices this many times apart usually mean a unit or denomination
+// bug rather than a market move, so the update waits for an operator
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DivergenceGuard {
+    pub max_factor: Option<u128>,
+    // Only alert, and send anyway
+    pub force: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Clamp {
+    pub target: u128,
+    pub bounded: u128,
 }
 
 // Enum to represent transaction status
-#[derive(Debug)]
-enum TransactionStatus {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionStatus {
     Confirmed,
     Failed,
     Pending,
 }
 
-pub async fn check_fee_update(
-    url: Url,
+impl TransactionStatus {
+    // Parse the result of a `starknet_subscriptionTransactionStatus` notification
+    pub fn from_notification(result: &serde_json::Value) -> Option<(Felt, Self)> {
+        let tx_hash = Felt::from_hex(result.get("transaction_hash")?.as_str()?).ok()?;
+        let status = result.get("status")?;
+        let finality = status.get("finality_status")?.as_str()?;
+        let execution = status
+            .get("execution_status")
+            .and_then(serde_json::Value::as_str);
+
+        let status = match (finality, execution) {
+            ("REJECTED", _) | (_, Some("REVERTED")) => TransactionStatus::Failed,
+            ("ACCEPTED_ON_L2" | "ACCEPTED_ON_L1", _) => TransactionStatus::Confirmed,
+            _ => TransactionStatus::Pending,
+        };
+        Some((tx_hash, status))
+    }
+
+    pub fn is_final(self) -> bool {
+        self != TransactionStatus::Pending
+    }
+}
+
+pub const DEFAULT_CHECK_CONCURRENCY: usize = 8;
+pub const DEFAULT_PENDING_EXPIRY_BLOCKS: u64 = 20;
+
+// An account allowed to write the prices
+#[derive(Debug, Clone, Copy)]
+pub struct SenderKey {
+    pub address: Felt,
+    pub private_key: Felt,
+}
+
+// Senders whose STRK balance is below `min` are passed over
+#[derive(Debug, Clone, Copy)]
+pub struct SenderBalance {
+    pub token: Felt,
+    pub min: u128,
+}
+
+type SenderAccount = SingleOwnerAccount<RpcClient, LocalWallet>;
+
+// Long-lived updater holding the provider and sender accounts across blocks
+pub struct Updater {
+    provider: RpcClient,
+    // The owner first, then any further senders, used in turn
+    accounts: Vec<SenderAccount>,
+    next_sender: usize,
+    sender_balance: Option<SenderBalance>,
     contract_address: Felt,
-    pending_update: &mut Option<PendingUpdate>,
-    upward_threshold_const: u128,
-    downward_threshold_const: u128,
-    upward_buffer_const: u128,
-    downward_buffer_const: u128,
-) -> Result<(bool, Felt), UpdaterError> {
-    let provider = JsonRpcClient::new(HttpTransport::new(url));
-
-    // If there's a pending update, first check if it was confirmed or failed
-    if let Some(pending) = *pending_update {
-        info!("⏳ Checking status of pending transaction: {:?}", pending.tx_hash);
-
-        match check_transaction_status(
-            &provider,
-            pending.tx_hash,
+    components: Vec<FeeComponent>,
+    pending_updates: Vec<PendingUpdate>,
+    quorum: Option<Quorum>,
+    backoff: BackoffPolicy,
+    // The RPC pool resends a failed call itself, so sends are not retried on top of it;
+    // it also tells whether a send went out more than once
+    pool: EndpointPool,
+    price_block: PriceBlock,
+    denomination: Denomination,
+    price_sources: Option<CompositeSource>,
+    alerter: Option<Alerter>,
+    cooldown: CooldownTracker,
+    spikes: SpikeTracker,
+    percentiles: PercentileTracker,
+    trends: TrendTracker,
+    breaches: BreachTracker,
+    staleness: StalenessTracker,
+    // Components whose target is currently clamped, alerted once per episode
+    clamped: HashSet<String>,
+    divergence: DivergenceGuard,
+    // Components currently diverging, alerted once per episode
+    divergent: HashSet<String>,
+    // Divergent updates an operator let through, one each
+    confirmed_divergent: HashSet<String>,
+    paused: bool,
+    // Cleared on replicas that do not hold the leader lock
+    leader: bool,
+    // Contract reads in flight at once during a check
+    check_concurrency: usize,
+    // Dry-run every transaction before broadcasting it
+    simulate: bool,
+    resource_bounds: ResourceBounds,
+    // Contract value of each component as last read or confirmed
+    known_prices: HashMap<String, Felt>,
+    overrides: OverrideTracker,
+    // Detected since the daemon last took them
+    outside_changes: Vec<PriceChange>,
+    // Resolved since the daemon last took them
+    resolved: Vec<ResolvedUpdate>,
+    // Times the head being checked until its update is broadcast
+    head_timer: Option<HeadTimer>,
+    // Head of the latest check
+    head: Option<u64>,
+    // Blocks after which a transaction the node does not know is given up
+    pending_expiry: u64,
+}
+
+impl Updater {
+    // `senders` starts with the owner account
+    pub async fn new(
+        rpc: &EndpointPool,
+        contract_address: Felt,
+        senders: &[SenderKey],
+        chain_id: Felt,
+        components: Vec<FeeComponent>,
+        encoding: AccountEncoding,
+    ) -> Result<Self, UpdaterError> {
+        let provider = rpc.client();
+
+        let mut accounts = Vec::with_capacity(senders.len());
+        for sender in senders {
+            let encoding = encoding.resolve(&provider, sender.address).await?;
+            accounts.push(SingleOwnerAccount::new(
+                provider.clone(),
+                LocalWallet::from(SigningKey::from_secret_scalar(sender.private_key)),
+                sender.address,
+                chain_id,
+                encoding,
+            ));
+        }
+
+        Ok(Self {
+            provider,
+            accounts,
+            next_sender: 0,
+            sender_balance: None,
             contract_address,
-            pending.gas_price,
-        )
-        .await
+            components,
+            pending_updates: Vec::new(),
+            quorum: None,
+            backoff: BackoffPolicy::default(),
+            pool: rpc.clone(),
+            price_block: PriceBlock::Latest,
+            denomination: Denomination::Fri,
+            price_sources: None,
+            alerter: None,
+            cooldown: CooldownTracker::default(),
+            spikes: SpikeTracker::default(),
+            percentiles: PercentileTracker::default(),
+            trends: TrendTracker::default(),
+            breaches: BreachTracker::default(),
+            staleness: StalenessTracker::default(),
+            clamped: HashSet::new(),
+            divergence: DivergenceGuard::default(),
+            divergent: HashSet::new(),
+            confirmed_divergent: HashSet::new(),
+            paused: false,
+            leader: true,
+            check_concurrency: DEFAULT_CHECK_CONCURRENCY,
+            simulate: true,
+            resource_bounds: ResourceBounds::default(),
+            known_prices: HashMap::new(),
+            overrides: OverrideTracker::default(),
+            outside_changes: Vec::new(),
+            resolved: Vec::new(),
+            head_timer: None,
+            head: None,
+            pending_expiry: DEFAULT_PENDING_EXPIRY_BLOCKS,
+        })
+    }
+
+    // Retry reads and sends that hit a transient RPC error instead of skipping the block
+    pub fn with_backoff(mut self, backoff: BackoffPolicy) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn with_price_block(mut self, price_block: PriceBlock) -> Self {
+        self.price_block = price_block;
+        self
+    }
+
+    pub fn with_denomination(mut self, denomination: Denomination) -> Self {
+        self.denomination = denomination;
+        self
+    }
+
+    // Fold further gas price sources into the block prices
+    pub fn with_price_sources(mut self, price_sources: CompositeSource) -> Self {
+        self.price_sources = Some(price_sources);
+        self
+    }
+
+    pub fn with_check_concurrency(mut self, check_concurrency: usize) -> Self {
+        self.check_concurrency = check_concurrency.max(1);
+        self
+    }
+
+    pub fn with_simulation(mut self, simulate: bool) -> Self {
+        self.simulate = simulate;
+        self
+    }
+
+    // Fix some or all v3 resource bounds instead of deriving them from the fee estimate
+    pub fn with_resource_bounds(mut self, resource_bounds: ResourceBounds) -> Self {
+        self.resource_bounds = resource_bounds;
+        self
+    }
+
+    pub fn with_sender_balance(mut self, sender_balance: SenderBalance) -> Self {
+        self.sender_balance = Some(sender_balance);
+        self
+    }
+
+    pub fn with_cooldown(mut self, cooldown: Cooldown) -> Self {
+        self.cooldown = CooldownTracker::new(cooldown);
+        self
+    }
+
+    pub fn with_spike_filter(mut self, filter: SpikeFilter) -> Self {
+        self.spikes = SpikeTracker::new(filter);
+        self
+    }
+
+    pub fn with_percentile_target(mut self, target: PercentileTarget) -> Self {
+        self.percentiles = PercentileTracker::new(target);
+        self
+    }
+
+    pub fn with_trend_pricing(mut self, trend: TrendPricing) -> Self {
+        self.trends = TrendTracker::new(trend);
+        self
+    }
+
+    pub fn with_breach_confirmations(mut self, confirmations: BreachConfirmations) -> Self {
+        self.breaches = BreachTracker::new(confirmations);
+        self
+    }
+
+    pub fn with_divergence_guard(mut self, guard: DivergenceGuard) -> Self {
+        self.divergence = guard;
+        self
+    }
+
+    pub fn with_max_staleness(mut self, max_staleness: MaxStaleness) -> Self {
+        self.staleness = StalenessTracker::new(max_staleness);
+        self
+    }
+
+    // Classify outside price changes and hold manual ones for a grace period
+    pub fn with_overrides(mut self, overrides: OverridePolicy) -> Self {
+        self.overrides = OverrideTracker::new(overrides);
+        self
+    }
+
+    pub fn with_alerter(mut self, alerter: Alerter) -> Self {
+        self.alerter = Some(alerter);
+        self
+    }
+
+    // A sent transaction the node still does not know this many blocks later was lost, and
+    // is dropped so its update is sent again
+    pub fn with_pending_expiry(mut self, blocks: u64) -> Self {
+        self.pending_expiry = blocks.max(1);
+        self
+    }
+
+    // Read prices from several providers instead of the single best endpoint
+    pub fn with_quorum(mut self, quorum: Quorum) -> Self {
+        self.quorum = Some(quorum);
+        self
+    }
+
+    pub fn pending_updates(&self) -> &[PendingUpdate] {
+        &self.pending_updates
+    }
+
+    pub fn components(&self) -> &[FeeComponent] {
+        &self.components
+    }
+
+    // The contract a component is read from and written to
+    pub fn pool_of(&self, component: &FeeComponent) -> Felt {
+        component.pool.unwrap_or(self.contract_address)
+    }
+
+    // Blocks keep being checked while paused, but no update is sent
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // Lets the next divergent update of the component through
+    pub fn confirm_divergent(&mut self, component: &str) -> Result<(), UpdaterError> {
+        if !self
+            .components
+            .iter()
+            .any(|candidate| candidate.name == component)
         {
-            Ok(TransactionStatus::Confirmed) => {
-                info!("✅ Pending transaction confirmed on contract");
-                *pending_update = None;
-                // Continue with normal check below
+            return Err(UpdaterError::Configuration(format!(
+                "Unknown component `{component}`"
+            )));
+        }
+        warn!(
+            "✋ [{}] Next divergent update confirmed by the operator",
+            component
+        );
+        self.confirmed_divergent.insert(component.to_string());
+        Ok(())
+    }
+
+    // Only the leader sends anything, manual prices included
+    pub fn set_leader(&mut self, leader: bool) {
+        self.leader = leader;
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.leader
+    }
+
+    // Applies the patch to the named component, or to all; nothing changes when the
+    // result is invalid for any of them
+    pub fn set_strategy(
+        &mut self,
+        component: Option<&str>,
+        patch: &StrategyPatch,
+    ) -> Result<Vec<String>, UpdaterError> {
+        let mut changes = Vec::new();
+        for (index, existing) in self.components.iter().enumerate() {
+            if component.is_some_and(|name| name != existing.name) {
+                continue;
+            }
+            let strategy = patch.apply(existing.strategy);
+            if let (Some(min_price), Some(max_price)) = (strategy.min_price, strategy.max_price) {
+                if min_price > max_price {
+                    return Err(UpdaterError::Configuration(format!(
+                        "Component `{}` would get min_price {} above max_price {}",
+                        existing.name, min_price, max_price
+                    )));
+                }
             }
-            Ok(TransactionStatus::Failed) => {
-                warn!("❌ Pending transaction failed, clearing pending state");
-                *pending_update = None;
-                // Continue with normal check below
+            changes.push((index, strategy));
+        }
+        if changes.is_empty() {
+            return Err(UpdaterError::Configuration(format!(
+                "Unknown component `{}`",
+                component.unwrap_or_default()
+            )));
+        }
+
+        let mut changed = Vec::with_capacity(changes.len());
+        for (index, strategy) in changes {
+            let component = &mut self.components[index];
+            info!("🎛️ [{}] Strategy changed to {:?}", component.name, strategy);
+            component.strategy = strategy;
+            changed.push(component.name.clone());
+        }
+        Ok(changed)
+    }
+
+    // Swaps in a reloaded configuration; pending transactions, cooldowns and staleness
+    // carry over for the components that keep their name
+    pub fn reload(
+        &mut self,
+        components: Vec<FeeComponent>,
+        price_sources: Option<CompositeSource>,
+    ) {
+        self.clamped
+            .retain(|name| components.iter().any(|component| &component.name == name));
+        self.divergent
+            .retain(|name| components.iter().any(|component| &component.name == name));
+        self.confirmed_divergent
+            .retain(|name| components.iter().any(|component| &component.name == name));
+        self.known_prices
+            .retain(|name, _| components.iter().any(|component| &component.name == name));
+        self.overrides
+            .retain(|name| components.iter().any(|component| component.name == name));
+        self.components = components;
+        self.price_sources = price_sources;
+    }
+
+    // Transaction of ours carrying an update of the component, if one is in flight
+    fn in_flight(&self, component: &str) -> Option<Felt> {
+        self.pending_updates
+            .iter()
+            .find(|pending| {
+                pending
+                    .updates
+                    .iter()
+                    .any(|update| update.component == component)
+            })
+            .map(|pending| pending.tx_hash)
+    }
+
+    pub fn set_head_timer(&mut self, timer: Option<HeadTimer>) {
+        self.head_timer = timer;
+    }
+
+    fn lap(&mut self, phase: &'static str) {
+        if let Some(timer) = &mut self.head_timer {
+            timer.lap(phase);
+        }
+    }
+
+    pub fn is_own_transaction(&self, tx_hash: Felt) -> bool {
+        self.pending_updates
+            .iter()
+            .any(|pending| pending.tx_hash == tx_hash)
+    }
+
+    // Re-reads the components of a pool that emitted a price update; values that moved to
+    // something other than what we last saw or sent are classified by the transaction's
+    // sender and queued as outside changes
+    pub async fn reconcile_pool(
+        &mut self,
+        pool: Felt,
+        tx_hash: Felt,
+        block_number: Option<u64>,
+    ) -> Result<bool, UpdaterError> {
+        let mut moved = Vec::new();
+        for component in &self.components {
+            if self.pool_of(component) != pool {
+                continue;
             }
-            Ok(TransactionStatus::Pending) => {
-                debug!("⏳ Transaction still pending, skipping check");
-                return Ok((false, Felt::ZERO));
+            let current = retry_transient(
+                &self.backoff,
+                "contract price read",
+                UpdaterError::is_transient,
+                || read_component_price(&self.provider, pool, component.getter, &component.layout),
+            )
+            .await?;
+            // Our own update may land before its status is pushed
+            let ours = self
+                .pending_updates
+                .iter()
+                .flat_map(|pending| &pending.updates)
+                .any(|update| update.component == component.name && update.gas_price == current);
+            let previous = self.known_prices.insert(component.name.clone(), current);
+            match previous {
+                Some(previous) if previous != current && !ours => {
+                    moved.push((component.name.clone(), previous, current))
+                }
+                _ => {}
             }
+        }
+        if moved.is_empty() {
+            return Ok(false);
+        }
+
+        let sender = match transaction_sender(&self.provider, tx_hash).await {
+            Ok(sender) => sender,
             Err(e) => {
-                error!("❌ Error checking transaction status: {:?}", e);
-                // Clear pending to avoid being stuck forever
-                *pending_update = None;
-                // Continue with normal check below
+                warn!("Could not read the sender of {:#x}: {:?}", tx_hash, e);
+                None
             }
+        };
+        let senders: Vec<Felt> = self
+            .accounts
+            .iter()
+            .map(|account| account.address())
+            .collect();
+        let origin = self.overrides.classify(sender, &senders);
+        for (component, previous, current) in moved {
+            self.overrides.on_outside_change(&component, origin);
+            self.outside_changes.push(PriceChange {
+                component,
+                block_number,
+                previous,
+                current,
+                origin,
+                tx_hash: Some(tx_hash),
+                sender,
+            });
         }
+        Ok(true)
     }
 
-    let current_block = provider
-        .get_block_with_tx_hashes(BlockId::Tag(BlockTag::Latest))
-        .await?;
+    pub fn take_outside_changes(&mut self) -> Vec<PriceChange> {
+        std::mem::take(&mut self.outside_changes)
+    }
+
+    pub fn take_resolved(&mut self) -> Vec<ResolvedUpdate> {
+        std::mem::take(&mut self.resolved)
+    }
 
-    // Extract the gas price from l1_gas_price field
-    let current_gas_price = match current_block {
-        starknet::core::types::MaybePendingBlockWithTxHashes::Block(block) => {
-            // Access the l1_gas_price field and extract price_in_fri
-            let gas_price = block.l1_gas_price.price_in_fri;
-            gas_price
+    // Record a pushed status; the pending update is resolved on the next check
+    pub fn on_transaction_status(&mut self, tx_hash: Felt, status: TransactionStatus) {
+        if let Some(pending) = self
+            .pending_updates
+            .iter_mut()
+            .find(|pending| pending.tx_hash == tx_hash)
+        {
+            debug!("📨 Transaction {:#x} status pushed: {:?}", tx_hash, status);
+            pending.pushed_status = Some(status);
         }
-        starknet::core::types::MaybePendingBlockWithTxHashes::PendingBlock(_) => {
-            return Err(UpdaterError::InvalidGasPrice(
-                "Cannot get gas price from pending block".to_string(),
-            ));
+    }
+
+    // `header_prices` come from the head notification and save a block fetch; quorum
+    // reads ignore them since they are a single provider's word
+    pub async fn check_fee_update(
+        &mut self,
+        block_number: Option<u64>,
+        header_prices: Option<BlockPrices>,
+    ) -> Result<Option<Vec<FeeDecision>>, UpdaterError> {
+        self.check(block_number, CheckPrices::Header(header_prices))
+            .await
+    }
+
+    // A historical head is judged on its own prices, whatever the price block and quorum;
+    // contract values are read at their current state
+    pub async fn check_replayed_block(
+        &mut self,
+        block_number: u64,
+        prices: BlockPrices,
+    ) -> Result<Option<Vec<FeeDecision>>, UpdaterError> {
+        self.check(Some(block_number), CheckPrices::Replayed(prices))
+            .await
+    }
+
+    async fn check(
+        &mut self,
+        block_number: Option<u64>,
+        prices: CheckPrices,
+    ) -> Result<Option<Vec<FeeDecision>>, UpdaterError> {
+        self.head = block_number;
+        // If there are pending updates, first check if they were confirmed or failed
+        let mut still_pending = Vec::new();
+        for pending in std::mem::take(&mut self.pending_updates) {
+            info!(
+                "⏳ Checking status of pending transaction: {:?}",
+                pending.tx_hash
+            );
+
+            let status = match pending.pushed_status {
+                // Accepted per the subscription, only the contract values remain to verify
+                Some(TransactionStatus::Confirmed) => {
+                    verify_contract_values(&self.provider, &pending).await
+                }
+                Some(status) => Ok(status),
+                // No notification yet, fall back to polling the receipt
+                None => check_transaction_status(&self.provider, &pending).await,
+            };
+            let outcome = match status {
+                Ok(TransactionStatus::Confirmed) => {
+                    info!("✅ Pending transaction confirmed on contract");
+                    self.cooldown.on_confirmed(block_number);
+                    for update in &pending.updates {
+                        self.staleness.on_refreshed(&update.component, block_number);
+                        self.known_prices
+                            .insert(update.component.clone(), update.gas_price);
+                    }
+                    TxOutcome::Confirmed
+                }
+                Ok(TransactionStatus::Failed) => {
+                    warn!("❌ Pending transaction failed, clearing pending state");
+                    TxOutcome::Failed
+                }
+                Ok(TransactionStatus::Pending) => {
+                    let age = block_number
+                        .zip(pending.sent_block)
+                        .map(|(head, sent)| head.saturating_sub(sent));
+                    if age.is_none_or(|age| age < self.pending_expiry)
+                        || !is_unknown_to_node(&self.provider, pending.tx_hash).await
+                    {
+                        still_pending.push(pending);
+                        continue;
+                    }
+                    warn!(
+                        "⌛ Transaction {:#x} is still unknown to the node after {} blocks, dropping it so the update is sent again",
+                        pending.tx_hash,
+                        age.unwrap_or_default()
+                    );
+                    metrics::counter!("pp_fee_updater_pending_expired_total").increment(1);
+                    TxOutcome::Expired
+                }
+                Err(e) => {
+                    // Clear pending to avoid being stuck forever
+                    error!("❌ Error checking transaction status: {:?}", e);
+                    TxOutcome::Unknown
+                }
+            };
+            self.resolved.push(ResolvedUpdate {
+                pending,
+                block_number,
+                outcome,
+            });
         }
-    };
+        self.pending_updates = still_pending;
 
-    info!("Current gas price (in fri): {}", current_gas_price);
+        // A single account sends one transaction at a time; with more, the free senders
+        // carry on while the components already in flight wait
+        if !self.pending_updates.is_empty() && self.accounts.len() == 1 {
+            debug!(
+                "⏳ {} transaction(s) still pending, skipping check",
+                self.pending_updates.len()
+            );
+            return Ok(None);
+        }
 
-    let gas_price_on_contract = provider
-        .call(
-            FunctionCall {
-                calldata: vec![],
-                contract_address,
-                entry_point_selector: get_selector_from_name("get_current_gas_price")
-                    .map_err(|e| UpdaterError::Conversion(format!("Invalid selector: {}", e)))?,
+        if let Some(remaining) = self.cooldown.remaining(block_number) {
+            debug!(
+                "🧊 Cooling down after the last update ({} left), skipping check",
+                remaining
+            );
+            return Ok(None);
+        }
+
+        // The new heads header describes the latest block, never the pending one
+        let block_prices = match (prices, &self.quorum) {
+            (CheckPrices::Replayed(prices), _) => vec![prices],
+            (CheckPrices::Header(Some(prices)), None) if self.price_block == PriceBlock::Latest => {
+                vec![prices]
+            }
+            _ => self.fetch_block_prices().await?,
+        };
+
+        // The other sources are read once, against the first block reading
+        let source_readings = match &self.price_sources {
+            Some(price_sources) => {
+                price_sources
+                    .fetch(SourceContext {
+                        denomination: self.denomination,
+                        block: &block_prices[0],
+                    })
+                    .await
+            }
+            None => Vec::new(),
+        };
+
+        // Exchange rates are read once per check, however many components use them
+        let mut rates: HashMap<Felt, Rate> = HashMap::new();
+        let mut network_prices = Vec::with_capacity(self.components.len());
+        for component in &self.components {
+            let mut prices: Vec<Felt> = block_prices
+                .iter()
+                .map(|prices| {
+                    let price = component.network_price(
+                        self.denomination,
+                        &prices.l1_gas,
+                        &prices.l1_data_gas,
+                        &prices.l2_gas,
+                    );
+                    match &self.price_sources {
+                        Some(price_sources) => {
+                            price_sources.combine(component.source, price, &source_readings)
+                        }
+                        None => price,
+                    }
+                })
+                .collect();
+
+            if let Some(token) = &component.token {
+                let rate = match rates.get(&token.address) {
+                    Some(rate) => *rate,
+                    None => {
+                        let rate = retry_transient(
+                            &self.backoff,
+                            "exchange rate read",
+                            UpdaterError::is_transient,
+                            || token.fetch_rate(&self.provider),
+                        )
+                        .await?;
+                        info!(
+                            target: BLOCKS,
+                            "💱 {} exchange rate: {} ({} decimals{})",
+                            token.symbol,
+                            rate.value,
+                            rate.decimals,
+                            if rate.invert { ", inverted" } else { "" }
+                        );
+                        rates.insert(token.address, rate);
+                        rate
+                    }
+                };
+                prices = prices
+                    .into_iter()
+                    .map(|price| token.convert(price, rate))
+                    .collect::<Result<_, _>>()?;
+            }
+            network_prices.push(prices);
+        }
+
+        // The contract reads of all components and pools go out side by side, a bounded
+        // number at a time
+        let contract_prices: Vec<Vec<Felt>> = stream::iter(&self.components)
+            .map(|component| self.read_contract_prices(component))
+            .buffered(self.check_concurrency)
+            .try_collect()
+            .await?;
+        self.lap("price_read");
+
+        let mut decisions = Vec::with_capacity(self.components.len());
+        for ((component, network_prices), contract_prices) in self
+            .components
+            .iter()
+            .zip(network_prices)
+            .zip(contract_prices)
+        {
+            let (current_gas_price, gas_price_on_contract) = match &self.quorum {
+                Some(quorum) => (
+                    quorum
+                        .resolve(&component.name, "network gas price", network_prices)
+                        .await,
+                    quorum
+                        .resolve(&component.name, "contract gas price", contract_prices)
+                        .await,
+                ),
+                None => (network_prices[0], contract_prices[0]),
+            };
+            let current_gas_price = self.spikes.filter(&component.name, current_gas_price);
+            let headroom = self.trends.headroom(&component.name, current_gas_price);
+            let current_gas_price = self.percentiles.target(&component.name, current_gas_price);
+            // Resolved updates were applied above, so a value we did not see coming was
+            // written by someone else, unless one of ours for it is still in flight
+            let in_flight = self.in_flight(&component.name);
+            let previous = self
+                .known_prices
+                .insert(component.name.clone(), gas_price_on_contract);
+            if let Some(previous) = previous
+                .filter(|previous| *previous != gas_price_on_contract && in_flight.is_none())
+            {
+                self.overrides
+                    .on_outside_change(&component.name, ChangeOrigin::Unknown);
+                self.outside_changes.push(PriceChange {
+                    component: component.name.clone(),
+                    block_number,
+                    previous,
+                    current: gas_price_on_contract,
+                    origin: ChangeOrigin::Unknown,
+                    tx_hash: None,
+                    sender: None,
+                });
+            }
+
+            let held = self.overrides.remaining(&component.name);
+            // Once the grace period is over our target is written even inside the dead-band
+            let reapply = held.is_none() && self.overrides.take_expired(&component.name);
+            if reapply {
+                info!(
+                    "🔁 [{}] Grace period of the outside price is over, re-applying our target",
+                    component.name
+                );
+            }
+            // Initializing an unset contract price waits for nothing either
+            let forced = self.staleness.is_stale(&component.name, block_number)
+                || reapply
+                || gas_price_on_contract == Felt::ZERO;
+            let mut decision = check_component(
+                component,
+                self.pool_of(component),
+                self.denomination,
+                current_gas_price,
+                gas_price_on_contract,
+                forced,
+                headroom,
+            )?;
+            // Forced refreshes do not wait for the breach to last
+            let breach = if decision.should_update && !forced {
+                decision.direction
+            } else {
+                Direction::None
+            };
+            if let Some((seen, needed)) = self.breaches.observe(&component.name, breach) {
+                info!(
+                    "🔎 [{}] {} threshold breached for {}/{} check(s), waiting",
+                    component.name,
+                    breach.as_str(),
+                    seen,
+                    needed
+                );
+                decision.hold();
+            }
+            if let (Some(remaining), true) = (held, decision.should_update) {
+                info!(
+                    "🤚 [{}] Keeping the outside price for {}s more",
+                    component.name,
+                    remaining.as_secs().max(1)
+                );
+                decision.hold();
+            }
+            if let (Some(tx_hash), true) = (in_flight, decision.should_update) {
+                info!(
+                    "⏳ [{}] Update already in flight in {:#x}, not sending another",
+                    component.name, tx_hash
+                );
+                decision.hold();
+            }
+            decisions.push(decision);
+        }
+        self.guard_divergence(&mut decisions).await;
+        self.lap("decision");
+
+        self.alert_on_clamps(&decisions).await;
+
+        Ok(Some(decisions))
+    }
+
+    // One reading per quorum provider, or the single one
+    async fn read_contract_prices(
+        &self,
+        component: &FeeComponent,
+    ) -> Result<Vec<Felt>, UpdaterError> {
+        let contract_address = self.pool_of(component);
+        retry_transient(
+            &self.backoff,
+            "contract price read",
+            UpdaterError::is_transient,
+            || async {
+                match &self.quorum {
+                    Some(quorum) => {
+                        quorum
+                            .contract_prices(contract_address, component.getter, &component.layout)
+                            .await
+                    }
+                    None => Ok(vec![
+                        read_component_price(
+                            &self.provider,
+                            contract_address,
+                            component.getter,
+                            &component.layout,
+                        )
+                        .await?,
+                    ]),
+                }
+            },
+        )
+        .await
+    }
+
+    // Holds the updates of components whose network and contract prices are too far apart,
+    // unless forced or confirmed; an unset contract price is initialized regardless
+    async fn guard_divergence(&mut self, decisions: &mut [FeeDecision]) {
+        let Some(max_factor) = self.divergence.max_factor else {
+            return;
+        };
+        for decision in decisions.iter_mut() {
+            let low = decision.network_gas_price.min(decision.contract_gas_price);
+            let high = decision.network_gas_price.max(decision.contract_gas_price);
+            if low == 0 || high / low < max_factor {
+                self.divergent.remove(&decision.component);
+                continue;
+            }
+            if self.divergent.insert(decision.component.clone()) {
+                let message = format!(
+                    "[{}] Network price {} and contract price {} are {}x apart, check the units \
+                     and denomination",
+                    decision.component,
+                    decision.network_gas_price,
+                    decision.contract_gas_price,
+                    high / low
+                );
+                match &self.alerter {
+                    Some(alerter) => {
+                        alerter
+                            .raise(AlertSeverity::Critical, "price_divergence", message)
+                            .await
+                    }
+                    None => error!("🚨 {}", message),
+                }
+            }
+            if !decision.should_update
+                || self.divergence.force
+                || self.confirmed_divergent.remove(&decision.component)
+            {
+                continue;
+            }
+            warn!(
+                "🛑 [{}] Divergent update held, confirm it with `ctl confirm {}` or set \
+                 FORCE_DIVERGENT_UPDATES",
+                decision.component, decision.component
+            );
+            decision.hold();
+        }
+    }
+
+    async fn alert_on_clamps(&mut self, decisions: &[FeeDecision]) {
+        for decision in decisions {
+            let Some(clamp) = decision.clamp else {
+                self.clamped.remove(&decision.component);
+                continue;
+            };
+            if !self.clamped.insert(decision.component.clone()) {
+                continue;
+            }
+            let message = format!(
+                "[{}] Target price {} is clamped to {} by the configured price bounds",
+                decision.component, clamp.target, clamp.bounded
+            );
+            match &self.alerter {
+                Some(alerter) => {
+                    alerter
+                        .raise(AlertSeverity::Warning, "price_clamped", message)
+                        .await
+                }
+                None => warn!("⚠️ {}", message),
+            }
+        }
+    }
+
+    async fn fetch_block_prices(&self) -> Result<Vec<BlockPrices>, UpdaterError> {
+        retry_transient(
+            &self.backoff,
+            "block price read",
+            UpdaterError::is_transient,
+            || async {
+                match &self.quorum {
+                    Some(quorum) => quorum.block_prices(self.price_block).await,
+                    None => Ok(vec![block_prices(&self.provider, self.price_block).await?]),
+                }
             },
-            BlockId::Tag(BlockTag::Latest),
         )
-        .await?[0];
+        .await
+    }
+
+    // Send the queued transactions in priority order with sequential nonces
+    pub async fn update_fee(&mut self, queue: SendQueue) -> Result<(), UpdaterError> {
+        if self.paused {
+            warn!(
+                "⏸️ Updates are paused, {} transaction(s) not sent",
+                queue.batches.len()
+            );
+            return Ok(());
+        }
+        self.send(queue).await
+    }
+
+    // Write `price` to a component right away, bypassing the policy and the pause
+    pub async fn force_price(&mut self, component: &str, price: u128) -> Result<(), UpdaterError> {
+        let component = self
+            .components
+            .iter()
+            .find(|candidate| candidate.name == component)
+            .ok_or_else(|| {
+                UpdaterError::Configuration(format!("Unknown component `{component}`"))
+            })?;
+        warn!(
+            "✋ [{}] Manually setting the price to {}",
+            component.name, price
+        );
+        let update = ComponentUpdate {
+            component: component.name.clone(),
+            contract_address: self.pool_of(component),
+            getter: component.getter,
+            setter: component.setter,
+            layout: component.layout.clone(),
+            gas_price: Felt::from(price),
+        };
+        self.send(SendQueue {
+            batches: vec![vec![update]],
+            deferred: Vec::new(),
+        })
+        .await
+    }
+
+    async fn send(&mut self, queue: SendQueue) -> Result<(), UpdaterError> {
+        if !self.leader {
+            info!(
+                "🪑 Standing by for the leader, {} transaction(s) not sent",
+                queue.batches.len()
+            );
+            return Ok(());
+        }
+        // All transactions of a cycle come from one sender, sequenced by nonce
+        let Some(sender) = self.pick_sender().await else {
+            warn!(
+                "🚫 No sender account is free, {} transaction(s) not sent",
+                queue.batches.len()
+            );
+            return Ok(());
+        };
+        let sender_address = self.accounts[sender].address();
+        let mut next_nonce: Option<Felt> = None;
+        for mut updates in queue.batches {
+            // Another instance or an operator may have written the targets since the check
+            self.skip_applied(&mut updates).await;
+            if updates.is_empty() {
+                continue;
+            }
+
+            let chain_nonce = retry_transient(
+                &self.backoff,
+                "nonce read",
+                UpdaterError::is_transient,
+                || async { Ok(self.accounts[sender].get_nonce().await?) },
+            )
+            .await?;
+            // Our own earlier sends may not be visible to the node yet, so the queue is
+            // sequenced locally unless someone else used the account meanwhile
+            let nonce = match next_nonce {
+                Some(next) if next >= chain_nonce => next,
+                Some(next) => {
+                    warn!(
+                        "⚠️ Nonce of {:#x} moved to {} outside this updater (expected {})",
+                        sender_address, chain_nonce, next
+                    );
+                    chain_nonce
+                }
+                None => chain_nonce,
+            };
+
+            let calls: Vec<Call> = updates
+                .iter()
+                .map(|update| {
+                    update.layout.setter_call(
+                        &self.accounts[sender],
+                        update.contract_address,
+                        update.setter,
+                        update.gas_price,
+                    )
+                })
+                .collect();
+            if self.simulate {
+                self.simulate(sender, &calls, nonce).await?;
+            }
+
+            self.lap("prepare");
+
+            // The bounds are resolved once, so every resend is the same transaction
+            let account = &self.accounts[sender];
+            let resource_bounds = &self.resource_bounds;
+            let prepared = retry_transient(
+                &self.backoff,
+                "fee estimate",
+                UpdaterError::is_transient,
+                || {
+                    let calls = calls.clone();
+                    async move {
+                        resource_bounds
+                            .prepare(account.execute_v3(calls).nonce(nonce))
+                            .await
+                            .map_err(|e| match e {
+                                AccountError::Provider(e) => UpdaterError::Provider(e),
+                                e => UpdaterError::Account(format!("{e:?}")),
+                            })
+                    }
+                },
+            )
+            .await?;
+            let tx_hash = prepared.transaction_hash(false);
+
+            // A pool that fails over already resent the transaction; retrying on top of it
+            // would multiply the attempts
+            let backoff = if self.pool.fails_over() {
+                BackoffPolicy {
+                    attempts: 0,
+                    ..self.backoff
+                }
+            } else {
+                self.backoff
+            };
+            let mut attempts = 0;
+            let resends = self.pool.resends();
+            let invoke_result = retry_transient(
+                &backoff,
+                "transaction send",
+                UpdaterError::is_transient,
+                || {
+                    attempts += 1;
+                    let prepared = &prepared;
+                    async move {
+                        prepared
+                            .send()
+                            .await
+                            .map(|result| result.transaction_hash)
+                            .map_err(|e| match e {
+                                AccountError::Provider(e) => UpdaterError::Provider(e),
+                                e => UpdaterError::Account(format!("{e:?}")),
+                            })
+                    }
+                },
+            )
+            .await;
+            // A resend rejected as a duplicate or for its nonce means an earlier attempt may
+            // have reached the node although its reply was lost; only a hash the node knows
+            // is tracked, anything else would stay pending for good
+            let resent = attempts > 1 || self.pool.resends() > resends;
+            let invoke_result = match invoke_result {
+                Err(e) if resent && e.is_already_sent() => {
+                    match self.provider.get_transaction_status(tx_hash).await {
+                        Ok(status) => {
+                            warn!(
+                                "⚠️ Resent transaction {:#x} was rejected ({}), but an earlier attempt landed ({:?}); tracking it as pending",
+                                tx_hash, e, status
+                            );
+                            metrics::counter!("pp_fee_updater_possibly_landed_total").increment(1);
+                            Ok(tx_hash)
+                        }
+                        Err(status_error) => {
+                            warn!(
+                                "⚠️ Resent transaction {:#x} was rejected and its status is unavailable: {}",
+                                tx_hash, status_error
+                            );
+                            Err(e)
+                        }
+                    }
+                }
+                result => result,
+            };
+
+            match invoke_result {
+                Ok(transaction_hash) => {
+                    info!(
+                        "✅ Transaction sent: {:?} (sender {:#x}, nonce {}, {} component update(s))",
+                        transaction_hash,
+                        sender_address,
+                        nonce,
+                        updates.len()
+                    );
+                    info!("⏳ Will check transaction status on next block");
+                    if let Some(timer) = self.head_timer.take() {
+                        timer.broadcast();
+                    }
+
+                    // Track pending update with transaction hash
+                    self.pending_updates.push(PendingUpdate {
+                        updates,
+                        tx_hash: transaction_hash,
+                        sender: sender_address,
+                        pushed_status: None,
+                        sent_block: self.head,
+                    });
+                    next_nonce = Some(nonce + Felt::ONE);
+                }
+                Err(e) => {
+                    // Later transactions would leave a nonce gap, so stop here
+                    error!("❌ Error sending transaction: {:?}", e);
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Round-robin over the senders, passing over those with a transaction in flight and,
+    // with a minimum balance set, those too low on STRK to pay the fee
+    async fn pick_sender(&mut self) -> Option<usize> {
+        let count = self.accounts.len();
+        for offset in 0..count {
+            let index = (self.next_sender + offset) % count;
+            let address = self.accounts[index].address();
+            if self
+                .pending_updates
+                .iter()
+                .any(|pending| pending.sender == address)
+            {
+                debug!("Sender {:#x} has a transaction in flight", address);
+                continue;
+            }
+            if let Some(threshold) = self.sender_balance {
+                match fetch_token_balance(&self.provider, threshold.token, address).await {
+                    Ok(balance) if balance < threshold.min => {
+                        warn!(
+                            "💸 Sender {:#x} holds {} fri, below the minimum of {}, passing it over",
+                            address, balance, threshold.min
+                        );
+                        continue;
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!("Could not read the balance of {:#x}: {:?}", address, e),
+                }
+            }
+            self.next_sender = (index + 1) % count;
+            return Some(index);
+        }
+        None
+    }
+
+    // Dry-runs the invoke, so a transaction that would revert, from a wrong owner, a paused
+    // pool or bad calldata, is never broadcast and never charged for
+    async fn simulate(
+        &self,
+        sender: usize,
+        calls: &[Call],
+        nonce: Felt,
+    ) -> Result<(), UpdaterError> {
+        let account = &self.accounts[sender];
+        let resource_bounds = &self.resource_bounds;
+        let simulated = retry_transient(
+            &self.backoff,
+            "transaction simulation",
+            UpdaterError::is_transient,
+            || {
+                let calls = calls.to_vec();
+                async move {
+                    resource_bounds
+                        .apply(account.execute_v3(calls).nonce(nonce))
+                        .simulate(false, false)
+                        .await
+                        .map_err(|e| match e {
+                            AccountError::Provider(ProviderError::StarknetError(
+                                StarknetError::TransactionExecutionError(data),
+                            )) => UpdaterError::Simulation(format!("{:?}", data.execution_error)),
+                            AccountError::Provider(ProviderError::StarknetError(
+                                StarknetError::ValidationFailure(reason),
+                            )) => UpdaterError::Simulation(format!("validation failed: {reason}")),
+                            AccountError::Provider(e) => UpdaterError::Provider(e),
+                            e => UpdaterError::Account(format!("{e:?}")),
+                        })
+                }
+            },
+        )
+        .await;
+        let reverted = match simulated {
+            Ok(simulated) => match simulated.transaction_trace {
+                TransactionTrace::Invoke(InvokeTransactionTrace {
+                    execute_invocation: ExecuteInvocation::Reverted(reverted),
+                    ..
+                }) => Some(reverted.revert_reason),
+                _ => None,
+            },
+            Err(UpdaterError::Simulation(reason)) => Some(reason),
+            Err(e) => return Err(e),
+        };
+        match reverted {
+            Some(reason) => {
+                error!("🧪 Simulation reverted, not sending: {}", reason);
+                Err(UpdaterError::Simulation(reason))
+            }
+            None => {
+                debug!("🧪 Simulation succeeded");
+                Ok(())
+            }
+        }
+    }
+
+    // Drops the updates whose value the contract already holds; a failed read keeps the
+    // update, since the nonce still prevents a duplicate of our own transaction
+    async fn skip_applied(&self, updates: &mut Vec<ComponentUpdate>) {
+        let mut kept = Vec::with_capacity(updates.len());
+        for update in updates.drain(..) {
+            match check_if_update_completed(&self.provider, &update).await {
+                Ok(true) => info!(
+                    "⏭️ [{}] Contract already holds {}, not sending",
+                    update.component, update.gas_price
+                ),
+                Ok(false) => kept.push(update),
+                Err(e) => {
+                    warn!(
+                        "[{}] Could not re-read the contract before sending: {:?}",
+                        update.component, e
+                    );
+                    kept.push(update);
+                }
+            }
+        }
+        *updates = kept;
+    }
+}
 
-    info!("Gas price on contract: {}", gas_price_on_contract);
+// What a component decides at one block, given the network and contract prices
+pub fn check_component(
+    component: &FeeComponent,
+    contract_address: Felt,
+    denomination: Denomination,
+    current_gas_price: Felt,
+    gas_price_on_contract: Felt,
+    stale: bool,
+    headroom: u128,
+) -> Result<FeeDecision, UpdaterError> {
+    info!(
+        target: BLOCKS,
+        "[{}] Current gas price (in {}): {}",
+        component.name,
+        denomination.as_str(),
+        current_gas_price
+    );
+    info!(
+        target: BLOCKS,
+        "[{}] Gas price on contract: {}",
+        component.name, gas_price_on_contract
+    );
 
-    // Check if current gas price differs by more than 20% from contract gas price
-    // Convert Felt to u128 for calculation (Fri values should fit in u128)
+    // Convert Felt to u128 for calculation (fri and wei values should fit in u128)
     let contract_price_u128: u128 =
         gas_price_on_contract.to_biguint().try_into().map_err(|_| {
             UpdaterError::Conversion("Contract gas price too large for u128".to_string())
@@ -130,205 +1574,963 @@ pub async fn check_fee_update(
         UpdaterError::Conversion("Current gas price too large for u128".to_string())
     })?;
 
-    // Asymmetric paymaster thresholds for profit optimization
-    let upward_threshold = contract_price_u128 * upward_threshold_const / 100;   // +5% threshold
-    let downward_threshold = contract_price_u128 * downward_threshold_const / 100; // -15% threshold
-
-    // Determine update type and direction
-    let (should_update, update_direction) = if current_price_u128 > upward_threshold {
-        (true, "upward") // Gas price rising - quick reaction for profits
-    } else if current_price_u128 < downward_threshold {
-        (true, "downward") // Gas price falling - slow reaction to preserve margins
-    } else {
-        (false, "none") // Within acceptable range
+    // A fresh deployment reads zero, which no percentage of means anything
+    let bootstrap = contract_price_u128 == 0 && current_price_u128 > 0;
+    let (update_direction, target_price) = match component
+        .strategy
+        .target(current_price_u128, contract_price_u128)
+    {
+        _ if bootstrap => {
+            info!(
+                "🌱 [{}] Contract price is unset, initializing it from the network price {}",
+                component.name, current_price_u128
+            );
+            (
+                Direction::Upward,
+                Some(percent_of(
+                    current_price_u128,
+                    component.strategy.upward_buffer,
+                )),
+            )
+        }
+        (_, None) if stale => {
+            info!(
+                "[{}] Contract value is stale, forcing a refresh",
+                component.name
+            );
+            let (direction, target) = component
+                .strategy
+                .refresh_target(current_price_u128, contract_price_u128);
+            (direction, Some(target))
+        }
+        decided => decided,
     };
+    // A steady rise is priced ahead, so the next blocks do not need another update
+    let target_price = match (update_direction, target_price) {
+        (Direction::Upward, Some(target)) if headroom > 0 && !bootstrap => {
+            info!(
+                "📐 [{}] Gas rising steadily, adding {} of headroom to the target {}",
+                component.name, headroom, target
+            );
+            Some(target.saturating_add(headroom))
+        }
+        (_, target) => target,
+    };
+    let buffered_price = target_price.map(|target| {
+        let limited = component.strategy.limit_change(contract_price_u128, target);
+        if limited != target {
+            info!(
+                "[{}] Target {} limited to {} (max {}% change per update)",
+                component.name,
+                target,
+                limited,
+                component.strategy.max_change_percent.unwrap_or_default()
+            );
+        }
+        limited
+    });
+    let clamp = buffered_price.and_then(|target| {
+        let bounded = component.strategy.bound(target);
+        (bounded != target).then_some(Clamp { target, bounded })
+    });
+    // A clamped target equal to the contract price leaves nothing to write
+    let buffered_price = buffered_price
+        .map(|target| component.strategy.bound(target))
+        .filter(|target| *target != contract_price_u128);
+    let should_update = buffered_price.is_some();
 
     debug!(
-        "Paymaster gas price analysis - Network: {}, Contract: {}",
-        current_price_u128, contract_price_u128
+        "[{}] Paymaster gas price analysis - Network: {}, Contract: {}",
+        component.name, current_price_u128, contract_price_u128
     );
     debug!(
-        "Thresholds - Upward (+5%): {}, Downward (-15%): {}",
-        upward_threshold, downward_threshold
+        "[{}] Thresholds - Upward: {}%, Downward: {}%",
+        component.name, component.strategy.upward_threshold, component.strategy.downward_threshold
     );
     info!(
-        "Fee update required: {} (direction: {}, network vs contract: {}%)", 
-        should_update, 
-        update_direction,
+        target: BLOCKS,
+        "[{}] Fee update required: {} (direction: {}, network vs contract: {:.0}%)",
+        component.name,
+        should_update,
+        update_direction.as_str(),
+        // Floats, so prices beyond i128 do not wrap
         if contract_price_u128 > 0 {
-            (current_price_u128 as i128 - contract_price_u128 as i128) * 100 / contract_price_u128 as i128
-        } else { 0 }
+            (current_price_u128 as f64 / contract_price_u128 as f64 - 1.0) * 100.0
+        } else {
+            0.0
+        }
     );
 
-    let new_gas_price = if should_update {
-        let (buffered_price, margin_percent) = match update_direction {
-            "upward" => {
-                // Gas rising: Set higher price with 10% margin for consistent profit
-                let price = current_price_u128 * upward_buffer_const / 100;
-                (price, 10)
-            },
-            "downward" => {
-                // Gas falling: Set lower price with 10% margin to preserve profits
-                let price = current_price_u128 * downward_buffer_const / 100;
-                (price, 10)
-            },
-            _ => (current_price_u128, 0) // Fallback, shouldn't happen
-        };
-        
-        let paymaster_profit = buffered_price.saturating_sub(current_price_u128);
-        info!(
-            "New gas price for users: {} (network: {} + {}% margin = {} profit per tx)", 
-            buffered_price, 
-            current_price_u128, 
-            margin_percent,
-            paymaster_profit
-        );
-        Felt::from(buffered_price)
-    } else {
-        Felt::ZERO
+    let new_gas_price = match buffered_price {
+        Some(buffered_price) => {
+            let margin_percent = match update_direction {
+                Direction::Upward => component.strategy.upward_buffer.saturating_sub(100),
+                Direction::Downward => component.strategy.downward_buffer.saturating_sub(100),
+                Direction::None => 0,
+            };
+            let paymaster_profit = buffered_price.saturating_sub(current_price_u128);
+            info!(
+                "[{}] New gas price for users: {} (network: {} + {}% margin = {} profit per tx)",
+                component.name,
+                buffered_price,
+                current_price_u128,
+                margin_percent,
+                paymaster_profit
+            );
+            Felt::from(buffered_price)
+        }
+        None => Felt::ZERO,
     };
 
-    Ok((should_update, new_gas_price))
+    Ok(FeeDecision {
+        component: component.name.clone(),
+        should_update,
+        new_gas_price,
+        network_gas_price: current_price_u128,
+        contract_gas_price: contract_price_u128,
+        direction: update_direction,
+        clamp,
+        update: should_update.then(|| ComponentUpdate {
+            component: component.name.clone(),
+            contract_address,
+            getter: component.getter,
+            setter: component.setter,
+            layout: component.layout.clone(),
+            gas_price: new_gas_price,
+        }),
+    })
 }
 
-pub async fn update_fee(
-    url: Url,
-    gas_price: Felt,
-    contract_address: Felt,
-    owner_address: Felt,
-    owner_private_key: Felt,
-    pending_update: &mut Option<PendingUpdate>,
-) -> Result<(), UpdaterError> {
-    let provider = JsonRpcClient::new(HttpTransport::new(url));
-
-    let paymaster_account = SingleOwnerAccount::new(
-        provider.clone(),
-        LocalWallet::from(SigningKey::from_secret_scalar(owner_private_key)),
-        owner_address,
-        provider.chain_id().await?,
-        ExecutionEncoding::New,
-    );
-
-    let selector = get_selector_from_name("set_current_gas_price")
-        .map_err(|e| UpdaterError::Conversion(format!("Invalid selector: {}", e)))?;
-
-    let call = Call {
-        to: contract_address,
-        selector,
-        calldata: [gas_price, Felt::ZERO].to_vec(),
-    };
-
-    let invoke_result = paymaster_account.execute_v3(vec![call]).send().await;
-
-    match &invoke_result {
-        Ok(result) => {
-            info!("✅ Transaction sent: {:?}", result.transaction_hash);
-            info!("⏳ Will check transaction status on next block");
-
-            // Set pending update with transaction hash
-            *pending_update = Some(PendingUpdate {
-                gas_price,
-                tx_hash: result.transaction_hash,
-            });
-        }
-        Err(e) => {
-            error!("❌ Error sending transaction: {:?}", e);
-            *pending_update = None;
-            return Err(UpdaterError::Account(format!("{:?}", e)));
-        }
-    }
+// The account that sent an invoke transaction
+async fn transaction_sender(
+    provider: &RpcClient,
+    tx_hash: Felt,
+) -> Result<Option<Felt>, UpdaterError> {
+    Ok(match provider.get_transaction_by_hash(tx_hash).await? {
+        Transaction::Invoke(InvokeTransaction::V0(tx)) => Some(tx.contract_address),
+        Transaction::Invoke(InvokeTransaction::V1(tx)) => Some(tx.sender_address),
+        Transaction::Invoke(InvokeTransaction::V3(tx)) => Some(tx.sender_address),
+        _ => None,
+    })
+}
 
-    // Result already handled above
-    Ok(())
+// Whether the node has never heard of the transaction, as opposed to not having included
+// it yet or failing to answer
+async fn is_unknown_to_node(provider: &RpcClient, tx_hash: Felt) -> bool {
+    matches!(
+        provider.get_transaction_status(tx_hash).await,
+        Err(ProviderError::StarknetError(
+            StarknetError::TransactionHashNotFound
+        ))
+    )
 }
 
 // Function to check transaction status
 async fn check_transaction_status(
-    provider: &JsonRpcClient<HttpTransport>,
-    tx_hash: Felt,
-    contract_address: Felt,
-    expected_gas_price: Felt,
+    provider: &RpcClient,
+    pending: &PendingUpdate,
 ) -> Result<TransactionStatus, UpdaterError> {
     // First try to get transaction receipt
-    match provider.get_transaction_receipt(tx_hash).await {
-        Ok(_receipt) => {
-            // If we got a receipt, the transaction was included in a block
-            // Now check if contract was actually updated with expected value
-            debug!("Transaction receipt found, checking if contract was updated with expected value: {}", expected_gas_price);
-            match check_if_update_completed(provider, contract_address, expected_gas_price).await {
-                Ok(true) => {
-                    info!("✅ Transaction confirmed - contract updated successfully");
-                    Ok(TransactionStatus::Confirmed)
-                },
-                Ok(false) => {
-                    // Transaction was included but contract value doesn't match
-                    // Let's see what the actual value is
-                    let actual_value = provider
-                        .call(
-                            FunctionCall {
-                                calldata: vec![],
-                                contract_address,
-                                entry_point_selector: get_selector_from_name(
-                                    "get_current_gas_price",
-                                )
-                                .map_err(|e| {
-                                    UpdaterError::Conversion(format!("Invalid selector: {}", e))
-                                })?,
-                            },
-                            BlockId::Tag(BlockTag::Latest),
-                        )
-                        .await
-                        .map(|result| result[0])
-                        .unwrap_or(Felt::ZERO);
-
-                    warn!("⚠️ Transaction included but contract value doesn't match expected");
-                    warn!("   Expected: {}, Actual: {}", expected_gas_price, actual_value);
-                    warn!("   Expected_hex: {:x}, Actual_hex: {:x}", expected_gas_price, actual_value);
-                    
-                    // Check if values are actually the same (debug false positive)
-                    if actual_value == expected_gas_price {
-                        error!("🐛 BUG: Values are identical but check_if_update_completed returned false!");
-                        return Ok(TransactionStatus::Confirmed); // Force success
-                    }
-                    
-                    Ok(TransactionStatus::Failed)
-                }
-                Err(e) => {
-                    error!("❌ Error checking contract value: {:?}", e);
-                    Ok(TransactionStatus::Failed)
+    if provider
+        .get_transaction_receipt(pending.tx_hash)
+        .await
+        .is_err()
+    {
+        // Transaction receipt not found, assume it's still pending
+        return Ok(TransactionStatus::Pending);
+    }
+
+    verify_contract_values(provider, pending).await
+}
+
+async fn verify_contract_values(
+    provider: &RpcClient,
+    pending: &PendingUpdate,
+) -> Result<TransactionStatus, UpdaterError> {
+    // If we got a receipt, the transaction was included in a block
+    // Now check if every component was actually updated with expected value
+    for update in &pending.updates {
+        debug!(
+            "[{}] Transaction receipt found, checking if contract was updated with expected value: {}",
+            update.component, update.gas_price
+        );
+        match check_if_update_completed(provider, update).await {
+            Ok(true) => {}
+            Ok(false) => {
+                // Transaction was included but contract value doesn't match
+                // Let's see what the actual value is
+                let actual_value = read_component_price(
+                    provider,
+                    update.contract_address,
+                    update.getter,
+                    &update.layout,
+                )
+                .await
+                .unwrap_or(Felt::ZERO);
+
+                warn!(
+                    "⚠️ [{}] Transaction included but contract value doesn't match expected",
+                    update.component
+                );
+                warn!(
+                    "   Expected: {}, Actual: {}",
+                    update.gas_price, actual_value
+                );
+                warn!(
+                    "   Expected_hex: {:x}, Actual_hex: {:x}",
+                    update.gas_price, actual_value
+                );
+
+                // Check if values are actually the same (debug false positive)
+                if actual_value == update.gas_price {
+                    error!("🐛 BUG: Values are identical but check_if_update_completed returned false!");
+                    continue; // Force success
                 }
+
+                return Ok(TransactionStatus::Failed);
+            }
+            Err(e) => {
+                error!("❌ Error checking contract value: {:?}", e);
+                return Ok(TransactionStatus::Failed);
             }
-        }
-        Err(_) => {
-            // Transaction receipt not found, assume it's still pending
-            Ok(TransactionStatus::Pending)
         }
     }
+
+    info!("✅ Transaction confirmed - contract updated successfully");
+    Ok(TransactionStatus::Confirmed)
 }
 
 // Helper function to check if update was confirmed
 async fn check_if_update_completed(
-    provider: &JsonRpcClient<HttpTransport>,
-    contract_address: Felt,
-    expected_gas_price: Felt,
+    provider: &RpcClient,
+    update: &ComponentUpdate,
 ) -> Result<bool, UpdaterError> {
-    let current_contract_price = provider
+    let current_contract_price = read_component_price(
+        provider,
+        update.contract_address,
+        update.getter,
+        &update.layout,
+    )
+    .await?;
+
+    let is_match = current_contract_price == update.gas_price;
+    debug!(
+        "Update completion check - Contract: {}, Expected: {}, Match: {}, Contract_hex: {:x}, Expected_hex: {:x}",
+        current_contract_price, update.gas_price, is_match, current_contract_price, update.gas_price
+    );
+
+    Ok(is_match)
+}
+
+// Resource prices of the configured price block
+pub async fn block_prices(
+    provider: &RpcClient,
+    price_block: PriceBlock,
+) -> Result<BlockPrices, UpdaterError> {
+    match provider
+        .get_block_with_tx_hashes(BlockId::Tag(price_block.tag()))
+        .await?
+    {
+        MaybePendingBlockWithTxHashes::Block(block) => Ok(BlockPrices {
+            l1_gas: block.l1_gas_price,
+            l1_data_gas: block.l1_data_gas_price,
+            l2_gas: block.l2_gas_price,
+        }),
+        MaybePendingBlockWithTxHashes::PendingBlock(block)
+            if price_block == PriceBlock::Pending =>
+        {
+            Ok(BlockPrices {
+                l1_gas: block.l1_gas_price,
+                l1_data_gas: block.l1_data_gas_price,
+                l2_gas: block.l2_gas_price,
+            })
+        }
+        MaybePendingBlockWithTxHashes::PendingBlock(_) => Err(UpdaterError::InvalidGasPrice(
+            "Cannot get gas price from pending block".to_string(),
+        )),
+    }
+}
+
+// Resource prices of a past block
+pub async fn block_prices_at(
+    provider: &RpcClient,
+    block_number: u64,
+) -> Result<BlockPrices, UpdaterError> {
+    match provider
+        .get_block_with_tx_hashes(BlockId::Number(block_number))
+        .await?
+    {
+        MaybePendingBlockWithTxHashes::Block(block) => Ok(BlockPrices {
+            l1_gas: block.l1_gas_price,
+            l1_data_gas: block.l1_data_gas_price,
+            l2_gas: block.l2_gas_price,
+        }),
+        MaybePendingBlockWithTxHashes::PendingBlock(_) => Err(UpdaterError::InvalidGasPrice(
+            format!("Block {block_number} is still pending"),
+        )),
+    }
+}
+
+pub async fn read_component_price(
+    provider: &RpcClient,
+    contract_address: Felt,
+    getter: Felt,
+    layout: &CalldataLayout,
+) -> Result<Felt, UpdaterError> {
+    if let Some(binding) = layout.binding {
+        return binding.read(provider, contract_address).await;
+    }
+    let result = provider
         .call(
             FunctionCall {
-                calldata: vec![],
+                calldata: layout.getter_args.clone(),
                 contract_address,
-                entry_point_selector: get_selector_from_name("get_current_gas_price")
-                    .map_err(|e| UpdaterError::Conversion(format!("Invalid selector: {}", e)))?,
+                entry_point_selector: getter,
             },
             BlockId::Tag(BlockTag::Latest),
         )
-        .await?[0];
+        .await?;
 
-    let is_match = current_contract_price == expected_gas_price;
-    debug!(
-        "Update completion check - Contract: {}, Expected: {}, Match: {}, Contract_hex: {:x}, Expected_hex: {:x}",
-        current_contract_price, expected_gas_price, is_match, current_contract_price, expected_gas_price
-    );
-    
-    Ok(is_match)
+    decode_price(&result)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use serde_json::{json, Value};
+    use wiremock::ResponseTemplate;
+
+    use super::*;
+    use crate::components::{selector, PriceSource, ThresholdStrategy};
+    use crate::fees::Bound;
+    use crate::fixtures::{fixture, MockNode, Reply, StubNode};
+    use crate::queue::QueuedUpdate;
+    use crate::transport::RetryPolicy;
+
+    const TX_HASH: &str = "0x7a5";
+
+    // State of the stubbed chain: the pool's price, whether our transaction was included or
+    // at least received, and the replies to the next sends
+    #[derive(Default)]
+    struct Chain {
+        price: u128,
+        included: bool,
+        received: bool,
+        sends: VecDeque<Reply>,
+    }
+
+    fn node(chain: &Arc<Mutex<Chain>>) -> StubNode {
+        let chain = chain.clone();
+        StubNode::new(move |method, _| {
+            let mut chain = chain.lock().unwrap();
+            match method {
+                "starknet_call" => Reply::Result(json!([format!("{:#x}", chain.price), "0x0"])),
+                "starknet_getNonce" => Reply::Result(json!("0x3")),
+                "starknet_addInvokeTransaction" => chain
+                    .sends
+                    .pop_front()
+                    .unwrap_or_else(|| Reply::Result(json!({ "transaction_hash": TX_HASH }))),
+                "starknet_getTransactionReceipt" if chain.included => Reply::Result(receipt()),
+                "starknet_getTransactionStatus" if chain.included || chain.received => {
+                    Reply::Result(json!({ "finality_status": "RECEIVED" }))
+                }
+                // Transaction hash not found
+                "starknet_getTransactionReceipt" | "starknet_getTransactionStatus" => {
+                    Reply::Error(29)
+                }
+                _ => Reply::Error(-32601),
+            }
+        })
+    }
+
+    fn receipt() -> Value {
+        json!({
+            "type": "INVOKE",
+            "transaction_hash": TX_HASH,
+            "actual_fee": { "amount": "0x1", "unit": "FRI" },
+            "finality_status": "ACCEPTED_ON_L2",
+            "messages_sent": [],
+            "events": [],
+            "execution_resources": { "l1_gas": 0, "l1_data_gas": 0, "l2_gas": 0 },
+            "execution_status": "SUCCEEDED",
+            "block_hash": "0x1",
+            "block_number": 1
+        })
+    }
+
+    fn component() -> FeeComponent {
+        FeeComponent {
+            name: "l2_gas".to_string(),
+            source: PriceSource::L2Gas,
+            flat_value: None,
+            getter: selector("get_l2_gas_price").unwrap(),
+            setter: selector("set_l2_gas_price").unwrap(),
+            pool: None,
+            layout: CalldataLayout {
+                getter_args: Vec::new(),
+                setter_args: Vec::new(),
+                encoding: Default::default(),
+                binding: None,
+            },
+            token: None,
+            strategy: ThresholdStrategy {
+                upward_threshold: 105,
+                downward_threshold: 85,
+                upward_buffer: 110,
+                downward_buffer: 110,
+                max_change_percent: None,
+                min_price: None,
+                max_price: None,
+            },
+        }
+    }
+
+    async fn updater(node: &StubNode) -> Updater {
+        updater_on(&StubNode::pool(&[node], RetryPolicy::default())).await
+    }
+
+    // One sender over the pool, with every resource bound fixed so nothing is estimated
+    async fn updater_on(pool: &EndpointPool) -> Updater {
+        let sender = SenderKey {
+            address: Felt::from(0x5e4du64),
+            private_key: Felt::from(0x2au8),
+        };
+        let fixed = ResourceBounds {
+            l1_gas: Bound::Fixed(0),
+            l1_gas_price: Bound::Fixed(1),
+            l2_gas: Bound::Fixed(1_000_000),
+            l2_gas_price: Bound::Fixed(1),
+            l1_data_gas: Bound::Fixed(1_000),
+            l1_data_gas_price: Bound::Fixed(1),
+            ..ResourceBounds::default()
+        };
+        Updater::new(
+            pool,
+            Felt::from(0x9001u64),
+            &[sender],
+            Felt::from_hex("0x534e5f5345504f4c4941").unwrap(),
+            vec![component()],
+            AccountEncoding::New,
+        )
+        .await
+        .unwrap()
+        .with_simulation(false)
+        .with_resource_bounds(fixed)
+    }
+
+    fn prices(l2_gas: u128) -> Option<BlockPrices> {
+        let price = |fri: u128| ResourcePrice {
+            price_in_fri: Felt::from(fri),
+            price_in_wei: Felt::ONE,
+        };
+        Some(BlockPrices {
+            l1_gas: price(1),
+            l1_data_gas: price(1),
+            l2_gas: price(l2_gas),
+        })
+    }
+
+    fn queue(decisions: Vec<FeeDecision>) -> SendQueue {
+        let items = decisions
+            .into_iter()
+            .filter_map(QueuedUpdate::from_decision)
+            .collect();
+        SendQueue::build(items, None, None)
+    }
+
+    // Checks at `network` and sends whatever was decided
+    async fn check_and_send(updater: &mut Updater, network: u128) -> Vec<FeeDecision> {
+        let decisions = updater
+            .check_fee_update(Some(1), prices(network))
+            .await
+            .unwrap()
+            .expect("checked");
+        updater.update_fee(queue(decisions.clone())).await.unwrap();
+        decisions
+    }
+
+    #[tokio::test]
+    async fn only_prices_outside_the_dead_band_are_updated() {
+        let chain = Arc::new(Mutex::new(Chain {
+            price: 100,
+            ..Chain::default()
+        }));
+        let mut updater = updater(&node(&chain)).await;
+
+        for network in [86, 100, 105] {
+            let decisions = updater
+                .check_fee_update(Some(1), prices(network))
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(!decisions[0].should_update, "network {network}");
+        }
+        let decisions = updater
+            .check_fee_update(Some(1), prices(200))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(decisions[0].should_update);
+        assert_eq!(decisions[0].direction, Direction::Upward);
+        assert_eq!(decisions[0].new_gas_price, Felt::from(220u8));
+        let decisions = updater
+            .check_fee_update(Some(1), prices(50))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(decisions[0].direction, Direction::Downward);
+        assert_eq!(decisions[0].new_gas_price, Felt::from(55u8));
+    }
+
+    #[tokio::test]
+    async fn sent_update_stays_pending_until_confirmed() {
+        let chain = Arc::new(Mutex::new(Chain {
+            price: 100,
+            ..Chain::default()
+        }));
+        let node = node(&chain);
+        let mut updater = updater(&node).await;
+
+        check_and_send(&mut updater, 200).await;
+        assert_eq!(node.requests("starknet_addInvokeTransaction").len(), 1);
+        let tx_hash = Felt::from_hex(TX_HASH).unwrap();
+        assert_eq!(updater.pending_updates().len(), 1);
+        assert_eq!(updater.pending_updates()[0].tx_hash, tx_hash);
+        assert_eq!(
+            updater.pending_updates()[0].updates[0].gas_price,
+            Felt::from(220u8)
+        );
+
+        // No receipt yet: a single sender waits for it instead of checking
+        let checked = updater
+            .check_fee_update(Some(2), prices(200))
+            .await
+            .unwrap();
+        assert!(checked.is_none());
+        assert_eq!(updater.pending_updates().len(), 1);
+        assert!(updater.take_resolved().is_empty());
+
+        {
+            let mut chain = chain.lock().unwrap();
+            chain.price = 220;
+            chain.included = true;
+        }
+        let decisions = updater
+            .check_fee_update(Some(3), prices(200))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(updater.pending_updates().is_empty());
+        let resolved = updater.take_resolved();
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].outcome, TxOutcome::Confirmed);
+        assert_eq!(resolved[0].pending.tx_hash, tx_hash);
+        // The confirmed value is ours, not an outside change, and within the dead-band
+        assert!(updater.take_outside_changes().is_empty());
+        assert!(!decisions[0].should_update);
+    }
+
+    #[tokio::test]
+    async fn included_transaction_that_left_another_value_failed() {
+        let chain = Arc::new(Mutex::new(Chain {
+            price: 100,
+            ..Chain::default()
+        }));
+        let mut updater = updater(&node(&chain)).await;
+
+        check_and_send(&mut updater, 200).await;
+        chain.lock().unwrap().included = true;
+        let decisions = updater
+            .check_fee_update(Some(2), prices(200))
+            .await
+            .unwrap()
+            .unwrap();
+        let resolved = updater.take_resolved();
+        assert_eq!(resolved[0].outcome, TxOutcome::Failed);
+        // The update is due again right away
+        assert!(decisions[0].should_update);
+    }
+
+    #[tokio::test]
+    async fn pushed_status_resolves_without_a_receipt() {
+        let chain = Arc::new(Mutex::new(Chain {
+            price: 100,
+            ..Chain::default()
+        }));
+        let node = node(&chain);
+        let mut updater = updater(&node).await;
+        let tx_hash = Felt::from_hex(TX_HASH).unwrap();
+
+        check_and_send(&mut updater, 200).await;
+        updater.on_transaction_status(tx_hash, TransactionStatus::Failed);
+        updater
+            .check_fee_update(Some(2), prices(200))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(updater.take_resolved()[0].outcome, TxOutcome::Failed);
+
+        check_and_send(&mut updater, 200).await;
+        chain.lock().unwrap().price = 220;
+        updater.on_transaction_status(tx_hash, TransactionStatus::Confirmed);
+        updater
+            .check_fee_update(Some(3), prices(200))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(updater.take_resolved()[0].outcome, TxOutcome::Confirmed);
+        assert!(node.requests("starknet_getTransactionReceipt").is_empty());
+    }
+
+    #[tokio::test]
+    async fn resent_duplicate_the_node_knows_is_tracked_as_landed() {
+        let chain = Arc::new(Mutex::new(Chain {
+            price: 100,
+            received: true,
+            // Internal error, then the duplicate transaction error
+            sends: VecDeque::from([Reply::Error(-32603), Reply::Error(59)]),
+            ..Chain::default()
+        }));
+        let node = node(&chain);
+        let mut updater = updater(&node).await.with_backoff(BackoffPolicy {
+            attempts: 2,
+            base_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+        });
+
+        check_and_send(&mut updater, 200).await;
+        assert_eq!(node.requests("starknet_addInvokeTransaction").len(), 2);
+        assert_eq!(node.requests("starknet_getTransactionStatus").len(), 1);
+        assert_eq!(updater.pending_updates().len(), 1);
+        // Tracked under the locally computed hash, as no reply carried one
+        assert_ne!(
+            updater.pending_updates()[0].tx_hash,
+            Felt::from_hex(TX_HASH).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn resent_duplicate_the_node_does_not_know_is_an_error() {
+        let chain = Arc::new(Mutex::new(Chain {
+            price: 100,
+            sends: VecDeque::from([Reply::Error(-32603), Reply::Error(59)]),
+            ..Chain::default()
+        }));
+        let mut updater = updater(&node(&chain)).await.with_backoff(BackoffPolicy {
+            attempts: 2,
+            base_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+        });
+
+        let decisions = updater
+            .check_fee_update(Some(1), prices(200))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(updater.update_fee(queue(decisions)).await.is_err());
+        assert!(updater.pending_updates().is_empty());
+    }
+
+    #[tokio::test]
+    async fn duplicate_resent_by_the_pool_is_tracked_as_landed() {
+        let chain = Arc::new(Mutex::new(Chain {
+            price: 100,
+            received: true,
+            // The first endpoint drops the connection, the other already has the transaction
+            sends: VecDeque::from([Reply::Down, Reply::Error(59)]),
+            ..Chain::default()
+        }));
+        let pool = StubNode::pool(&[&node(&chain), &node(&chain)], RetryPolicy::default());
+        let mut updater = updater_on(&pool).await;
+
+        check_and_send(&mut updater, 200).await;
+        assert_eq!(updater.pending_updates().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn duplicate_on_the_first_attempt_is_an_error() {
+        let chain = Arc::new(Mutex::new(Chain {
+            price: 100,
+            received: true,
+            sends: VecDeque::from([Reply::Error(59)]),
+            ..Chain::default()
+        }));
+        let node = node(&chain);
+        // A pool that could fail over, but never resent the send
+        let retry = RetryPolicy {
+            retries: 2,
+            backoff: Duration::ZERO,
+        };
+        let mut updater = updater_on(&StubNode::pool(&[&node], retry)).await;
+
+        let decisions = updater
+            .check_fee_update(Some(1), prices(200))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(updater.update_fee(queue(decisions)).await.is_err());
+        assert!(updater.pending_updates().is_empty());
+        assert!(node.requests("starknet_getTransactionStatus").is_empty());
+    }
+
+    #[tokio::test]
+    async fn transaction_the_node_never_saw_expires_and_is_resent() {
+        let chain = Arc::new(Mutex::new(Chain {
+            price: 100,
+            ..Chain::default()
+        }));
+        let node = node(&chain);
+        let mut updater = updater(&node).await.with_pending_expiry(5);
+
+        check_and_send(&mut updater, 200).await;
+        assert_eq!(updater.pending_updates()[0].sent_block, Some(1));
+        let checked = updater
+            .check_fee_update(Some(5), prices(200))
+            .await
+            .unwrap();
+        assert!(checked.is_none());
+        assert!(node.requests("starknet_getTransactionStatus").is_empty());
+
+        let decisions = updater
+            .check_fee_update(Some(6), prices(200))
+            .await
+            .unwrap()
+            .expect("checked once the transaction expired");
+        assert!(updater.pending_updates().is_empty());
+        assert_eq!(updater.take_resolved()[0].outcome, TxOutcome::Expired);
+        updater.update_fee(queue(decisions)).await.unwrap();
+        assert_eq!(node.requests("starknet_addInvokeTransaction").len(), 2);
+    }
+
+    #[tokio::test]
+    async fn expired_transaction_the_node_knows_stays_pending() {
+        let chain = Arc::new(Mutex::new(Chain {
+            price: 100,
+            received: true,
+            ..Chain::default()
+        }));
+        let node = node(&chain);
+        let mut updater = updater(&node).await.with_pending_expiry(5);
+
+        check_and_send(&mut updater, 200).await;
+        let checked = updater
+            .check_fee_update(Some(10), prices(200))
+            .await
+            .unwrap();
+        assert!(checked.is_none());
+        assert_eq!(updater.pending_updates().len(), 1);
+        assert_eq!(node.requests("starknet_getTransactionStatus").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn paused_updater_sends_nothing() {
+        let chain = Arc::new(Mutex::new(Chain {
+            price: 100,
+            ..Chain::default()
+        }));
+        let node = node(&chain);
+        let mut updater = updater(&node).await;
+        updater.set_paused(true);
+
+        let decisions = check_and_send(&mut updater, 200).await;
+        assert!(decisions[0].should_update);
+        assert!(node.requests("starknet_addInvokeTransaction").is_empty());
+        assert!(updater.pending_updates().is_empty());
+    }
+
+    #[tokio::test]
+    async fn value_written_by_someone_else_is_an_outside_change() {
+        let chain = Arc::new(Mutex::new(Chain {
+            price: 100,
+            ..Chain::default()
+        }));
+        let mut updater = updater(&node(&chain)).await;
+
+        updater
+            .check_fee_update(Some(1), prices(100))
+            .await
+            .unwrap();
+        chain.lock().unwrap().price = 150;
+        updater
+            .check_fee_update(Some(2), prices(150))
+            .await
+            .unwrap();
+        let changes = updater.take_outside_changes();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].previous, Felt::from(100u8));
+        assert_eq!(changes[0].current, Felt::from(150u8));
+    }
+
+    // Prices of the block_latest and block_pending fixtures, and what the pool holds
+    const LATEST_L2_GAS: u128 = 50_000_000;
+    const PENDING_L2_GAS: u128 = 40_000_000;
+    const CONTRACT_PRICE: u128 = 40_000_000;
+
+    async fn http_updater(node: &MockNode) -> Updater {
+        updater_on(&MockNode::pool(&[node], RetryPolicy::default())).await
+    }
+
+    #[tokio::test]
+    async fn check_reads_the_latest_block_over_http() {
+        let node = MockNode::start().await;
+        node.replay("starknet_getBlockWithTxHashes", "block_latest")
+            .await;
+        node.replay("starknet_call", "call_price").await;
+        let mut updater = http_updater(&node).await;
+
+        let decisions = updater
+            .check_fee_update(Some(812346), None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(decisions[0].network_gas_price, LATEST_L2_GAS);
+        assert_eq!(decisions[0].contract_gas_price, CONTRACT_PRICE);
+        assert!(decisions[0].should_update);
+        assert_eq!(
+            decisions[0].new_gas_price,
+            Felt::from(percent_of(LATEST_L2_GAS, 110))
+        );
+    }
+
+    #[tokio::test]
+    async fn pending_price_block_reads_the_pending_block() {
+        let node = MockNode::start().await;
+        node.replay("starknet_getBlockWithTxHashes", "block_pending")
+            .await;
+        node.replay("starknet_call", "call_price").await;
+        let mut updater = http_updater(&node)
+            .await
+            .with_price_block(PriceBlock::Pending);
+
+        let decisions = updater
+            .check_fee_update(Some(812346), None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(decisions[0].network_gas_price, PENDING_L2_GAS);
+        assert!(!decisions[0].should_update);
+    }
+
+    #[tokio::test]
+    async fn latest_price_block_rejects_a_pending_block() {
+        let node = MockNode::start().await;
+        node.replay("starknet_getBlockWithTxHashes", "block_pending")
+            .await;
+        node.replay("starknet_call", "call_price").await;
+        let mut updater = http_updater(&node).await;
+
+        let result = updater.check_fee_update(Some(812346), None).await;
+        assert!(matches!(result, Err(UpdaterError::InvalidGasPrice(_))));
+    }
+
+    #[tokio::test]
+    async fn malformed_results_fail_the_check() {
+        let node = MockNode::start().await;
+        node.replay("starknet_getBlockWithTxHashes", "block_malformed")
+            .await;
+        node.replay("starknet_call", "call_price").await;
+        let mut updater = http_updater(&node).await;
+        assert!(updater.check_fee_update(Some(1), None).await.is_err());
+
+        let node = MockNode::start().await;
+        node.replay("starknet_call", "call_malformed").await;
+        let mut updater = http_updater(&node).await;
+        let result = updater
+            .check_fee_update(Some(1), prices(LATEST_L2_GAS))
+            .await;
+        assert!(result.is_err());
+        assert!(updater.take_outside_changes().is_empty());
+    }
+
+    #[tokio::test]
+    async fn rate_limited_read_is_retried() {
+        let node = MockNode::start().await;
+        node.respond_first(
+            "starknet_call",
+            1,
+            ResponseTemplate::new(429).set_body_json(fixture("rate_limited")),
+        )
+        .await;
+        node.replay("starknet_call", "call_price").await;
+        let mut updater = http_updater(&node).await.with_backoff(BackoffPolicy {
+            attempts: 1,
+            base_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+        });
+
+        let decisions = updater
+            .check_fee_update(Some(1), prices(LATEST_L2_GAS))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(decisions[0].contract_gas_price, CONTRACT_PRICE);
+        assert_eq!(node.requests("starknet_call").await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn update_is_sent_and_confirmed_over_http() {
+        let node = MockNode::start().await;
+        node.replay("starknet_getBlockWithTxHashes", "block_latest")
+            .await;
+        // The check and the re-read before sending see the old price, later reads ours
+        node.respond_first(
+            "starknet_call",
+            2,
+            ResponseTemplate::new(200).set_body_json(fixture("call_price")),
+        )
+        .await;
+        node.replay("starknet_call", "call_price_updated").await;
+        node.replay("starknet_getNonce", "nonce").await;
+        node.replay("starknet_addInvokeTransaction", "add_invoke")
+            .await;
+        node.respond_first(
+            "starknet_getTransactionReceipt",
+            1,
+            ResponseTemplate::new(200).set_body_json(fixture("tx_hash_not_found")),
+        )
+        .await;
+        node.replay("starknet_getTransactionReceipt", "receipt_invoke")
+            .await;
+        let mut updater = http_updater(&node).await;
+        let tx_hash = fixture("add_invoke")["result"]["transaction_hash"].clone();
+        let tx_hash = Felt::from_hex(tx_hash.as_str().unwrap()).unwrap();
+
+        let decisions = updater
+            .check_fee_update(Some(812346), None)
+            .await
+            .unwrap()
+            .unwrap();
+        updater.update_fee(queue(decisions)).await.unwrap();
+        let invokes = node.requests("starknet_addInvokeTransaction").await;
+        assert_eq!(invokes.len(), 1);
+        let invoke = &invokes[0]["params"]["invoke_transaction"];
+        assert_eq!(invoke["nonce"], "0x1c");
+        // The new price is the low limb of the u256
+        assert!(invoke["calldata"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("0x3473bc0")));
+        assert_eq!(updater.pending_updates()[0].tx_hash, tx_hash);
+
+        // Not found yet, then included with the new value
+        assert!(updater
+            .check_fee_update(Some(812347), None)
+            .await
+            .unwrap()
+            .is_none());
+        updater
+            .check_fee_update(Some(812348), None)
+            .await
+            .unwrap()
+            .unwrap();
+        let resolved = updater.take_resolved();
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].outcome, TxOutcome::Confirmed);
+        assert!(updater.pending_updates().is_empty());
+    }
 }
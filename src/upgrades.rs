@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use starknet::{
+    core::types::{BlockId, BlockTag, Felt},
+    providers::Provider,
+};
+use tracing::{debug, info};
+
+use crate::alerts::{AlertSeverity, Alerter};
+use crate::components::{pools, FeeComponent};
+use crate::endpoints::EndpointPool;
+use crate::preflight::check_pool_entry_points;
+use crate::updater::UpdaterError;
+
+// Watches the class hash of every pool; an upgrade may rename the fee entry points and
+// break the updater without a single failed call until the next update reverts
+#[derive(Debug)]
+pub struct UpgradeWatcher {
+    default_pool: Felt,
+    // Never checked when unset
+    interval: Option<Duration>,
+    last_check: Option<Instant>,
+    class_hashes: HashMap<Felt, Felt>,
+}
+
+impl UpgradeWatcher {
+    pub fn new(default_pool: Felt, interval: Option<Duration>) -> Self {
+        Self {
+            default_pool,
+            interval,
+            last_check: None,
+            class_hashes: HashMap::new(),
+        }
+    }
+
+    // Compares the class hashes with the ones seen before once the interval has passed;
+    // on a change the entry point checks are re-run and the outcome alerted
+    pub async fn check(
+        &mut self,
+        rpc: &EndpointPool,
+        alerter: &Alerter,
+        components: &[FeeComponent],
+    ) -> Result<(), UpdaterError> {
+        let Some(interval) = self.interval else {
+            return Ok(());
+        };
+        if self
+            .last_check
+            .is_some_and(|last_check| last_check.elapsed() < interval)
+        {
+            return Ok(());
+        }
+        self.last_check = Some(Instant::now());
+
+        let provider = rpc.client();
+        for pool in pools(self.default_pool, components) {
+            let class_hash = provider
+                .get_class_hash_at(BlockId::Tag(BlockTag::Latest), pool)
+                .await?;
+            let Some(previous) = self.class_hashes.insert(pool, class_hash) else {
+                debug!("Pool {:#x} runs class {:#x}", pool, class_hash);
+                continue;
+            };
+            if previous == class_hash {
+                continue;
+            }
+            info!(
+                "🧬 Pool {:#x} upgraded from class {:#x} to {:#x}, re-running the entry point checks",
+                pool, previous, class_hash
+            );
+            let on_pool: Vec<FeeComponent> = components
+                .iter()
+                .filter(|component| component.pool.unwrap_or(self.default_pool) == pool)
+                .cloned()
+                .collect();
+            let failures = check_pool_entry_points(&provider, pool, &on_pool).await;
+            let upgraded = format!(
+                "Pool {:#x} was upgraded from class {:#x} to {:#x}",
+                pool, previous, class_hash
+            );
+            if failures.is_empty() {
+                alerter
+                    .raise(
+                        AlertSeverity::Warning,
+                        "contract_upgraded",
+                        format!("{upgraded}; the fee entry points still check out"),
+                    )
+                    .await;
+                continue;
+            }
+            let failures: Vec<String> = failures.iter().map(ToString::to_string).collect();
+            alerter
+                .raise(
+                    AlertSeverity::Critical,
+                    "contract_upgraded",
+                    format!(
+                        "{}; fee updates will fail: {}",
+                        upgraded,
+                        failures.join("; ")
+                    ),
+                )
+                .await;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+use std::net::SocketAddr;
+
+use axum::{extract::State, response::Html, routing::get, Json, Router};
+use tracing::{error, info};
+
+use crate::live::{LiveState, LiveView, Status};
+
+const DASHBOARD_PAGE: &str = include_str!("../assets/dashboard.html");
+
+// Serve the single-page dashboard, the state it polls and the status for runbooks;
+// binding happens before returning so a taken port fails startup
+pub async fn serve_dashboard(addr: SocketAddr, live: LiveView) -> anyhow::Result<()> {
+    let app = Router::new()
+        .route("/", get(|| async { Html(DASHBOARD_PAGE) }))
+        .route("/api/state", get(state))
+        .route("/status", get(status))
+        .with_state(live);
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!("🖥️ Serving the dashboard on http://{}/", addr);
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, app).await {
+            error!("Dashboard server stopped: {:?}", e);
+        }
+    });
+    Ok(())
+}
+
+async fn state(State(live): State<LiveView>) -> Json<LiveState> {
+    Json(live.snapshot())
+}
+
+async fn status(State(live): State<LiveView>) -> Json<Status> {
+    Json(live.status())
+}
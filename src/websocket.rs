@@ -0,0 +1,332 @@
+use std::{collections::HashMap, time::Duration};
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use starknet::core::types::Felt;
+use tokio::time::Instant;
+use tokio_tungstenite::{
+    client_async_tls_with_config,
+    tungstenite::{
+        client::IntoClientRequest,
+        http::{HeaderName, HeaderValue},
+        Message,
+    },
+};
+use tracing::{debug, error, info, warn};
+use url::Url;
+
+use crate::errors::ErrorClass;
+use crate::preflight::WsStream;
+use crate::spec::SpecVersion;
+use crate::transport::TransportOptions;
+
+// Connect to the first reachable URL, trying them in order starting at `start`
+pub async fn connect(
+    urls: &[Url],
+    options: &TransportOptions,
+    start: usize,
+) -> anyhow::Result<(WsStream, usize)> {
+    for offset in 0..urls.len() {
+        let index = (start + offset) % urls.len();
+        info!("Connecting to Starknet WebSocket at: {}", urls[index]);
+        match connect_one(&urls[index], options).await {
+            Ok(ws_stream) => {
+                info!("Successfully connected to Starknet WebSocket");
+                return Ok((ws_stream, index));
+            }
+            Err(e) => warn!("⚠️ WebSocket {} unreachable: {}", urls[index], e),
+        }
+    }
+    Err(
+        anyhow::anyhow!("none of the {} WebSocket URL(s) is reachable", urls.len())
+            .context(ErrorClass::Network),
+    )
+}
+
+// Fail back to a URL listed before the current one once it accepts connections again
+pub async fn connect_preferred(
+    urls: &[Url],
+    options: &TransportOptions,
+    current: usize,
+) -> Option<(WsStream, usize)> {
+    for (index, url) in urls.iter().enumerate().take(current) {
+        if let Ok(ws_stream) = connect_one(url, options).await {
+            info!("✅ WebSocket {} recovered, failing back", url);
+            return Some((ws_stream, index));
+        }
+    }
+    None
+}
+
+// Handshake carrying the headers configured for the URL's host, through the proxy if any
+// and with the configured TLS material
+pub async fn connect_one(url: &Url, options: &TransportOptions) -> anyhow::Result<WsStream> {
+    let mut request = url.into_client_request()?;
+    for header in options.headers_for(url) {
+        request.headers_mut().append(
+            HeaderName::from_bytes(header.name.as_bytes())?,
+            HeaderValue::from_str(&header.value)?,
+        );
+    }
+    let stream = options.connect_tcp(url).await?;
+    let (ws_stream, _) =
+        client_async_tls_with_config(request, stream, None, options.ws_tls_connector()?).await?;
+    Ok(ws_stream)
+}
+
+// One-off call on a connection nothing else reads from yet, such as the startup checks
+pub async fn request(ws_stream: &mut WsStream, id: u64, method: &str) -> Result<Value, String> {
+    let request = json!({
+        "jsonrpc": "2.0",
+        "method": method,
+        "params": [],
+        "id": id
+    });
+    ws_stream
+        .send(Message::Text(request.to_string()))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    while let Some(msg) = ws_stream.next().await {
+        let text = match msg {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Ping(data)) => {
+                ws_stream
+                    .send(Message::Pong(data))
+                    .await
+                    .map_err(|e| e.to_string())?;
+                continue;
+            }
+            Ok(Message::Close(_)) => break,
+            Ok(_) => continue,
+            Err(e) => return Err(e.to_string()),
+        };
+
+        let Ok(mut value) = serde_json::from_str::<Value>(&text) else {
+            continue;
+        };
+        if value.get("id").and_then(Value::as_u64) != Some(id) {
+            continue;
+        }
+        if let Some(error) = value.get("error") {
+            return Err(error.to_string());
+        }
+        return match value.get_mut("result") {
+            Some(result) => Ok(result.take()),
+            None => Err(format!("unexpected response: {text}")),
+        };
+    }
+
+    Err("connection closed before a response arrived".to_string())
+}
+
+// Notices a connection gone quiet, such as a half-open TCP connection that never
+// reports an error; any frame counts as a sign of life, pongs included
+#[derive(Debug)]
+pub struct Heartbeat {
+    timeout: Duration,
+    last_frame: Instant,
+}
+
+impl Heartbeat {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            last_frame: Instant::now(),
+        }
+    }
+
+    pub fn on_frame(&mut self) {
+        self.last_frame = Instant::now();
+    }
+
+    pub fn silence(&self) -> Duration {
+        self.last_frame.elapsed()
+    }
+
+    pub fn is_stalled(&self) -> bool {
+        self.silence() >= self.timeout
+    }
+}
+
+// Without a new heads notification for this long the subscription is renewed
+pub const NEW_HEADS_STALL_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Request {
+    NewHeads,
+    TransactionStatus(Felt),
+    Events(Felt),
+    Unsubscribe,
+}
+
+// Subscriptions opened on one connection, keyed by the ids the node assigned them.
+// Request id 0 is left to the chain id query.
+#[derive(Debug)]
+pub struct Subscriptions {
+    spec: SpecVersion,
+    next_id: u64,
+    requests: HashMap<u64, Request>,
+    new_heads: Option<Value>,
+    transactions: HashMap<Felt, Value>,
+    // Event subscriptions by the contract they watch
+    events: HashMap<Felt, Value>,
+    last_new_heads: Instant,
+}
+
+impl Subscriptions {
+    pub fn new(spec: SpecVersion) -> Self {
+        Self {
+            spec,
+            next_id: 1,
+            requests: HashMap::new(),
+            new_heads: None,
+            transactions: HashMap::new(),
+            events: HashMap::new(),
+            last_new_heads: Instant::now(),
+        }
+    }
+
+    pub fn spec(&self) -> SpecVersion {
+        self.spec
+    }
+
+    fn request(&mut self, request: Request, method: &str, params: Value) -> Message {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.requests.insert(id, request);
+        Message::Text(
+            json!({
+                "jsonrpc": "2.0",
+                "method": method,
+                "params": params,
+                "id": id
+            })
+            .to_string(),
+        )
+    }
+
+    pub fn subscribe_new_heads(&mut self) -> Message {
+        self.last_new_heads = Instant::now();
+        let (method, params) = self.spec.subscribe_new_heads();
+        self.request(Request::NewHeads, method, params)
+    }
+
+    // Push notifications for a sent transaction until it is accepted or rejected; `None`
+    // when the node's spec has no such subscription
+    pub fn subscribe_transaction_status(&mut self, tx_hash: Felt) -> Option<Message> {
+        let (method, params) = self
+            .spec
+            .subscribe_transaction_status(&format!("{tx_hash:#x}"))?;
+        Some(self.request(Request::TransactionStatus(tx_hash), method, params))
+    }
+
+    // Push notifications for the events `from_address` emits with `key`; `None` when the
+    // node's spec has no such subscription
+    pub fn subscribe_events(&mut self, from_address: Felt, key: Felt) -> Option<Message> {
+        let (method, params) = self
+            .spec
+            .subscribe_events(&format!("{from_address:#x}"), &format!("{key:#x}"))?;
+        Some(self.request(Request::Events(from_address), method, params))
+    }
+
+    pub fn unsubscribe(&mut self, subscription_id: &Value) -> Message {
+        let (method, params) = self.spec.unsubscribe(subscription_id);
+        self.request(Request::Unsubscribe, method, params)
+    }
+
+    // Record the subscription id a node returns for one of our requests
+    pub fn on_response(&mut self, response: &Value) {
+        let Some(request) = response
+            .get("id")
+            .and_then(Value::as_u64)
+            .and_then(|id| self.requests.remove(&id))
+        else {
+            return;
+        };
+        if let Some(error) = response.get("error") {
+            error!("❌ WebSocket {:?} request failed: {}", request, error);
+            return;
+        }
+        let Some(result) = response.get("result") else {
+            return;
+        };
+        match request {
+            Request::NewHeads => {
+                info!("✅ New heads subscription confirmed: {}", result);
+                self.new_heads = Some(result.clone());
+            }
+            Request::TransactionStatus(tx_hash) => {
+                debug!("Subscribed to status of {:#x}: {}", tx_hash, result);
+                self.transactions.insert(tx_hash, result.clone());
+            }
+            Request::Events(from_address) => {
+                info!(
+                    "✅ Event subscription for {:#x} confirmed: {}",
+                    from_address, result
+                );
+                self.events.insert(from_address, result.clone());
+            }
+            Request::Unsubscribe => debug!("Unsubscribed: {}", result),
+        }
+    }
+
+    // Whether a new heads notification belongs to the live subscription; ones for an
+    // unknown id are left over from a replaced subscription
+    pub fn is_current_new_heads(&mut self, subscription_id: Option<&Value>) -> bool {
+        let current = match (&self.new_heads, subscription_id) {
+            (Some(expected), Some(id)) => expected == id,
+            // Not confirmed yet, or a node that omits the id
+            _ => true,
+        };
+        if current {
+            self.last_new_heads = Instant::now();
+        } else if let Some(subscription_id) = subscription_id {
+            warn!(
+                "Ignoring new heads notification for stale subscription {}",
+                subscription_id
+            );
+        }
+        current
+    }
+
+    // Whether the node confirmed the new heads subscription
+    pub fn new_heads_live(&self) -> bool {
+        self.new_heads.is_some()
+    }
+
+    pub fn new_heads_stalled(&self) -> bool {
+        self.last_new_heads.elapsed() >= NEW_HEADS_STALL_TIMEOUT
+    }
+
+    // Drop the stalled new heads subscription and open a fresh one
+    pub fn renew_new_heads(&mut self) -> Vec<Message> {
+        let mut messages = Vec::new();
+        if let Some(subscription_id) = self.new_heads.take() {
+            messages.push(self.unsubscribe(&subscription_id));
+        }
+        messages.push(self.subscribe_new_heads());
+        messages
+    }
+
+    // The transaction reached a final status, so its subscription can go
+    pub fn finish_transaction(&mut self, tx_hash: Felt) -> Option<Message> {
+        let subscription_id = self.transactions.remove(&tx_hash)?;
+        Some(self.unsubscribe(&subscription_id))
+    }
+
+    // Unsubscribe messages for everything still open, for a clean shutdown
+    pub fn unsubscribe_all(&mut self) -> Vec<Message> {
+        let subscription_ids: Vec<Value> = self
+            .new_heads
+            .take()
+            .into_iter()
+            .chain(self.transactions.drain().map(|(_, id)| id))
+            .chain(self.events.drain().map(|(_, id)| id))
+            .collect();
+        subscription_ids
+            .iter()
+            .map(|subscription_id| self.unsubscribe(subscription_id))
+            .collect()
+    }
+}
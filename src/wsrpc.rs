@@ -0,0 +1,212 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use async_trait::async_trait;
+use futures_util::{SinkExt, StreamExt};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::json;
+use starknet::providers::{
+    jsonrpc::{JsonRpcMethod, JsonRpcResponse, JsonRpcTransport},
+    ProviderRequestData,
+};
+use thiserror::Error;
+use tokio::sync::{mpsc, oneshot};
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{debug, info, trace, warn};
+use url::Url;
+
+use crate::transport::TransportOptions;
+use crate::websocket;
+
+type Pending = Arc<Mutex<HashMap<u64, oneshot::Sender<String>>>>;
+
+#[derive(Debug, Error)]
+pub enum WsTransportError {
+    #[error("WebSocket connection failed: {0}")]
+    Connect(String),
+    #[error("WebSocket connection closed before the response arrived")]
+    Closed,
+    #[error("no response within {0:?}")]
+    Timeout(std::time::Duration),
+    #[error("batch requests are not supported over WebSocket")]
+    BatchUnsupported,
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+// One live socket: a writer task drains `outgoing`, a reader task hands each response to
+// the caller waiting on its request id
+#[derive(Debug)]
+struct Connection {
+    outgoing: mpsc::UnboundedSender<Message>,
+    pending: Pending,
+    closed: Arc<AtomicBool>,
+}
+
+// JSON-RPC transport over a dedicated WebSocket, for nodes that expose no HTTP endpoint.
+// Connects on first use and again after the socket drops.
+#[derive(Debug)]
+pub struct WsTransport {
+    url: Url,
+    options: TransportOptions,
+    connection: tokio::sync::Mutex<Option<Connection>>,
+    next_id: AtomicU64,
+}
+
+impl WsTransport {
+    pub fn new(url: Url, options: TransportOptions) -> Self {
+        Self {
+            url,
+            options,
+            connection: tokio::sync::Mutex::new(None),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    // Register the id before sending so a fast response can't be missed
+    async fn call(&self, id: u64, request: String) -> Result<String, WsTransportError> {
+        let (response_tx, response_rx) = oneshot::channel();
+        {
+            let mut connection = self.connection.lock().await;
+            if connection
+                .as_ref()
+                .is_none_or(|c| c.closed.load(Ordering::Relaxed))
+            {
+                *connection = Some(self.connect().await?);
+            }
+            let connection = connection.as_ref().expect("connected above");
+            lock(&connection.pending).insert(id, response_tx);
+            if connection.outgoing.send(Message::Text(request)).is_err() {
+                lock(&connection.pending).remove(&id);
+                return Err(WsTransportError::Closed);
+            }
+        }
+
+        match tokio::time::timeout(self.options.timeout, response_rx).await {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(_)) => Err(WsTransportError::Closed),
+            Err(_) => {
+                if let Some(connection) = self.connection.lock().await.as_ref() {
+                    lock(&connection.pending).remove(&id);
+                }
+                Err(WsTransportError::Timeout(self.options.timeout))
+            }
+        }
+    }
+
+    async fn connect(&self) -> Result<Connection, WsTransportError> {
+        let ws_stream = websocket::connect_one(&self.url, &self.options)
+            .await
+            .map_err(|e| WsTransportError::Connect(e.to_string()))?;
+        info!("Connected JSON-RPC WebSocket to {}", self.url);
+        let (mut write, mut read) = ws_stream.split();
+        let (outgoing, mut outgoing_rx) = mpsc::unbounded_channel::<Message>();
+        let pending: Pending = Arc::default();
+        let closed = Arc::new(AtomicBool::new(false));
+
+        tokio::spawn(async move {
+            while let Some(message) = outgoing_rx.recv().await {
+                if write.send(message).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        let reader_pending = pending.clone();
+        let reader_closed = closed.clone();
+        let pong = outgoing.clone();
+        let url = self.url.clone();
+        tokio::spawn(async move {
+            while let Some(message) = read.next().await {
+                match message {
+                    Ok(Message::Text(text)) => dispatch(&reader_pending, text),
+                    Ok(Message::Ping(payload)) => {
+                        let _ = pong.send(Message::Pong(payload));
+                    }
+                    Ok(Message::Close(_)) => break,
+                    Ok(_) => {}
+                    Err(e) => {
+                        warn!("⚠️ JSON-RPC WebSocket {} failed: {}", url, e);
+                        break;
+                    }
+                }
+            }
+            // Dropping the senders fails every call still waiting on this socket
+            reader_closed.store(true, Ordering::Relaxed);
+            lock(&reader_pending).clear();
+            debug!("JSON-RPC WebSocket {} closed", url);
+        });
+
+        Ok(Connection {
+            outgoing,
+            pending,
+            closed,
+        })
+    }
+}
+
+#[async_trait]
+impl JsonRpcTransport for WsTransport {
+    type Error = WsTransportError;
+
+    async fn send_request<P, R>(
+        &self,
+        method: JsonRpcMethod,
+        params: P,
+    ) -> Result<JsonRpcResponse<R>, Self::Error>
+    where
+        P: Serialize + Send + Sync,
+        R: DeserializeOwned,
+    {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        })
+        .to_string();
+        trace!("Sending request via JSON-RPC WebSocket: {}", request);
+
+        let response = self.call(id, request).await?;
+        trace!("Response from JSON-RPC WebSocket: {}", response);
+        Ok(serde_json::from_str(&response)?)
+    }
+
+    async fn send_requests<R>(
+        &self,
+        _requests: R,
+    ) -> Result<Vec<JsonRpcResponse<serde_json::Value>>, Self::Error>
+    where
+        R: AsRef<[ProviderRequestData]> + Send + Sync,
+    {
+        Err(WsTransportError::BatchUnsupported)
+    }
+}
+
+// Route a response to its caller; subscription notifications and late responses to
+// timed out calls have no waiter and are dropped
+fn dispatch(pending: &Pending, text: String) {
+    let id = serde_json::from_str::<serde_json::Value>(&text)
+        .ok()
+        .and_then(|message| message.get("id").and_then(|id| id.as_u64()));
+    let Some(id) = id else {
+        trace!("Ignoring WebSocket message without a request id: {}", text);
+        return;
+    };
+    match lock(pending).remove(&id) {
+        Some(waiter) => {
+            let _ = waiter.send(text);
+        }
+        None => debug!("Dropping WebSocket response to unknown request {}", id),
+    }
+}
+
+fn lock(pending: &Pending) -> std::sync::MutexGuard<'_, HashMap<u64, oneshot::Sender<String>>> {
+    pending.lock().unwrap_or_else(|e| e.into_inner())
+}
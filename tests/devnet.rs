@@ -0,0 +1,294 @@
+// Full check, send and confirm cycles of the updater against a real starknet-devnet and the
+// mock pool under tests/fixtures/devnet. Needs `starknet-devnet` (or the binary named by
+// STARKNET_DEVNET) and `scarb` on the PATH:
+//
+//     cargo test --test devnet -- --ignored
+use std::{
+    net::TcpListener,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use starknet::{
+    core::types::{BlockId, BlockTag, Felt, FunctionCall},
+    providers::Provider,
+};
+use url::Url;
+
+use pp_fee_updater::calldata::decode_u128;
+use pp_fee_updater::components::{
+    percent_of, selector, ComponentConfig, FeeComponent, PriceSource, ThresholdStrategy,
+};
+use pp_fee_updater::devnet::{Devnet, DevnetPrices, MOCK_POOL_DIR};
+use pp_fee_updater::queue::{QueuedUpdate, SendQueue};
+use pp_fee_updater::updater::{AccountEncoding, FeeDecision, SenderKey, TxOutcome, Updater};
+
+const PRICES: DevnetPrices = DevnetPrices {
+    l1_gas: 30_000_000_000_000,
+    l1_data_gas: 2_000,
+    l2_gas: 4_000_000_000,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+// Killed with the test, pass or fail
+struct DevnetProcess {
+    child: Child,
+    url: Url,
+}
+
+impl Drop for DevnetProcess {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+async fn start_devnet() -> DevnetProcess {
+    let binary = std::env::var("STARKNET_DEVNET").unwrap_or_else(|_| "starknet-devnet".into());
+    let port = TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("free port")
+        .port();
+    let child = Command::new(&binary)
+        .args([
+            "--seed",
+            "0",
+            "--accounts",
+            "1",
+            "--port",
+            &port.to_string(),
+        ])
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap_or_else(|e| panic!("failed to start {binary}: {e}"));
+    let process = DevnetProcess {
+        child,
+        url: Url::parse(&format!("http://127.0.0.1:{port}")).expect("devnet URL"),
+    };
+
+    let alive = process.url.join("is_alive").expect("is_alive URL");
+    let deadline = Instant::now() + TIMEOUT;
+    while reqwest::get(alive.clone()).await.is_err() {
+        assert!(Instant::now() < deadline, "devnet did not come up");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    process
+}
+
+fn build_mock_pool() -> PathBuf {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(MOCK_POOL_DIR);
+    let status = Command::new("scarb")
+        .arg("build")
+        .current_dir(&dir)
+        .status()
+        .expect("failed to run scarb");
+    assert!(status.success(), "scarb build failed");
+    dir
+}
+
+fn components() -> Vec<FeeComponent> {
+    let strategy = ThresholdStrategy {
+        upward_threshold: 105,
+        downward_threshold: 85,
+        upward_buffer: 110,
+        downward_buffer: 110,
+        max_change_percent: None,
+        min_price: None,
+        max_price: None,
+    };
+    [
+        (
+            "l1_gas",
+            PriceSource::L1Gas,
+            "get_current_gas_price",
+            "set_current_gas_price",
+        ),
+        (
+            "l1_data_gas",
+            PriceSource::L1DataGas,
+            "get_current_data_gas_price",
+            "set_current_data_gas_price",
+        ),
+        (
+            "l2_gas",
+            PriceSource::L2Gas,
+            "get_l2_gas_price",
+            "set_l2_gas_price",
+        ),
+    ]
+    .into_iter()
+    .map(|(name, source, getter, setter)| {
+        ComponentConfig::builtin(name, source, getter, setter)
+            .resolve(strategy)
+            .unwrap()
+    })
+    .collect()
+}
+
+struct Harness {
+    // Held for its Drop
+    _process: DevnetProcess,
+    devnet: Devnet,
+    pool: Felt,
+    updater: Updater,
+}
+
+impl Harness {
+    // A devnet charging `PRICES` and a pool holding them, with an updater sending from
+    // the predeployed account
+    async fn start() -> Self {
+        let contract_dir = build_mock_pool();
+        let process = start_devnet().await;
+        let devnet = Devnet::new(&process.url).unwrap();
+        let owner: SenderKey = devnet.predeployed_account().await.unwrap();
+        devnet.set_gas_prices(PRICES).await.unwrap();
+        let pool = devnet
+            .deploy_mock_pool(&contract_dir, owner, PRICES)
+            .await
+            .unwrap();
+        let chain_id = devnet.rpc().client().chain_id().await.unwrap();
+        let updater = Updater::new(
+            devnet.rpc(),
+            pool,
+            &[owner],
+            chain_id,
+            components(),
+            AccountEncoding::New,
+        )
+        .await
+        .unwrap();
+        Self {
+            _process: process,
+            devnet,
+            pool,
+            updater,
+        }
+    }
+
+    async fn check(&mut self) -> Option<Vec<FeeDecision>> {
+        let block_number = self.devnet.rpc().client().block_number().await.unwrap();
+        self.updater
+            .check_fee_update(Some(block_number), None)
+            .await
+            .unwrap()
+    }
+
+    // Sends what the check at the current prices decided and checks on until it resolves
+    async fn update(&mut self) -> (Vec<FeeDecision>, TxOutcome) {
+        let decisions = self.check().await.expect("nothing pending");
+        let items = decisions
+            .iter()
+            .cloned()
+            .filter_map(QueuedUpdate::from_decision)
+            .collect();
+        self.updater
+            .update_fee(SendQueue::build(items, None, None))
+            .await
+            .unwrap();
+        assert_eq!(self.updater.pending_updates().len(), 1);
+
+        let deadline = Instant::now() + TIMEOUT;
+        loop {
+            self.check().await;
+            if let Some(resolved) = self.updater.take_resolved().pop() {
+                assert!(self.updater.pending_updates().is_empty());
+                return (decisions, resolved.outcome);
+            }
+            assert!(Instant::now() < deadline, "update was never resolved");
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+    }
+
+    async fn contract_price(&self, getter: &str) -> u128 {
+        let result = self
+            .devnet
+            .rpc()
+            .client()
+            .call(
+                FunctionCall {
+                    contract_address: self.pool,
+                    entry_point_selector: selector(getter).unwrap(),
+                    calldata: vec![],
+                },
+                BlockId::Tag(BlockTag::Latest),
+            )
+            .await
+            .unwrap();
+        decode_u128(&result).unwrap()
+    }
+}
+
+fn decision<'a>(decisions: &'a [FeeDecision], component: &str) -> &'a FeeDecision {
+    decisions
+        .iter()
+        .find(|decision| decision.component == component)
+        .unwrap()
+}
+
+#[tokio::test]
+#[ignore = "needs starknet-devnet and scarb"]
+async fn prices_inside_the_dead_band_send_nothing() {
+    let mut harness = Harness::start().await;
+
+    let decisions = harness.check().await.unwrap();
+    assert_eq!(decisions.len(), 3);
+    assert!(decisions.iter().all(|decision| !decision.should_update));
+    assert!(harness.updater.pending_updates().is_empty());
+}
+
+#[tokio::test]
+#[ignore = "needs starknet-devnet and scarb"]
+async fn price_moves_are_sent_and_confirmed() {
+    let mut harness = Harness::start().await;
+
+    // L2 gas up by a fifth: past the upward threshold, the others unchanged
+    let risen = PRICES.l2_gas * 120 / 100;
+    harness
+        .devnet
+        .set_gas_prices(DevnetPrices {
+            l2_gas: risen,
+            ..PRICES
+        })
+        .await
+        .unwrap();
+    let (decisions, outcome) = harness.update().await;
+    assert_eq!(outcome, TxOutcome::Confirmed);
+    assert!(decision(&decisions, "l2_gas").should_update);
+    assert!(!decision(&decisions, "l1_gas").should_update);
+    assert!(!decision(&decisions, "l1_data_gas").should_update);
+    assert_eq!(
+        harness.contract_price("get_l2_gas_price").await,
+        percent_of(risen, 110)
+    );
+    assert_eq!(
+        harness.contract_price("get_current_gas_price").await,
+        PRICES.l1_gas
+    );
+
+    // L1 gas and L2 gas both down by half: one multicall writes both
+    let l1_gas = PRICES.l1_gas / 2;
+    let fallen = risen / 2;
+    harness
+        .devnet
+        .set_gas_prices(DevnetPrices {
+            l1_gas,
+            l2_gas: fallen,
+            ..PRICES
+        })
+        .await
+        .unwrap();
+    let (decisions, outcome) = harness.update().await;
+    assert_eq!(outcome, TxOutcome::Confirmed);
+    assert!(decision(&decisions, "l1_gas").should_update);
+    assert!(decision(&decisions, "l2_gas").should_update);
+    assert_eq!(
+        harness.contract_price("get_current_gas_price").await,
+        percent_of(l1_gas, 110)
+    );
+    assert_eq!(
+        harness.contract_price("get_l2_gas_price").await,
+        percent_of(fallen, 110)
+    );
+}
@@ -0,0 +1,97 @@
+// Stand-in for the privacy pool on a devnet: the three gas price getters and setters the
+// updater calls by default, stored as u256 and writable by the owner only
+
+#[starknet::interface]
+pub trait IMockPool<TContractState> {
+    fn owner(self: @TContractState) -> starknet::ContractAddress;
+    fn get_current_gas_price(self: @TContractState) -> u256;
+    fn set_current_gas_price(ref self: TContractState, price: u256);
+    fn get_current_data_gas_price(self: @TContractState) -> u256;
+    fn set_current_data_gas_price(ref self: TContractState, price: u256);
+    fn get_l2_gas_price(self: @TContractState) -> u256;
+    fn set_l2_gas_price(ref self: TContractState, price: u256);
+}
+
+#[starknet::contract]
+pub mod MockPool {
+    use starknet::storage::{StoragePointerReadAccess, StoragePointerWriteAccess};
+    use starknet::{ContractAddress, get_caller_address};
+
+    #[storage]
+    struct Storage {
+        pool_owner: ContractAddress,
+        gas_price: u256,
+        data_gas_price: u256,
+        l2_gas_price: u256,
+    }
+
+    #[event]
+    #[derive(Drop, starknet::Event)]
+    pub enum Event {
+        PriceUpdated: PriceUpdated,
+    }
+
+    #[derive(Drop, starknet::Event)]
+    pub struct PriceUpdated {
+        pub selector: felt252,
+        pub price: u256,
+    }
+
+    #[constructor]
+    fn constructor(
+        ref self: ContractState,
+        owner: ContractAddress,
+        gas_price: u256,
+        data_gas_price: u256,
+        l2_gas_price: u256,
+    ) {
+        self.pool_owner.write(owner);
+        self.gas_price.write(gas_price);
+        self.data_gas_price.write(data_gas_price);
+        self.l2_gas_price.write(l2_gas_price);
+    }
+
+    #[abi(embed_v0)]
+    impl MockPoolImpl of super::IMockPool<ContractState> {
+        fn owner(self: @ContractState) -> ContractAddress {
+            self.pool_owner.read()
+        }
+
+        fn get_current_gas_price(self: @ContractState) -> u256 {
+            self.gas_price.read()
+        }
+
+        fn set_current_gas_price(ref self: ContractState, price: u256) {
+            self.assert_owner();
+            self.gas_price.write(price);
+            self.emit(PriceUpdated { selector: selector!("set_current_gas_price"), price });
+        }
+
+        fn get_current_data_gas_price(self: @ContractState) -> u256 {
+            self.data_gas_price.read()
+        }
+
+        fn set_current_data_gas_price(ref self: ContractState, price: u256) {
+            self.assert_owner();
+            self.data_gas_price.write(price);
+            self.emit(PriceUpdated { selector: selector!("set_current_data_gas_price"), price });
+        }
+
+        fn get_l2_gas_price(self: @ContractState) -> u256 {
+            self.l2_gas_price.read()
+        }
+
+        fn set_l2_gas_price(ref self: ContractState, price: u256) {
+            self.assert_owner();
+            self.l2_gas_price.write(price);
+            self.emit(PriceUpdated { selector: selector!("set_l2_gas_price"), price });
+        }
+    }
+
+    #[generate_trait]
+    impl InternalImpl of InternalTrait {
+        fn assert_owner(self: @ContractState) {
+            assert(get_caller_address() == self.pool_owner.read(), 'Caller is not the owner');
+        }
+    }
+}